@@ -5,8 +5,9 @@ use std::io::{self, BufReader, BufWriter};
 use std::path::Path;
 
 use mp4::{
-    AacConfig, AvcConfig, HevcConfig, MediaConfig, MediaType, Mp4Config, Result, TrackConfig,
-    TtxtConfig, Vp9Config, WvttConfig,
+    AacConfig, Ac3Config, Ac4Config, AlacConfig, Av1Config, AvcConfig, Ec3Config, FlacConfig,
+    HevcConfig, MediaConfig, MediaType, Mp3Config, Mp4Config, Mpeg4VisualConfig, OpusConfig,
+    Result, TrackConfig, TtxtConfig, Vp9Config, WvttConfig,
 };
 
 fn main() {
@@ -53,17 +54,103 @@ fn copy<P: AsRef<Path>>(src_filename: &P, dst_filename: &P) -> Result<()> {
             MediaType::H265 => MediaConfig::HevcConfig(HevcConfig {
                 width: track.width(),
                 height: track.height(),
+                // hev1 tracks may carry their parameter sets in band only.
+                video_param_set: track
+                    .video_parameter_set()
+                    .map(|vps| vps.to_vec())
+                    .unwrap_or_default(),
+                seq_param_set: track
+                    .sequence_parameter_set()
+                    .map(|sps| sps.to_vec())
+                    .unwrap_or_default(),
+                pic_param_set: track
+                    .picture_parameter_set()
+                    .map(|pps| pps.to_vec())
+                    .unwrap_or_default(),
             }),
             MediaType::VP9 => MediaConfig::Vp9Config(Vp9Config {
                 width: track.width(),
                 height: track.height(),
             }),
+            MediaType::AV1 => MediaConfig::Av1Config(Av1Config {
+                width: track.width(),
+                height: track.height(),
+                sequence_header: track.av1_config()?.config_obus.clone(),
+            }),
+            MediaType::MP4V => MediaConfig::Mpeg4VisualConfig(Mpeg4VisualConfig {
+                width: track.width(),
+                height: track.height(),
+                bitrate: track.bitrate(),
+                decoder_config: track.decoder_config()?.dec_specific.data.clone(),
+            }),
+            MediaType::MP3 => MediaConfig::Mp3Config(Mp3Config {
+                bitrate: track.bitrate(),
+                sample_rate: track.sample_rate()?,
+                channel_count: track.channel_count()?,
+            }),
             MediaType::AAC => MediaConfig::AacConfig(AacConfig {
                 bitrate: track.bitrate(),
                 profile: track.audio_profile()?,
                 freq_index: track.sample_freq_index()?,
                 chan_conf: track.channel_config()?,
             }),
+            MediaType::OPUS => {
+                let dops = track.opus_config()?;
+                MediaConfig::OpusConfig(OpusConfig {
+                    channel_count: dops.output_channel_count,
+                    pre_skip: dops.pre_skip,
+                    input_sample_rate: dops.input_sample_rate,
+                    output_gain: dops.output_gain,
+                    channel_mapping_family: dops.channel_mapping_family,
+                    channel_mapping_table: dops.channel_mapping_table.clone(),
+                })
+            }
+            MediaType::AC3 => {
+                let dac3 = track.ac3_config()?;
+                let layout = dac3.channel_layout()?;
+                MediaConfig::Ac3Config(Ac3Config {
+                    bitrate: dac3.bitrate()?,
+                    sample_rate: dac3.sample_rate(),
+                    channel_mode: layout.channel_mode,
+                    lfe: layout.lfe,
+                    bsid: dac3.bsid,
+                    bsmod: dac3.bsmod,
+                })
+            }
+            MediaType::EAC3 => {
+                let dec3 = track.eac3_config()?;
+                MediaConfig::Ec3Config(Ec3Config {
+                    bitrate: dec3.data_rate as u32 * 1000,
+                    substreams: dec3.substreams.clone(),
+                    complexity_index: dec3.complexity_index,
+                })
+            }
+            MediaType::AC4 => {
+                let ac4 = track.trak.mdia.minf.stbl.stsd.ac4().unwrap();
+                MediaConfig::Ac4Config(Ac4Config {
+                    channel_count: ac4.channelcount,
+                    dsi: ac4.dac4.to_dsi(),
+                })
+            }
+            MediaType::FLAC => {
+                let dfla = track.flac_config()?;
+                MediaConfig::FlacConfig(FlacConfig {
+                    stream_info: dfla.stream_info.clone(),
+                    metadata_blocks: dfla.metadata_blocks.clone(),
+                })
+            }
+            MediaType::ALAC => {
+                let alac = track.alac_config()?;
+                MediaConfig::AlacConfig(AlacConfig {
+                    frame_length: alac.frame_length,
+                    bit_depth: alac.bit_depth,
+                    channel_count: alac.num_channels,
+                    sample_rate: alac.sample_rate,
+                    max_frame_bytes: alac.max_frame_bytes,
+                    bitrate: alac.avg_bit_rate,
+                })
+            }
+            MediaType::PCM => MediaConfig::PcmConfig(track.pcm_config()?),
             MediaType::TTXT => MediaConfig::TtxtConfig(TtxtConfig {}),
             MediaType::WVTT => MediaConfig::WvttConfig(WvttConfig {}),
         };
@@ -73,6 +160,7 @@ fn copy<P: AsRef<Path>>(src_filename: &P, dst_filename: &P) -> Result<()> {
             timescale: track.timescale(),
             language: track.language().to_string(),
             media_conf,
+            references: Vec::new(),
         };
 
         mp4_writer.add_track(&track_conf)?;
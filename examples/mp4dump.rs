@@ -1,10 +1,10 @@
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
-use std::io::{self, BufReader};
+use std::io::{self, BufReader, SeekFrom};
 use std::path::Path;
 
-use mp4::{Mp4Box, Result};
+use mp4::*;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -25,7 +25,13 @@ fn dump<P: AsRef<Path>>(filename: &P) -> Result<()> {
 
     // print out boxes
     for b in boxes.iter() {
-        println!("[{}] size={} {}", b.name, b.size, b.summary);
+        println!(
+            "{}[{}] size={} {}",
+            "  ".repeat(b.indent as usize),
+            b.name,
+            b.size,
+            b.summary
+        );
     }
 
     Ok(())
@@ -39,127 +45,101 @@ pub struct Box {
     indent: u32,
 }
 
-fn get_boxes(file: File) -> Result<Vec<Box>> {
-    let size = file.metadata()?.len();
-    let reader = BufReader::new(file);
-    let mp4 = mp4::Mp4Reader::read_header(reader, size)?;
-
-    // collect known boxes
-    let mut boxes = vec![
-        build_box(&mp4.ftyp),
-        build_box(&mp4.moov),
-        build_box(&mp4.moov.mvhd),
-    ];
-
-    if let Some(ref mvex) = &mp4.moov.mvex {
-        boxes.push(build_box(mvex));
-        if let Some(mehd) = &mvex.mehd {
-            boxes.push(build_box(mehd));
-        }
-        for trex in mvex.trexs.iter() {
-            boxes.push(build_box(trex));
-        }
-    }
+struct Collector<R> {
+    reader: R,
+    boxes: Vec<Box>,
+}
 
-    // trak.
-    for track in mp4.tracks().values() {
-        boxes.push(build_box(&track.trak));
-        boxes.push(build_box(&track.trak.tkhd));
-        if let Some(ref edts) = track.trak.edts {
-            boxes.push(build_box(edts));
-            if let Some(ref elst) = edts.elst {
-                boxes.push(build_box(elst));
+impl<R: Read + Seek> Visitor for Collector<R> {
+    fn enter(&mut self, node: &BoxNode, depth: usize) -> bool {
+        let mut summary = format!("offset={}", node.offset);
+        if let Ok(Some(s)) = read_summary(&mut self.reader, node) {
+            if !s.is_empty() {
+                summary.push(' ');
             }
-        }
-
-        // trak.mdia
-        let mdia = &track.trak.mdia;
-        boxes.push(build_box(mdia));
-        boxes.push(build_box(&mdia.mdhd));
-        boxes.push(build_box(&mdia.hdlr));
-        boxes.push(build_box(&track.trak.mdia.minf));
-
-        // trak.mdia.minf
-        let minf = &track.trak.mdia.minf;
-        if let Some(ref vmhd) = &minf.vmhd {
-            boxes.push(build_box(vmhd));
-        }
-        if let Some(ref smhd) = &minf.smhd {
-            boxes.push(build_box(smhd));
-        }
-
-        // trak.mdia.minf.stbl
-        let stbl = &track.trak.mdia.minf.stbl;
-        boxes.push(build_box(stbl));
-        boxes.push(build_box(&stbl.stsd));
-        if let Some(ref avc1) = &stbl.stsd.avc1 {
-            boxes.push(build_box(avc1));
-        }
-        if let Some(ref hev1) = &stbl.stsd.hev1 {
-            boxes.push(build_box(hev1));
-        }
-        if let Some(ref mp4a) = &stbl.stsd.mp4a {
-            boxes.push(build_box(mp4a));
-        }
-        let mut sinf = None;
-        if let Some(ref encv) = &stbl.stsd.encv {
-            boxes.push(build_box(encv));
-            sinf = Some(&encv.sinf)
-        }
-        if let Some(ref enca) = &stbl.stsd.enca {
-            boxes.push(build_box(enca));
-            sinf = Some(&enca.sinf)
-        }
-        if let Some(sinf) = sinf {
-            boxes.push(build_box(sinf));
-            if let Some(ref schm) = sinf.schm {
-                boxes.push(build_box(schm));
-            }
-            if let Some(ref schi) = sinf.schi {
-                boxes.push(build_box(schi));
-                if let Some(ref tenc) = schi.tenc {
-                    boxes.push(build_box(tenc));
-                }
-            }
-        }
-        boxes.push(build_box(&stbl.stts));
-        if let Some(ref ctts) = &stbl.ctts {
-            boxes.push(build_box(ctts));
-        }
-        if let Some(ref stss) = &stbl.stss {
-            boxes.push(build_box(stss));
-        }
-        boxes.push(build_box(&stbl.stsc));
-        boxes.push(build_box(&stbl.stsz));
-        if let Some(ref stco) = &stbl.stco {
-            boxes.push(build_box(stco));
-        }
-        if let Some(ref co64) = &stbl.co64 {
-            boxes.push(build_box(co64));
-        }
+            summary.push_str(&s);
+        }
+        self.boxes.push(Box {
+            name: node.box_type.to_string(),
+            size: node.size(),
+            summary,
+            indent: depth as u32,
+        });
+        true
     }
+}
 
-    // If fragmented, add moof boxes.
-    for moof in mp4.moofs.iter() {
-        boxes.push(build_box(moof));
-        boxes.push(build_box(&moof.mfhd));
-        for traf in moof.trafs.iter() {
-            boxes.push(build_box(traf));
-            boxes.push(build_box(&traf.tfhd));
-            if let Some(ref trun) = &traf.trun {
-                boxes.push(build_box(trun));
-            }
+macro_rules! read_summary {
+    ($reader:expr, $node:expr, $($name:ident => $box:ty),* $(,)?) => {
+        match $node.box_type {
+            $(BoxType::$name => {
+                <$box>::read_box($reader, $node.payload_size + HEADER_SIZE)?.summary()?
+            })*
+            _ => return Ok(None),
         }
+    };
+}
+
+// Summary of the boxes with a typed parser. Containers are summarised from
+// the tree instead, so that their children are not parsed again for every
+// level above them.
+fn read_summary<R: Read + Seek>(reader: &mut R, node: &BoxNode) -> Result<Option<String>> {
+    let count = |box_type| {
+        node.children
+            .iter()
+            .filter(|child| child.box_type == box_type)
+            .count()
+    };
+    match node.box_type {
+        BoxType::MoovBox => return Ok(Some(format!("traks={}", count(BoxType::TrakBox)))),
+        BoxType::MoofBox => return Ok(Some(format!("trafs={}", count(BoxType::TrafBox)))),
+        BoxType::StsdBox => return Ok(Some(format!("entries={}", node.children.len()))),
+        _ => {}
     }
 
-    Ok(boxes)
+    reader.seek(SeekFrom::Start(node.payload_offset()))?;
+    let summary = read_summary!(reader, node,
+        FtypBox => FtypBox,
+        MvhdBox => MvhdBox,
+        MehdBox => MehdBox,
+        TrexBox => TrexBox,
+        TkhdBox => TkhdBox,
+        ElstBox => ElstBox,
+        MdhdBox => MdhdBox,
+        HdlrBox => HdlrBox,
+        VmhdBox => VmhdBox,
+        SmhdBox => SmhdBox,
+        Avc1Box => Avc1Box,
+        Hev1Box => Hev1Box,
+        Hvc1Box => Hev1Box,
+        Mp4aBox => Mp4aBox,
+        EncaBox => EncaBox,
+        EncvBox => EncvBox,
+        SchmBox => SchmBox,
+        TencBox => TencBox,
+        SttsBox => SttsBox,
+        CttsBox => CttsBox,
+        StssBox => StssBox,
+        StscBox => StscBox,
+        StszBox => StszBox,
+        StcoBox => StcoBox,
+        Co64Box => Co64Box,
+        MfhdBox => MfhdBox,
+        TfhdBox => TfhdBox,
+        TrunBox => TrunBox,
+    );
+    Ok(Some(summary))
 }
 
-fn build_box<M: Mp4Box + std::fmt::Debug>(m: &M) -> Box {
-    Box {
-        name: m.box_type().to_string(),
-        size: m.box_size(),
-        summary: m.summary().unwrap(),
-        indent: 0,
-    }
+fn get_boxes(file: File) -> Result<Vec<Box>> {
+    let size = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let tree = BoxTree::read(&mut reader, size)?;
+
+    let mut collector = Collector {
+        reader,
+        boxes: Vec::new(),
+    };
+    tree.walk(&mut collector);
+    Ok(collector.boxes)
 }
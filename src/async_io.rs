@@ -0,0 +1,239 @@
+use std::io::{Cursor, SeekFrom};
+use std::ops::Deref;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+use crate::mp4box::*;
+use crate::reader::TopLevelBoxes;
+use crate::*;
+
+/// Asynchronous counterpart of [Mp4Reader].
+///
+/// Only the metadata boxes (`ftyp`, `moov`, `moof`, ...) are read into memory
+/// while the header is parsed; the media data is skipped and read sample by
+/// sample. The parsed header is available through [Deref] to [Mp4Reader].
+#[derive(Debug)]
+pub struct AsyncMp4Reader<R> {
+    inner: Mp4Reader<R>,
+}
+
+impl<R> Deref for AsyncMp4Reader<R> {
+    type Target = Mp4Reader<R>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<R> AsyncMp4Reader<R> {
+    /// Consume self, returning the inner reader.
+    pub fn into_reader(self) -> R {
+        self.inner.reader
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncMp4Reader<R> {
+    pub async fn read_header(mut reader: R, size: u64) -> Result<Self> {
+        let start = reader.stream_position().await?;
+
+        let mut boxes = TopLevelBoxes::default();
+        let mut current = start;
+        while current < size {
+            let (header, header_size) = read_box_header(&mut reader, current).await?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "file contains a box with a larger size than it",
+                ));
+            }
+
+            // Break if size zero BoxHeader, which can result in dead-loop.
+            if s == 0 {
+                break;
+            }
+
+            // BoxHeader excludes the 64-bit largesize field from the size.
+            let box_size = s + header_size - HEADER_SIZE;
+            match name {
+                BoxType::FtypBox => {
+                    let mut box_reader =
+                        read_box_data(&mut reader, current, box_size, header_size).await?;
+                    boxes.ftyp = Some(FtypBox::read_box(&mut box_reader, s)?);
+                }
+                BoxType::MoovBox => {
+                    let mut box_reader =
+                        read_box_data(&mut reader, current, box_size, header_size).await?;
+                    boxes.moov = Some(MoovBox::read_box(&mut box_reader, s)?);
+                }
+                BoxType::MoofBox => {
+                    let mut box_reader =
+                        read_box_data(&mut reader, current, box_size, header_size).await?;
+                    boxes.moofs.push(MoofBox::read_box(&mut box_reader, s)?);
+                    boxes.moof_offsets.push(current);
+                }
+                BoxType::EmsgBox => {
+                    let mut box_reader =
+                        read_box_data(&mut reader, current, box_size, header_size).await?;
+                    boxes.emsgs.push(EmsgBox::read_box(&mut box_reader, s)?);
+                }
+                BoxType::UuidBox => {
+                    let mut box_reader =
+                        read_box_data(&mut reader, current, box_size, header_size).await?;
+                    boxes.uuids.push(UuidBox::read_box(&mut box_reader, s)?);
+                }
+                BoxType::SidxBox => {
+                    let mut box_reader =
+                        read_box_data(&mut reader, current, box_size, header_size).await?;
+                    boxes.sidxs.push(SidxBox::read_box(&mut box_reader, s)?);
+                    boxes.sidx_ends.push(current + box_size);
+                }
+                BoxType::MfraBox => {
+                    let mut box_reader =
+                        read_box_data(&mut reader, current, box_size, header_size).await?;
+                    boxes.mfra = Some(MfraBox::read_box(&mut box_reader, s)?);
+                }
+                _ => {
+                    // mdat, free and unknown boxes are skipped.
+                }
+            }
+            current += box_size;
+        }
+
+        let size = current - start;
+        let inner = Mp4Reader::from_boxes(reader, size, boxes)?;
+        Ok(Self { inner })
+    }
+
+    pub async fn read_sample(
+        &mut self,
+        track_id: u32,
+        sample_id: u32,
+    ) -> Result<Option<Mp4Sample>> {
+        let track = self
+            .inner
+            .tracks()
+            .get(&track_id)
+            .ok_or(Error::TrakNotFound(track_id))?;
+        let entry = match track.sample_entry(sample_id)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let mut buffer = vec![0x0u8; entry.size as usize];
+        let reader = &mut self.inner.reader;
+        reader.seek(SeekFrom::Start(entry.offset)).await?;
+        reader.read_exact(&mut buffer).await?;
+        Ok(Some(entry.to_sample(Bytes::from(buffer))))
+    }
+}
+
+/// Read the header of the box at `offset`, returning it along with its size
+/// in bytes.
+async fn read_box_header<R>(reader: &mut R, offset: u64) -> Result<(BoxHeader, u64)>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut buf = [0u8; 16];
+    reader.seek(SeekFrom::Start(offset)).await?;
+    reader.read_exact(&mut buf[..8]).await?;
+    let header_size = if buf[..4] == [0, 0, 0, 1] {
+        reader.read_exact(&mut buf[8..]).await?;
+        16
+    } else {
+        8
+    };
+    let header = BoxHeader::read(&mut &buf[..header_size])?;
+    Ok((header, header_size as u64))
+}
+
+/// Read a whole box into memory, positioned right after its header as the
+/// [ReadBox] implementations expect.
+async fn read_box_data<R>(
+    reader: &mut R,
+    offset: u64,
+    size: u64,
+    header_size: u64,
+) -> Result<Cursor<Vec<u8>>>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut buf = vec![0u8; size as usize];
+    reader.seek(SeekFrom::Start(offset)).await?;
+    reader.read_exact(&mut buf).await?;
+    let mut box_reader = Cursor::new(buf);
+    box_reader.set_position(header_size);
+    Ok(box_reader)
+}
+
+/// Asynchronous counterpart of [Mp4Writer], for sinks that cannot seek such
+/// as sockets and pipes.
+///
+/// Like [Mp4StreamWriter], samples are buffered in memory and the file is
+/// written out by [AsyncMp4Writer::write_end], with `moov` in front of `mdat`.
+/// Memory use therefore grows to the full size of the output file. For live
+/// output, or output too large to hold in memory, use
+/// [Mp4FragmentWriter] instead.
+#[derive(Debug)]
+pub struct AsyncMp4Writer<W> {
+    writer: W,
+    inner: Mp4Writer<Cursor<Vec<u8>>>,
+}
+
+impl<W> AsyncMp4Writer<W> {
+    /// Consume self, returning the inner writer.
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncMp4Writer<W> {
+    /// Start a file that is written to `writer` by [AsyncMp4Writer::write_end].
+    /// Nothing is written to `writer` until then.
+    pub fn write_start(writer: W, config: &Mp4Config) -> Result<Self> {
+        let inner = Mp4Writer::write_start(Cursor::new(Vec::new()), config)?;
+        Ok(Self { writer, inner })
+    }
+
+    /// See [Mp4Writer::add_track].
+    pub fn add_track(&mut self, config: &TrackConfig) -> Result<()> {
+        self.inner.add_track(config)
+    }
+
+    /// See [Mp4Writer::set_edit_list].
+    pub fn set_edit_list(&mut self, track_id: u32, edits: Vec<Edit>) -> Result<()> {
+        self.inner.set_edit_list(track_id, edits)
+    }
+
+    /// See [Mp4Writer::add_sample_description].
+    pub fn add_sample_description(
+        &mut self,
+        track_id: u32,
+        media_conf: &MediaConfig,
+    ) -> Result<u32> {
+        self.inner.add_sample_description(track_id, media_conf)
+    }
+
+    /// See [Mp4Writer::set_metadata].
+    pub fn set_metadata(&mut self, metadata: IlstBox) {
+        self.inner.set_metadata(metadata)
+    }
+
+    /// See [Mp4Writer::set_chapters].
+    pub fn set_chapters(&mut self, chapters: Vec<Chapter>) {
+        self.inner.set_chapters(chapters)
+    }
+
+    /// Buffer a sample in memory until [AsyncMp4Writer::write_end].
+    pub fn write_sample(&mut self, track_id: u32, sample: &Mp4Sample) -> Result<()> {
+        self.inner.write_sample(track_id, sample)
+    }
+
+    /// Finish the file and write it to the sink. See
+    /// [Mp4StreamWriter::write_end] for the peak memory use.
+    pub async fn write_end(&mut self) -> Result<()> {
+        let buf = self.inner.finish_buffer()?;
+        self.writer.write_all(&buf).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use crate::*;
+
+/// Reads big-endian bit fields, as used by codec configuration syntax.
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// Number of bits left to read.
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    pub(crate) fn read_bit(&mut self) -> Result<bool> {
+        Ok(self.read_bits(1)? == 1)
+    }
+
+    /// Read `count` bits, at most 64.
+    pub(crate) fn read_bits(&mut self, count: u32) -> Result<u64> {
+        if count as usize > self.remaining() {
+            return Err(Error::InvalidData("unexpected end of bitstream"));
+        }
+        let mut value = 0u64;
+        for _ in 0..count {
+            let byte = self.data[self.position / 8];
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u64;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn skip_bits(&mut self, count: usize) -> Result<()> {
+        if count > self.remaining() {
+            return Err(Error::InvalidData("unexpected end of bitstream"));
+        }
+        self.position += count;
+        Ok(())
+    }
+
+    /// Skip to the next byte boundary.
+    pub(crate) fn byte_align(&mut self) {
+        self.position = (self.position + 7) / 8 * 8;
+    }
+
+    /// Read an unsigned Exp-Golomb code, `ue(v)` in the H.264 and H.265
+    /// syntax.
+    pub(crate) fn read_ue(&mut self) -> Result<u32> {
+        let mut leading_zeros = 0;
+        while !self.read_bit()? {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return Err(Error::InvalidData("invalid exp-golomb code"));
+            }
+        }
+        let value = (1u64 << leading_zeros) - 1 + self.read_bits(leading_zeros)?;
+        Ok(value as u32)
+    }
+}
+
+/// Writes big-endian bit fields, the counterpart of [BitReader].
+#[derive(Default)]
+pub(crate) struct BitWriter {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl BitWriter {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn write_bit(&mut self, bit: bool) {
+        self.write_bits(bit as u64, 1)
+    }
+
+    /// Write the `count` low bits of `value`, at most 64.
+    pub(crate) fn write_bits(&mut self, value: u64, count: u32) {
+        for i in (0..count).rev() {
+            if self.position % 8 == 0 {
+                self.data.push(0);
+            }
+            let bit = (value >> i) as u8 & 1;
+            *self.data.last_mut().unwrap() |= bit << (7 - self.position % 8);
+            self.position += 1;
+        }
+    }
+
+    /// Pad with zero bits up to the next byte boundary.
+    pub(crate) fn byte_align(&mut self) {
+        self.position = self.data.len() * 8;
+    }
+
+    /// The bytes written, the last one padded with zero bits.
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Strip the emulation prevention bytes (`0x000003`) of a NAL unit, giving
+/// its raw byte sequence payload.
+pub(crate) fn nal_unit_rbsp(nal_unit: &[u8]) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(nal_unit.len());
+    let mut zeros = 0;
+    for &byte in nal_unit {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    rbsp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_reader() {
+        // 1, 010, 011, 00100, then 0b0110 and padding.
+        let data = [0b1010_0110, 0b0100_0110];
+        let mut reader = BitReader::new(&data);
+        assert_eq!(reader.read_ue().unwrap(), 0);
+        assert_eq!(reader.read_ue().unwrap(), 1);
+        assert_eq!(reader.read_ue().unwrap(), 2);
+        assert_eq!(reader.read_ue().unwrap(), 3);
+        assert_eq!(reader.read_bits(4).unwrap(), 0b0110);
+        assert_eq!(reader.remaining(), 0);
+        assert!(reader.read_bit().is_err());
+    }
+
+    #[test]
+    fn test_bit_writer() {
+        let mut writer = BitWriter::new();
+        writer.write_bit(true);
+        writer.write_bits(0b010, 3);
+        writer.write_bits(0xABC, 12);
+        writer.write_bits(0b11, 2);
+        let data = writer.into_bytes();
+        assert_eq!(data, [0b1010_1010, 0b1011_1100, 0b1100_0000]);
+
+        let mut reader = BitReader::new(&data);
+        assert!(reader.read_bit().unwrap());
+        assert_eq!(reader.read_bits(3).unwrap(), 0b010);
+        assert_eq!(reader.read_bits(12).unwrap(), 0xABC);
+        assert_eq!(reader.read_bits(2).unwrap(), 0b11);
+    }
+
+    #[test]
+    fn test_nal_unit_rbsp() {
+        let nal_unit = [0x42, 0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0x03];
+        assert_eq!(
+            nal_unit_rbsp(&nal_unit),
+            [0x42, 0x00, 0x00, 0x01, 0x00, 0x00]
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! Generic, untyped view of the box structure of a file.
+//!
+//! [`BoxTree`] records the type, position and size of every box, including
+//! boxes this crate has no typed parser for, which makes it useful for
+//! inspecting files and for debugging broken muxer output.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::mp4box::*;
+use crate::*;
+
+/// Boxes nested deeper than this are kept as leaves.
+const MAX_DEPTH: usize = 32;
+
+/// One box of a [`BoxTree`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BoxNode {
+    pub box_type: BoxType,
+    /// Absolute offset of the box header.
+    pub offset: u64,
+    /// Size of the box header, 8 or 16 bytes.
+    pub header_size: u64,
+    /// Size of the box data following the header.
+    pub payload_size: u64,
+
+    /// Child boxes, empty unless the box is a known container.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<BoxNode>,
+}
+
+impl BoxNode {
+    /// Total size of the box, header included.
+    pub fn size(&self) -> u64 {
+        self.header_size + self.payload_size
+    }
+
+    /// Absolute offset of the box data.
+    pub fn payload_offset(&self) -> u64 {
+        self.offset + self.header_size
+    }
+
+    /// Finds a descendant of this box by path, see [`BoxTree::find`].
+    pub fn find(&self, path: &str) -> Option<&BoxNode> {
+        find_path(&self.children, path)
+    }
+
+    /// Walks this box and its descendants depth first.
+    pub fn walk<V: Visitor>(&self, visitor: &mut V) {
+        self.walk_at(visitor, 0);
+    }
+
+    fn walk_at<V: Visitor>(&self, visitor: &mut V, depth: usize) {
+        if visitor.enter(self, depth) {
+            for child in self.children.iter() {
+                child.walk_at(visitor, depth + 1);
+            }
+        }
+        visitor.leave(self, depth);
+    }
+}
+
+/// Callbacks for [`BoxTree::walk`].
+pub trait Visitor {
+    /// Called before the children of `node` are visited. Returning `false`
+    /// skips the children.
+    fn enter(&mut self, node: &BoxNode, depth: usize) -> bool;
+
+    /// Called after the children of `node` have been visited.
+    fn leave(&mut self, _node: &BoxNode, _depth: usize) {}
+}
+
+/// The boxes of a file, in file order.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct BoxTree {
+    pub boxes: Vec<BoxNode>,
+}
+
+impl BoxTree {
+    /// Reads the box structure of the `size` bytes starting at the current
+    /// position of `reader`. Only box headers are read; box data is skipped.
+    pub fn read<R: Read + Seek>(reader: &mut R, size: u64) -> Result<Self> {
+        let start = reader.stream_position()?;
+        let boxes = read_boxes(reader, None, start, start + size, 0)?;
+        Ok(BoxTree { boxes })
+    }
+
+    /// Finds a box by a `/` separated path of box types, for example
+    /// `moov/trak[1]/mdia/minf/stbl/stsd`.
+    ///
+    /// An index in brackets selects among siblings of the same type and is
+    /// 0-based, so `trak[1]` is the second `trak`; without an index the first
+    /// one is used, the same as `[0]`. Characters up to U+00FF
+    /// map to a single byte, so `ilst/©nam` matches the `\xa9nam` box.
+    pub fn find(&self, path: &str) -> Option<&BoxNode> {
+        find_path(&self.boxes, path)
+    }
+
+    /// Walks all boxes depth first.
+    pub fn walk<V: Visitor>(&self, visitor: &mut V) {
+        for node in self.boxes.iter() {
+            node.walk(visitor);
+        }
+    }
+}
+
+fn read_boxes<R: Read + Seek>(
+    reader: &mut R,
+    parent: Option<FourCC>,
+    start: u64,
+    end: u64,
+    depth: usize,
+) -> Result<Vec<BoxNode>> {
+    let mut boxes = Vec::new();
+    let mut current = start;
+    while current + HEADER_SIZE <= end {
+        reader.seek(SeekFrom::Start(current))?;
+        let header = BoxHeader::read(reader)?;
+        let header_size = reader.stream_position()? - current;
+
+        // `BoxHeader::read` does not count the largesize field.
+        let size = match header.size {
+            0 => end - current,
+            s if header_size > HEADER_SIZE => s + 8,
+            s => s,
+        };
+        if size < header_size || current + size > end {
+            return Err(Error::InvalidData(
+                "box size does not fit in the enclosing box",
+            ));
+        }
+
+        let mut node = BoxNode {
+            box_type: header.name,
+            offset: current,
+            header_size,
+            payload_size: size - header_size,
+            children: Vec::new(),
+        };
+        if depth < MAX_DEPTH {
+            if let Some(skip) = children_offset(reader, parent, &node)? {
+                let children_start = node.payload_offset() + skip;
+                if children_start <= current + size {
+                    node.children = read_boxes(
+                        reader,
+                        Some(header.name.into()),
+                        children_start,
+                        current + size,
+                        depth + 1,
+                    )?;
+                }
+            }
+        }
+        boxes.push(node);
+
+        current += size;
+    }
+    Ok(boxes)
+}
+
+/// Where the child boxes of `node` start, relative to its data, or `None` if
+/// it is not a container.
+fn children_offset<R: Read + Seek>(
+    reader: &mut R,
+    parent: Option<FourCC>,
+    node: &BoxNode,
+) -> Result<Option<u64>> {
+    let fourcc = FourCC::from(node.box_type);
+    if parent == Some(FourCC::from(BoxType::IlstBox)) {
+        // Metadata items hold `data` boxes.
+        return Ok(Some(0));
+    }
+    if parent == Some(FourCC::from(BoxType::StsdBox)) {
+        return sample_entry_offset(reader, node);
+    }
+
+    let offset = match &fourcc.value {
+        b"moov" | b"trak" | b"mdia" | b"minf" | b"stbl" | b"dinf" | b"edts" | b"mvex" | b"moof"
+        | b"traf" | b"mfra" | b"udta" | b"tref" | b"sinf" | b"schi" | b"ilst" | b"wave" => 0,
+        // Full box header followed by an entry count.
+        b"stsd" | b"dref" => 8,
+        b"meta" => {
+            // QuickTime `meta` boxes lack the full box header of ISO ones.
+            if node.payload_size < 4 {
+                return Ok(None);
+            }
+            reader.seek(SeekFrom::Start(node.payload_offset()))?;
+            if reader.read_u32::<BigEndian>()? == 0 {
+                4
+            } else {
+                0
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(offset))
+}
+
+/// Size of the fixed fields of a sample entry, which are followed by
+/// configuration boxes.
+fn sample_entry_offset<R: Read + Seek>(reader: &mut R, node: &BoxNode) -> Result<Option<u64>> {
+    let fourcc = FourCC::from(node.box_type);
+    let offset = match &fourcc.value {
+        b"avc1" | b"avc3" | b"hev1" | b"hvc1" | b"vp08" | b"vp09" | b"av01" | b"mp4v" | b"encv" => {
+            78
+        }
+        b"mp4a" | b"enca" | b"Opus" | b"ac-3" | b"ec-3" | b"ac-4" | b"fLaC" | b"alac" | b"ipcm"
+        | b"fpcm" | b"lpcm" | b"sowt" | b"twos" => {
+            // QuickTime sound sample descriptions version 1 and 2 are longer.
+            if node.payload_size < 10 {
+                return Ok(None);
+            }
+            reader.seek(SeekFrom::Start(node.payload_offset() + 8))?;
+            match reader.read_u16::<BigEndian>()? {
+                1 => 44,
+                2 => 64,
+                _ => 28,
+            }
+        }
+        b"wvtt" => 8,
+        _ => return Ok(None),
+    };
+    Ok(Some(offset))
+}
+
+fn find_path<'a>(mut nodes: &'a [BoxNode], path: &str) -> Option<&'a BoxNode> {
+    let mut found = None;
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        let (name, index) = match segment.strip_suffix(']') {
+            Some(s) => {
+                let (name, index) = s.split_once('[')?;
+                (name, index.parse::<usize>().ok()?)
+            }
+            None => (segment, 0),
+        };
+        let fourcc = path_fourcc(name)?;
+        let node = nodes
+            .iter()
+            .filter(|node| FourCC::from(node.box_type) == fourcc)
+            .nth(index)?;
+        nodes = &node.children;
+        found = Some(node);
+    }
+    found
+}
+
+fn path_fourcc(name: &str) -> Option<FourCC> {
+    let mut value = [0u8; 4];
+    let mut chars = name.chars();
+    for byte in value.iter_mut() {
+        *byte = u8::try_from(u32::from(chars.next()?)).ok()?;
+    }
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(FourCC { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn boxed(name: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut buf = (8 + payload.len() as u32).to_be_bytes().to_vec();
+        buf.extend_from_slice(name);
+        buf.extend_from_slice(payload);
+        buf
+    }
+
+    struct Types(Vec<(usize, String)>);
+
+    impl Visitor for Types {
+        fn enter(&mut self, node: &BoxNode, depth: usize) -> bool {
+            self.0.push((depth, node.box_type.to_string()));
+            node.box_type != BoxType::UdtaBox
+        }
+    }
+
+    #[test]
+    fn test_box_tree() {
+        let trak0 = boxed(b"trak", &boxed(b"tkhd", &[0; 4]));
+        let trak1 = boxed(b"trak", &boxed(b"xyz1", &[1, 2]));
+        let udta = boxed(b"udta", &boxed(b"\xa9too", &[]));
+        let moov = boxed(b"moov", &[trak0, trak1, udta].concat());
+        let data = [boxed(b"ftyp", &[0; 8]), moov, boxed(b"mdat", &[0; 3])].concat();
+
+        let mut reader = Cursor::new(&data);
+        let tree = BoxTree::read(&mut reader, data.len() as u64).unwrap();
+        assert_eq!(tree.boxes.len(), 3);
+
+        let xyz1 = tree.find("moov/trak[1]/xyz1").unwrap();
+        assert_eq!(xyz1.offset, 16 + 8 + 20 + 8);
+        assert_eq!(xyz1.header_size, 8);
+        assert_eq!(xyz1.payload_size, 2);
+        assert!(tree.find("moov/trak[2]").is_none());
+        assert!(tree.find("moov/trak/xyz1").is_none());
+        assert_eq!(tree.find("moov/udta/©too").unwrap().size(), 8);
+        assert_eq!(tree.find("mdat").unwrap().offset, data.len() as u64 - 11);
+
+        let mut types = Types(Vec::new());
+        tree.walk(&mut types);
+        let names: Vec<_> = types.0.iter().map(|(d, t)| format!("{d}{t}")).collect();
+        assert_eq!(
+            names,
+            ["0ftyp", "0moov", "1trak", "2tkhd", "1trak", "2xyz1", "1udta", "0mdat"]
+        );
+    }
+
+    #[test]
+    fn test_box_tree_find_index() {
+        let trak0 = boxed(b"trak", &boxed(b"tkhd", &[0]));
+        let trak1 = boxed(b"trak", &boxed(b"tkhd", &[1]));
+        let data = boxed(b"moov", &[trak0, trak1].concat());
+
+        let mut reader = Cursor::new(&data);
+        let tree = BoxTree::read(&mut reader, data.len() as u64).unwrap();
+        let first = tree.find("moov/trak[0]/tkhd").unwrap();
+        assert_eq!(first.offset, 16);
+        assert_eq!(tree.find("moov/trak/tkhd"), Some(first));
+        assert_eq!(tree.find("moov/trak[1]/tkhd").unwrap().offset, 16 + 17);
+        assert!(tree.find("moov/trak[2]").is_none());
+    }
+
+    #[test]
+    fn test_box_tree_invalid_size() {
+        let mut data = boxed(b"moov", &boxed(b"trak", &[]));
+        data[11] = 16;
+        let mut reader = Cursor::new(&data);
+        assert!(matches!(
+            BoxTree::read(&mut reader, data.len() as u64),
+            Err(Error::InvalidData(_))
+        ));
+    }
+}
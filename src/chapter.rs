@@ -0,0 +1,239 @@
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::io::{Seek, Write};
+use std::time::Duration;
+
+use crate::track::Mp4TrackWriter;
+use crate::*;
+
+/// A chapter marker of a movie.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Chapter {
+    /// Start of the chapter on the presentation timeline.
+    pub start_time: Duration,
+    pub title: String,
+}
+
+impl Chapter {
+    pub fn new(start_time: Duration, title: impl Into<String>) -> Self {
+        Self {
+            start_time,
+            title: title.into(),
+        }
+    }
+}
+
+const NMHD_BOX: BoxType = BoxType::UnknownBox(0x6e6d6864);
+
+// Text encoding box appended to QuickTime text samples, declaring UTF-8.
+const ENCD_UTF8: [u8; 12] = [
+    0x00, 0x00, 0x00, 0x0C, b'e', b'n', b'c', b'd', 0x00, 0x00, 0x01, 0x00,
+];
+
+/// Id of the chapter track, the first one referenced by a `tref/chap` box.
+pub(crate) fn chapter_track_id(moov: &MoovBox) -> Option<u32> {
+    moov.traks
+        .iter()
+        .filter_map(|trak| trak.tref.as_ref())
+        .find_map(|tref| tref.track_ids(TrackReferenceType::Chap).first().copied())
+}
+
+/// Text of a QuickTime text sample, a 16-bit length followed by UTF-8 text or
+/// UTF-16 text starting with a byte order mark.
+pub(crate) fn text_sample_title(bytes: &[u8]) -> String {
+    if bytes.len() < 2 {
+        return String::new();
+    }
+    let len = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+    let text = &bytes[2..bytes.len().min(2 + len)];
+    match text {
+        [0xFE, 0xFF, utf16 @ ..] => {
+            let units = utf16
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]));
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        [0xFF, 0xFE, utf16 @ ..] => {
+            let units = utf16
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]));
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        _ => String::from_utf8_lossy(text).into_owned(),
+    }
+}
+
+fn text_sample(title: &str) -> Bytes {
+    let title = truncate(title, u16::MAX as usize);
+    let mut buf = Vec::with_capacity(2 + title.len() + ENCD_UTF8.len());
+    buf.extend_from_slice(&(title.len() as u16).to_be_bytes());
+    buf.extend_from_slice(title.as_bytes());
+    buf.extend_from_slice(&ENCD_UTF8);
+    Bytes::from(buf)
+}
+
+/// Longest prefix of `s` of at most `max` bytes that ends on a character
+/// boundary.
+fn truncate(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+pub(crate) fn chapters_from_chpl(chpl: &ChplBox) -> Vec<Chapter> {
+    chpl.entries
+        .iter()
+        .map(|entry| {
+            Chapter::new(
+                Duration::from_nanos(entry.start_time.saturating_mul(100)),
+                entry.title.clone(),
+            )
+        })
+        .collect()
+}
+
+/// Nero chapter list of `chapters`, or `None` if there are too many chapters
+/// for it. Titles are shortened to 255 bytes.
+fn chpl_from_chapters(chapters: &[Chapter]) -> Option<ChplBox> {
+    if chapters.len() > u8::MAX as usize {
+        return None;
+    }
+    let entries = chapters
+        .iter()
+        .map(|chapter| ChplEntry {
+            start_time: (chapter.start_time.as_nanos() / 100) as u64,
+            title: truncate(&chapter.title, u8::MAX as usize).to_owned(),
+        })
+        .collect();
+    Some(ChplBox {
+        version: 1,
+        flags: 0,
+        entries,
+    })
+}
+
+/// Add `chapters` to `moov` both as a Nero `chpl` box and as a QuickTime
+/// chapter track, whose samples are written to `writer`. The audio and video
+/// tracks reference the chapter track through `tref/chap`.
+pub(crate) fn add_chapters<W: Write + Seek>(
+    moov: &mut MoovBox,
+    writer: &mut W,
+    chapters: &[Chapter],
+) -> Result<()> {
+    if chapters.is_empty() {
+        return Ok(());
+    }
+    let mut chapters = chapters.to_vec();
+    chapters.sort_by_key(|chapter| chapter.start_time);
+
+    let timescale = moov.mvhd.timescale;
+    let to_movie =
+        |time: Duration| -> u64 { (time.as_nanos() * timescale as u128 / 1_000_000_000) as u64 };
+
+    let track_id = moov
+        .traks
+        .iter()
+        .map(|trak| trak.tkhd.track_id)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let config = TrackConfig {
+        track_type: TrackType::Text,
+        timescale,
+        language: String::from("und"),
+        media_conf: MediaConfig::TtxtConfig(TtxtConfig::default()),
+        references: Vec::new(),
+    };
+    let mut track = Mp4TrackWriter::new(track_id, &config)?;
+
+    let first_start = to_movie(chapters[0].start_time);
+    if first_start > 0 {
+        track.set_edit_list(vec![Edit::empty(first_start), Edit::media(0, 0)]);
+    }
+    let movie_end = moov
+        .traks
+        .iter()
+        .map(|trak| trak.tkhd.duration)
+        .max()
+        .unwrap_or(0);
+    for (i, chapter) in chapters.iter().enumerate() {
+        let start = to_movie(chapter.start_time);
+        let end = match chapters.get(i + 1) {
+            Some(next) => to_movie(next.start_time),
+            None => movie_end.max(start + 1),
+        };
+        let sample = Mp4Sample {
+            start_time: start - first_start,
+            duration: u32::try_from(end - start)
+                .map_err(|_| Error::InvalidData("chapter is too long"))?,
+            rendering_offset: 0,
+            is_sync: true,
+            sample_description_index: 1,
+            bytes: text_sample(&chapter.title),
+        };
+        track.write_sample(writer, &sample, timescale)?;
+    }
+
+    let mut trak = track.write_end(writer, timescale)?;
+    // Chapter tracks are not presented on their own.
+    trak.tkhd.flags = 0;
+    if let Some(StsdEntry::Tx3g(tx3g)) = trak.mdia.minf.stbl.stsd.entries.first_mut() {
+        tx3g.data_reference_index = 1;
+    }
+    // The media header goes before dinf.
+    let minf = &mut trak.mdia.minf;
+    minf.unknown_boxes.push(UnknownBox {
+        position: Some(0),
+        ..UnknownBox::new(NMHD_BOX, vec![0; 4])
+    });
+
+    for trak in moov.traks.iter_mut() {
+        let track_type = TrackType::try_from(&trak.mdia.hdlr.handler_type);
+        if matches!(track_type, Ok(TrackType::Audio) | Ok(TrackType::Video)) {
+            trak.tref
+                .get_or_insert_with(TrefBox::default)
+                .add(TrackReferenceType::Chap, track_id);
+        }
+    }
+    moov.traks.push(trak);
+
+    moov.udta.get_or_insert_with(UdtaBox::default).chpl = chpl_from_chapters(&chapters);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_sample() {
+        let sample = text_sample("Chapitre 1 — Début");
+        assert_eq!(text_sample_title(&sample), "Chapitre 1 — Début");
+        assert!(sample.ends_with(&ENCD_UTF8));
+
+        let utf16 = [0x00, 0x06, 0xFE, 0xFF, 0x00, 0x48, 0x00, 0x69];
+        assert_eq!(text_sample_title(&utf16), "Hi");
+    }
+
+    #[test]
+    fn test_chpl_from_chapters() {
+        let long_title = "é".repeat(200);
+        let chapters = [
+            Chapter::new(Duration::ZERO, "Intro"),
+            Chapter::new(Duration::from_millis(1500), long_title),
+        ];
+        let chpl = chpl_from_chapters(&chapters).unwrap();
+        assert_eq!(chpl.entries[1].start_time, 15_000_000);
+        assert_eq!(chpl.entries[1].title.len(), 254);
+        assert_eq!(chapters_from_chpl(&chpl)[0], chapters[0]);
+
+        let chapters = vec![Chapter::new(Duration::ZERO, ""); 256];
+        assert!(chpl_from_chapters(&chapters).is_none());
+    }
+}
@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::convert::TryFrom;
+
+use crate::mp4box::elst::{ElstBox, ElstEntry};
+
+/// One entry of a track's edit list (`elst`), mapping a span of the
+/// presentation timeline onto the media timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Edit {
+    /// Duration of the edit on the presentation timeline, in movie timescale
+    /// units.
+    pub duration: u64,
+
+    /// Media time the edit starts at, in media timescale units. `None` for an
+    /// empty edit, which presents nothing for `duration`.
+    pub media_time: Option<u64>,
+
+    /// Playback rate of the edit. `1` plays the media normally, `0` is a dwell
+    /// that holds `media_time` for `duration`.
+    pub media_rate: u16,
+}
+
+impl Edit {
+    /// An edit that plays the media from `media_time` for `duration`.
+    pub fn media(media_time: u64, duration: u64) -> Self {
+        Self {
+            duration,
+            media_time: Some(media_time),
+            media_rate: 1,
+        }
+    }
+
+    /// An edit that presents nothing for `duration`, delaying what follows.
+    pub fn empty(duration: u64) -> Self {
+        Self {
+            duration,
+            media_time: None,
+            media_rate: 1,
+        }
+    }
+
+    /// An edit that holds the frame at `media_time` for `duration`.
+    pub fn dwell(media_time: u64, duration: u64) -> Self {
+        Self {
+            duration,
+            media_time: Some(media_time),
+            media_rate: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.media_time.is_none()
+    }
+
+    pub fn is_dwell(&self) -> bool {
+        self.media_time.is_some() && self.media_rate == 0
+    }
+}
+
+impl From<&ElstEntry> for Edit {
+    fn from(entry: &ElstEntry) -> Self {
+        Self {
+            duration: entry.segment_duration,
+            media_time: u64::try_from(entry.media_time).ok(),
+            media_rate: entry.media_rate,
+        }
+    }
+}
+
+impl From<&Edit> for ElstEntry {
+    fn from(edit: &Edit) -> Self {
+        Self {
+            segment_duration: edit.duration,
+            media_time: edit.media_time.map_or(-1, |t| t as i64),
+            media_rate: edit.media_rate,
+            media_rate_fraction: 0,
+        }
+    }
+}
+
+pub(crate) fn elst_from_edits(edits: &[Edit]) -> ElstBox {
+    let entries: Vec<ElstEntry> = edits.iter().map(ElstEntry::from).collect();
+    let version = if entries.iter().any(|entry| {
+        entry.segment_duration > u32::MAX as u64
+            || entry.media_time > i32::MAX as i64
+            || entry.media_time < -1
+    }) {
+        1
+    } else {
+        0
+    };
+    ElstBox {
+        version,
+        flags: 0,
+        entries,
+    }
+}
+
+/// Map a media time onto the presentation timeline described by `edits`.
+///
+/// Edit durations are converted from `movie_timescale` into
+/// `media_timescale`, so both the argument and the result are in media
+/// timescale units. Returns `None` if no edit presents `media_time`. A normal
+/// edit with a zero duration extends to the end of the media, as in
+/// fragmented files.
+pub(crate) fn presentation_time(
+    edits: &[Edit],
+    media_time: u64,
+    movie_timescale: u32,
+    media_timescale: u32,
+) -> Option<u64> {
+    let to_media = |duration: u64| -> u64 {
+        if movie_timescale == 0 {
+            duration
+        } else {
+            (duration as u128 * media_timescale as u128 / movie_timescale as u128) as u64
+        }
+    };
+
+    let mut presentation_start = 0u64;
+    for edit in edits {
+        let duration = to_media(edit.duration);
+        if let Some(start) = edit.media_time {
+            if edit.is_dwell() {
+                if media_time == start {
+                    return Some(presentation_start);
+                }
+            } else if media_time >= start && (edit.duration == 0 || media_time - start < duration) {
+                return Some(presentation_start + (media_time - start));
+            }
+        }
+        presentation_start += duration;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presentation_time() {
+        // Media before the start of the edit is not presented.
+        let edits = [Edit::media(1024, 90_000)];
+        assert_eq!(presentation_time(&edits, 1023, 1000, 48000), None);
+        assert_eq!(presentation_time(&edits, 1024, 1000, 48000), Some(0));
+        assert_eq!(presentation_time(&edits, 2048, 1000, 48000), Some(1024));
+
+        // Empty edit of half a second, then a one second dwell.
+        let edits = [Edit::empty(500), Edit::dwell(0, 1000), Edit::media(0, 2000)];
+        assert_eq!(presentation_time(&edits, 0, 1000, 90000), Some(45000));
+        assert_eq!(presentation_time(&edits, 9000, 1000, 90000), Some(144000));
+        assert_eq!(presentation_time(&edits, 180000, 1000, 90000), None);
+
+        // Zero duration extends to the end of the media.
+        let edits = [Edit::media(2, 0)];
+        assert_eq!(
+            presentation_time(&edits, 1_000_000, 1000, 90000),
+            Some(999_998)
+        );
+    }
+
+    #[test]
+    fn test_elst_from_edits() {
+        let edits = [Edit::empty(500), Edit::media(1024, 10000)];
+        let elst = elst_from_edits(&edits);
+        assert_eq!(elst.version, 0);
+        assert_eq!(elst.entries[0].media_time, -1);
+        let read: Vec<Edit> = elst.entries.iter().map(Edit::from).collect();
+        assert_eq!(read, edits);
+
+        let elst = elst_from_edits(&[Edit::media(0, u32::MAX as u64 + 1)]);
+        assert_eq!(elst.version, 1);
+    }
+}
@@ -0,0 +1,278 @@
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use crate::faststart::{read_top_level_boxes, TopLevelBox};
+use crate::mp4box::*;
+use crate::*;
+
+/// Edits the `moov` box of an existing file without rewriting the media data.
+///
+/// [`save`](Mp4Editor::save) writes the new `moov` over the old one when it
+/// fits into the space of the old `moov` and any `free` boxes around it,
+/// padding the rest with a `free` box. Otherwise the new `moov` is appended to
+/// the end of the file and the old one is turned into a `free` box. `mdat`
+/// boxes are never moved or written, so chunk offsets stay valid.
+///
+/// Child boxes of `moov` that were not edited are copied from the file as is,
+/// so anything the typed boxes do not keep is preserved.
+#[derive(Debug)]
+pub struct Mp4Editor<F> {
+    file: F,
+    boxes: Vec<TopLevelBox>,
+    pub moov: MoovBox,
+
+    // The moov as read and the type and bytes of each of its child boxes.
+    original: MoovBox,
+    children: Vec<(BoxType, Vec<u8>)>,
+}
+
+impl<F: Read + Write + Seek> Mp4Editor<F> {
+    pub fn new(mut file: F) -> Result<Self> {
+        let boxes = read_top_level_boxes(&mut file)?;
+        let moov = boxes
+            .iter()
+            .find(|b| b.name == BoxType::MoovBox)
+            .ok_or(Error::BoxNotFound(BoxType::MoovBox))?;
+        let end = moov.offset + moov.size;
+        file.seek(SeekFrom::Start(moov.offset))?;
+        let header = BoxHeader::read(&mut file)?;
+        let start = file.stream_position()?;
+        let moov = MoovBox::read_box(&mut file, header.size)?;
+        let children = read_children(&mut file, start, end)?;
+
+        Ok(Mp4Editor {
+            file,
+            boxes,
+            original: moov.clone(),
+            moov,
+            children,
+        })
+    }
+
+    pub fn metadata(&self) -> impl Metadata<'_> {
+        self.moov.udta.as_ref().and_then(|udta| {
+            udta.meta.as_ref().and_then(|meta| match meta {
+                MetaBox::Mdir { ilst, .. } => ilst.as_ref(),
+                _ => None,
+            })
+        })
+    }
+
+    /// The `moov/udta/meta/ilst` box, created if the file has none.
+    pub fn ilst_mut(&mut self) -> Result<&mut IlstBox> {
+        let udta = self.moov.udta.get_or_insert_with(UdtaBox::default);
+        let meta = udta.meta.get_or_insert(MetaBox::Mdir {
+            ilst: None,
+            unknown_boxes: Vec::new(),
+        });
+        match meta {
+            MetaBox::Mdir { ilst, .. } => Ok(ilst.get_or_insert_with(IlstBox::default)),
+            MetaBox::Unknown { .. } => Err(Error::InvalidData(
+                "udta meta box has an unsupported handler type",
+            )),
+        }
+    }
+
+    /// Writes the edited `moov` box to the file.
+    pub fn save(&mut self) -> Result<()> {
+        let index = self
+            .boxes
+            .iter()
+            .position(|b| b.name == BoxType::MoovBox)
+            .ok_or(Error::BoxNotFound(BoxType::MoovBox))?;
+
+        // The old moov and the free space next to it can be reused.
+        let is_free = |b: &TopLevelBox| b.name == BoxType::FreeBox || b.name == SKIP_BOX;
+        let mut first = index;
+        while first > 0 && is_free(&self.boxes[first - 1]) {
+            first -= 1;
+        }
+        let mut last = index;
+        while last + 1 < self.boxes.len() && is_free(&self.boxes[last + 1]) {
+            last += 1;
+        }
+        let start = self.boxes[first].offset;
+        let available = self.boxes[last].offset + self.boxes[last].size - start;
+        let at_end = last + 1 == self.boxes.len();
+
+        let moov = self.moov_bytes()?;
+        let size = moov.len() as u64;
+
+        if size == available || size + HEADER_SIZE <= available || at_end {
+            self.file.seek(SeekFrom::Start(start))?;
+            self.file.write_all(&moov)?;
+            if size < available {
+                // At the end of the file the padding may run past the old end.
+                let mut free = available - size;
+                if free < HEADER_SIZE {
+                    free += HEADER_SIZE;
+                }
+                write_free_box(&mut self.file, free)?;
+            }
+        } else {
+            if self.moov.mvex.is_some() || self.boxes.iter().any(|b| b.name == BoxType::MoofBox) {
+                return Err(Error::InvalidData(
+                    "moov of a fragmented file cannot be moved",
+                ));
+            }
+            let last_box = self.boxes[self.boxes.len() - 1];
+            self.file.seek(SeekFrom::Start(last_box.offset))?;
+            if BoxHeader::read(&mut self.file)?.size == 0 {
+                return Err(Error::InvalidData(
+                    "last box extends to the end of the file",
+                ));
+            }
+
+            // Write the new moov before giving up the old one.
+            self.file
+                .seek(SeekFrom::Start(last_box.offset + last_box.size))?;
+            self.file.write_all(&moov)?;
+            self.file.flush()?;
+            self.file.seek(SeekFrom::Start(start))?;
+            write_free_box(&mut self.file, available)?;
+        }
+        self.file.flush()?;
+
+        self.boxes = read_top_level_boxes(&mut self.file)?;
+        self.original = self.moov.clone();
+        let mut reader = Cursor::new(&moov);
+        BoxHeader::read(&mut reader)?;
+        let start = reader.position();
+        self.children = read_children(&mut reader, start, size)?;
+        Ok(())
+    }
+
+    /// The edited moov. Child boxes equal to the ones read are copied from the
+    /// file; the others are written from the typed boxes.
+    fn moov_bytes(&self) -> Result<Vec<u8>> {
+        let moov = &self.moov;
+        let original = &self.original;
+        let mut buf = Vec::new();
+        if moov.traks.len() != original.traks.len()
+            || moov.psshs != original.psshs
+            || moov.unknown_boxes != original.unknown_boxes
+        {
+            moov.write_box(&mut buf)?;
+            return Ok(buf);
+        }
+
+        let mut mvhd = Some((&moov.mvhd, &original.mvhd));
+        let mut meta = Some((moov.meta.as_ref(), original.meta.as_ref()));
+        let mut mvex = Some((moov.mvex.as_ref(), original.mvex.as_ref()));
+        let mut udta = Some((moov.udta.as_ref(), original.udta.as_ref()));
+        let mut traks = moov.traks.iter().zip(original.traks.iter());
+        for (name, data) in self.children.iter() {
+            match *name {
+                BoxType::MvhdBox => {
+                    if let Some((mvhd, original)) = mvhd.take() {
+                        write_child(&mut buf, Some(mvhd), Some(original), data)?;
+                    }
+                }
+                BoxType::MetaBox => {
+                    if let Some((meta, original)) = meta.take() {
+                        write_child(&mut buf, meta, original, data)?;
+                    }
+                }
+                BoxType::MvexBox => {
+                    if let Some((mvex, original)) = mvex.take() {
+                        write_child(&mut buf, mvex, original, data)?;
+                    }
+                }
+                BoxType::TrakBox => {
+                    if let Some((trak, original)) = traks.next() {
+                        write_child(&mut buf, Some(trak), Some(original), data)?;
+                    }
+                }
+                BoxType::UdtaBox => {
+                    if let Some((udta, original)) = udta.take() {
+                        write_child(&mut buf, udta, original, data)?;
+                    }
+                }
+                _ => buf.extend_from_slice(data),
+            }
+        }
+
+        // Boxes the file did not have.
+        if let Some((mvhd, _)) = mvhd {
+            mvhd.write_box(&mut buf)?;
+        }
+        if let Some((Some(meta), _)) = meta {
+            meta.write_box(&mut buf)?;
+        }
+        if let Some((Some(mvex), _)) = mvex {
+            mvex.write_box(&mut buf)?;
+        }
+        if let Some((Some(udta), _)) = udta {
+            udta.write_box(&mut buf)?;
+        }
+
+        let mut moov = Vec::with_capacity(buf.len() + HEADER_SIZE as usize);
+        let mut size = buf.len() as u64 + HEADER_SIZE;
+        if size > u32::MAX as u64 {
+            size += 8;
+        }
+        BoxHeader::new(BoxType::MoovBox, size).write(&mut moov)?;
+        moov.extend_from_slice(&buf);
+        Ok(moov)
+    }
+
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+}
+
+/// Reads the child boxes between `start` and `end`, headers included.
+fn read_children<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+) -> Result<Vec<(BoxType, Vec<u8>)>> {
+    let mut children = Vec::new();
+    let mut current = start;
+    while current < end {
+        reader.seek(SeekFrom::Start(current))?;
+        let BoxHeader { name, size } = BoxHeader::read(reader)?;
+        if size < HEADER_SIZE || size > end - current {
+            return Err(Error::InvalidData("moov child box has an invalid size"));
+        }
+        let mut data = vec![0; size as usize];
+        reader.seek(SeekFrom::Start(current))?;
+        reader.read_exact(&mut data)?;
+        children.push((name, data));
+        current += size;
+    }
+    Ok(children)
+}
+
+/// Copies `data`, the child box as read, if `child` is unchanged and writes
+/// `child` otherwise.
+fn write_child<B>(
+    buf: &mut Vec<u8>,
+    child: Option<&B>,
+    original: Option<&B>,
+    data: &[u8],
+) -> Result<()>
+where
+    B: PartialEq + for<'a> WriteBox<&'a mut Vec<u8>>,
+{
+    if child == original {
+        buf.extend_from_slice(data);
+    } else if let Some(child) = child {
+        child.write_box(buf)?;
+    }
+    Ok(())
+}
+
+const SKIP_BOX: BoxType = BoxType::UnknownBox(0x736b6970);
+
+/// Writes a zero filled `free` box of `size` bytes.
+fn write_free_box<W: Write>(writer: &mut W, size: u64) -> Result<()> {
+    let header_size = BoxHeader::new(BoxType::FreeBox, size).write(writer)?;
+    let zeros = [0u8; 4096];
+    let mut remaining = size - header_size;
+    while remaining > 0 {
+        let len = remaining.min(zeros.len() as u64) as usize;
+        writer.write_all(&zeros[..len])?;
+        remaining -= len as u64;
+    }
+    Ok(())
+}
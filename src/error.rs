@@ -27,5 +27,5 @@ pub enum Error {
     #[error("{0} version {1} is not supported")]
     UnsupportedBoxVersion(BoxType, u8),
     #[error("{0}")]
-    Utf8Error(#[from] std::string::FromUtf8Error),    
+    Utf8Error(#[from] std::string::FromUtf8Error),
 }
@@ -0,0 +1,212 @@
+use std::cmp;
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::mp4box::*;
+use crate::*;
+
+const COPY_BUFFER_SIZE: u64 = 1 << 20;
+
+/// Rewrite every chunk offset in `moov` through `relocate`.
+///
+/// Each track ends up with an `stco` box when all of its offsets fit in 32 bits
+/// and with a `co64` box otherwise.
+pub(crate) fn relocate_chunk_offsets<F>(moov: &mut MoovBox, relocate: F) -> Result<()>
+where
+    F: Fn(u64) -> Result<u64>,
+{
+    for trak in moov.traks.iter_mut() {
+        let stbl = &mut trak.mdia.minf.stbl;
+        let mut co64 = if let Some(ref co64) = stbl.co64 {
+            co64.clone()
+        } else if let Some(ref stco) = stbl.stco {
+            Co64Box::from(stco)
+        } else {
+            continue;
+        };
+        for offset in co64.entries.iter_mut() {
+            *offset = relocate(*offset)?;
+        }
+
+        if let Ok(stco) = StcoBox::try_from(&co64) {
+            stbl.stco = Some(stco);
+            stbl.co64 = None;
+        } else {
+            stbl.stco = None;
+            stbl.co64 = Some(co64);
+        }
+    }
+    Ok(())
+}
+
+/// Relocate the chunk offsets of a `moov` box that is about to be written in
+/// front of the media data.
+///
+/// `relocate` receives the final size of the `moov` box and an original chunk
+/// offset. Switching a track from `stco` to `co64` grows the `moov` box, which
+/// in turn moves the media data again, so the layout is repeated until the
+/// size settles.
+pub(crate) fn relocate_moov<F>(moov: &MoovBox, relocate: F) -> Result<MoovBox>
+where
+    F: Fn(u64, u64) -> Result<u64>,
+{
+    let mut moov_size = moov.box_size();
+    loop {
+        let mut relocated = moov.clone();
+        relocate_chunk_offsets(&mut relocated, |offset| relocate(moov_size, offset))?;
+        let size = relocated.box_size();
+        if size == moov_size {
+            return Ok(relocated);
+        }
+        moov_size = size;
+    }
+}
+
+/// Move the bytes in `start..end` forward by `shift` bytes, starting from the
+/// end so that the source is never overwritten before it has been copied.
+pub(crate) fn shift_bytes<T: Read + Write + Seek>(
+    stream: &mut T,
+    start: u64,
+    end: u64,
+    shift: u64,
+) -> Result<()> {
+    let mut buf = vec![0u8; cmp::min(COPY_BUFFER_SIZE, end - start) as usize];
+    let mut pos = end;
+    while pos > start {
+        let len = cmp::min(buf.len() as u64, pos - start);
+        pos -= len;
+        stream.seek(SeekFrom::Start(pos))?;
+        stream.read_exact(&mut buf[..len as usize])?;
+        stream.seek(SeekFrom::Start(pos + shift))?;
+        stream.write_all(&buf[..len as usize])?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TopLevelBox {
+    pub(crate) name: BoxType,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+}
+
+pub(crate) fn read_top_level_boxes<R: Read + Seek>(reader: &mut R) -> Result<Vec<TopLevelBox>> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    let mut boxes = Vec::new();
+    let mut offset = 0;
+    while offset < end {
+        reader.seek(SeekFrom::Start(offset))?;
+        let header = BoxHeader::read(reader)?;
+        let size = if header.size == 0 {
+            end - offset
+        } else if reader.stream_position()? - offset > HEADER_SIZE {
+            // 64-bit largesize; BoxHeader::read excludes the extra 8 bytes.
+            header.size + 8
+        } else {
+            header.size
+        };
+        if size < HEADER_SIZE || offset + size > end {
+            return Err(Error::InvalidData(
+                "file contains a box with a larger size than it",
+            ));
+        }
+        boxes.push(TopLevelBox {
+            name: header.name,
+            offset,
+            size,
+        });
+        offset += size;
+    }
+    Ok(boxes)
+}
+
+/// Rewrite the file behind `mp4` with the `moov` box in front of the media
+/// data, so it can start playing before it is fully downloaded.
+///
+/// The output starts with `ftyp` and `moov`, followed by every other top-level
+/// box of the source in its original order. Chunk offsets are adjusted to the
+/// new layout, switching to `co64` where they no longer fit in 32 bits.
+/// Fragmented files are not supported.
+pub fn optimize_for_streaming<R, W>(mp4: &mut Mp4Reader<R>, writer: &mut W) -> Result<()>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    if mp4.is_fragmented() {
+        return Err(Error::InvalidData(
+            "fragmented files cannot be optimized for streaming",
+        ));
+    }
+
+    let boxes = read_top_level_boxes(&mut mp4.reader)?;
+    let ftyp = boxes
+        .iter()
+        .find(|b| b.name == BoxType::FtypBox)
+        .ok_or(Error::BoxNotFound(BoxType::FtypBox))?;
+    let rest: Vec<TopLevelBox> = boxes
+        .iter()
+        .filter(|b| b.name != BoxType::FtypBox && b.name != BoxType::MoovBox)
+        .copied()
+        .collect();
+
+    let moov = relocate_moov(&mp4.moov, |moov_size, offset| {
+        let mut new_offset = ftyp.size + moov_size;
+        for b in rest.iter() {
+            if offset >= b.offset && offset < b.offset + b.size {
+                return Ok(new_offset + (offset - b.offset));
+            }
+            new_offset += b.size;
+        }
+        Err(Error::InvalidData(
+            "chunk offset outside of any top-level box",
+        ))
+    })?;
+
+    copy_box(&mut mp4.reader, writer, ftyp)?;
+    moov.write_box(writer)?;
+    for b in rest.iter() {
+        copy_box(&mut mp4.reader, writer, b)?;
+    }
+    Ok(())
+}
+
+fn copy_box<R: Read + Seek, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    b: &TopLevelBox,
+) -> Result<()> {
+    reader.seek(SeekFrom::Start(b.offset))?;
+    let copied = io::copy(&mut reader.take(b.size), writer)?;
+    if copied != b.size {
+        return Err(Error::InvalidData("unexpected end of file"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relocate_moov_switches_to_co64() {
+        let mut trak = TrakBox::default();
+        trak.mdia.minf.stbl.stco = Some(StcoBox {
+            version: 0,
+            flags: 0,
+            entries: vec![100, u32::MAX - 10],
+        });
+        let moov = MoovBox {
+            traks: vec![trak],
+            ..MoovBox::default()
+        };
+
+        let relocated = relocate_moov(&moov, |moov_size, offset| Ok(offset + moov_size)).unwrap();
+        let stbl = &relocated.traks[0].mdia.minf.stbl;
+        assert!(stbl.stco.is_none());
+        let moov_size = relocated.box_size();
+        assert_eq!(
+            stbl.co64.as_ref().unwrap().entries,
+            vec![100 + moov_size, u32::MAX as u64 - 10 + moov_size]
+        );
+    }
+}
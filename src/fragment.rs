@@ -0,0 +1,505 @@
+use bytes::BytesMut;
+use std::convert::TryFrom;
+use std::io::Write;
+
+use crate::mp4box::*;
+use crate::track::new_trak;
+use crate::*;
+
+/// Controls how [Mp4FragmentWriter] cuts the incoming samples into fragments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mp4FragmentConfig {
+    /// Target duration of a fragment, in movie timescale units.
+    pub fragment_duration: u64,
+
+    /// Only start a new fragment on a sync sample of the reference track.
+    pub split_on_sync: bool,
+
+    /// Index the fragments with a `sidx` box placed after the init segment,
+    /// as the DASH on-demand profiles require. The fragments are then held in
+    /// memory until [Mp4FragmentWriter::write_end].
+    pub write_sidx: bool,
+
+    /// Append an `mfra` box indexing the sync samples of every fragment, so
+    /// that readers can seek without parsing every `moof` box.
+    pub write_mfra: bool,
+}
+
+impl Default for Mp4FragmentConfig {
+    fn default() -> Self {
+        Self {
+            fragment_duration: 2000,
+            split_on_sync: true,
+            write_sidx: false,
+            write_mfra: false,
+        }
+    }
+}
+
+const SAMPLE_FLAGS_SYNC: u32 = 0x02000000; // sample_depends_on = 2
+const SAMPLE_FLAGS_NON_SYNC: u32 = 0x01010000; // sample_depends_on = 1, is_non_sync_sample
+
+#[derive(Debug)]
+struct FragmentTrackWriter {
+    trak: TrakBox,
+    base_media_decode_time: u64,
+
+    sample_durations: Vec<u32>,
+    sample_sizes: Vec<u32>,
+    sample_flags: Vec<u32>,
+    sample_cts: Vec<i32>,
+    data: BytesMut,
+}
+
+impl FragmentTrackWriter {
+    fn new(track_id: u32, config: &TrackConfig) -> Result<Self> {
+        let mut trak = new_trak(track_id, config)?;
+        trak.mdia.minf.stbl.stco = Some(StcoBox::default());
+        Ok(Self {
+            trak,
+            base_media_decode_time: 0,
+            sample_durations: Vec::new(),
+            sample_sizes: Vec::new(),
+            sample_flags: Vec::new(),
+            sample_cts: Vec::new(),
+            data: BytesMut::new(),
+        })
+    }
+
+    fn track_id(&self) -> u32 {
+        self.trak.tkhd.track_id
+    }
+
+    fn timescale(&self) -> u32 {
+        self.trak.mdia.mdhd.timescale
+    }
+
+    fn is_empty(&self) -> bool {
+        self.sample_sizes.is_empty()
+    }
+
+    fn pending_duration(&self) -> u64 {
+        self.sample_durations.iter().map(|d| *d as u64).sum()
+    }
+
+    /// Index and composition time of the first buffered sync sample.
+    fn first_sync_sample(&self) -> Option<(usize, u64)> {
+        let idx = self
+            .sample_flags
+            .iter()
+            .position(|flags| *flags == SAMPLE_FLAGS_SYNC)?;
+        let decode_time = self.base_media_decode_time
+            + self.sample_durations[..idx]
+                .iter()
+                .map(|d| *d as u64)
+                .sum::<u64>();
+        let time = (decode_time as i64 + self.sample_cts[idx] as i64).max(0) as u64;
+        Some((idx, time))
+    }
+
+    /// Earliest composition time of the buffered samples.
+    fn earliest_presentation_time(&self) -> u64 {
+        let mut decode_time = self.base_media_decode_time as i64;
+        let mut earliest = i64::MAX;
+        for (duration, cts) in self.sample_durations.iter().zip(self.sample_cts.iter()) {
+            earliest = earliest.min(decode_time + *cts as i64);
+            decode_time += *duration as i64;
+        }
+        earliest.max(0) as u64
+    }
+
+    fn push_sample(&mut self, sample: &Mp4Sample) {
+        self.sample_durations.push(sample.duration);
+        self.sample_sizes.push(sample.bytes.len() as u32);
+        self.sample_flags.push(if sample.is_sync {
+            SAMPLE_FLAGS_SYNC
+        } else {
+            SAMPLE_FLAGS_NON_SYNC
+        });
+        self.sample_cts.push(sample.rendering_offset);
+        self.data.extend_from_slice(&sample.bytes);
+    }
+
+    fn build_traf(&self) -> TrafBox {
+        let mut flags = TrunBox::FLAG_DATA_OFFSET
+            | TrunBox::FLAG_SAMPLE_DURATION
+            | TrunBox::FLAG_SAMPLE_SIZE
+            | TrunBox::FLAG_SAMPLE_FLAGS;
+        if self.sample_cts.iter().any(|cts| *cts != 0) {
+            flags |= TrunBox::FLAG_SAMPLE_CTS;
+        }
+        let version = if self.sample_cts.iter().any(|cts| *cts < 0) {
+            1
+        } else {
+            0
+        };
+
+        let trun = TrunBox {
+            version,
+            flags,
+            sample_count: self.sample_sizes.len() as u32,
+            data_offset: Some(0),
+            first_sample_flags: None,
+            sample_durations: self.sample_durations.clone(),
+            sample_sizes: self.sample_sizes.clone(),
+            sample_flags: self.sample_flags.clone(),
+            sample_cts: self.sample_cts.iter().map(|cts| *cts as u32).collect(),
+        };
+
+        TrafBox {
+            tfhd: TfhdBox {
+                flags: TfhdBox::FLAG_DEFAULT_BASE_IS_MOOF,
+                track_id: self.track_id(),
+                ..TfhdBox::default()
+            },
+            tfdt: Some(TfdtBox {
+                version: 1,
+                flags: 0,
+                base_media_decode_time: self.base_media_decode_time,
+            }),
+            trun: Some(trun),
+            ..TrafBox::default()
+        }
+    }
+
+    fn clear(&mut self) {
+        self.base_media_decode_time += self.pending_duration();
+        self.sample_durations.clear();
+        self.sample_sizes.clear();
+        self.sample_flags.clear();
+        self.sample_cts.clear();
+        self.data.clear();
+    }
+}
+
+/// Writes fragmented MP4 (fMP4/CMAF) output.
+///
+/// An init segment (`ftyp` + `moov` with `mvex`) is emitted before the first
+/// sample, followed by `moof` + `mdat` pairs. A new fragment is started once
+/// the reference track (the first video track, or the first track if there is
+/// no video) has buffered [Mp4FragmentConfig::fragment_duration] worth of
+/// samples. The writer never seeks, so any [Write] sink can be used.
+///
+/// With [Mp4FragmentConfig::write_sidx], a `sidx` box with one reference per
+/// fragment is written between the init segment and the first fragment. With
+/// [Mp4FragmentConfig::write_mfra], an `mfra` box is appended by
+/// [Mp4FragmentWriter::write_end]; its offsets assume the output starts at
+/// the beginning of the file.
+#[derive(Debug)]
+pub struct Mp4FragmentWriter<W> {
+    writer: W,
+    tracks: Vec<FragmentTrackWriter>,
+    timescale: u32,
+    fragment_config: Mp4FragmentConfig,
+    sequence_number: u32,
+    init_written: bool,
+
+    // Index and data of the fragments held back until write_end.
+    sidx: Option<SidxBox>,
+    fragments: Vec<u8>,
+
+    mfra: Option<MfraBox>,
+    // Bytes output so far, counting held back fragments but not the sidx box.
+    position: u64,
+}
+
+impl<W> Mp4FragmentWriter<W> {
+    /// Consume self, returning the inner writer.
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Mp4FragmentWriter<W> {
+    pub fn write_start(
+        mut writer: W,
+        config: &Mp4Config,
+        fragment_config: &Mp4FragmentConfig,
+    ) -> Result<Self> {
+        let ftyp = FtypBox {
+            major_brand: config.major_brand,
+            minor_version: config.minor_version,
+            compatible_brands: config.compatible_brands.clone(),
+        };
+        ftyp.write_box(&mut writer)?;
+        let position = ftyp.box_size();
+
+        Ok(Self {
+            writer,
+            tracks: Vec::new(),
+            timescale: config.timescale,
+            fragment_config: fragment_config.clone(),
+            sequence_number: 1,
+            init_written: false,
+            sidx: None,
+            fragments: Vec::new(),
+            mfra: None,
+            position,
+        })
+    }
+
+    pub fn add_track(&mut self, config: &TrackConfig) -> Result<()> {
+        if self.init_written {
+            return Err(Error::InvalidData(
+                "tracks must be added before the first sample is written",
+            ));
+        }
+        let track_id = self.tracks.len() as u32 + 1;
+        self.tracks
+            .push(FragmentTrackWriter::new(track_id, config)?);
+        Ok(())
+    }
+
+    fn reference_track_idx(&self) -> usize {
+        self.tracks
+            .iter()
+            .position(|track| {
+                matches!(
+                    TrackType::try_from(&track.trak.mdia.hdlr.handler_type),
+                    Ok(TrackType::Video)
+                )
+            })
+            .unwrap_or(0)
+    }
+
+    /// Write the init segment (`moov` with an `mvex` box). This is done
+    /// automatically before the first fragment.
+    pub fn write_init_segment(&mut self) -> Result<()> {
+        if self.init_written {
+            return Ok(());
+        }
+
+        let mut moov = MoovBox::default();
+        moov.mvhd.timescale = self.timescale;
+        moov.mvhd.next_track_id = self.tracks.len() as u32 + 1;
+
+        let mut mvex = MvexBox::default();
+        for track in self.tracks.iter() {
+            moov.traks.push(track.trak.clone());
+            mvex.trexs.push(TrexBox {
+                track_id: track.track_id(),
+                default_sample_description_index: 1,
+                ..TrexBox::default()
+            });
+        }
+        moov.mvex = Some(mvex);
+        moov.write_box(&mut self.writer)?;
+        self.position += moov.box_size();
+
+        if self.fragment_config.write_sidx && !self.tracks.is_empty() {
+            let reference_track = &self.tracks[self.reference_track_idx()];
+            self.sidx = Some(SidxBox {
+                reference_id: reference_track.track_id(),
+                timescale: reference_track.timescale(),
+                ..SidxBox::default()
+            });
+        }
+        if self.fragment_config.write_mfra {
+            self.mfra = Some(MfraBox {
+                tfras: self
+                    .tracks
+                    .iter()
+                    .map(|track| TfraBox {
+                        track_id: track.track_id(),
+                        ..TfraBox::default()
+                    })
+                    .collect(),
+                mfro: MfroBox::default(),
+                unknown_boxes: Vec::new(),
+            });
+        }
+
+        self.init_written = true;
+        Ok(())
+    }
+
+    fn should_split(&self, track_idx: usize, sample: &Mp4Sample) -> bool {
+        if track_idx != self.reference_track_idx() {
+            return false;
+        }
+        let track = &self.tracks[track_idx];
+        if track.is_empty() {
+            return false;
+        }
+        if self.fragment_config.split_on_sync && !sample.is_sync {
+            return false;
+        }
+        track.pending_duration() * self.timescale as u64
+            >= self.fragment_config.fragment_duration * track.timescale() as u64
+    }
+
+    /// Buffer a sample of a track, writing the pending fragment first if the
+    /// sample starts a new one.
+    ///
+    /// Every track has a single sample description, so samples with a
+    /// [Mp4Sample::sample_description_index] other than 1 are rejected.
+    pub fn write_sample(&mut self, track_id: u32, sample: &Mp4Sample) -> Result<()> {
+        if track_id == 0 || track_id as usize > self.tracks.len() {
+            return Err(Error::TrakNotFound(track_id));
+        }
+        if sample.sample_description_index != 1 {
+            return Err(Error::InvalidData(
+                "fragmented tracks only support sample description index 1",
+            ));
+        }
+        self.write_init_segment()?;
+
+        let track_idx = track_id as usize - 1;
+        if self.should_split(track_idx, sample) {
+            self.write_fragment()?;
+        }
+        self.tracks[track_idx].push_sample(sample);
+        Ok(())
+    }
+
+    /// Write all buffered samples as one `moof` + `mdat` pair, regardless of
+    /// the configured fragment duration.
+    pub fn write_fragment(&mut self) -> Result<()> {
+        self.write_init_segment()?;
+        if self.tracks.iter().all(|track| track.is_empty()) {
+            return Ok(());
+        }
+
+        let mut moof = MoofBox {
+            mfhd: MfhdBox {
+                sequence_number: self.sequence_number,
+                ..MfhdBox::default()
+            },
+            ..MoofBox::default()
+        };
+        let mut data_len = 0;
+        for track in self.tracks.iter().filter(|track| !track.is_empty()) {
+            moof.trafs.push(track.build_traf());
+            data_len += track.data.len() as u64;
+        }
+
+        let mdat_header_size = if data_len + HEADER_SIZE > u32::MAX as u64 {
+            HEADER_SIZE + 8
+        } else {
+            HEADER_SIZE
+        };
+
+        // Sample data offsets are relative to the start of the moof box.
+        let mut data_offset = moof.box_size() + mdat_header_size;
+        for traf in moof.trafs.iter_mut() {
+            let trun = traf.trun.as_mut().unwrap();
+            trun.data_offset = Some(i32::try_from(data_offset).map_err(|_| {
+                Error::InvalidData("fragment too large for a 32-bit trun data offset")
+            })?);
+            data_offset += trun.sample_sizes.iter().map(|s| *s as u64).sum::<u64>();
+        }
+
+        let mut fragment = Vec::with_capacity((data_offset - mdat_header_size) as usize);
+        moof.write_box(&mut fragment)?;
+        BoxHeader::new(BoxType::MdatBox, data_len + mdat_header_size).write(&mut fragment)?;
+        for track in self.tracks.iter().filter(|track| !track.is_empty()) {
+            fragment.extend_from_slice(&track.data);
+        }
+
+        if let Some(ref mut mfra) = self.mfra {
+            let tracks = self.tracks.iter().filter(|track| !track.is_empty());
+            for (traf_idx, track) in tracks.enumerate() {
+                if let Some((sample_idx, time)) = track.first_sync_sample() {
+                    let tfra = &mut mfra.tfras[track.track_id() as usize - 1];
+                    tfra.entries.push(TfraEntry {
+                        time,
+                        moof_offset: self.position,
+                        traf_number: traf_idx as u32 + 1,
+                        trun_number: 1,
+                        sample_number: sample_idx as u32 + 1,
+                    });
+                }
+            }
+        }
+        self.position += fragment.len() as u64;
+
+        let reference_idx = self.reference_track_idx();
+        if let Some(ref mut sidx) = self.sidx {
+            let reference_track = &self.tracks[reference_idx];
+            if sidx.references.is_empty() {
+                sidx.earliest_presentation_time = reference_track.earliest_presentation_time();
+            }
+            let starts_with_sap = reference_track
+                .sample_flags
+                .first()
+                .is_some_and(|flags| *flags == SAMPLE_FLAGS_SYNC);
+            sidx.references.push(SidxReference {
+                reference_type: false,
+                referenced_size: u32::try_from(fragment.len())
+                    .ok()
+                    .filter(|size| *size <= 0x7FFF_FFFF)
+                    .ok_or(Error::InvalidData(
+                        "fragment too large for a sidx reference",
+                    ))?,
+                subsegment_duration: u32::try_from(reference_track.pending_duration())
+                    .map_err(|_| Error::InvalidData("fragment too long for a sidx reference"))?,
+                starts_with_sap,
+                sap_type: if starts_with_sap { 1 } else { 0 },
+                sap_delta_time: 0,
+            });
+            self.fragments.extend_from_slice(&fragment);
+        } else {
+            self.writer.write_all(&fragment)?;
+        }
+        for track in self.tracks.iter_mut() {
+            track.clear();
+        }
+
+        self.sequence_number += 1;
+        Ok(())
+    }
+
+    pub fn write_end(&mut self) -> Result<()> {
+        self.write_fragment()?;
+        let mut sidx_size = 0;
+        if let Some(mut sidx) = self.sidx.take() {
+            if sidx.earliest_presentation_time > u32::MAX as u64 {
+                sidx.version = 1;
+            }
+            sidx.write_box(&mut self.writer)?;
+            sidx_size = sidx.box_size();
+            self.writer.write_all(&self.fragments)?;
+            self.fragments = Vec::new();
+        }
+        if let Some(mut mfra) = self.mfra.take() {
+            for tfra in mfra.tfras.iter_mut() {
+                for entry in tfra.entries.iter_mut() {
+                    entry.moof_offset += sidx_size;
+                }
+                finish_tfra(tfra);
+            }
+            mfra.mfro.size = u32::try_from(mfra.box_size())
+                .map_err(|_| Error::InvalidData("mfra box too large"))?;
+            mfra.write_box(&mut self.writer)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Pick the smallest version and field sizes that fit the entries of `tfra`.
+fn finish_tfra(tfra: &mut TfraBox) {
+    let length_size = |max: u32| -> u8 {
+        match max {
+            0..=0xFF => 0,
+            0x100..=0xFFFF => 1,
+            0x10000..=0xFF_FFFF => 2,
+            _ => 3,
+        }
+    };
+    let entries = &tfra.entries;
+    tfra.version = if entries
+        .iter()
+        .any(|e| e.time > u32::MAX as u64 || e.moof_offset > u32::MAX as u64)
+    {
+        1
+    } else {
+        0
+    };
+    tfra.length_size_of_traf_num =
+        length_size(entries.iter().map(|e| e.traf_number).max().unwrap_or(0));
+    tfra.length_size_of_trun_num =
+        length_size(entries.iter().map(|e| e.trun_number).max().unwrap_or(0));
+    tfra.length_size_of_sample_num =
+        length_size(entries.iter().map(|e| e.sample_number).max().unwrap_or(0));
+}
@@ -76,17 +76,45 @@ pub type Result<T> = std::result::Result<T, Error>;
 mod types;
 pub use types::*;
 
+mod bitreader;
+
 mod mp4box;
 pub use mp4box::*;
 
+mod edit;
+pub use edit::Edit;
+
+mod chapter;
+pub use chapter::Chapter;
+
 mod track;
-pub use track::{Mp4Track, TrackConfig};
+pub use track::{Mp4SampleIter, Mp4Track, SampleTable, TrackConfig};
 
 mod reader;
 pub use reader::Mp4Reader;
 
+mod parser;
+pub use parser::{Mp4Event, Mp4PushParser};
+
 mod writer;
-pub use writer::{Mp4Config, Mp4Writer};
+pub use writer::{Mp4Config, Mp4StreamWriter, Mp4Writer};
+
+mod faststart;
+pub use faststart::optimize_for_streaming;
+
+mod editor;
+pub use editor::Mp4Editor;
+
+mod fragment;
+pub use fragment::{Mp4FragmentConfig, Mp4FragmentWriter};
+
+mod box_tree;
+pub use box_tree::{BoxNode, BoxTree, Visitor};
+
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "async")]
+pub use async_io::{AsyncMp4Reader, AsyncMp4Writer};
 
 pub fn read_mp4(f: File) -> Result<Mp4Reader<BufReader<File>>> {
     let size = f.metadata()?.len();
@@ -0,0 +1,335 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::io::{Read, Seek, Write};
+
+use crate::mp4box::*;
+
+/// Nominal bit rates of AC-3 in kbit/s, indexed by `bit_rate_code`.
+const AC3_BIT_RATES: [u32; 19] = [
+    32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640,
+];
+
+/// Sample rate of an AC-3 or E-AC-3 sample rate code, `fscod`.
+pub(crate) fn ac3_sample_rate(fscod: u8) -> u32 {
+    match fscod {
+        1 => 44100,
+        2 => 32000,
+        _ => 48000,
+    }
+}
+
+fn ac3_fscod(sample_rate: u32) -> Result<u8> {
+    match sample_rate {
+        48000 => Ok(0),
+        44100 => Ok(1),
+        32000 => Ok(2),
+        _ => Err(Error::InvalidData("unsupported ac-3 sample rate")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ac3Box {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub dac3: Dac3Box,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
+}
+
+impl Default for Ac3Box {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dac3: Dac3Box::default(),
+            unknown_boxes: Vec::new(),
+        }
+    }
+}
+
+impl Ac3Box {
+    pub fn new(config: &Ac3Config) -> Result<Self> {
+        let dac3 = Dac3Box::new(config)?;
+        Ok(Self {
+            data_reference_index: 1,
+            channelcount: dac3.channel_layout()?.channel_count(),
+            samplesize: 16,
+            samplerate: FixedPointU16::new(config.sample_rate as u16),
+            dac3,
+            unknown_boxes: Vec::new(),
+        })
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::Ac3Box
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 20 + self.dac3.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
+    }
+}
+
+impl Mp4Box for Ac3Box {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount,
+            self.samplesize,
+            self.samplerate.value()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Ac3Box {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+        reader.read_u64::<BigEndian>()?; // reserved
+        let channelcount = reader.read_u16::<BigEndian>()?;
+        let samplesize = reader.read_u16::<BigEndian>()?;
+        reader.read_u32::<BigEndian>()?; // pre-defined, reserved
+        let samplerate = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+
+        let mut dac3 = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "ac-3 box contains a box with a larger size than it",
+                ));
+            }
+            if name == BoxType::Dac3Box {
+                dac3 = Some(Dac3Box::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
+        }
+        let dac3 = dac3.ok_or(Error::BoxNotFound(BoxType::Dac3Box))?;
+
+        skip_bytes_to(reader, end)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(Ac3Box {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            dac3,
+            unknown_boxes,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Ac3Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
+        self.dac3.write_box(writer)?;
+        unknown.finish(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// AC-3 specific box, repeating the fields of the bit stream information.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Dac3Box {
+    pub fscod: u8,
+    pub bsid: u8,
+    pub bsmod: u8,
+    pub acmod: u8,
+    pub lfeon: bool,
+    pub bit_rate_code: u8,
+}
+
+impl Dac3Box {
+    pub fn new(config: &Ac3Config) -> Result<Self> {
+        let bit_rate_code = AC3_BIT_RATES
+            .iter()
+            .position(|&rate| rate * 1000 == config.bitrate)
+            .ok_or(Error::InvalidData("unsupported ac-3 bit rate"))?;
+        Ok(Self {
+            fscod: ac3_fscod(config.sample_rate)?,
+            bsid: config.bsid,
+            bsmod: config.bsmod,
+            acmod: config.channel_mode as u8,
+            lfeon: config.lfe,
+            bit_rate_code: bit_rate_code as u8,
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        ac3_sample_rate(self.fscod)
+    }
+
+    /// Nominal bit rate in bit/s.
+    pub fn bitrate(&self) -> Result<u32> {
+        match AC3_BIT_RATES.get(self.bit_rate_code as usize) {
+            Some(rate) => Ok(rate * 1000),
+            None => Err(Error::InvalidData("invalid ac-3 bit rate code")),
+        }
+    }
+
+    pub fn channel_layout(&self) -> Result<DolbyChannelLayout> {
+        Ok(DolbyChannelLayout {
+            channel_mode: Ac3ChannelMode::try_from(self.acmod)?,
+            lfe: self.lfeon,
+            chan_loc: 0,
+        })
+    }
+}
+
+impl Mp4Box for Dac3Box {
+    fn box_type(&self) -> BoxType {
+        BoxType::Dac3Box
+    }
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + 3
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "fscod={} bsid={} bsmod={} acmod={} lfeon={} bit_rate_code={}",
+            self.fscod, self.bsid, self.bsmod, self.acmod, self.lfeon, self.bit_rate_code
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Dac3Box {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let params = reader.read_u24::<BigEndian>()?;
+        let fscod = (params >> 22) as u8 & 0b11;
+        let bsid = (params >> 17) as u8 & 0b11111;
+        let bsmod = (params >> 14) as u8 & 0b111;
+        let acmod = (params >> 11) as u8 & 0b111;
+        let lfeon = (params >> 10) & 1 == 1;
+        let bit_rate_code = (params >> 5) as u8 & 0b11111;
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(Dac3Box {
+            fscod,
+            bsid,
+            bsmod,
+            acmod,
+            lfeon,
+            bit_rate_code,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Dac3Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        let params = (self.fscod as u32 & 0b11) << 22
+            | (self.bsid as u32 & 0b11111) << 17
+            | (self.bsmod as u32 & 0b111) << 14
+            | (self.acmod as u32 & 0b111) << 11
+            | (self.lfeon as u32) << 10
+            | (self.bit_rate_code as u32 & 0b11111) << 5;
+        writer.write_u24::<BigEndian>(params)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_ac3() {
+        let src_box = Ac3Box::new(&Ac3Config {
+            bitrate: 448000,
+            channel_mode: Ac3ChannelMode::ThreeTwo,
+            lfe: true,
+            ..Ac3Config::default()
+        })
+        .unwrap();
+        assert_eq!(src_box.channelcount, 6);
+        assert_eq!(src_box.dac3.bitrate().unwrap(), 448000);
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+        // dac3 of 48 kHz, bsid 8, 3/2 with LFE at 448 kbit/s.
+        assert_eq!(buf[buf.len() - 3..], [0x10, 0x3D, 0xE0]);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::Ac3Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Ac3Box::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        assert!(Ac3Box::new(&Ac3Config {
+            bitrate: 100000,
+            ..Ac3Config::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,384 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::bitreader::{BitReader, BitWriter};
+use crate::mp4box::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ac4Box {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub dac4: Dac4Box,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
+}
+
+impl Default for Ac4Box {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dac4: Dac4Box::default(),
+            unknown_boxes: Vec::new(),
+        }
+    }
+}
+
+impl Ac4Box {
+    pub fn new(config: &Ac4Config) -> Result<Self> {
+        let dac4 = Dac4Box::from_dsi(&config.dsi)?;
+        Ok(Self {
+            data_reference_index: 1,
+            channelcount: config.channel_count,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(dac4.sample_rate() as u16),
+            dac4,
+            unknown_boxes: Vec::new(),
+        })
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::Ac4Box
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 20 + self.dac4.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
+    }
+}
+
+impl Mp4Box for Ac4Box {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount,
+            self.samplesize,
+            self.samplerate.value()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Ac4Box {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+        reader.read_u64::<BigEndian>()?; // reserved
+        let channelcount = reader.read_u16::<BigEndian>()?;
+        let samplesize = reader.read_u16::<BigEndian>()?;
+        reader.read_u32::<BigEndian>()?; // pre-defined, reserved
+        let samplerate = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+
+        let mut dac4 = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "ac-4 box contains a box with a larger size than it",
+                ));
+            }
+            if name == BoxType::Dac4Box {
+                dac4 = Some(Dac4Box::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
+        }
+        let dac4 = dac4.ok_or(Error::BoxNotFound(BoxType::Dac4Box))?;
+
+        skip_bytes_to(reader, end)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(Ac4Box {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            dac4,
+            unknown_boxes,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Ac4Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
+        self.dac4.write_box(writer)?;
+        unknown.finish(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// AC-4 specific box, holding the decoder specific information
+/// (`ac4_dsi_v1`). Its header is parsed; the presentations that follow are
+/// kept as they are.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Dac4Box {
+    pub ac4_dsi_version: u8,
+    pub bitstream_version: u8,
+    pub fs_index: u8,
+    pub frame_rate_index: u8,
+    pub n_presentations: u16,
+    /// Signalled for bitstream versions above 1.
+    pub short_program_id: Option<u16>,
+    pub program_uuid: Option<[u8; 16]>,
+    pub bit_rate_mode: u8,
+    /// Bit rate in bit/s, 0 if unknown.
+    pub bit_rate: u32,
+    pub bit_rate_precision: u32,
+    /// The byte aligned presentation and substream group information.
+    pub presentations: Vec<u8>,
+}
+
+impl Dac4Box {
+    /// Parse the decoder specific information produced by an AC-4 encoder,
+    /// the payload of the `dac4` box.
+    pub fn from_dsi(dsi: &[u8]) -> Result<Self> {
+        let mut bits = BitReader::new(dsi);
+
+        let ac4_dsi_version = bits.read_bits(3)? as u8;
+        let bitstream_version = bits.read_bits(7)? as u8;
+        let fs_index = bits.read_bits(1)? as u8;
+        let frame_rate_index = bits.read_bits(4)? as u8;
+        let n_presentations = bits.read_bits(9)? as u16;
+
+        let mut short_program_id = None;
+        let mut program_uuid = None;
+        if bitstream_version > 1 && bits.read_bit()? {
+            short_program_id = Some(bits.read_bits(16)? as u16);
+            if bits.read_bit()? {
+                let mut uuid = [0; 16];
+                for byte in uuid.iter_mut() {
+                    *byte = bits.read_bits(8)? as u8;
+                }
+                program_uuid = Some(uuid);
+            }
+        }
+
+        // ac4_bitrate_dsi
+        let bit_rate_mode = bits.read_bits(2)? as u8;
+        let bit_rate = bits.read_bits(32)? as u32;
+        let bit_rate_precision = bits.read_bits(32)? as u32;
+
+        // byte_align
+        let consumed = (dsi.len() * 8 - bits.remaining() + 7) / 8;
+        let presentations = dsi[consumed..].to_vec();
+
+        Ok(Dac4Box {
+            ac4_dsi_version,
+            bitstream_version,
+            fs_index,
+            frame_rate_index,
+            n_presentations,
+            short_program_id,
+            program_uuid,
+            bit_rate_mode,
+            bit_rate,
+            bit_rate_precision,
+            presentations,
+        })
+    }
+
+    /// The decoder specific information, the payload of the `dac4` box.
+    pub fn to_dsi(&self) -> Vec<u8> {
+        let mut bits = BitWriter::new();
+        bits.write_bits(self.ac4_dsi_version as u64, 3);
+        bits.write_bits(self.bitstream_version as u64, 7);
+        bits.write_bits(self.fs_index as u64, 1);
+        bits.write_bits(self.frame_rate_index as u64, 4);
+        bits.write_bits(self.n_presentations as u64, 9);
+        if self.bitstream_version > 1 {
+            bits.write_bit(self.short_program_id.is_some());
+            if let Some(short_program_id) = self.short_program_id {
+                bits.write_bits(short_program_id as u64, 16);
+                bits.write_bit(self.program_uuid.is_some());
+                if let Some(ref uuid) = self.program_uuid {
+                    for byte in uuid.iter() {
+                        bits.write_bits(*byte as u64, 8);
+                    }
+                }
+            }
+        }
+        bits.write_bits(self.bit_rate_mode as u64, 2);
+        bits.write_bits(self.bit_rate as u64, 32);
+        bits.write_bits(self.bit_rate_precision as u64, 32);
+
+        let mut dsi = bits.into_bytes();
+        dsi.extend_from_slice(&self.presentations);
+        dsi
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        if self.fs_index == 0 {
+            44100
+        } else {
+            48000
+        }
+    }
+
+    fn header_bits(&self) -> u64 {
+        let mut bits = 24 + 66;
+        if self.bitstream_version > 1 {
+            bits += 1;
+            if self.short_program_id.is_some() {
+                bits += 17;
+                if self.program_uuid.is_some() {
+                    bits += 128;
+                }
+            }
+        }
+        bits
+    }
+}
+
+impl Mp4Box for Dac4Box {
+    fn box_type(&self) -> BoxType {
+        BoxType::Dac4Box
+    }
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + (self.header_bits() + 7) / 8 + self.presentations.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "bitstream_version={} fs_index={} frame_rate_index={} n_presentations={} bit_rate={}",
+            self.bitstream_version,
+            self.fs_index,
+            self.frame_rate_index,
+            self.n_presentations,
+            self.bit_rate
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Dac4Box {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let mut dsi = vec![0; size.saturating_sub(HEADER_SIZE) as usize];
+        reader.read_exact(&mut dsi)?;
+        let dac4 = Dac4Box::from_dsi(&dsi)?;
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(dac4)
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Dac4Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_all(&self.to_dsi())?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_ac4() {
+        let src_box = Ac4Box::default();
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::Ac4Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Ac4Box::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_dac4_program_id() {
+        let src_box = Dac4Box {
+            ac4_dsi_version: 1,
+            bitstream_version: 2,
+            fs_index: 1,
+            frame_rate_index: 2,
+            n_presentations: 1,
+            short_program_id: Some(0x1234),
+            program_uuid: Some(*b"0123456789abcdef"),
+            bit_rate_mode: 1,
+            bit_rate: 256000,
+            bit_rate_precision: 0xFFFFFFFF,
+            presentations: vec![0x01, 0x0A, 0x20, 0x00],
+        };
+        let dsi = src_box.to_dsi();
+        assert_eq!(dsi.len() as u64, src_box.box_size() - HEADER_SIZE);
+        assert_eq!(Dac4Box::from_dsi(&dsi).unwrap(), src_box);
+
+        let config = Ac4Config {
+            channel_count: 2,
+            dsi,
+        };
+        let ac4 = Ac4Box::new(&config).unwrap();
+        assert_eq!(ac4.samplerate.value(), 48000);
+        assert_eq!(ac4.dac4, src_box);
+    }
+}
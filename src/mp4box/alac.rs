@@ -0,0 +1,365 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::mp4box::*;
+
+/// Apple Lossless sample entry. Its decoder configuration is a child box of
+/// the same `alac` type, see [AlacSpecificBox].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AlacBox {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    /// 0 for sample rates above 65535 Hz, see the specific box for the
+    /// actual rate.
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub config: AlacSpecificBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
+}
+
+impl Default for AlacBox {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(44100),
+            config: AlacSpecificBox::default(),
+            unknown_boxes: Vec::new(),
+        }
+    }
+}
+
+impl AlacBox {
+    pub fn new(config: &AlacConfig) -> Self {
+        let samplerate = if config.sample_rate <= u16::MAX as u32 {
+            FixedPointU16::new(config.sample_rate as u16)
+        } else {
+            FixedPointU16::new_raw(0)
+        };
+        Self {
+            data_reference_index: 1,
+            channelcount: config.channel_count as u16,
+            samplesize: config.bit_depth as u16,
+            samplerate,
+            config: AlacSpecificBox::new(config),
+            unknown_boxes: Vec::new(),
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::AlacBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 20 + self.config.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
+    }
+}
+
+impl Mp4Box for AlacBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount, self.samplesize, self.config.sample_rate
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for AlacBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+        reader.read_u64::<BigEndian>()?; // reserved
+        let channelcount = reader.read_u16::<BigEndian>()?;
+        let samplesize = reader.read_u16::<BigEndian>()?;
+        reader.read_u32::<BigEndian>()?; // pre-defined, reserved
+        let samplerate = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+
+        let mut config = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "alac box contains a box with a larger size than it",
+                ));
+            }
+            if name == BoxType::AlacBox {
+                config = Some(AlacSpecificBox::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
+        }
+        let config = config.ok_or(Error::BoxNotFound(BoxType::AlacBox))?;
+
+        skip_bytes_to(reader, end)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(AlacBox {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            config,
+            unknown_boxes,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for AlacBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
+        self.config.write_box(writer)?;
+        unknown.finish(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// ALAC specific box inside the `alac` sample entry, holding the
+/// `ALACSpecificConfig` (magic cookie) of the encoder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AlacSpecificBox {
+    pub version: u8,
+    pub flags: u32,
+    /// Samples per channel in a frame.
+    pub frame_length: u32,
+    pub compatible_version: u8,
+    pub bit_depth: u8,
+    /// Rice tuning parameters.
+    pub pb: u8,
+    pub mb: u8,
+    pub kb: u8,
+    pub num_channels: u8,
+    pub max_run: u16,
+    /// Largest frame in bytes, 0 if unknown.
+    pub max_frame_bytes: u32,
+    /// Average bit rate in bit/s, 0 if unknown.
+    pub avg_bit_rate: u32,
+    pub sample_rate: u32,
+    /// Data following the config, usually a `chan` channel layout atom for
+    /// more than two channels.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trailing_data: Vec<u8>,
+}
+
+impl Default for AlacSpecificBox {
+    fn default() -> Self {
+        Self::new(&AlacConfig::default())
+    }
+}
+
+impl AlacSpecificBox {
+    pub fn new(config: &AlacConfig) -> Self {
+        Self {
+            version: 0,
+            flags: 0,
+            frame_length: config.frame_length,
+            compatible_version: 0,
+            bit_depth: config.bit_depth,
+            pb: 40,
+            mb: 10,
+            kb: 14,
+            num_channels: config.channel_count,
+            max_run: 255,
+            max_frame_bytes: config.max_frame_bytes,
+            avg_bit_rate: config.bitrate,
+            sample_rate: config.sample_rate,
+            trailing_data: Vec::new(),
+        }
+    }
+}
+
+impl Mp4Box for AlacSpecificBox {
+    fn box_type(&self) -> BoxType {
+        BoxType::AlacBox
+    }
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + HEADER_EXT_SIZE + 24 + self.trailing_data.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "frame_length={} bit_depth={} num_channels={} avg_bit_rate={} sample_rate={}",
+            self.frame_length,
+            self.bit_depth,
+            self.num_channels,
+            self.avg_bit_rate,
+            self.sample_rate
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for AlacSpecificBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let (version, flags) = read_box_header_ext(reader)?;
+        let frame_length = reader.read_u32::<BigEndian>()?;
+        let compatible_version = reader.read_u8()?;
+        let bit_depth = reader.read_u8()?;
+        let pb = reader.read_u8()?;
+        let mb = reader.read_u8()?;
+        let kb = reader.read_u8()?;
+        let num_channels = reader.read_u8()?;
+        let max_run = reader.read_u16::<BigEndian>()?;
+        let max_frame_bytes = reader.read_u32::<BigEndian>()?;
+        let avg_bit_rate = reader.read_u32::<BigEndian>()?;
+        let sample_rate = reader.read_u32::<BigEndian>()?;
+
+        let trailing_size = (start + size)
+            .checked_sub(reader.stream_position()?)
+            .ok_or(Error::InvalidData("alac config box size too small"))?;
+        let mut trailing_data = vec![0; trailing_size as usize];
+        reader.read_exact(&mut trailing_data)?;
+
+        Ok(AlacSpecificBox {
+            version,
+            flags,
+            frame_length,
+            compatible_version,
+            bit_depth,
+            pb,
+            mb,
+            kb,
+            num_channels,
+            max_run,
+            max_frame_bytes,
+            avg_bit_rate,
+            sample_rate,
+            trailing_data,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for AlacSpecificBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+        writer.write_u32::<BigEndian>(self.frame_length)?;
+        writer.write_u8(self.compatible_version)?;
+        writer.write_u8(self.bit_depth)?;
+        writer.write_u8(self.pb)?;
+        writer.write_u8(self.mb)?;
+        writer.write_u8(self.kb)?;
+        writer.write_u8(self.num_channels)?;
+        writer.write_u16::<BigEndian>(self.max_run)?;
+        writer.write_u32::<BigEndian>(self.max_frame_bytes)?;
+        writer.write_u32::<BigEndian>(self.avg_bit_rate)?;
+        writer.write_u32::<BigEndian>(self.sample_rate)?;
+        writer.write_all(&self.trailing_data)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_alac() {
+        let src_box = AlacBox::new(&AlacConfig {
+            bit_depth: 24,
+            sample_rate: 192000,
+            ..AlacConfig::default()
+        });
+        assert_eq!(src_box.samplerate.raw_value(), 0);
+        assert_eq!(src_box.samplesize, 24);
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::AlacBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = AlacBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.config.sample_rate, 192000);
+    }
+
+    #[test]
+    fn test_alac_channel_layout() {
+        let mut src_box = AlacSpecificBox::new(&AlacConfig {
+            channel_count: 6,
+            ..AlacConfig::default()
+        });
+        // A chan atom with the 5.1 (C L R Ls Rs LFE) layout tag.
+        src_box.trailing_data = vec![
+            0, 0, 0, 24, b'c', b'h', b'a', b'n', 0, 0, 0, 0, 0, 0x7c, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::AlacBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = AlacSpecificBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
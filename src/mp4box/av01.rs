@@ -0,0 +1,573 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::bitreader::BitReader;
+use crate::mp4box::*;
+
+const OBU_SEQUENCE_HEADER: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Av01Box {
+    pub data_reference_index: u16,
+    pub width: u16,
+    pub height: u16,
+
+    #[serde(with = "value_u32")]
+    pub horizresolution: FixedPointU16,
+
+    #[serde(with = "value_u32")]
+    pub vertresolution: FixedPointU16,
+    pub frame_count: u16,
+    pub depth: u16,
+    pub av1c: Av1CBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
+}
+
+impl Default for Av01Box {
+    fn default() -> Self {
+        Av01Box {
+            data_reference_index: 0,
+            width: 0,
+            height: 0,
+            horizresolution: FixedPointU16::new(0x48),
+            vertresolution: FixedPointU16::new(0x48),
+            frame_count: 1,
+            depth: 0x0018,
+            av1c: Av1CBox::default(),
+            unknown_boxes: Vec::new(),
+        }
+    }
+}
+
+impl Av01Box {
+    pub fn new(config: &Av1Config) -> Result<Self> {
+        Ok(Av01Box {
+            data_reference_index: 1,
+            width: config.width,
+            height: config.height,
+            horizresolution: FixedPointU16::new(0x48),
+            vertresolution: FixedPointU16::new(0x48),
+            frame_count: 1,
+            depth: 0x0018,
+            av1c: Av1CBox::from_sequence_header(&config.sequence_header)?,
+            unknown_boxes: Vec::new(),
+        })
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::Av01Box
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 70 + self.av1c.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
+    }
+}
+
+impl Mp4Box for Av01Box {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "data_reference_index={} width={} height={} frame_count={}",
+            self.data_reference_index, self.width, self.height, self.frame_count
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Av01Box {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+
+        reader.read_u32::<BigEndian>()?; // pre-defined, reserved
+        reader.read_u64::<BigEndian>()?; // pre-defined
+        reader.read_u32::<BigEndian>()?; // pre-defined
+        let width = reader.read_u16::<BigEndian>()?;
+        let height = reader.read_u16::<BigEndian>()?;
+        let horizresolution = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+        let vertresolution = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+        reader.read_u32::<BigEndian>()?; // reserved
+        let frame_count = reader.read_u16::<BigEndian>()?;
+        skip_bytes(reader, 32)?; // compressorname
+        let depth = reader.read_u16::<BigEndian>()?;
+        reader.read_i16::<BigEndian>()?; // pre-defined
+
+        let mut av1c = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "av01 box contains a box with a larger size than it",
+                ));
+            }
+            if name == BoxType::Av1CBox {
+                av1c = Some(Av1CBox::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
+        }
+        let av1c = av1c.ok_or(Error::InvalidData("av1c not found"))?;
+
+        skip_bytes_to(reader, end)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(Av01Box {
+            data_reference_index,
+            width,
+            height,
+            horizresolution,
+            vertresolution,
+            frame_count,
+            depth,
+            av1c,
+            unknown_boxes,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Av01Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u32::<BigEndian>(0)?; // pre-defined, reserved
+        writer.write_u64::<BigEndian>(0)?; // pre-defined
+        writer.write_u32::<BigEndian>(0)?; // pre-defined
+        writer.write_u16::<BigEndian>(self.width)?;
+        writer.write_u16::<BigEndian>(self.height)?;
+        writer.write_u32::<BigEndian>(self.horizresolution.raw_value())?;
+        writer.write_u32::<BigEndian>(self.vertresolution.raw_value())?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.frame_count)?;
+        // skip compressorname
+        write_zeros(writer, 32)?;
+        writer.write_u16::<BigEndian>(self.depth)?;
+        writer.write_i16::<BigEndian>(-1)?; // pre-defined
+
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
+        self.av1c.write_box(writer)?;
+        unknown.finish(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// AV1 codec configuration record.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Av1CBox {
+    pub seq_profile: u8,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: bool,
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    /// Presentation delay in frames minus one, if signalled.
+    pub initial_presentation_delay_minus_one: Option<u8>,
+    /// Sequence header and metadata OBUs, each with its size field.
+    pub config_obus: Vec<u8>,
+}
+
+impl Av1CBox {
+    /// Build the configuration of a stream from its sequence header OBU,
+    /// which is also stored as the configuration OBUs.
+    pub fn from_sequence_header(obu: &[u8]) -> Result<Self> {
+        let info = parse_sequence_header(obu)?;
+        Ok(Av1CBox {
+            seq_profile: info.seq_profile,
+            seq_level_idx_0: info.seq_level_idx_0,
+            seq_tier_0: info.seq_tier_0,
+            high_bitdepth: info.high_bitdepth,
+            twelve_bit: info.twelve_bit,
+            monochrome: info.monochrome,
+            chroma_subsampling_x: info.chroma_subsampling_x,
+            chroma_subsampling_y: info.chroma_subsampling_y,
+            chroma_sample_position: info.chroma_sample_position,
+            initial_presentation_delay_minus_one: None,
+            config_obus: obu.to_vec(),
+        })
+    }
+
+    /// Bit depth of the samples, 8, 10 or 12.
+    pub fn bit_depth(&self) -> u8 {
+        match (self.high_bitdepth, self.twelve_bit) {
+            (false, _) => 8,
+            (true, false) => 10,
+            (true, true) => 12,
+        }
+    }
+}
+
+impl Mp4Box for Av1CBox {
+    fn box_type(&self) -> BoxType {
+        BoxType::Av1CBox
+    }
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + 4 + self.config_obus.len() as u64
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        Ok(format!(
+            "seq_profile={} seq_level_idx_0={} seq_tier_0={} bit_depth={} monochrome={} chroma_subsampling_x={} chroma_subsampling_y={} chroma_sample_position={}",
+            self.seq_profile,
+            self.seq_level_idx_0,
+            self.seq_tier_0,
+            self.bit_depth(),
+            self.monochrome,
+            self.chroma_subsampling_x,
+            self.chroma_subsampling_y,
+            self.chroma_sample_position
+        ))
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Av1CBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+        if size < HEADER_SIZE + 4 {
+            return Err(Error::InvalidData("av1c box is too small"));
+        }
+
+        let marker_version = reader.read_u8()?;
+        if marker_version != 0x81 {
+            return Err(Error::InvalidData("unsupported av1c version"));
+        }
+        let params = reader.read_u8()?;
+        let seq_profile = params >> 5;
+        let seq_level_idx_0 = params & 0b11111;
+
+        let params = reader.read_u8()?;
+        let seq_tier_0 = params & 0b10000000 > 0;
+        let high_bitdepth = params & 0b01000000 > 0;
+        let twelve_bit = params & 0b00100000 > 0;
+        let monochrome = params & 0b00010000 > 0;
+        let chroma_subsampling_x = params & 0b00001000 > 0;
+        let chroma_subsampling_y = params & 0b00000100 > 0;
+        let chroma_sample_position = params & 0b11;
+
+        let params = reader.read_u8()?;
+        let initial_presentation_delay_minus_one = if params & 0b00010000 > 0 {
+            Some(params & 0b1111)
+        } else {
+            None
+        };
+
+        let mut config_obus = vec![0; (size - HEADER_SIZE - 4) as usize];
+        reader.read_exact(&mut config_obus)?;
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(Av1CBox {
+            seq_profile,
+            seq_level_idx_0,
+            seq_tier_0,
+            high_bitdepth,
+            twelve_bit,
+            monochrome,
+            chroma_subsampling_x,
+            chroma_subsampling_y,
+            chroma_sample_position,
+            initial_presentation_delay_minus_one,
+            config_obus,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Av1CBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u8(0x81)?; // marker, version 1
+        writer.write_u8((self.seq_profile & 0b111) << 5 | self.seq_level_idx_0 & 0b11111)?;
+        writer.write_u8(
+            u8::from(self.seq_tier_0) << 7
+                | u8::from(self.high_bitdepth) << 6
+                | u8::from(self.twelve_bit) << 5
+                | u8::from(self.monochrome) << 4
+                | u8::from(self.chroma_subsampling_x) << 3
+                | u8::from(self.chroma_subsampling_y) << 2
+                | self.chroma_sample_position & 0b11,
+        )?;
+        match self.initial_presentation_delay_minus_one {
+            Some(delay) => writer.write_u8(0b00010000 | delay & 0b1111)?,
+            None => writer.write_u8(0)?,
+        }
+        writer.write_all(&self.config_obus)?;
+
+        Ok(size)
+    }
+}
+
+/// The fields of an AV1 sequence header that `av1C` repeats.
+struct SequenceHeaderInfo {
+    seq_profile: u8,
+    seq_level_idx_0: u8,
+    seq_tier_0: bool,
+    high_bitdepth: bool,
+    twelve_bit: bool,
+    monochrome: bool,
+    chroma_subsampling_x: bool,
+    chroma_subsampling_y: bool,
+    chroma_sample_position: u8,
+}
+
+fn parse_sequence_header(obu: &[u8]) -> Result<SequenceHeaderInfo> {
+    let mut reader = BitReader::new(obu);
+
+    // OBU header.
+    reader.skip_bits(1)?;
+    if reader.read_bits(4)? as u8 != OBU_SEQUENCE_HEADER {
+        return Err(Error::InvalidData("not an av1 sequence header"));
+    }
+    let extension_flag = reader.read_bit()?;
+    let has_size_field = reader.read_bit()?;
+    reader.skip_bits(1)?;
+    if extension_flag {
+        reader.skip_bits(8)?;
+    }
+    if has_size_field {
+        // leb128 obu_size
+        for _ in 0..8 {
+            if !reader.read_bit()? {
+                reader.skip_bits(7)?;
+                break;
+            }
+            reader.skip_bits(7)?;
+        }
+    }
+
+    let seq_profile = reader.read_bits(3)? as u8;
+    reader.skip_bits(1)?; // still_picture
+    let reduced_still_picture_header = reader.read_bit()?;
+    let seq_level_idx_0;
+    let mut seq_tier_0 = false;
+    if reduced_still_picture_header {
+        seq_level_idx_0 = reader.read_bits(5)? as u8;
+    } else {
+        let mut decoder_model_info_present = false;
+        let mut buffer_delay_length = 0;
+        if reader.read_bit()? {
+            // timing_info
+            reader.skip_bits(64)?; // num_units_in_display_tick, time_scale
+            if reader.read_bit()? {
+                read_uvlc(&mut reader)?; // num_ticks_per_picture_minus_1
+            }
+            decoder_model_info_present = reader.read_bit()?;
+            if decoder_model_info_present {
+                buffer_delay_length = reader.read_bits(5)? as usize + 1;
+                reader.skip_bits(32 + 5 + 5)?;
+            }
+        }
+        let initial_display_delay_present = reader.read_bit()?;
+        let operating_points_cnt = reader.read_bits(5)? + 1;
+        let mut first_level = None;
+        for _ in 0..operating_points_cnt {
+            reader.skip_bits(12)?; // operating_point_idc
+            let seq_level_idx = reader.read_bits(5)? as u8;
+            let seq_tier = seq_level_idx > 7 && reader.read_bit()?;
+            if decoder_model_info_present && reader.read_bit()? {
+                // operating_parameters_info
+                reader.skip_bits(2 * buffer_delay_length + 1)?;
+            }
+            if initial_display_delay_present && reader.read_bit()? {
+                reader.skip_bits(4)?;
+            }
+            first_level.get_or_insert((seq_level_idx, seq_tier));
+        }
+        let (level, tier) = first_level.unwrap_or_default();
+        seq_level_idx_0 = level;
+        seq_tier_0 = tier;
+    }
+
+    let frame_width_bits = reader.read_bits(4)? as usize + 1;
+    let frame_height_bits = reader.read_bits(4)? as usize + 1;
+    reader.skip_bits(frame_width_bits + frame_height_bits)?;
+    if !reduced_still_picture_header && reader.read_bit()? {
+        // frame_id_numbers_present_flag
+        reader.skip_bits(4 + 3)?;
+    }
+    // use_128x128_superblock, enable_filter_intra, enable_intra_edge_filter
+    reader.skip_bits(3)?;
+    if !reduced_still_picture_header {
+        // enable_interintra_compound, enable_masked_compound,
+        // enable_warped_motion, enable_dual_filter
+        reader.skip_bits(4)?;
+        let enable_order_hint = reader.read_bit()?;
+        if enable_order_hint {
+            reader.skip_bits(2)?; // enable_jnt_comp, enable_ref_frame_mvs
+        }
+        let seq_force_screen_content_tools = if reader.read_bit()? {
+            2 // SELECT_SCREEN_CONTENT_TOOLS
+        } else {
+            reader.read_bits(1)?
+        };
+        if seq_force_screen_content_tools > 0 && !reader.read_bit()? {
+            reader.skip_bits(1)?; // seq_force_integer_mv
+        }
+        if enable_order_hint {
+            reader.skip_bits(3)?; // order_hint_bits_minus_1
+        }
+    }
+    // enable_superres, enable_cdef, enable_restoration
+    reader.skip_bits(3)?;
+
+    // color_config
+    let high_bitdepth = reader.read_bit()?;
+    let twelve_bit = seq_profile == 2 && high_bitdepth && reader.read_bit()?;
+    let monochrome = seq_profile != 1 && reader.read_bit()?;
+    let (mut color_primaries, mut transfer_characteristics, mut matrix_coefficients) = (2, 2, 2);
+    if reader.read_bit()? {
+        color_primaries = reader.read_bits(8)?;
+        transfer_characteristics = reader.read_bits(8)?;
+        matrix_coefficients = reader.read_bits(8)?;
+    }
+    let mut chroma_sample_position = 0;
+    let (chroma_subsampling_x, chroma_subsampling_y) = if monochrome {
+        (true, true)
+    } else if color_primaries == 1 && transfer_characteristics == 13 && matrix_coefficients == 0 {
+        // sRGB
+        (false, false)
+    } else {
+        reader.skip_bits(1)?; // color_range
+        let subsampling = match seq_profile {
+            0 => (true, true),
+            1 => (false, false),
+            _ if twelve_bit => {
+                let x = reader.read_bit()?;
+                (x, x && reader.read_bit()?)
+            }
+            _ => (true, false),
+        };
+        if subsampling == (true, true) {
+            chroma_sample_position = reader.read_bits(2)? as u8;
+        }
+        subsampling
+    };
+
+    Ok(SequenceHeaderInfo {
+        seq_profile,
+        seq_level_idx_0,
+        seq_tier_0,
+        high_bitdepth,
+        twelve_bit,
+        monochrome,
+        chroma_subsampling_x,
+        chroma_subsampling_y,
+        chroma_sample_position,
+    })
+}
+
+/// Read a variable length unsigned integer, `uvlc()` in the AV1 syntax.
+fn read_uvlc(reader: &mut BitReader) -> Result<u32> {
+    let mut leading_zeros = 0;
+    while !reader.read_bit()? {
+        leading_zeros += 1;
+        if leading_zeros >= 32 {
+            return Ok(u32::MAX);
+        }
+    }
+    let value = reader.read_bits(leading_zeros)?;
+    Ok(((1u64 << leading_zeros) - 1 + value) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    // 1920x1080 Main profile, level 4.0, 8-bit 4:2:0.
+    const SEQUENCE_HEADER: [u8; 13] = [
+        0x0A, 0x0B, 0x00, 0x00, 0x00, 0x42, 0xAB, 0xBF, 0xC3, 0x73, 0xFF, 0xE6, 0x01,
+    ];
+
+    #[test]
+    fn test_av01() {
+        let src_box = Av01Box::new(&Av1Config {
+            width: 1920,
+            height: 1080,
+            sequence_header: SEQUENCE_HEADER.to_vec(),
+        })
+        .unwrap();
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::Av01Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Av01Box::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_av1c_from_sequence_header() {
+        let av1c = Av1CBox::from_sequence_header(&SEQUENCE_HEADER).unwrap();
+        assert_eq!(av1c.seq_profile, 0);
+        assert_eq!(av1c.seq_level_idx_0, 8);
+        assert!(!av1c.seq_tier_0);
+        assert_eq!(av1c.bit_depth(), 8);
+        assert!(!av1c.monochrome);
+        assert!(av1c.chroma_subsampling_x);
+        assert!(av1c.chroma_subsampling_y);
+        assert_eq!(av1c.chroma_sample_position, 0);
+        assert_eq!(av1c.config_obus, SEQUENCE_HEADER);
+
+        let mut buf = Vec::new();
+        av1c.write_box(&mut buf).unwrap();
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::Av1CBox);
+        let dst_box = Av1CBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(av1c, dst_box);
+
+        // A temporal delimiter OBU.
+        assert!(Av1CBox::from_sequence_header(&[0x12, 0x00]).is_err());
+    }
+}
@@ -19,6 +19,9 @@ pub struct Avc1Box {
     pub depth: u16,
     pub inband_parameter_sets: bool,
     pub avcc: AvcCBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl Default for Avc1Box {
@@ -33,6 +36,7 @@ impl Default for Avc1Box {
             depth: 0x0018,
             inband_parameter_sets: false,
             avcc: AvcCBox::default(),
+            unknown_boxes: Vec::new(),
         }
     }
 }
@@ -49,6 +53,7 @@ impl Avc1Box {
             depth: 0x0018,
             inband_parameter_sets: false,
             avcc: AvcCBox::new(&config.seq_param_set, &config.pic_param_set),
+            unknown_boxes: Vec::new(),
         }
     }
 
@@ -61,7 +66,11 @@ impl Avc1Box {
     }
 
     pub fn get_size(&self) -> u64 {
-        HEADER_SIZE + 8 + 70 + self.avcc.box_size()
+        let mut size = HEADER_SIZE + 8 + 70 + self.avcc.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
     }
 }
 
@@ -108,12 +117,12 @@ impl<R: Read + Seek> ReadBox<&mut R> for Avc1Box {
         let depth = reader.read_u16::<BigEndian>()?;
         reader.read_i16::<BigEndian>()?; // pre-defined
 
+        let mut avcc = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
         let end = start + size;
-        loop {
-            let current = reader.stream_position()?;
-            if current >= end {
-                return Err(Error::InvalidData("avcc not found"));
-            }
+        while current < end {
             let header = BoxHeader::read(reader)?;
             let BoxHeader { name, size: s } = header;
             if s > size {
@@ -122,25 +131,32 @@ impl<R: Read + Seek> ReadBox<&mut R> for Avc1Box {
                 ));
             }
             if name == BoxType::AvcCBox {
-                let avcc = AvcCBox::read_box(reader, s)?;
-
-                skip_bytes_to(reader, start + size)?;
-
-                return Ok(Avc1Box {
-                    data_reference_index,
-                    width,
-                    height,
-                    horizresolution,
-                    vertresolution,
-                    frame_count,
-                    depth,
-                    inband_parameter_sets: false,
-                    avcc,
-                });
+                avcc = Some(AvcCBox::read_box(reader, s)?);
             } else {
-                skip_bytes_to(reader, current + s)?;
+                unknown.read(reader, name, s)?;
             }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
         }
+        let avcc = avcc.ok_or(Error::InvalidData("avcc not found"))?;
+
+        skip_bytes_to(reader, end)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(Avc1Box {
+            data_reference_index,
+            width,
+            height,
+            horizresolution,
+            vertresolution,
+            frame_count,
+            depth,
+            inband_parameter_sets: false,
+            avcc,
+            unknown_boxes,
+        })
     }
 }
 
@@ -167,7 +183,10 @@ impl<W: Write> WriteBox<&mut W> for Avc1Box {
         writer.write_u16::<BigEndian>(self.depth)?;
         writer.write_i16::<BigEndian>(-1)?; // pre-defined
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
         self.avcc.write_box(writer)?;
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -347,6 +366,7 @@ mod tests {
                     bytes: vec![0x68, 0xEB, 0xE3, 0xCB, 0x22, 0xC0],
                 }],
             },
+            unknown_boxes: Vec::new(),
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -360,4 +380,45 @@ mod tests {
         let dst_box = Avc1Box::read_box(&mut reader, header.size).unwrap();
         assert_eq!(src_box, dst_box);
     }
+
+    #[test]
+    fn test_avc1_colr_pasp() {
+        let colr = BoxType::from(u32::from_be_bytes(*b"colr"));
+        let pasp = BoxType::from(u32::from_be_bytes(*b"pasp"));
+        let mut src_box = Avc1Box::new(&AvcConfig {
+            width: 320,
+            height: 240,
+            seq_param_set: vec![0x67, 0x64, 0x00, 0x0d],
+            pic_param_set: vec![0x68, 0xee, 0x3c, 0x80],
+        });
+        src_box.avcc.length_size_minus_one = 3;
+        src_box.unknown_boxes = vec![
+            UnknownBox {
+                position: Some(0),
+                ..UnknownBox::new(colr, b"nclx\x00\x01\x00\x01\x00\x01\x00".to_vec())
+            },
+            UnknownBox::new(pasp, vec![0, 0, 0, 1, 0, 0, 0, 1]),
+        ];
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        // colr was read before avcC and is written back there.
+        let children = &buf[HEADER_SIZE as usize + 78..];
+        assert_eq!(&children[4..8], b"colr");
+        assert_eq!(&children[19 + 4..19 + 8], b"avcC");
+        assert_eq!(&buf[buf.len() - 16..buf.len() - 8], b"\x00\x00\x00\x10pasp");
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::Avc1Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Avc1Box::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        let mut dst_buf = Vec::new();
+        dst_box.write_box(&mut dst_buf).unwrap();
+        assert_eq!(buf, dst_buf);
+    }
 }
@@ -0,0 +1,163 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::mp4box::*;
+
+/// Nero chapter list, found in `moov/udta`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct ChplBox {
+    pub version: u8,
+    pub flags: u32,
+    pub entries: Vec<ChplEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct ChplEntry {
+    /// Start time in units of 100 nanoseconds.
+    pub start_time: u64,
+    pub title: String,
+}
+
+impl ChplBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::ChplBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 1;
+        if self.version == 1 {
+            size += 4;
+        }
+        for entry in self.entries.iter() {
+            size += 8 + 1 + entry.title.len() as u64;
+        }
+        size
+    }
+}
+
+impl Mp4Box for ChplBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("entries={}", self.entries.len());
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for ChplBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let (version, flags) = read_box_header_ext(reader)?;
+        if version == 1 {
+            reader.read_u32::<BigEndian>()?; // reserved
+        }
+
+        let entry_count = reader.read_u8()?;
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let start_time = reader.read_u64::<BigEndian>()?;
+            let title_len = reader.read_u8()?;
+            let mut title = vec![0; title_len as usize];
+            reader.read_exact(&mut title)?;
+            entries.push(ChplEntry {
+                start_time,
+                title: String::from_utf8_lossy(&title).into_owned(),
+            });
+        }
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(ChplBox {
+            version,
+            flags,
+            entries,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for ChplBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        if self.entries.len() > u8::MAX as usize {
+            return Err(Error::InvalidData("chpl box holds at most 255 chapters"));
+        }
+        if self
+            .entries
+            .iter()
+            .any(|e| e.title.len() > u8::MAX as usize)
+        {
+            return Err(Error::InvalidData(
+                "chpl chapter titles are at most 255 bytes long",
+            ));
+        }
+
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+        if self.version == 1 {
+            writer.write_u32::<BigEndian>(0)?; // reserved
+        }
+
+        writer.write_u8(self.entries.len() as u8)?;
+        for entry in self.entries.iter() {
+            writer.write_u64::<BigEndian>(entry.start_time)?;
+            writer.write_u8(entry.title.len() as u8)?;
+            writer.write_all(entry.title.as_bytes())?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    fn test_chpl_version(version: u8) {
+        let src_box = ChplBox {
+            version,
+            flags: 0,
+            entries: vec![
+                ChplEntry {
+                    start_time: 0,
+                    title: String::from("Prologue"),
+                },
+                ChplEntry {
+                    start_time: 1_234_500_000,
+                    title: String::from("Chapitre 1 — Début"),
+                },
+            ],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::ChplBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = ChplBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_chpl() {
+        test_chpl_version(0);
+        test_chpl_version(1);
+    }
+}
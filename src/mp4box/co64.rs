@@ -95,6 +95,16 @@ impl<W: Write> WriteBox<&mut W> for Co64Box {
     }
 }
 
+impl std::convert::From<&stco::StcoBox> for Co64Box {
+    fn from(stco: &stco::StcoBox) -> Self {
+        Self {
+            version: 0,
+            flags: 0,
+            entries: stco.entries.iter().map(|offset| *offset as u64).collect(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
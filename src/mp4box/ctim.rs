@@ -3,19 +3,11 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct CtimBox {
     pub current_time: String,
 }
 
-impl Default for CtimBox {
-    fn default() -> Self {
-        CtimBox {
-            current_time: String::new(),
-        }
-    }
-}
-
 impl CtimBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::CtimBox
@@ -67,7 +67,7 @@ impl<W: Write> WriteBox<&mut W> for DataBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
-        writer.write_u32::<BigEndian>(self.data_type.clone() as u32)?;
+        writer.write_u32::<BigEndian>(u32::from(&self.data_type))?;
         writer.write_u32::<BigEndian>(0)?; // reserved = 0
         writer.write_all(&self.data)?;
 
@@ -115,4 +115,25 @@ mod tests {
         let dst_box = DataBox::read_box(&mut reader, header.size).unwrap();
         assert_eq!(src_box, dst_box);
     }
+
+    #[test]
+    fn test_data_other_type() {
+        let src_box = DataBox {
+            data_type: DataType::Other(0x000016),
+            data: vec![1],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::DataBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = DataBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(u32::from(dst_box.data_type), 0x000016);
+        assert_eq!(u32::from(DataType::Text), 0x000001);
+    }
 }
@@ -6,6 +6,9 @@ use crate::mp4box::*;
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct DinfBox {
     dref: DrefBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl DinfBox {
@@ -14,7 +17,11 @@ impl DinfBox {
     }
 
     pub fn get_size(&self) -> u64 {
-        HEADER_SIZE + self.dref.box_size()
+        let mut size = HEADER_SIZE + self.dref.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
     }
 }
 
@@ -42,6 +49,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for DinfBox {
         let start = box_start(reader)?;
 
         let mut dref = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -60,10 +68,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for DinfBox {
                     dref = Some(DrefBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -74,8 +82,11 @@ impl<R: Read + Seek> ReadBox<&mut R> for DinfBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(DinfBox {
             dref: dref.unwrap(),
+            unknown_boxes,
         })
     }
 }
@@ -84,7 +95,12 @@ impl<W: Write> WriteBox<&mut W> for DinfBox {
     fn write_box(&self, writer: &mut W) -> Result<u64> {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
         self.dref.write_box(writer)?;
+        unknown.finish(writer)?;
+
         Ok(size)
     }
 }
@@ -232,7 +248,7 @@ impl UrlBox {
         let mut size = HEADER_SIZE + HEADER_EXT_SIZE;
 
         if !self.location.is_empty() {
-            size += self.location.bytes().len() as u64 + 1;
+            size += self.location.len() as u64 + 1;
         }
 
         size
@@ -0,0 +1,417 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::io::{Read, Seek, Write};
+
+use crate::bitreader::{BitReader, BitWriter};
+use crate::mp4box::ac3::ac3_sample_rate;
+use crate::mp4box::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ec3Box {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub dec3: Dec3Box,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
+}
+
+impl Default for Ec3Box {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dec3: Dec3Box::default(),
+            unknown_boxes: Vec::new(),
+        }
+    }
+}
+
+impl Ec3Box {
+    pub fn new(config: &Ec3Config) -> Result<Self> {
+        let dec3 = Dec3Box::new(config)?;
+        Ok(Self {
+            data_reference_index: 1,
+            channelcount: dec3.channel_layout()?.channel_count(),
+            samplesize: 16,
+            samplerate: FixedPointU16::new(dec3.sample_rate() as u16),
+            dec3,
+            unknown_boxes: Vec::new(),
+        })
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::Ec3Box
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 20 + self.dec3.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
+    }
+}
+
+impl Mp4Box for Ec3Box {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount,
+            self.samplesize,
+            self.samplerate.value()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Ec3Box {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+        reader.read_u64::<BigEndian>()?; // reserved
+        let channelcount = reader.read_u16::<BigEndian>()?;
+        let samplesize = reader.read_u16::<BigEndian>()?;
+        reader.read_u32::<BigEndian>()?; // pre-defined, reserved
+        let samplerate = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+
+        let mut dec3 = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "ec-3 box contains a box with a larger size than it",
+                ));
+            }
+            if name == BoxType::Dec3Box {
+                dec3 = Some(Dec3Box::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
+        }
+        let dec3 = dec3.ok_or(Error::BoxNotFound(BoxType::Dec3Box))?;
+
+        skip_bytes_to(reader, end)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(Ec3Box {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            dec3,
+            unknown_boxes,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Ec3Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
+        self.dec3.write_box(writer)?;
+        unknown.finish(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// E-AC-3 specific box, describing the independent substreams of the
+/// stream.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Dec3Box {
+    /// Data rate in kbit/s.
+    pub data_rate: u16,
+    pub substreams: Vec<Ec3IndependentSubstream>,
+    /// Object complexity of Dolby Atmos content, if signalled.
+    pub complexity_index: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct Ec3IndependentSubstream {
+    pub fscod: u8,
+    pub bsid: u8,
+    pub asvc: bool,
+    pub bsmod: u8,
+    pub acmod: u8,
+    pub lfeon: bool,
+    /// Number of dependent substreams, which add the channels of
+    /// `chan_loc`.
+    pub num_dep_sub: u8,
+    pub chan_loc: u16,
+}
+
+impl Dec3Box {
+    pub fn new(config: &Ec3Config) -> Result<Self> {
+        if config.substreams.is_empty() || config.substreams.len() > 8 {
+            return Err(Error::InvalidData(
+                "e-ac-3 needs between 1 and 8 independent substreams",
+            ));
+        }
+        Ok(Self {
+            data_rate: (config.bitrate / 1000) as u16,
+            substreams: config.substreams.clone(),
+            complexity_index: config.complexity_index,
+        })
+    }
+
+    /// Sample rate of the first independent substream.
+    pub fn sample_rate(&self) -> u32 {
+        ac3_sample_rate(self.substreams.first().map_or(0, |s| s.fscod))
+    }
+
+    /// Layout of the first independent substream and its dependent
+    /// substreams.
+    pub fn channel_layout(&self) -> Result<DolbyChannelLayout> {
+        let substream = self
+            .substreams
+            .first()
+            .ok_or(Error::InvalidData("dec3 has no independent substream"))?;
+        Ok(DolbyChannelLayout {
+            channel_mode: Ac3ChannelMode::try_from(substream.acmod)?,
+            lfe: substream.lfeon,
+            chan_loc: substream.chan_loc,
+        })
+    }
+
+    fn data_size(&self) -> u64 {
+        let mut size = 2;
+        for substream in self.substreams.iter() {
+            size += if substream.num_dep_sub > 0 { 4 } else { 3 };
+        }
+        if self.complexity_index.is_some() {
+            size += 2;
+        }
+        size
+    }
+}
+
+impl Mp4Box for Dec3Box {
+    fn box_type(&self) -> BoxType {
+        BoxType::Dec3Box
+    }
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + self.data_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "data_rate={} num_ind_sub={}",
+            self.data_rate,
+            self.substreams.len()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for Dec3Box {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let mut data = vec![0; size.saturating_sub(HEADER_SIZE) as usize];
+        reader.read_exact(&mut data)?;
+        let mut bits = BitReader::new(&data);
+
+        let data_rate = bits.read_bits(13)? as u16;
+        let num_ind_sub = bits.read_bits(3)? + 1;
+        let mut substreams = Vec::with_capacity(num_ind_sub as usize);
+        for _ in 0..num_ind_sub {
+            let fscod = bits.read_bits(2)? as u8;
+            let bsid = bits.read_bits(5)? as u8;
+            bits.skip_bits(1)?; // reserved
+            let asvc = bits.read_bit()?;
+            let bsmod = bits.read_bits(3)? as u8;
+            let acmod = bits.read_bits(3)? as u8;
+            let lfeon = bits.read_bit()?;
+            bits.skip_bits(3)?; // reserved
+            let num_dep_sub = bits.read_bits(4)? as u8;
+            let chan_loc = if num_dep_sub > 0 {
+                bits.read_bits(9)? as u16
+            } else {
+                bits.skip_bits(1)?; // reserved
+                0
+            };
+            substreams.push(Ec3IndependentSubstream {
+                fscod,
+                bsid,
+                asvc,
+                bsmod,
+                acmod,
+                lfeon,
+                num_dep_sub,
+                chan_loc,
+            });
+        }
+
+        let mut complexity_index = None;
+        if bits.remaining() >= 16 {
+            bits.skip_bits(7)?; // reserved
+            if bits.read_bit()? {
+                complexity_index = Some(bits.read_bits(8)? as u8);
+            }
+        }
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(Dec3Box {
+            data_rate,
+            substreams,
+            complexity_index,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for Dec3Box {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        if self.substreams.is_empty() || self.substreams.len() > 8 {
+            return Err(Error::InvalidData(
+                "e-ac-3 needs between 1 and 8 independent substreams",
+            ));
+        }
+
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        let mut bits = BitWriter::new();
+        bits.write_bits(self.data_rate as u64, 13);
+        bits.write_bits(self.substreams.len() as u64 - 1, 3);
+        for substream in self.substreams.iter() {
+            bits.write_bits(substream.fscod as u64, 2);
+            bits.write_bits(substream.bsid as u64, 5);
+            bits.write_bit(false); // reserved
+            bits.write_bit(substream.asvc);
+            bits.write_bits(substream.bsmod as u64, 3);
+            bits.write_bits(substream.acmod as u64, 3);
+            bits.write_bit(substream.lfeon);
+            bits.write_bits(0, 3); // reserved
+            bits.write_bits(substream.num_dep_sub as u64, 4);
+            if substream.num_dep_sub > 0 {
+                bits.write_bits(substream.chan_loc as u64, 9);
+            } else {
+                bits.write_bit(false); // reserved
+            }
+        }
+        if let Some(complexity_index) = self.complexity_index {
+            bits.write_bits(0, 7); // reserved
+            bits.write_bit(true);
+            bits.write_bits(complexity_index as u64, 8);
+        }
+        writer.write_all(&bits.into_bytes())?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_ec3() {
+        let src_box = Ec3Box::new(&Ec3Config::default()).unwrap();
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::Ec3Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Ec3Box::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_dec3_atmos() {
+        // 7.1 at 768 kbit/s with a Dolby Atmos complexity index of 16.
+        let data = [0x18, 0x00, 0x20, 0x0F, 0x02, 0x02, 0x01, 0x10];
+        let mut buf = Vec::new();
+        BoxHeader::new(BoxType::Dec3Box, HEADER_SIZE + data.len() as u64)
+            .write(&mut buf)
+            .unwrap();
+        buf.extend_from_slice(&data);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let dec3 = Dec3Box::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(dec3.data_rate, 768);
+        assert_eq!(dec3.substreams.len(), 1);
+        assert_eq!(dec3.substreams[0].bsid, 16);
+        assert_eq!(dec3.substreams[0].num_dep_sub, 1);
+        assert_eq!(dec3.complexity_index, Some(16));
+
+        let layout = dec3.channel_layout().unwrap();
+        assert_eq!(layout.channel_mode, Ac3ChannelMode::ThreeTwo);
+        assert!(layout.lfe);
+        assert_eq!(layout.channel_count(), 8);
+
+        let mut dst_buf = Vec::new();
+        dec3.write_box(&mut dst_buf).unwrap();
+        assert_eq!(buf, dst_buf);
+    }
+
+    #[test]
+    fn test_dec3_no_substreams() {
+        let dec3 = Dec3Box {
+            data_rate: 0,
+            substreams: Vec::new(),
+            complexity_index: None,
+        };
+        let mut buf = Vec::new();
+        assert!(dec3.write_box(&mut buf).is_err());
+        assert!(buf.is_empty());
+    }
+}
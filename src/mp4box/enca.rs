@@ -100,7 +100,12 @@ impl<R: Read + Seek> ReadBox<&mut R> for EncaBox {
 
         let original_format: BoxType = sinf.frma.original_format.into();
         if original_format == BoxType::Mp4aBox {
-            mp4a = Some(Mp4aBox::read_box(reader, size)?);
+            // The original entry reads sinf as an unknown box.
+            let mut entry = Mp4aBox::read_box(reader, size)?;
+            entry
+                .unknown_boxes
+                .retain(|unknown| unknown.box_type != BoxType::SinfBox);
+            mp4a = Some(entry);
         }
 
         skip_bytes_to(reader, start + size)?;
@@ -16,6 +16,9 @@ pub struct EncvBox {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub vp09: Option<Vp09Box>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub av01: Option<Av01Box>,
+
     pub sinf: SinfBox,
 }
 
@@ -35,6 +38,9 @@ impl EncvBox {
         } else if let Some(ref vp09) = self.vp09 {
             // HEADER_SIZE intentionally omitted
             size += vp09.box_size();
+        } else if let Some(ref av01) = self.av01 {
+            // HEADER_SIZE intentionally omitted
+            size += av01.box_size();
         } else {
             size += HEADER_SIZE + RESERVED_DATA_SIZE;
         }
@@ -63,6 +69,8 @@ impl Mp4Box for EncvBox {
             hev1.summary()
         } else if let Some(ref vp09) = self.vp09 {
             vp09.summary()
+        } else if let Some(ref av01) = self.av01 {
+            av01.summary()
         } else {
             Err(Error::InvalidData(""))
         };
@@ -82,6 +90,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for EncvBox {
         let mut avc1 = None;
         let mut hev1 = None;
         let mut vp09 = None;
+        let mut av01 = None;
         let mut sinf = None;
 
         // skip current container items
@@ -124,18 +133,50 @@ impl<R: Read + Seek> ReadBox<&mut R> for EncvBox {
             BoxType::Hev1Box => {
                 hev1 = Some(Hev1Box::read_box(reader, size)?);
             }
+            BoxType::Hvc1Box => {
+                hev1 = Some(Hev1Box {
+                    inband_parameter_sets: false,
+                    ..Hev1Box::read_box(reader, size)?
+                });
+            }
             BoxType::Vp09Box => {
                 vp09 = Some(Vp09Box::read_box(reader, size)?);
             }
+            BoxType::Av01Box => {
+                av01 = Some(Av01Box::read_box(reader, size)?);
+            }
             _ => (),
         }
 
+        // The original entry reads sinf as an unknown box.
+        if let Some(ref mut entry) = avc1 {
+            entry
+                .unknown_boxes
+                .retain(|unknown| unknown.box_type != BoxType::SinfBox);
+        }
+        if let Some(ref mut entry) = hev1 {
+            entry
+                .unknown_boxes
+                .retain(|unknown| unknown.box_type != BoxType::SinfBox);
+        }
+        if let Some(ref mut entry) = vp09 {
+            entry
+                .unknown_boxes
+                .retain(|unknown| unknown.box_type != BoxType::SinfBox);
+        }
+        if let Some(ref mut entry) = av01 {
+            entry
+                .unknown_boxes
+                .retain(|unknown| unknown.box_type != BoxType::SinfBox);
+        }
+
         skip_bytes_to(reader, start + size)?;
 
         Ok(EncvBox {
             avc1,
             hev1,
             vp09,
+            av01,
             sinf,
         })
     }
@@ -164,6 +205,12 @@ impl<W: Write> WriteBox<&mut W> for EncvBox {
             let mut buf = Vec::with_capacity(vp09.box_size() as usize);
             vp09.write_box(&mut buf)?;
             writer.write_all(&buf[HEADER_SIZE as usize..])?;
+        } else if let Some(ref av01) = self.av01 {
+            // the encv box header is used, so the header from this box
+            // must be removed
+            let mut buf = Vec::with_capacity(av01.box_size() as usize);
+            av01.write_box(&mut buf)?;
+            writer.write_all(&buf[HEADER_SIZE as usize..])?;
         } else {
             writer.write_all(&[0; RESERVED_DATA_SIZE as usize])?;
         }
@@ -0,0 +1,452 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::bitreader::{BitReader, BitWriter};
+use crate::mp4box::*;
+
+const FLAC_METADATA_STREAMINFO: u8 = 0;
+const FLAC_STREAMINFO_SIZE: usize = 34;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FlacBox {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    /// 0 for sample rates above 65535 Hz, see `dfla` for the actual rate.
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    pub dfla: DflaBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
+}
+
+impl Default for FlacBox {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            dfla: DflaBox::default(),
+            unknown_boxes: Vec::new(),
+        }
+    }
+}
+
+impl FlacBox {
+    pub fn new(config: &FlacConfig) -> Self {
+        let stream_info = &config.stream_info;
+        let samplerate = if stream_info.sample_rate <= u16::MAX as u32 {
+            FixedPointU16::new(stream_info.sample_rate as u16)
+        } else {
+            FixedPointU16::new_raw(0)
+        };
+        Self {
+            data_reference_index: 1,
+            channelcount: stream_info.channel_count as u16,
+            samplesize: stream_info.bits_per_sample as u16,
+            samplerate,
+            dfla: DflaBox::new(config),
+            unknown_boxes: Vec::new(),
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::FlacBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 20 + self.dfla.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
+    }
+}
+
+impl Mp4Box for FlacBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount, self.samplesize, self.dfla.stream_info.sample_rate
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for FlacBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+        reader.read_u64::<BigEndian>()?; // reserved
+        let channelcount = reader.read_u16::<BigEndian>()?;
+        let samplesize = reader.read_u16::<BigEndian>()?;
+        reader.read_u32::<BigEndian>()?; // pre-defined, reserved
+        let samplerate = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+
+        let mut dfla = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "fLaC box contains a box with a larger size than it",
+                ));
+            }
+            if name == BoxType::DflaBox {
+                dfla = Some(DflaBox::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
+        }
+        let dfla = dfla.ok_or(Error::BoxNotFound(BoxType::DflaBox))?;
+
+        skip_bytes_to(reader, end)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(FlacBox {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            dfla,
+            unknown_boxes,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for FlacBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
+        self.dfla.write_box(writer)?;
+        unknown.finish(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// FLAC specific box, holding the FLAC metadata blocks of the stream.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct DflaBox {
+    pub version: u8,
+    pub flags: u32,
+    pub stream_info: FlacStreamInfo,
+    /// The metadata blocks following `STREAMINFO`, such as `SEEKTABLE` or
+    /// `VORBIS_COMMENT`.
+    pub metadata_blocks: Vec<FlacMetadataBlock>,
+}
+
+/// The `STREAMINFO` metadata block of a FLAC stream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FlacStreamInfo {
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+    /// Minimum frame size in bytes, 0 if unknown.
+    pub min_frame_size: u32,
+    /// Maximum frame size in bytes, 0 if unknown.
+    pub max_frame_size: u32,
+    pub sample_rate: u32,
+    pub channel_count: u8,
+    pub bits_per_sample: u8,
+    /// Samples per channel in the stream, 0 if unknown.
+    pub total_samples: u64,
+    /// MD5 of the unencoded audio data, all zero if unknown.
+    pub md5: [u8; 16],
+}
+
+impl Default for FlacStreamInfo {
+    fn default() -> Self {
+        Self {
+            min_block_size: 4096,
+            max_block_size: 4096,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            sample_rate: 48000,
+            channel_count: 2,
+            bits_per_sample: 16,
+            total_samples: 0,
+            md5: [0; 16],
+        }
+    }
+}
+
+impl FlacStreamInfo {
+    fn read(data: &[u8]) -> Result<Self> {
+        let mut bits = BitReader::new(data);
+        let min_block_size = bits.read_bits(16)? as u16;
+        let max_block_size = bits.read_bits(16)? as u16;
+        let min_frame_size = bits.read_bits(24)? as u32;
+        let max_frame_size = bits.read_bits(24)? as u32;
+        let sample_rate = bits.read_bits(20)? as u32;
+        let channel_count = bits.read_bits(3)? as u8 + 1;
+        let bits_per_sample = bits.read_bits(5)? as u8 + 1;
+        let total_samples = bits.read_bits(36)?;
+        let mut md5 = [0; 16];
+        for byte in md5.iter_mut() {
+            *byte = bits.read_bits(8)? as u8;
+        }
+        Ok(FlacStreamInfo {
+            min_block_size,
+            max_block_size,
+            min_frame_size,
+            max_frame_size,
+            sample_rate,
+            channel_count,
+            bits_per_sample,
+            total_samples,
+            md5,
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        if !(1..=8).contains(&self.channel_count) || !(4..=32).contains(&self.bits_per_sample) {
+            return Err(Error::InvalidData("invalid flac stream info"));
+        }
+        let mut bits = BitWriter::new();
+        bits.write_bits(self.min_block_size as u64, 16);
+        bits.write_bits(self.max_block_size as u64, 16);
+        bits.write_bits(self.min_frame_size as u64, 24);
+        bits.write_bits(self.max_frame_size as u64, 24);
+        bits.write_bits(self.sample_rate as u64, 20);
+        bits.write_bits(self.channel_count as u64 - 1, 3);
+        bits.write_bits(self.bits_per_sample as u64 - 1, 5);
+        bits.write_bits(self.total_samples, 36);
+        for byte in self.md5.iter() {
+            bits.write_bits(*byte as u64, 8);
+        }
+        Ok(bits.into_bytes())
+    }
+}
+
+/// A FLAC metadata block other than `STREAMINFO`, kept as it is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FlacMetadataBlock {
+    pub block_type: u8,
+    pub data: Vec<u8>,
+}
+
+impl DflaBox {
+    pub fn new(config: &FlacConfig) -> Self {
+        Self {
+            version: 0,
+            flags: 0,
+            stream_info: config.stream_info.clone(),
+            metadata_blocks: config.metadata_blocks.clone(),
+        }
+    }
+}
+
+impl Mp4Box for DflaBox {
+    fn box_type(&self) -> BoxType {
+        BoxType::DflaBox
+    }
+
+    fn box_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 4 + FLAC_STREAMINFO_SIZE as u64;
+        for block in self.metadata_blocks.iter() {
+            size += 4 + block.data.len() as u64;
+        }
+        size
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "sample_rate={} channel_count={} bits_per_sample={} total_samples={} metadata_blocks={}",
+            self.stream_info.sample_rate,
+            self.stream_info.channel_count,
+            self.stream_info.bits_per_sample,
+            self.stream_info.total_samples,
+            self.metadata_blocks.len()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for DflaBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let (version, flags) = read_box_header_ext(reader)?;
+        if version != 0 {
+            return Err(Error::InvalidData("unsupported dfla version"));
+        }
+
+        let mut stream_info = None;
+        let mut metadata_blocks = Vec::new();
+        let end = start + size;
+        loop {
+            if reader.stream_position()? + 4 > end {
+                return Err(Error::InvalidData("dfla metadata block exceeds the box"));
+            }
+            let header = reader.read_u8()?;
+            let last_block = header & 0x80 != 0;
+            let block_type = header & 0x7F;
+            let length = reader.read_u24::<BigEndian>()? as u64;
+            if reader.stream_position()? + length > end {
+                return Err(Error::InvalidData("dfla metadata block exceeds the box"));
+            }
+            let mut data = vec![0; length as usize];
+            reader.read_exact(&mut data)?;
+
+            if stream_info.is_none() {
+                if block_type != FLAC_METADATA_STREAMINFO || data.len() != FLAC_STREAMINFO_SIZE {
+                    return Err(Error::InvalidData(
+                        "dfla does not start with a streaminfo block",
+                    ));
+                }
+                stream_info = Some(FlacStreamInfo::read(&data)?);
+            } else {
+                metadata_blocks.push(FlacMetadataBlock { block_type, data });
+            }
+            if last_block {
+                break;
+            }
+        }
+
+        skip_bytes_to(reader, end)?;
+
+        Ok(DflaBox {
+            version,
+            flags,
+            stream_info: stream_info.unwrap(),
+            metadata_blocks,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for DflaBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+
+        let last_flag = |last: bool| if last { 0x80 } else { 0 };
+        writer.write_u8(last_flag(self.metadata_blocks.is_empty()) | FLAC_METADATA_STREAMINFO)?;
+        writer.write_u24::<BigEndian>(FLAC_STREAMINFO_SIZE as u32)?;
+        writer.write_all(&self.stream_info.to_bytes()?)?;
+        for (i, block) in self.metadata_blocks.iter().enumerate() {
+            if block.block_type == FLAC_METADATA_STREAMINFO || block.block_type == 0x7F {
+                return Err(Error::InvalidData("invalid flac metadata block type"));
+            }
+            let last = i + 1 == self.metadata_blocks.len();
+            writer.write_u8(last_flag(last) | block.block_type)?;
+            writer.write_u24::<BigEndian>(block.data.len() as u32)?;
+            writer.write_all(&block.data)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_flac() {
+        let src_box = FlacBox::new(&FlacConfig::default());
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::FlacBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = FlacBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_dfla_metadata_blocks() {
+        let src_box = DflaBox {
+            version: 0,
+            flags: 0,
+            stream_info: FlacStreamInfo {
+                min_frame_size: 14,
+                max_frame_size: 12288,
+                sample_rate: 96000,
+                bits_per_sample: 24,
+                total_samples: 0x1_2345_6789,
+                md5: *b"0123456789abcdef",
+                ..FlacStreamInfo::default()
+            },
+            // A padding block.
+            metadata_blocks: vec![FlacMetadataBlock {
+                block_type: 1,
+                data: vec![0; 8],
+            }],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+        // STREAMINFO is not the last block, the padding is.
+        assert_eq!(buf[12], 0x00);
+        assert_eq!(buf[50], 0x81);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::DflaBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = DflaBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        // Above 65535 Hz the sample entry leaves the rate to dfLa.
+        let flac = FlacBox::new(&FlacConfig {
+            stream_info: src_box.stream_info.clone(),
+            metadata_blocks: Vec::new(),
+        });
+        assert_eq!(flac.samplerate.raw_value(), 0);
+        assert_eq!(flac.samplesize, 24);
+    }
+}
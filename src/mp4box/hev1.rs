@@ -2,8 +2,13 @@ use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
 use std::io::{Read, Seek, Write};
 
+use crate::bitreader::{nal_unit_rbsp, BitReader};
 use crate::mp4box::*;
 
+pub(crate) const HEVC_NAL_VPS: u8 = 32;
+pub(crate) const HEVC_NAL_SPS: u8 = 33;
+pub(crate) const HEVC_NAL_PPS: u8 = 34;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Hev1Box {
     pub data_reference_index: u16,
@@ -17,7 +22,13 @@ pub struct Hev1Box {
     pub vertresolution: FixedPointU16,
     pub frame_count: u16,
     pub depth: u16,
+    /// Written as `hev1` if parameter sets may also be sent in the samples,
+    /// or as `hvc1` if they are only stored in `hvcc`.
+    pub inband_parameter_sets: bool,
     pub hvcc: HvcCBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl Default for Hev1Box {
@@ -30,14 +41,22 @@ impl Default for Hev1Box {
             vertresolution: FixedPointU16::new(0x48),
             frame_count: 1,
             depth: 0x0018,
+            inband_parameter_sets: true,
             hvcc: HvcCBox::default(),
+            unknown_boxes: Vec::new(),
         }
     }
 }
 
 impl Hev1Box {
-    pub fn new(config: &HevcConfig) -> Self {
-        Hev1Box {
+    /// An `hvc1` sample entry holding the parameter sets of `config`. If one
+    /// of them is missing, the samples have to carry it and the entry is
+    /// written as `hev1` instead.
+    pub fn new(config: &HevcConfig) -> Result<Self> {
+        let inband_parameter_sets = config.video_param_set.is_empty()
+            || config.seq_param_set.is_empty()
+            || config.pic_param_set.is_empty();
+        Ok(Hev1Box {
             data_reference_index: 1,
             width: config.width,
             height: config.height,
@@ -45,16 +64,30 @@ impl Hev1Box {
             vertresolution: FixedPointU16::new(0x48),
             frame_count: 1,
             depth: 0x0018,
-            hvcc: HvcCBox::new(),
-        }
+            inband_parameter_sets,
+            hvcc: HvcCBox::from_parameter_sets(
+                &config.video_param_set,
+                &config.seq_param_set,
+                &config.pic_param_set,
+            )?,
+            unknown_boxes: Vec::new(),
+        })
     }
 
     pub fn get_type(&self) -> BoxType {
-        BoxType::Hev1Box
+        if self.inband_parameter_sets {
+            BoxType::Hev1Box
+        } else {
+            BoxType::Hvc1Box
+        }
     }
 
     pub fn get_size(&self) -> u64 {
-        HEADER_SIZE + 8 + 70 + self.hvcc.box_size()
+        let mut size = HEADER_SIZE + 8 + 70 + self.hvcc.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
     }
 }
 
@@ -101,31 +134,46 @@ impl<R: Read + Seek> ReadBox<&mut R> for Hev1Box {
         let depth = reader.read_u16::<BigEndian>()?;
         reader.read_i16::<BigEndian>()?; // pre-defined
 
-        let header = BoxHeader::read(reader)?;
-        let BoxHeader { name, size: s } = header;
-        if s > size {
-            return Err(Error::InvalidData(
-                "hev1 box contains a box with a larger size than it",
-            ));
-        }
-        if name == BoxType::HvcCBox {
-            let hvcc = HvcCBox::read_box(reader, s)?;
-
-            skip_bytes_to(reader, start + size)?;
-
-            Ok(Hev1Box {
-                data_reference_index,
-                width,
-                height,
-                horizresolution,
-                vertresolution,
-                frame_count,
-                depth,
-                hvcc,
-            })
-        } else {
-            Err(Error::InvalidData("hvcc not found"))
+        let mut hvcc = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "hev1 box contains a box with a larger size than it",
+                ));
+            }
+            if name == BoxType::HvcCBox {
+                hvcc = Some(HvcCBox::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
         }
+        let hvcc = hvcc.ok_or(Error::InvalidData("hvcc not found"))?;
+
+        skip_bytes_to(reader, end)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(Hev1Box {
+            data_reference_index,
+            width,
+            height,
+            horizresolution,
+            vertresolution,
+            frame_count,
+            depth,
+            inband_parameter_sets: true,
+            hvcc,
+            unknown_boxes,
+        })
     }
 }
 
@@ -152,7 +200,10 @@ impl<W: Write> WriteBox<&mut W> for Hev1Box {
         writer.write_u16::<BigEndian>(self.depth)?;
         writer.write_i16::<BigEndian>(-1)?; // pre-defined
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
         self.hvcc.write_box(writer)?;
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -187,6 +238,152 @@ impl HvcCBox {
             ..Default::default()
         }
     }
+
+    /// Build the configuration of a stream from its parameter sets, given
+    /// as NAL units without start codes. Empty parameter sets are left out.
+    ///
+    /// The profile, tier, level, chroma format, bit depths and temporal
+    /// layering are taken from the SPS.
+    pub fn from_parameter_sets(vps: &[u8], sps: &[u8], pps: &[u8]) -> Result<Self> {
+        let mut hvcc = HvcCBox {
+            configuration_version: 1,
+            length_size_minus_one: 3,
+            ..Default::default()
+        };
+
+        if !sps.is_empty() {
+            let info = parse_sps(sps)?;
+            hvcc.general_profile_space = info.profile_space;
+            hvcc.general_tier_flag = info.tier_flag;
+            hvcc.general_profile_idc = info.profile_idc;
+            hvcc.general_profile_compatibility_flags = info.profile_compatibility_flags;
+            hvcc.general_constraint_indicator_flag = info.constraint_indicator_flags;
+            hvcc.general_level_idc = info.level_idc;
+            hvcc.chroma_format_idc = info.chroma_format_idc;
+            hvcc.bit_depth_luma_minus8 = info.bit_depth_luma_minus8;
+            hvcc.bit_depth_chroma_minus8 = info.bit_depth_chroma_minus8;
+            hvcc.num_temporal_layers = info.max_sub_layers_minus1 + 1;
+            hvcc.temporal_id_nested = info.temporal_id_nesting;
+        }
+
+        for (nal_unit_type, data) in [
+            (HEVC_NAL_VPS, vps),
+            (HEVC_NAL_SPS, sps),
+            (HEVC_NAL_PPS, pps),
+        ] {
+            if data.is_empty() {
+                continue;
+            }
+            hvcc.arrays.push(HvcCArray {
+                completeness: true,
+                nal_unit_type,
+                nalus: vec![HvcCArrayNalu {
+                    size: data.len() as u16,
+                    data: data.to_vec(),
+                }],
+            });
+        }
+        Ok(hvcc)
+    }
+
+    /// The first NAL unit of the given type, such as 33 for the SPS.
+    pub fn nal_unit(&self, nal_unit_type: u8) -> Option<&[u8]> {
+        self.arrays
+            .iter()
+            .filter(|array| array.nal_unit_type == nal_unit_type)
+            .flat_map(|array| array.nalus.iter())
+            .map(|nalu| &nalu.data[..])
+            .next()
+    }
+}
+
+/// The fields of an H.265 sequence parameter set that `hvcC` repeats.
+struct SpsInfo {
+    max_sub_layers_minus1: u8,
+    temporal_id_nesting: bool,
+    profile_space: u8,
+    tier_flag: bool,
+    profile_idc: u8,
+    profile_compatibility_flags: u32,
+    constraint_indicator_flags: u64,
+    level_idc: u8,
+    chroma_format_idc: u8,
+    bit_depth_luma_minus8: u8,
+    bit_depth_chroma_minus8: u8,
+}
+
+fn parse_sps(sps: &[u8]) -> Result<SpsInfo> {
+    let rbsp = nal_unit_rbsp(sps);
+    let mut reader = BitReader::new(&rbsp);
+
+    // NAL unit header.
+    reader.skip_bits(1)?;
+    if reader.read_bits(6)? as u8 != HEVC_NAL_SPS {
+        return Err(Error::InvalidData("not an hevc sequence parameter set"));
+    }
+    reader.skip_bits(9)?;
+
+    reader.skip_bits(4)?; // sps_video_parameter_set_id
+    let max_sub_layers_minus1 = reader.read_bits(3)? as u8;
+    let temporal_id_nesting = reader.read_bit()?;
+
+    // profile_tier_level
+    let profile_space = reader.read_bits(2)? as u8;
+    let tier_flag = reader.read_bit()?;
+    let profile_idc = reader.read_bits(5)? as u8;
+    let profile_compatibility_flags = reader.read_bits(32)? as u32;
+    let constraint_indicator_flags = reader.read_bits(48)?;
+    let level_idc = reader.read_bits(8)? as u8;
+    let mut sub_layers = Vec::with_capacity(max_sub_layers_minus1 as usize);
+    for _ in 0..max_sub_layers_minus1 {
+        let profile_present = reader.read_bit()?;
+        let level_present = reader.read_bit()?;
+        sub_layers.push((profile_present, level_present));
+    }
+    if max_sub_layers_minus1 > 0 {
+        reader.skip_bits(2 * (8 - max_sub_layers_minus1 as usize))?; // reserved
+    }
+    for (profile_present, level_present) in sub_layers {
+        if profile_present {
+            reader.skip_bits(88)?;
+        }
+        if level_present {
+            reader.skip_bits(8)?;
+        }
+    }
+
+    reader.read_ue()?; // sps_seq_parameter_set_id
+    let chroma_format_idc = reader.read_ue()?;
+    if chroma_format_idc == 3 {
+        reader.skip_bits(1)?; // separate_colour_plane_flag
+    }
+    reader.read_ue()?; // pic_width_in_luma_samples
+    reader.read_ue()?; // pic_height_in_luma_samples
+    if reader.read_bit()? {
+        // conformance window offsets
+        for _ in 0..4 {
+            reader.read_ue()?;
+        }
+    }
+    let bit_depth_luma_minus8 = reader.read_ue()?;
+    let bit_depth_chroma_minus8 = reader.read_ue()?;
+    if chroma_format_idc > 3 || bit_depth_luma_minus8 > 7 || bit_depth_chroma_minus8 > 7 {
+        return Err(Error::InvalidData("invalid hevc sequence parameter set"));
+    }
+
+    Ok(SpsInfo {
+        max_sub_layers_minus1,
+        temporal_id_nesting,
+        profile_space,
+        tier_flag,
+        profile_idc,
+        profile_compatibility_flags,
+        constraint_indicator_flags,
+        level_idc,
+        chroma_format_idc: chroma_format_idc as u8,
+        bit_depth_luma_minus8: bit_depth_luma_minus8 as u8,
+        bit_depth_chroma_minus8: bit_depth_chroma_minus8 as u8,
+    })
 }
 
 impl Mp4Box for HvcCBox {
@@ -248,8 +445,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for HvcCBox {
     fn read_box(reader: &mut R, _size: u64) -> Result<Self> {
         let configuration_version = reader.read_u8()?;
         let params = reader.read_u8()?;
-        let general_profile_space = params & 0b11000000 >> 6;
-        let general_tier_flag = (params & 0b00100000 >> 5) > 0;
+        let general_profile_space = (params & 0b11000000) >> 6;
+        let general_tier_flag = (params & 0b00100000) > 0;
         let general_profile_idc = params & 0b00011111;
 
         let general_profile_compatibility_flags = reader.read_u32::<BigEndian>()?;
@@ -263,9 +460,9 @@ impl<R: Read + Seek> ReadBox<&mut R> for HvcCBox {
         let avg_frame_rate = reader.read_u16::<BigEndian>()?;
 
         let params = reader.read_u8()?;
-        let constant_frame_rate = params & 0b11000000 >> 6;
-        let num_temporal_layers = params & 0b00111000 >> 3;
-        let temporal_id_nested = (params & 0b00000100 >> 2) > 0;
+        let constant_frame_rate = (params & 0b11000000) >> 6;
+        let num_temporal_layers = (params & 0b00111000) >> 3;
+        let temporal_id_nested = (params & 0b00000100) > 0;
         let length_size_minus_one = params & 0b000011;
 
         let num_of_arrays = reader.read_u8()?;
@@ -330,11 +527,12 @@ impl<W: Write> WriteBox<&mut W> for HvcCBox {
         writer.write_u48::<BigEndian>(self.general_constraint_indicator_flag)?;
         writer.write_u8(self.general_level_idc)?;
 
-        writer.write_u16::<BigEndian>(self.min_spatial_segmentation_idc & 0x0FFF)?;
-        writer.write_u8(self.parallelism_type & 0b11)?;
-        writer.write_u8(self.chroma_format_idc & 0b11)?;
-        writer.write_u8(self.bit_depth_luma_minus8 & 0b111)?;
-        writer.write_u8(self.bit_depth_chroma_minus8 & 0b111)?;
+        // Reserved bits are all set.
+        writer.write_u16::<BigEndian>(0xF000 | self.min_spatial_segmentation_idc & 0x0FFF)?;
+        writer.write_u8(0b11111100 | self.parallelism_type & 0b11)?;
+        writer.write_u8(0b11111100 | self.chroma_format_idc & 0b11)?;
+        writer.write_u8(0b11111000 | self.bit_depth_luma_minus8 & 0b111)?;
+        writer.write_u8(0b11111000 | self.bit_depth_chroma_minus8 & 0b111)?;
         writer.write_u16::<BigEndian>(self.avg_frame_rate)?;
 
         let constant_frame_rate = (self.constant_frame_rate & 0b11) << 6;
@@ -375,10 +573,12 @@ mod tests {
             vertresolution: FixedPointU16::new(0x48),
             frame_count: 1,
             depth: 24,
+            inband_parameter_sets: true,
             hvcc: HvcCBox {
                 configuration_version: 1,
                 ..Default::default()
             },
+            ..Default::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -392,4 +592,76 @@ mod tests {
         let dst_box = Hev1Box::read_box(&mut reader, header.size).unwrap();
         assert_eq!(src_box, dst_box);
     }
+
+    #[test]
+    fn test_hvc1() {
+        let src_box = Hev1Box {
+            data_reference_index: 1,
+            width: 1280,
+            height: 720,
+            inband_parameter_sets: false,
+            hvcc: HvcCBox::new(),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::Hvc1Box);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Hev1Box::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box.hvcc, dst_box.hvcc);
+    }
+
+    #[test]
+    fn test_hev1_new_without_parameter_sets() {
+        let hev1 = Hev1Box::new(&HevcConfig::default()).unwrap();
+        assert!(hev1.inband_parameter_sets);
+        assert_eq!(hev1.box_type(), BoxType::Hev1Box);
+        assert!(hev1.hvcc.arrays.is_empty());
+    }
+
+    #[test]
+    fn test_hvcc_from_parameter_sets() {
+        // 1280x720 Main profile, level 3.1.
+        let vps = [
+            0x40, 0x01, 0x0C, 0x01, 0xFF, 0xFF, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00,
+            0x00, 0x03, 0x00, 0x00, 0x03, 0x00, 0x5D, 0x95, 0x98, 0x09,
+        ];
+        let sps = [
+            0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00,
+            0x00, 0x03, 0x00, 0x5D, 0xA0, 0x02, 0x80, 0x80, 0x2D, 0x16, 0x59, 0x59, 0xA4, 0x93,
+            0x2B, 0xC0, 0x5A, 0x70, 0x80, 0x00, 0x01, 0xF4, 0x80, 0x00, 0x3A, 0x98, 0x04,
+        ];
+        let pps = [0x44, 0x01, 0xC1, 0x72, 0xB4, 0x62, 0x40];
+
+        let hvcc = HvcCBox::from_parameter_sets(&vps, &sps, &pps).unwrap();
+        assert_eq!(hvcc.general_profile_space, 0);
+        assert!(!hvcc.general_tier_flag);
+        assert_eq!(hvcc.general_profile_idc, 1);
+        assert_eq!(hvcc.general_profile_compatibility_flags, 0x6000_0000);
+        assert_eq!(hvcc.general_constraint_indicator_flag, 0x9000_0000_0000);
+        assert_eq!(hvcc.general_level_idc, 93);
+        assert_eq!(hvcc.chroma_format_idc, 1);
+        assert_eq!(hvcc.bit_depth_luma_minus8, 0);
+        assert_eq!(hvcc.bit_depth_chroma_minus8, 0);
+        assert_eq!(hvcc.num_temporal_layers, 1);
+        assert!(hvcc.temporal_id_nested);
+        assert_eq!(hvcc.nal_unit(HEVC_NAL_VPS), Some(&vps[..]));
+        assert_eq!(hvcc.nal_unit(HEVC_NAL_SPS), Some(&sps[..]));
+        assert_eq!(hvcc.nal_unit(HEVC_NAL_PPS), Some(&pps[..]));
+
+        let mut buf = Vec::new();
+        hvcc.write_box(&mut buf).unwrap();
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::HvcCBox);
+        let dst_box = HvcCBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(hvcc, dst_box);
+
+        assert!(HvcCBox::from_parameter_sets(&vps, &pps, &sps).is_err());
+    }
 }
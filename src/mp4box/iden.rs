@@ -3,19 +3,11 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct IdenBox {
     pub cue_id: String,
 }
 
-impl Default for IdenBox {
-    fn default() -> Self {
-        IdenBox {
-            cue_id: String::new(),
-        }
-    }
-}
-
 impl IdenBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::IdenBox
@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom};
 
 use byteorder::ByteOrder;
 use serde::Serialize;
@@ -8,9 +8,16 @@ use serde::Serialize;
 use crate::mp4box::data::DataBox;
 use crate::mp4box::*;
 
+const FREEFORM: [u8; 4] = *b"----";
+const MEAN: [u8; 4] = *b"mean";
+const NAME: [u8; 4] = *b"name";
+
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct IlstBox {
     pub items: HashMap<MetadataKey, IlstItemBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl IlstBox {
@@ -20,11 +27,141 @@ impl IlstBox {
 
     pub fn get_size(&self) -> u64 {
         let mut size = HEADER_SIZE;
-        for item in self.items.values() {
-            size += item.get_size();
+        for (key, item) in self.items.iter() {
+            size += item_size(key, item);
+        }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
         }
         size
     }
+
+    /// Sets a text item such as [`MetadataKey::Artist`] or one of the sort
+    /// fields.
+    pub fn set_text(&mut self, key: MetadataKey, value: &str) {
+        self.set_item(key, DataType::Text, value.as_bytes().to_vec());
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.set_text(MetadataKey::Title, title);
+    }
+
+    pub fn set_year(&mut self, year: u32) {
+        self.set_text(MetadataKey::Year, &year.to_string());
+    }
+
+    /// Sets a single JPEG cover image.
+    pub fn set_poster(&mut self, poster: Vec<u8>) {
+        self.set_item(MetadataKey::Poster, DataType::Image, poster);
+    }
+
+    /// Sets the cover images; `data_type` of each is [`DataType::Image`] for
+    /// JPEG or [`DataType::Png`].
+    pub fn set_posters(&mut self, posters: Vec<DataBox>) {
+        let mut posters = posters.into_iter();
+        if let Some(data) = posters.next() {
+            let item = IlstItemBox {
+                data,
+                additional_data: posters.collect(),
+                ..Default::default()
+            };
+            self.items.insert(MetadataKey::Poster, item);
+        } else {
+            self.items.remove(&MetadataKey::Poster);
+        }
+    }
+
+    pub fn set_summary(&mut self, summary: &str) {
+        self.set_text(MetadataKey::Summary, summary);
+    }
+
+    pub fn set_artist(&mut self, artist: &str) {
+        self.set_text(MetadataKey::Artist, artist);
+    }
+
+    pub fn set_album(&mut self, album: &str) {
+        self.set_text(MetadataKey::Album, album);
+    }
+
+    pub fn set_album_artist(&mut self, album_artist: &str) {
+        self.set_text(MetadataKey::AlbumArtist, album_artist);
+    }
+
+    pub fn set_genre(&mut self, genre: &str) {
+        self.set_text(MetadataKey::Genre, genre);
+    }
+
+    /// Sets the track number and the total number of tracks, 0 if unknown.
+    pub fn set_track_number(&mut self, number: u16, total: u16) {
+        let mut data = vec![0u8; 8];
+        BigEndian::write_u16(&mut data[2..4], number);
+        BigEndian::write_u16(&mut data[4..6], total);
+        self.set_item(MetadataKey::TrackNumber, DataType::Binary, data);
+    }
+
+    /// Sets the disc number and the total number of discs, 0 if unknown.
+    pub fn set_disc_number(&mut self, number: u16, total: u16) {
+        let mut data = vec![0u8; 6];
+        BigEndian::write_u16(&mut data[2..4], number);
+        BigEndian::write_u16(&mut data[4..6], total);
+        self.set_item(MetadataKey::DiscNumber, DataType::Binary, data);
+    }
+
+    pub fn set_composer(&mut self, composer: &str) {
+        self.set_text(MetadataKey::Composer, composer);
+    }
+
+    pub fn set_comment(&mut self, comment: &str) {
+        self.set_text(MetadataKey::Comment, comment);
+    }
+
+    pub fn set_encoder(&mut self, encoder: &str) {
+        self.set_text(MetadataKey::Encoder, encoder);
+    }
+
+    pub fn set_copyright(&mut self, copyright: &str) {
+        self.set_text(MetadataKey::Copyright, copyright);
+    }
+
+    /// Sets the content rating: 0 for none, 1 for explicit and 2 for clean.
+    pub fn set_rating(&mut self, rating: u8) {
+        self.set_item(MetadataKey::Rating, DataType::TempoCpil, vec![rating]);
+    }
+
+    pub fn set_tv_show(&mut self, tv_show: &str) {
+        self.set_text(MetadataKey::TvShow, tv_show);
+    }
+
+    pub fn set_tv_season(&mut self, season: u32) {
+        let data = season.to_be_bytes().to_vec();
+        self.set_item(MetadataKey::TvSeason, DataType::TempoCpil, data);
+    }
+
+    pub fn set_tv_episode(&mut self, episode: u32) {
+        let data = episode.to_be_bytes().to_vec();
+        self.set_item(MetadataKey::TvEpisode, DataType::TempoCpil, data);
+    }
+
+    /// Sets a free-form `----` text item.
+    pub fn set_freeform(&mut self, mean: &str, name: &str, value: &str) {
+        let key = MetadataKey::Freeform {
+            mean: mean.to_string(),
+            name: name.to_string(),
+        };
+        self.set_text(key, value);
+    }
+
+    pub fn remove(&mut self, key: &MetadataKey) -> Option<IlstItemBox> {
+        self.items.remove(key)
+    }
+
+    fn set_item(&mut self, key: MetadataKey, data_type: DataType, data: Vec<u8>) {
+        let item = IlstItemBox {
+            data: DataBox { data, data_type },
+            ..Default::default()
+        };
+        self.items.insert(key, item);
+    }
 }
 
 impl Mp4Box for IlstBox {
@@ -51,6 +188,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for IlstBox {
         let start = box_start(reader)?;
 
         let mut items = HashMap::new();
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -64,31 +202,36 @@ impl<R: Read + Seek> ReadBox<&mut R> for IlstBox {
                 ));
             }
 
-            match name {
-                BoxType::NameBox => {
-                    items.insert(MetadataKey::Title, IlstItemBox::read_box(reader, s)?);
-                }
-                BoxType::DayBox => {
-                    items.insert(MetadataKey::Year, IlstItemBox::read_box(reader, s)?);
-                }
-                BoxType::CovrBox => {
-                    items.insert(MetadataKey::Poster, IlstItemBox::read_box(reader, s)?);
-                }
-                BoxType::DescBox => {
-                    items.insert(MetadataKey::Summary, IlstItemBox::read_box(reader, s)?);
-                }
-                _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+            if FourCC::from(name).value == FREEFORM {
+                let item_start = reader.stream_position()?;
+                match read_item(reader, s)? {
+                    (Some(key), item) => {
+                        items.insert(key, item);
+                    }
+                    (None, _) => {
+                        // Without mean and name there is no key for it.
+                        reader.seek(SeekFrom::Start(item_start))?;
+                        unknown.read(reader, name, s)?;
+                    }
                 }
+            } else if let Some(key) = metadata_key(name) {
+                items.insert(key, IlstItemBox::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
 
         skip_bytes_to(reader, start + size)?;
 
-        Ok(IlstBox { items })
+        let unknown_boxes = unknown.finish();
+
+        Ok(IlstBox {
+            items,
+            unknown_boxes,
+        })
     }
 }
 
@@ -97,16 +240,25 @@ impl<W: Write> WriteBox<&mut W> for IlstBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
-        for (key, value) in &self.items {
-            let name = match key {
-                MetadataKey::Title => BoxType::NameBox,
-                MetadataKey::Year => BoxType::DayBox,
-                MetadataKey::Poster => BoxType::CovrBox,
-                MetadataKey::Summary => BoxType::DescBox,
-            };
-            BoxHeader::new(name, value.get_size()).write(writer)?;
-            value.data.write_box(writer)?;
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        for (key, item) in &self.items {
+            unknown.before(writer)?;
+            BoxHeader::new(item_box_type(key), item_size(key, item)).write(writer)?;
+            let mut item_unknown = UnknownBoxWriter::new(&item.unknown_boxes);
+            if let MetadataKey::Freeform { mean, name } = key {
+                item_unknown.before(writer)?;
+                write_string_box(writer, MEAN, mean)?;
+                item_unknown.before(writer)?;
+                write_string_box(writer, NAME, name)?;
+            }
+            for data in item.data_boxes() {
+                item_unknown.before(writer)?;
+                data.write_box(writer)?;
+            }
+            item_unknown.finish(writer)?;
         }
+        unknown.finish(writer)?;
+
         Ok(size)
     }
 }
@@ -114,59 +266,200 @@ impl<W: Write> WriteBox<&mut W> for IlstBox {
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct IlstItemBox {
     pub data: DataBox,
+
+    /// Further values, such as additional cover images.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub additional_data: Vec<DataBox>,
+
+    /// Other child boxes, such as `itif`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl IlstItemBox {
     fn get_size(&self) -> u64 {
-        HEADER_SIZE + self.data.box_size()
+        let mut size = HEADER_SIZE + self.data_boxes().map(|data| data.box_size()).sum::<u64>();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
+    }
+
+    /// All values of the item, in order.
+    pub fn data_boxes(&self) -> impl Iterator<Item = &DataBox> {
+        std::iter::once(&self.data).chain(self.additional_data.iter())
     }
 }
 
 impl<R: Read + Seek> ReadBox<&mut R> for IlstItemBox {
     fn read_box(reader: &mut R, size: u64) -> Result<Self> {
-        let start = box_start(reader)?;
+        let (_, item) = read_item(reader, size)?;
+        Ok(item)
+    }
+}
 
-        let mut data = None;
+/// Read an item, along with the key of a `----` item if it has `mean` and
+/// `name` boxes.
+fn read_item<R: Read + Seek>(
+    reader: &mut R,
+    size: u64,
+) -> Result<(Option<MetadataKey>, IlstItemBox)> {
+    let start = box_start(reader)?;
+
+    let mut data = Vec::new();
+    let mut mean = None;
+    let mut name = None;
+    let mut unknown = UnknownBoxReader::default();
+
+    let mut current = reader.stream_position()?;
+    let end = start + size;
+    while current < end {
+        // Get box header.
+        let header = BoxHeader::read(reader)?;
+        let BoxHeader {
+            name: box_name,
+            size: s,
+        } = header;
+        if s > size {
+            return Err(Error::InvalidData(
+                "ilst item box contains a box with a larger size than it",
+            ));
+        }
 
-        let mut current = reader.stream_position()?;
-        let end = start + size;
-        while current < end {
-            // Get box header.
-            let header = BoxHeader::read(reader)?;
-            let BoxHeader { name, size: s } = header;
-            if s > size {
-                return Err(Error::InvalidData(
-                    "ilst item box contains a box with a larger size than it",
-                ));
+        match FourCC::from(box_name).value {
+            MEAN => mean = Some(read_string_box(reader, s)?),
+            NAME => name = Some(read_string_box(reader, s)?),
+            _ if box_name == BoxType::DataBox => {
+                data.push(DataBox::read_box(reader, s)?);
             }
-
-            match name {
-                BoxType::DataBox => {
-                    data = Some(DataBox::read_box(reader, s)?);
-                }
-                _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
-                }
+            _ => {
+                unknown.read(reader, box_name, s)?;
             }
-
-            current = reader.stream_position()?;
         }
+        unknown.child_read();
 
-        if data.is_none() {
-            return Err(Error::BoxNotFound(BoxType::DataBox));
-        }
+        current = reader.stream_position()?;
+    }
 
-        skip_bytes_to(reader, start + size)?;
+    if data.is_empty() {
+        return Err(Error::BoxNotFound(BoxType::DataBox));
+    }
 
-        Ok(IlstItemBox {
-            data: data.unwrap(),
-        })
+    skip_bytes_to(reader, start + size)?;
+
+    let key = match (mean, name) {
+        (Some(mean), Some(name)) => Some(MetadataKey::Freeform { mean, name }),
+        _ => None,
+    };
+    let unknown_boxes = unknown.finish();
+    let mut data = data.into_iter();
+    let item = IlstItemBox {
+        data: data.next().unwrap(),
+        additional_data: data.collect(),
+        unknown_boxes,
+    };
+    Ok((key, item))
+}
+
+fn item_size(key: &MetadataKey, item: &IlstItemBox) -> u64 {
+    let mut size = item.get_size();
+    if let MetadataKey::Freeform { mean, name } = key {
+        size += 2 * (HEADER_SIZE + HEADER_EXT_SIZE) + mean.len() as u64 + name.len() as u64;
+    }
+    size
+}
+
+/// Read the text of a `mean` or `name` box.
+fn read_string_box<R: Read + Seek>(reader: &mut R, size: u64) -> Result<String> {
+    let start = box_start(reader)?;
+    read_box_header_ext(reader)?;
+    let len = (start + size)
+        .checked_sub(reader.stream_position()?)
+        .ok_or(Error::InvalidData("string box size too small"))?;
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    String::from_utf8(buf).map_err(|_| Error::InvalidData("string box is not valid UTF-8"))
+}
+
+fn write_string_box<W: Write>(writer: &mut W, name: [u8; 4], value: &str) -> Result<()> {
+    let size = HEADER_SIZE + HEADER_EXT_SIZE + value.len() as u64;
+    BoxHeader::new(string_box_type(name), size).write(writer)?;
+    write_box_header_ext(writer, 0, 0)?;
+    writer.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn string_box_type(name: [u8; 4]) -> BoxType {
+    BoxType::from(u32::from_be_bytes(name))
+}
+
+fn item_fourcc(key: &MetadataKey) -> [u8; 4] {
+    match key {
+        MetadataKey::Title => *b"\xa9nam",
+        MetadataKey::Year => *b"\xa9day",
+        MetadataKey::Poster => *b"covr",
+        MetadataKey::Summary => *b"desc",
+        MetadataKey::Artist => *b"\xa9ART",
+        MetadataKey::Album => *b"\xa9alb",
+        MetadataKey::AlbumArtist => *b"aART",
+        MetadataKey::Genre => *b"\xa9gen",
+        MetadataKey::TrackNumber => *b"trkn",
+        MetadataKey::DiscNumber => *b"disk",
+        MetadataKey::Composer => *b"\xa9wrt",
+        MetadataKey::Comment => *b"\xa9cmt",
+        MetadataKey::Encoder => *b"\xa9too",
+        MetadataKey::Copyright => *b"cprt",
+        MetadataKey::Rating => *b"rtng",
+        MetadataKey::TvShow => *b"tvsh",
+        MetadataKey::TvSeason => *b"tvsn",
+        MetadataKey::TvEpisode => *b"tves",
+        MetadataKey::SortTitle => *b"sonm",
+        MetadataKey::SortArtist => *b"soar",
+        MetadataKey::SortAlbum => *b"soal",
+        MetadataKey::SortAlbumArtist => *b"soaa",
+        MetadataKey::SortComposer => *b"soco",
+        MetadataKey::SortTvShow => *b"sosn",
+        MetadataKey::Freeform { .. } => FREEFORM,
     }
 }
 
+fn item_box_type(key: &MetadataKey) -> BoxType {
+    BoxType::from(u32::from_be_bytes(item_fourcc(key)))
+}
+
+fn metadata_key(name: BoxType) -> Option<MetadataKey> {
+    let key = match &FourCC::from(name).value {
+        b"\xa9nam" => MetadataKey::Title,
+        b"\xa9day" => MetadataKey::Year,
+        b"covr" => MetadataKey::Poster,
+        b"desc" => MetadataKey::Summary,
+        b"\xa9ART" => MetadataKey::Artist,
+        b"\xa9alb" => MetadataKey::Album,
+        b"aART" => MetadataKey::AlbumArtist,
+        b"\xa9gen" => MetadataKey::Genre,
+        b"trkn" => MetadataKey::TrackNumber,
+        b"disk" => MetadataKey::DiscNumber,
+        b"\xa9wrt" => MetadataKey::Composer,
+        b"\xa9cmt" => MetadataKey::Comment,
+        b"\xa9too" => MetadataKey::Encoder,
+        b"cprt" => MetadataKey::Copyright,
+        b"rtng" => MetadataKey::Rating,
+        b"tvsh" => MetadataKey::TvShow,
+        b"tvsn" => MetadataKey::TvSeason,
+        b"tves" => MetadataKey::TvEpisode,
+        b"sonm" => MetadataKey::SortTitle,
+        b"soar" => MetadataKey::SortArtist,
+        b"soal" => MetadataKey::SortAlbum,
+        b"soaa" => MetadataKey::SortAlbumArtist,
+        b"soco" => MetadataKey::SortComposer,
+        b"sosn" => MetadataKey::SortTvShow,
+        _ => return None,
+    };
+    Some(key)
+}
+
 impl<'a> Metadata<'a> for IlstBox {
-    fn title(&self) -> Option<Cow<str>> {
+    fn title(&self) -> Option<Cow<'_, str>> {
         self.items.get(&MetadataKey::Title).map(item_to_str)
     }
 
@@ -178,27 +471,52 @@ impl<'a> Metadata<'a> for IlstBox {
         self.items.get(&MetadataKey::Poster).map(item_to_bytes)
     }
 
-    fn summary(&self) -> Option<Cow<str>> {
+    fn summary(&self) -> Option<Cow<'_, str>> {
         self.items.get(&MetadataKey::Summary).map(item_to_str)
     }
+
+    fn item(&self, key: &MetadataKey) -> Option<&IlstItemBox> {
+        self.items.get(key)
+    }
 }
 
 fn item_to_bytes(item: &IlstItemBox) -> &[u8] {
     &item.data.data
 }
 
-fn item_to_str(item: &IlstItemBox) -> Cow<str> {
+pub(crate) fn item_to_str(item: &IlstItemBox) -> Cow<'_, str> {
     String::from_utf8_lossy(&item.data.data)
 }
 
-fn item_to_u32(item: &IlstItemBox) -> Option<u32> {
+pub(crate) fn item_to_u32(item: &IlstItemBox) -> Option<u32> {
+    let data = &item.data.data;
     match item.data.data_type {
-        DataType::Binary if item.data.data.len() == 4 => Some(BigEndian::read_u32(&item.data.data)),
-        DataType::Text => String::from_utf8_lossy(&item.data.data).parse::<u32>().ok(),
+        DataType::Binary | DataType::TempoCpil if !data.is_empty() && data.len() <= 4 => {
+            Some(BigEndian::read_uint(data, data.len()) as u32)
+        }
+        // Dates such as "2008-05-20" start with the year.
+        DataType::Text => {
+            let text = String::from_utf8_lossy(data);
+            text.parse::<u32>()
+                .ok()
+                .or_else(|| text.get(..4).and_then(|year| year.parse::<u32>().ok()))
+        }
         _ => None,
     }
 }
 
+/// Decode the number and total of a `trkn` or `disk` item.
+pub(crate) fn item_to_pair(item: &IlstItemBox) -> Option<(u16, u16)> {
+    let data = &item.data.data;
+    if data.len() < 6 {
+        return None;
+    }
+    Some((
+        BigEndian::read_u16(&data[2..4]),
+        BigEndian::read_u16(&data[4..6]),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,6 +530,7 @@ mod tests {
                 data_type: DataType::Text,
                 data: b"test_year".to_vec(),
             },
+            ..Default::default()
         };
         let src_box = IlstBox {
             items: [
@@ -221,6 +540,7 @@ mod tests {
                 (MetadataKey::Summary, IlstItemBox::default()),
             ]
             .into(),
+            unknown_boxes: Vec::new(),
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -250,4 +570,101 @@ mod tests {
         let dst_box = IlstBox::read_box(&mut reader, header.size).unwrap();
         assert_eq!(src_box, dst_box);
     }
+
+    #[test]
+    fn test_ilst_typed() {
+        let mut src_box = IlstBox::default();
+        src_box.set_title("Title");
+        src_box.set_artist("Artist");
+        src_box.set_album("Album");
+        src_box.set_album_artist("Album Artist");
+        src_box.set_genre("Genre");
+        src_box.set_track_number(3, 12);
+        src_box.set_disc_number(1, 2);
+        src_box.set_composer("Composer");
+        src_box.set_comment("Comment");
+        src_box.set_encoder("Encoder");
+        src_box.set_copyright("Copyright");
+        src_box.set_rating(1);
+        src_box.set_tv_show("Show");
+        src_box.set_tv_season(4);
+        src_box.set_tv_episode(7);
+        src_box.set_text(MetadataKey::SortArtist, "Artist, The");
+        src_box.set_posters(vec![
+            DataBox {
+                data_type: DataType::Image,
+                data: vec![0xff, 0xd8],
+            },
+            DataBox {
+                data_type: DataType::Png,
+                data: b"\x89PNG".to_vec(),
+            },
+        ]);
+        src_box.set_freeform("com.apple.iTunes", "iTunNORM", "00000100");
+        src_box.set_text(MetadataKey::Year, "2008-05-20T07:00:00Z");
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        let dst_box = IlstBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        assert_eq!(dst_box.title(), Some("Title".into()));
+        assert_eq!(dst_box.year(), Some(2008));
+        assert_eq!(dst_box.artist(), Some("Artist".into()));
+        assert_eq!(dst_box.album(), Some("Album".into()));
+        assert_eq!(dst_box.album_artist(), Some("Album Artist".into()));
+        assert_eq!(dst_box.genre(), Some("Genre".into()));
+        assert_eq!(dst_box.track_number(), Some((3, 12)));
+        assert_eq!(dst_box.disc_number(), Some((1, 2)));
+        assert_eq!(dst_box.composer(), Some("Composer".into()));
+        assert_eq!(dst_box.comment(), Some("Comment".into()));
+        assert_eq!(dst_box.encoder(), Some("Encoder".into()));
+        assert_eq!(dst_box.copyright(), Some("Copyright".into()));
+        assert_eq!(dst_box.rating(), Some(1));
+        assert_eq!(dst_box.tv_show(), Some("Show".into()));
+        assert_eq!(dst_box.tv_season(), Some(4));
+        assert_eq!(dst_box.tv_episode(), Some(7));
+        assert_eq!(dst_box.sort_artist(), Some("Artist, The".into()));
+        assert_eq!(dst_box.sort_title(), None);
+        assert_eq!(dst_box.poster(), Some(&[0xff, 0xd8][..]));
+        let posters = dst_box.posters();
+        assert_eq!(posters.len(), 2);
+        assert_eq!(posters[1].data_type, DataType::Png);
+        assert_eq!(
+            dst_box.freeform("com.apple.iTunes", "iTunNORM"),
+            Some(&b"00000100"[..])
+        );
+        assert!(dst_box
+            .to_json()
+            .unwrap()
+            .contains("----:com.apple.iTunes:iTunNORM"));
+    }
+
+    #[test]
+    fn test_ilst_unknown_children() {
+        // A title with an itif box and a ---- item without mean and name.
+        let data = b"\x00\x00\x00\x45ilst\
+            \x00\x00\x00\x25\xa9nam\
+            \x00\x00\x00\x10data\x00\x00\x00\x01\x00\x00\x00\x00\
+            \x00\x00\x00\x0ditif\x00\x00\x00\x00\x01\
+            \x00\x00\x00\x18----\
+            \x00\x00\x00\x10data\x00\x00\x00\x01\x00\x00\x00\x00";
+        let mut reader = Cursor::new(&data[..]);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::IlstBox);
+
+        let ilst = IlstBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(ilst.title(), Some("".into()));
+        let title = ilst.item(&MetadataKey::Title).unwrap();
+        assert_eq!(title.unknown_boxes.len(), 1);
+        assert_eq!(ilst.unknown_boxes.len(), 1);
+
+        let mut buf = Vec::new();
+        ilst.write_box(&mut buf).unwrap();
+        assert_eq!(buf, data);
+    }
 }
@@ -9,6 +9,9 @@ pub struct MdiaBox {
     pub mdhd: MdhdBox,
     pub hdlr: HdlrBox,
     pub minf: MinfBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl MdiaBox {
@@ -17,7 +20,12 @@ impl MdiaBox {
     }
 
     pub fn get_size(&self) -> u64 {
-        HEADER_SIZE + self.mdhd.box_size() + self.hdlr.box_size() + self.minf.box_size()
+        let mut size =
+            HEADER_SIZE + self.mdhd.box_size() + self.hdlr.box_size() + self.minf.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
     }
 }
 
@@ -47,6 +55,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MdiaBox {
         let mut mdhd = None;
         let mut hdlr = None;
         let mut minf = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -71,10 +80,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for MdiaBox {
                     minf = Some(MinfBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -91,10 +100,13 @@ impl<R: Read + Seek> ReadBox<&mut R> for MdiaBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(MdiaBox {
             mdhd: mdhd.unwrap(),
             hdlr: hdlr.unwrap(),
             minf: minf.unwrap(),
+            unknown_boxes,
         })
     }
 }
@@ -104,9 +116,15 @@ impl<W: Write> WriteBox<&mut W> for MdiaBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+
+        unknown.before(writer)?;
         self.mdhd.write_box(writer)?;
+        unknown.before(writer)?;
         self.hdlr.write_box(writer)?;
+        unknown.before(writer)?;
         self.minf.write_box(writer)?;
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -13,6 +13,9 @@ pub enum MetaBox {
     Mdir {
         #[serde(skip_serializing_if = "Option::is_none")]
         ilst: Option<IlstBox>,
+
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        unknown_boxes: Vec<UnknownBox>,
     },
 
     #[serde(skip)]
@@ -35,11 +38,18 @@ impl MetaBox {
     pub fn get_size(&self) -> u64 {
         let mut size = HEADER_SIZE + HEADER_EXT_SIZE;
         match self {
-            Self::Mdir { ilst } => {
+            Self::Mdir {
+                ilst,
+                unknown_boxes,
+                ..
+            } => {
                 size += HdlrBox::default().box_size();
                 if let Some(ilst) = ilst {
                     size += ilst.box_size();
                 }
+                for unknown in unknown_boxes.iter() {
+                    size += unknown.box_size();
+                }
             }
             Self::Unknown { hdlr, data } => {
                 size += hdlr.box_size()
@@ -142,25 +152,34 @@ impl<R: Read + Seek> ReadBox<&mut R> for MetaBox {
 
         match hdlr.handler_type {
             MDIR => {
+                let mut unknown = UnknownBoxReader::default();
                 while current < end {
                     // Get box header.
                     let header = BoxHeader::read(reader)?;
                     let BoxHeader { name, size: s } = header;
 
                     match name {
+                        BoxType::HdlrBox => {
+                            skip_box(reader, s)?;
+                        }
                         BoxType::IlstBox => {
                             ilst = Some(IlstBox::read_box(reader, s)?);
                         }
                         _ => {
-                            // XXX warn!()
-                            skip_box(reader, s)?;
+                            unknown.read(reader, name, s)?;
                         }
                     }
+                    unknown.child_read();
 
                     current = reader.stream_position()?;
                 }
 
-                Ok(MetaBox::Mdir { ilst })
+                let unknown_boxes = unknown.finish();
+
+                Ok(MetaBox::Mdir {
+                    ilst,
+                    unknown_boxes,
+                })
             }
             _ => {
                 let mut data = Vec::new();
@@ -175,10 +194,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MetaBox {
                             skip_box(reader, s)?;
                         }
                         _ => {
-                            let mut box_data = vec![0; (s - HEADER_SIZE) as usize];
-                            reader.read_exact(&mut box_data)?;
-
-                            data.push((name, box_data));
+                            data.push((name, read_unknown_box(reader, s)?));
                         }
                     }
 
@@ -198,22 +214,26 @@ impl<W: Write> WriteBox<&mut W> for MetaBox {
 
         write_box_header_ext(writer, 0, 0)?;
 
-        let hdlr = match self {
-            Self::Mdir { .. } => HdlrBox {
-                handler_type: MDIR,
-                ..Default::default()
-            },
-            Self::Unknown { hdlr, .. } => hdlr.clone(),
-        };
-        hdlr.write_box(writer)?;
-
         match self {
-            Self::Mdir { ilst } => {
+            Self::Mdir {
+                ilst,
+                unknown_boxes,
+            } => {
+                let mut unknown = UnknownBoxWriter::new(unknown_boxes);
+                unknown.before(writer)?;
+                HdlrBox {
+                    handler_type: MDIR,
+                    ..Default::default()
+                }
+                .write_box(writer)?;
                 if let Some(ilst) = ilst {
+                    unknown.before(writer)?;
                     ilst.write_box(writer)?;
                 }
+                unknown.finish(writer)?;
             }
-            Self::Unknown { data, .. } => {
+            Self::Unknown { hdlr, data } => {
+                hdlr.write_box(writer)?;
                 for (box_type, data) in data {
                     BoxHeader::new(*box_type, data.len() as u64 + HEADER_SIZE).write(writer)?;
                     writer.write_all(data)?;
@@ -232,7 +252,10 @@ mod tests {
 
     #[test]
     fn test_meta_mdir_empty() {
-        let src_box = MetaBox::Mdir { ilst: None };
+        let src_box = MetaBox::Mdir {
+            ilst: None,
+            unknown_boxes: Vec::new(),
+        };
 
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -251,6 +274,7 @@ mod tests {
     fn test_meta_mdir() {
         let src_box = MetaBox::Mdir {
             ilst: Some(IlstBox::default()),
+            unknown_boxes: vec![UnknownBox::new(BoxType::FreeBox, vec![0; 4])],
         };
 
         let mut buf = Vec::new();
@@ -276,11 +300,14 @@ mod tests {
         let meta_box = MetaBox::read_box(&mut reader, header.size).unwrap();
 
         // this contains \xa9too box in the ilst
-        // it designates the tool that created the file, but is not yet supported by this crate
+        // it designates the tool that created the file
+        let mut ilst = IlstBox::default();
+        ilst.set_encoder("TMPGEnc Video Mastering Works 7 Version 7.0.15.17");
         assert_eq!(
             meta_box,
             MetaBox::Mdir {
-                ilst: Some(IlstBox::default())
+                ilst: Some(ilst),
+                unknown_boxes: Vec::new(),
             }
         );
     }
@@ -0,0 +1,167 @@
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::mp4box::*;
+use crate::mp4box::{mfro::MfroBox, tfra::TfraBox};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct MfraBox {
+    #[serde(rename = "tfra")]
+    pub tfras: Vec<TfraBox>,
+
+    pub mfro: MfroBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
+}
+
+impl MfraBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::MfraBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE;
+        for tfra in self.tfras.iter() {
+            size += tfra.box_size();
+        }
+        size += self.mfro.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
+    }
+
+    /// The random access entries of a track.
+    pub fn tfra(&self, track_id: u32) -> Option<&TfraBox> {
+        self.tfras.iter().find(|tfra| tfra.track_id == track_id)
+    }
+}
+
+impl Mp4Box for MfraBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("tfras={}", self.tfras.len());
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for MfraBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let mut tfras = Vec::new();
+        let mut mfro = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "mfra box contains a box with a larger size than it",
+                ));
+            }
+
+            match name {
+                BoxType::TfraBox => {
+                    tfras.push(TfraBox::read_box(reader, s)?);
+                }
+                BoxType::MfroBox => {
+                    mfro = Some(MfroBox::read_box(reader, s)?);
+                }
+                _ => {
+                    unknown.read(reader, name, s)?;
+                }
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
+        }
+
+        let mfro = mfro.ok_or(Error::BoxNotFound(BoxType::MfroBox))?;
+
+        skip_bytes_to(reader, start + size)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(MfraBox {
+            tfras,
+            mfro,
+            unknown_boxes,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for MfraBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        for tfra in self.tfras.iter() {
+            unknown.before(writer)?;
+            tfra.write_box(writer)?;
+        }
+        unknown.before(writer)?;
+        self.mfro.write_box(writer)?;
+        unknown.finish(writer)?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::tfra::TfraEntry;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_mfra() {
+        let mut src_box = MfraBox {
+            tfras: vec![TfraBox {
+                track_id: 1,
+                entries: vec![TfraEntry {
+                    time: 0,
+                    moof_offset: 800,
+                    traf_number: 1,
+                    trun_number: 1,
+                    sample_number: 1,
+                }],
+                ..TfraBox::default()
+            }],
+            mfro: MfroBox::default(),
+            unknown_boxes: Vec::new(),
+        };
+        src_box.mfro.size = src_box.box_size() as u32;
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::MfraBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = MfraBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert!(dst_box.tfra(1).is_some());
+        assert!(dst_box.tfra(2).is_none());
+    }
+}
@@ -14,6 +14,9 @@ pub struct MinfBox {
 
     pub dinf: DinfBox,
     pub stbl: StblBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl MinfBox {
@@ -31,6 +34,9 @@ impl MinfBox {
         }
         size += self.dinf.box_size();
         size += self.stbl.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -62,6 +68,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MinfBox {
         let mut smhd = None;
         let mut dinf = None;
         let mut stbl = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -89,10 +96,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for MinfBox {
                     stbl = Some(StblBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -106,11 +113,14 @@ impl<R: Read + Seek> ReadBox<&mut R> for MinfBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(MinfBox {
             vmhd,
             smhd,
             dinf: dinf.unwrap(),
             stbl: stbl.unwrap(),
+            unknown_boxes,
         })
     }
 }
@@ -120,14 +130,21 @@ impl<W: Write> WriteBox<&mut W> for MinfBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+
         if let Some(ref vmhd) = self.vmhd {
+            unknown.before(writer)?;
             vmhd.write_box(writer)?;
         }
         if let Some(ref smhd) = self.smhd {
+            unknown.before(writer)?;
             smhd.write_box(writer)?;
         }
+        unknown.before(writer)?;
         self.dinf.write_box(writer)?;
+        unknown.before(writer)?;
         self.stbl.write_box(writer)?;
+        unknown.finish(writer)?;
 
         Ok(size)
     }
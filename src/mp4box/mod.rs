@@ -14,11 +14,13 @@
 //! moov
 //!     mvhd
 //!     udta
+//!         chpl
 //!         meta
 //!             ilst
 //!                 data
 //!     trak
 //!         tkhd
+//!         tref
 //!         mdia
 //!             mdhd
 //!             hdlr
@@ -27,7 +29,30 @@
 //!                     stsd
 //!                         avc1
 //!                         hev1
+//!                         hvc1
+//!                         av01
+//!                         mp4v
+//!                             esds
 //!                         mp4a
+//!                             esds
+//!                         Opus
+//!                             dOps
+//!                         ac-3
+//!                             dac3
+//!                         ec-3
+//!                             dec3
+//!                         ac-4
+//!                             dac4
+//!                         fLaC
+//!                             dfLa
+//!                         alac
+//!                             alac
+//!                         ipcm
+//!                         fpcm
+//!                             pcmC
+//!                         lpcm
+//!                         sowt
+//!                         twos
 //!                         tx3g
 //!                         wvtt
 //!                         enca
@@ -56,6 +81,7 @@
 //!     pssh
 //! emsg
 //! uuid
+//! sidx
 //! moof
 //!     mfhd
 //!     traf
@@ -65,27 +91,38 @@
 //!         saiz
 //!         saio
 //!         senc
+//! mfra
+//!     tfra
+//!     mfro
 //! mdat
 //! free
 //!
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
 use std::convert::TryInto;
 use std::io::{Read, Seek, SeekFrom, Write};
 
 use crate::*;
 
+pub(crate) mod ac3;
+pub(crate) mod ac4;
+pub(crate) mod alac;
+pub(crate) mod av01;
 pub(crate) mod avc1;
+pub(crate) mod chpl;
 pub(crate) mod co64;
 pub(crate) mod ctim;
 pub(crate) mod ctts;
 pub(crate) mod data;
 pub(crate) mod dinf;
+pub(crate) mod ec3;
 pub(crate) mod edts;
 pub(crate) mod elst;
 pub(crate) mod emsg;
 pub(crate) mod enca;
 pub(crate) mod encv;
+pub(crate) mod flac;
 pub(crate) mod frma;
 pub(crate) mod ftyp;
 pub(crate) mod hdlr;
@@ -97,19 +134,25 @@ pub(crate) mod mdia;
 pub(crate) mod mehd;
 pub(crate) mod meta;
 pub(crate) mod mfhd;
+pub(crate) mod mfra;
+pub(crate) mod mfro;
 pub(crate) mod minf;
 pub(crate) mod moof;
 pub(crate) mod moov;
 pub(crate) mod mp4a;
+pub(crate) mod mp4v;
 pub(crate) mod mvex;
 pub(crate) mod mvhd;
+pub(crate) mod opus;
 pub(crate) mod payl;
+pub(crate) mod pcm;
 pub(crate) mod pssh;
 pub(crate) mod saio;
 pub(crate) mod saiz;
 pub(crate) mod schi;
 pub(crate) mod schm;
 pub(crate) mod senc;
+pub(crate) mod sidx;
 pub(crate) mod sinf;
 pub(crate) mod smhd;
 pub(crate) mod stbl;
@@ -123,9 +166,11 @@ pub(crate) mod stts;
 pub(crate) mod tenc;
 pub(crate) mod tfdt;
 pub(crate) mod tfhd;
+pub(crate) mod tfra;
 pub(crate) mod tkhd;
 pub(crate) mod traf;
 pub(crate) mod trak;
+pub(crate) mod tref;
 pub(crate) mod trex;
 pub(crate) mod trun;
 pub(crate) mod tx3g;
@@ -142,47 +187,63 @@ pub(crate) mod vttc;
 pub(crate) mod vtte;
 pub(crate) mod wvtt;
 
+pub use ac3::{Ac3Box, Dac3Box};
+pub use ac4::{Ac4Box, Dac4Box};
+pub use alac::{AlacBox, AlacSpecificBox};
+pub use av01::{Av01Box, Av1CBox};
 pub use avc1::Avc1Box;
+pub use chpl::{ChplBox, ChplEntry};
 pub use co64::Co64Box;
 pub use ctim::CtimBox;
 pub use ctts::CttsBox;
 pub use data::DataBox;
 pub use dinf::DinfBox;
+pub use ec3::{Dec3Box, Ec3Box, Ec3IndependentSubstream};
 pub use edts::EdtsBox;
 pub use elst::ElstBox;
 pub use emsg::EmsgBox;
 pub use enca::EncaBox;
 pub use encv::EncvBox;
+pub use flac::{DflaBox, FlacBox, FlacMetadataBlock, FlacStreamInfo};
 pub use frma::FrmaBox;
 pub use ftyp::FtypBox;
 pub use hdlr::HdlrBox;
 pub use hev1::Hev1Box;
 pub use iden::IdenBox;
-pub use ilst::IlstBox;
+pub use ilst::{IlstBox, IlstItemBox};
 pub use mdhd::MdhdBox;
 pub use mdia::MdiaBox;
 pub use mehd::MehdBox;
 pub use meta::MetaBox;
 pub use mfhd::MfhdBox;
+pub use mfra::MfraBox;
+pub use mfro::MfroBox;
 pub use minf::MinfBox;
 pub use moof::MoofBox;
 pub use moov::MoovBox;
-pub use mp4a::Mp4aBox;
+pub use mp4a::{
+    DecoderConfigDescriptor, DecoderSpecificDescriptor, ESDescriptor, EsdsBox, Mp4aBox,
+    PceChannelElement, PceCouplingElement, ProgramConfigElement, SLConfigDescriptor, SbrSignalling,
+};
+pub use mp4v::Mp4vBox;
 pub use mvex::MvexBox;
 pub use mvhd::MvhdBox;
+pub use opus::{DopsBox, OpusBox, OpusChannelMappingTable};
 pub use payl::PaylBox;
+pub use pcm::{IpcmBox, LpcmBox, PcmCBox, TwosBox};
 pub use pssh::PsshBox;
 pub use saio::SaioBox;
 pub use saiz::SaizBox;
 pub use schi::SchiBox;
 pub use schm::SchmBox;
 pub use senc::SencBox;
+pub use sidx::{SidxBox, SidxReference};
 pub use sinf::SinfBox;
 pub use smhd::SmhdBox;
 pub use stbl::StblBox;
 pub use stco::StcoBox;
 pub use stsc::StscBox;
-pub use stsd::StsdBox;
+pub use stsd::{StsdBox, StsdEntry};
 pub use stss::StssBox;
 pub use stsz::StszBox;
 pub use sttg::SttgBox;
@@ -190,9 +251,11 @@ pub use stts::SttsBox;
 pub use tenc::TencBox;
 pub use tfdt::TfdtBox;
 pub use tfhd::TfhdBox;
+pub use tfra::{TfraBox, TfraEntry};
 pub use tkhd::TkhdBox;
 pub use traf::TrafBox;
 pub use trak::TrakBox;
+pub use tref::{TrackReference, TrefBox};
 pub use trex::TrexBox;
 pub use trun::TrunBox;
 pub use tx3g::Tx3gBox;
@@ -252,6 +315,10 @@ boxtype! {
     MehdBox => 0x6d656864,
     TrexBox => 0x74726578,
     EmsgBox => 0x656d7367,
+    SidxBox => 0x73696478,
+    MfraBox => 0x6d667261,
+    TfraBox => 0x74667261,
+    MfroBox => 0x6d66726f,
     MoofBox => 0x6d6f6f66,
     TkhdBox => 0x746b6864,
     TfhdBox => 0x74666864,
@@ -285,12 +352,33 @@ boxtype! {
     Avc3Box => 0x61766333,
     AvcCBox => 0x61766343,
     Hev1Box => 0x68657631,
+    Hvc1Box => 0x68766331,
     HvcCBox => 0x68766343,
     Mp4aBox => 0x6d703461,
+    Mp4vBox => 0x6d703476,
     EsdsBox => 0x65736473,
+    OpusBox => 0x4f707573,
+    DopsBox => 0x644f7073,
+    Ac3Box  => 0x61632d33,
+    Dac3Box => 0x64616333,
+    Ec3Box  => 0x65632d33,
+    Dec3Box => 0x64656333,
+    Ac4Box  => 0x61632d34,
+    Dac4Box => 0x64616334,
+    FlacBox => 0x664c6143,
+    DflaBox => 0x64664c61,
+    AlacBox => 0x616c6163,
+    IpcmBox => 0x6970636d,
+    FpcmBox => 0x6670636d,
+    PcmCBox => 0x70636d43,
+    LpcmBox => 0x6c70636d,
+    SowtBox => 0x736f7774,
+    TwosBox => 0x74776f73,
     Tx3gBox => 0x74783367,
     VpccBox => 0x76706343,
     Vp09Box => 0x76703039,
+    Av01Box => 0x61763031,
+    Av1CBox => 0x61763143,
     DataBox => 0x64617461,
     IlstBox => 0x696c7374,
     NameBox => 0xa96e616d,
@@ -321,7 +409,9 @@ boxtype! {
     SttgBox => 0x73747467,
     PaylBox => 0x7061796c,
     VtteBox => 0x76747465,
-    VttaBox => 0x76747461
+    VttaBox => 0x76747461,
+    ChplBox => 0x6368706c,
+    TrefBox => 0x74726566
 }
 
 pub trait Mp4Box: Sized {
@@ -435,6 +525,153 @@ pub fn skip_box<S: Seek>(seeker: &mut S, size: u64) -> Result<()> {
     Ok(())
 }
 
+/// Reads the payload of a child box this crate does not parse, so that it can
+/// be written back unchanged by [`write_unknown_box`].
+pub fn read_unknown_box<R: Read + Seek>(reader: &mut R, size: u64) -> Result<Vec<u8>> {
+    let start = box_start(reader)?;
+    let len = (start + size)
+        .checked_sub(reader.stream_position()?)
+        .ok_or(Error::InvalidData("box size too small"))?;
+    let mut data = Vec::new();
+    reader.take(len).read_to_end(&mut data)?;
+    if data.len() as u64 != len {
+        return Err(Error::InvalidData("box data extends past the end of input"));
+    }
+    Ok(data)
+}
+
+pub fn unknown_box_size(data: &[u8]) -> u64 {
+    let size = HEADER_SIZE + data.len() as u64;
+    if size > u32::MAX as u64 {
+        size + 8
+    } else {
+        size
+    }
+}
+
+pub fn write_unknown_box<W: Write>(writer: &mut W, name: BoxType, data: &[u8]) -> Result<u64> {
+    let size = unknown_box_size(data);
+    BoxHeader::new(name, size).write(writer)?;
+    writer.write_all(data)?;
+    Ok(size)
+}
+
+/// A child box this crate does not parse, kept so that it can be written
+/// back where it was read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnknownBox {
+    pub box_type: BoxType,
+    /// Box data after the header.
+    pub data: Vec<u8>,
+    /// Number of known sibling boxes read before this one. The box is written
+    /// back after as many known boxes, or after all of them when `None`.
+    #[serde(skip)]
+    pub position: Option<usize>,
+}
+
+impl UnknownBox {
+    /// An unknown box written after the known child boxes.
+    pub fn new(box_type: BoxType, data: Vec<u8>) -> Self {
+        Self {
+            box_type,
+            data,
+            position: None,
+        }
+    }
+
+    pub fn box_size(&self) -> u64 {
+        unknown_box_size(&self.data)
+    }
+
+    pub fn write_box<W: Write>(&self, writer: &mut W) -> Result<u64> {
+        write_unknown_box(writer, self.box_type, &self.data)
+    }
+}
+
+/// Collects the unknown children of a box while it is read, counting the
+/// known children read before each of them.
+#[derive(Debug, Default)]
+pub(crate) struct UnknownBoxReader {
+    boxes: Vec<UnknownBox>,
+    known: usize,
+    children: usize,
+}
+
+impl UnknownBoxReader {
+    pub fn read<R: Read + Seek>(&mut self, reader: &mut R, name: BoxType, size: u64) -> Result<()> {
+        self.boxes.push(UnknownBox {
+            box_type: name,
+            data: read_unknown_box(reader, size)?,
+            position: Some(self.known),
+        });
+        Ok(())
+    }
+
+    /// Called after each child box has been read, known or not.
+    pub fn child_read(&mut self) {
+        if self.boxes.len() == self.children {
+            self.known += 1;
+        }
+        self.children = self.boxes.len();
+    }
+
+    /// The unknown boxes, with those after the last known child box left
+    /// without a position.
+    pub fn finish(mut self) -> Vec<UnknownBox> {
+        for unknown in self.boxes.iter_mut() {
+            if unknown.position == Some(self.known) {
+                unknown.position = None;
+            }
+        }
+        self.boxes
+    }
+}
+
+/// Writes the unknown children of a box back in place, see
+/// [UnknownBox::position].
+pub(crate) struct UnknownBoxWriter<'a> {
+    boxes: Vec<&'a UnknownBox>,
+    next: usize,
+    known: usize,
+}
+
+impl<'a> UnknownBoxWriter<'a> {
+    pub fn new(boxes: &'a [UnknownBox]) -> Self {
+        let mut boxes: Vec<&UnknownBox> = boxes.iter().collect();
+        boxes.sort_by_key(|unknown| unknown.position.unwrap_or(usize::MAX));
+        Self {
+            boxes,
+            next: 0,
+            known: 0,
+        }
+    }
+
+    /// Write the unknown boxes that go before the next known child box,
+    /// which is about to be written.
+    pub fn before<W: Write>(&mut self, writer: &mut W) -> Result<()> {
+        while let Some(unknown) = self.boxes.get(self.next) {
+            if !unknown
+                .position
+                .is_some_and(|position| position <= self.known)
+            {
+                break;
+            }
+            unknown.write_box(writer)?;
+            self.next += 1;
+        }
+        self.known += 1;
+        Ok(())
+    }
+
+    /// Write the remaining unknown boxes, after the last known child box.
+    pub fn finish<W: Write>(self, writer: &mut W) -> Result<()> {
+        for unknown in self.boxes[self.next..].iter() {
+            unknown.write_box(writer)?;
+        }
+        Ok(())
+    }
+}
+
 pub fn write_zeros<W: Write>(writer: &mut W, size: u64) -> Result<()> {
     for _ in 0..size {
         writer.write_u8(0)?;
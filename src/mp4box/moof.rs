@@ -10,6 +10,9 @@ pub struct MoofBox {
 
     #[serde(rename = "traf")]
     pub trafs: Vec<TrafBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl MoofBox {
@@ -22,6 +25,9 @@ impl MoofBox {
         for traf in self.trafs.iter() {
             size += traf.box_size();
         }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -51,6 +57,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoofBox {
 
         let mut mfhd = None;
         let mut trafs = Vec::new();
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -73,10 +80,11 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoofBox {
                     trafs.push(traf);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
+
             current = reader.stream_position()?;
         }
 
@@ -86,9 +94,12 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoofBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(MoofBox {
             mfhd: mfhd.unwrap(),
             trafs,
+            unknown_boxes,
         })
     }
 }
@@ -98,10 +109,16 @@ impl<W: Write> WriteBox<&mut W> for MoofBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+
+        unknown.before(writer)?;
         self.mfhd.write_box(writer)?;
         for traf in self.trafs.iter() {
+            unknown.before(writer)?;
             traf.write_box(writer)?;
         }
+        unknown.finish(writer)?;
+
         Ok(0)
     }
 }
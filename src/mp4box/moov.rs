@@ -23,6 +23,9 @@ pub struct MoovBox {
 
     #[serde(rename = "pssh")]
     pub psshs: Vec<PsshBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl MoovBox {
@@ -52,6 +55,9 @@ impl MoovBox {
         for pssh in &self.psshs {
             size += pssh.box_size();
         }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -85,6 +91,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoovBox {
         let mut mvex = None;
         let mut traks = Vec::new();
         let mut psshs = Vec::new();
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -120,10 +127,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoovBox {
                     psshs.push(pssh);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -134,6 +141,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoovBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(MoovBox {
             mvhd: mvhd.unwrap(),
             meta,
@@ -141,6 +150,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MoovBox {
             mvex,
             traks,
             psshs,
+            unknown_boxes,
         })
     }
 }
@@ -150,22 +160,32 @@ impl<W: Write> WriteBox<&mut W> for MoovBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+
+        unknown.before(writer)?;
         self.mvhd.write_box(writer)?;
         for trak in self.traks.iter() {
+            unknown.before(writer)?;
             trak.write_box(writer)?;
         }
         if let Some(mvex) = &self.mvex {
+            unknown.before(writer)?;
             mvex.write_box(writer)?;
         }
         if let Some(meta) = &self.meta {
+            unknown.before(writer)?;
             meta.write_box(writer)?;
         }
         if let Some(udta) = &self.udta {
+            unknown.before(writer)?;
             udta.write_box(writer)?;
         }
         for pssh in &self.psshs {
+            unknown.before(writer)?;
             pssh.write_box(writer)?;
         }
+        unknown.finish(writer)?;
+
         Ok(0)
     }
 }
@@ -180,17 +200,36 @@ mod tests {
     fn test_moov() {
         let src_box = MoovBox {
             mvhd: MvhdBox::default(),
-            mvex: Some(MvexBox::default()),
+            mvex: Some(MvexBox {
+                mehd: None,
+                trexs: vec![TrexBox::default()],
+                unknown_boxes: Vec::new(),
+            }),
             traks: vec![],
             meta: Some(MetaBox::default()),
             udta: Some(UdtaBox::default()),
             psshs: vec![],
+            unknown_boxes: vec![
+                UnknownBox {
+                    position: Some(1),
+                    ..UnknownBox::new(
+                        BoxType::from(u32::from_be_bytes(*b"xyz1")),
+                        vec![1, 2, 3, 4],
+                    )
+                },
+                UnknownBox::new(BoxType::FreeBox, vec![]),
+            ],
         };
 
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
         assert_eq!(buf.len(), src_box.box_size() as usize);
 
+        // xyz1 is written back in place, between mvhd and mvex.
+        let mvhd_end = (HEADER_SIZE + src_box.mvhd.box_size()) as usize;
+        assert_eq!(&buf[mvhd_end + 4..mvhd_end + 8], b"xyz1");
+        assert_eq!(&buf[buf.len() - 4..], b"free");
+
         let mut reader = Cursor::new(&buf);
         let header = BoxHeader::read(&mut reader).unwrap();
         assert_eq!(header.name, BoxType::MoovBox);
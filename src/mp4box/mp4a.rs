@@ -1,7 +1,9 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use serde::Serialize;
+use std::convert::TryFrom;
 use std::io::{Read, Seek, Write};
 
+use crate::bitreader::{BitReader, BitWriter};
 use crate::mp4box::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -13,6 +15,9 @@ pub struct Mp4aBox {
     #[serde(with = "value_u32")]
     pub samplerate: FixedPointU16,
     pub esds: Option<EsdsBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl Default for Mp4aBox {
@@ -23,6 +28,7 @@ impl Default for Mp4aBox {
             samplesize: 16,
             samplerate: FixedPointU16::new(48000),
             esds: Some(EsdsBox::default()),
+            unknown_boxes: Vec::new(),
         }
     }
 }
@@ -35,6 +41,20 @@ impl Mp4aBox {
             samplesize: 16,
             samplerate: FixedPointU16::new(config.freq_index.freq() as u16),
             esds: Some(EsdsBox::new(config)),
+            unknown_boxes: Vec::new(),
+        }
+    }
+
+    pub fn new_mp3(config: &Mp3Config) -> Self {
+        Self {
+            data_reference_index: 1,
+            channelcount: config.channel_count,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(config.sample_rate as u16),
+            esds: Some(EsdsBox::with_decoder_config(
+                DecoderConfigDescriptor::new_mp3(config),
+            )),
+            unknown_boxes: Vec::new(),
         }
     }
 
@@ -47,6 +67,9 @@ impl Mp4aBox {
         if let Some(ref esds) = self.esds {
             size += esds.box_size();
         }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -98,6 +121,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for Mp4aBox {
 
         // Find esds in mp4a or wave
         let mut esds = None;
+        let mut unknown = UnknownBoxReader::default();
+        let mut wave_end = 0;
         let end = start + size;
         loop {
             let current = reader.stream_position()?;
@@ -113,24 +138,31 @@ impl<R: Read + Seek> ReadBox<&mut R> for Mp4aBox {
             }
             if name == BoxType::EsdsBox {
                 esds = Some(EsdsBox::read_box(reader, s)?);
-                break;
             } else if name == BoxType::WaveBox {
                 // Typically contains frma, mp4a, esds, and a terminator atom
+                wave_end = current + s;
+                continue;
+            } else if current < wave_end {
+                // Skip the rest of wave
+                skip_bytes_to(reader, current + s)?;
+                continue;
             } else {
-                // Skip boxes
-                let skip_to = current + s;
-                skip_bytes_to(reader, skip_to)?;
+                unknown.read(reader, name, s)?;
             }
+            unknown.child_read();
         }
 
         skip_bytes_to(reader, end)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(Mp4aBox {
             data_reference_index,
             channelcount,
             samplesize,
             samplerate,
             esds,
+            unknown_boxes,
         })
     }
 }
@@ -150,9 +182,12 @@ impl<W: Write> WriteBox<&mut W> for Mp4aBox {
         writer.write_u32::<BigEndian>(0)?; // reserved
         writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
         if let Some(ref esds) = self.esds {
+            unknown.before(writer)?;
             esds.write_box(writer)?;
         }
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -173,6 +208,18 @@ impl EsdsBox {
             es_desc: ESDescriptor::new(config),
         }
     }
+
+    pub fn with_decoder_config(dec_config: DecoderConfigDescriptor) -> Self {
+        Self {
+            version: 0,
+            flags: 0,
+            es_desc: ESDescriptor {
+                es_id: 1,
+                dec_config,
+                sl_config: SLConfigDescriptor::new(),
+            },
+        }
+    }
 }
 
 impl Mp4Box for EsdsBox {
@@ -184,8 +231,8 @@ impl Mp4Box for EsdsBox {
         HEADER_SIZE
             + HEADER_EXT_SIZE
             + 1
-            + size_of_length(ESDescriptor::desc_size()) as u64
-            + ESDescriptor::desc_size() as u64
+            + size_of_length(self.es_desc.desc_size()) as u64
+            + self.es_desc.desc_size() as u64
     }
 
     fn to_json(&self) -> Result<String> {
@@ -247,7 +294,7 @@ impl<W: Write> WriteBox<&mut W> for EsdsBox {
 
 trait Descriptor: Sized {
     fn desc_tag() -> u8;
-    fn desc_size() -> u32;
+    fn desc_size(&self) -> u32;
 }
 
 trait ReadDesc<T>: Sized {
@@ -285,7 +332,7 @@ fn size_of_length(size: u32) -> u32 {
 fn write_desc<W: Write>(writer: &mut W, tag: u8, size: u32) -> Result<u64> {
     writer.write_u8(tag)?;
 
-    if size as u64 > std::u32::MAX as u64 {
+    if size as u64 > u32::MAX as u64 {
         return Err(Error::InvalidData("invalid descriptor length range"));
     }
 
@@ -325,13 +372,13 @@ impl Descriptor for ESDescriptor {
         0x03
     }
 
-    fn desc_size() -> u32 {
+    fn desc_size(&self) -> u32 {
         3 + 1
-            + size_of_length(DecoderConfigDescriptor::desc_size())
-            + DecoderConfigDescriptor::desc_size()
+            + size_of_length(self.dec_config.desc_size())
+            + self.dec_config.desc_size()
             + 1
-            + size_of_length(SLConfigDescriptor::desc_size())
-            + SLConfigDescriptor::desc_size()
+            + size_of_length(self.sl_config.desc_size())
+            + self.sl_config.desc_size()
     }
 }
 
@@ -373,7 +420,7 @@ impl<R: Read + Seek> ReadDesc<&mut R> for ESDescriptor {
 
 impl<W: Write> WriteDesc<&mut W> for ESDescriptor {
     fn write_desc(&self, writer: &mut W) -> Result<u32> {
-        let size = Self::desc_size();
+        let size = self.desc_size();
         write_desc(writer, Self::desc_tag(), size)?;
 
         writer.write_u16::<BigEndian>(self.es_id)?;
@@ -410,6 +457,54 @@ impl DecoderConfigDescriptor {
             dec_specific: DecoderSpecificDescriptor::new(config),
         }
     }
+
+    pub fn new_mp3(config: &Mp3Config) -> Self {
+        let object_type = if config.sample_rate < 32000 {
+            ObjectTypeIndication::Mpeg2Audio
+        } else {
+            ObjectTypeIndication::Mpeg1Audio
+        };
+        Self {
+            object_type_indication: object_type as u8,
+            stream_type: 0x05, // Audio
+            up_stream: 0,
+            buffer_size_db: 0,
+            max_bitrate: config.bitrate,
+            avg_bitrate: config.bitrate,
+            dec_specific: DecoderSpecificDescriptor::default(),
+        }
+    }
+
+    pub fn new_mpeg4_visual(config: &Mpeg4VisualConfig) -> Self {
+        Self {
+            object_type_indication: ObjectTypeIndication::Mpeg4Visual as u8,
+            stream_type: 0x04, // Visual
+            up_stream: 0,
+            buffer_size_db: 0,
+            max_bitrate: config.bitrate,
+            avg_bitrate: config.bitrate,
+            dec_specific: DecoderSpecificDescriptor {
+                data: config.decoder_config.clone(),
+                ..DecoderSpecificDescriptor::default()
+            },
+        }
+    }
+
+    pub fn object_type(&self) -> Result<ObjectTypeIndication> {
+        ObjectTypeIndication::try_from(self.object_type_indication)
+    }
+
+    /// Whether the decoder specific info is an AudioSpecificConfig, parsed
+    /// into the fields of `dec_specific` rather than kept as raw data.
+    fn is_aac(&self) -> bool {
+        self.object_type()
+            .is_ok_and(|object_type| object_type.is_aac())
+    }
+
+    /// Whether there is a decoder specific info to write. MP3 has none.
+    fn has_dec_specific(&self) -> bool {
+        self.is_aac() || !self.dec_specific.data.is_empty()
+    }
 }
 
 impl Descriptor for DecoderConfigDescriptor {
@@ -417,10 +512,12 @@ impl Descriptor for DecoderConfigDescriptor {
         0x04
     }
 
-    fn desc_size() -> u32 {
-        13 + 1
-            + size_of_length(DecoderSpecificDescriptor::desc_size())
-            + DecoderSpecificDescriptor::desc_size()
+    fn desc_size(&self) -> u32 {
+        if self.has_dec_specific() {
+            13 + 1 + size_of_length(self.dec_specific.desc_size()) + self.dec_specific.desc_size()
+        } else {
+            13
+        }
     }
 }
 
@@ -435,6 +532,8 @@ impl<R: Read + Seek> ReadDesc<&mut R> for DecoderConfigDescriptor {
         let buffer_size_db = reader.read_u24::<BigEndian>()?;
         let max_bitrate = reader.read_u32::<BigEndian>()?;
         let avg_bitrate = reader.read_u32::<BigEndian>()?;
+        let is_aac = ObjectTypeIndication::try_from(object_type_indication)
+            .is_ok_and(|object_type| object_type.is_aac());
 
         let mut dec_specific = None;
 
@@ -443,9 +542,17 @@ impl<R: Read + Seek> ReadDesc<&mut R> for DecoderConfigDescriptor {
         while current < end {
             let (desc_tag, desc_size) = read_desc(reader)?;
             match desc_tag {
-                0x05 => {
+                0x05 if is_aac => {
                     dec_specific = Some(DecoderSpecificDescriptor::read_desc(reader, desc_size)?);
                 }
+                0x05 => {
+                    let mut data = vec![0; desc_size as usize];
+                    reader.read_exact(&mut data)?;
+                    dec_specific = Some(DecoderSpecificDescriptor {
+                        data,
+                        ..DecoderSpecificDescriptor::default()
+                    });
+                }
                 _ => {
                     skip_bytes(reader, desc_size as u64)?;
                 }
@@ -467,7 +574,7 @@ impl<R: Read + Seek> ReadDesc<&mut R> for DecoderConfigDescriptor {
 
 impl<W: Write> WriteDesc<&mut W> for DecoderConfigDescriptor {
     fn write_desc(&self, writer: &mut W) -> Result<u32> {
-        let size = Self::desc_size();
+        let size = self.desc_size();
         write_desc(writer, Self::desc_tag(), size)?;
 
         writer.write_u8(self.object_type_indication)?;
@@ -476,26 +583,332 @@ impl<W: Write> WriteDesc<&mut W> for DecoderConfigDescriptor {
         writer.write_u32::<BigEndian>(self.max_bitrate)?;
         writer.write_u32::<BigEndian>(self.avg_bitrate)?;
 
-        self.dec_specific.write_desc(writer)?;
+        if self.has_dec_specific() {
+            self.dec_specific.write_desc(writer)?;
+        }
 
         Ok(size)
     }
 }
 
+const AOT_SBR: u8 = 5;
+const AOT_PS: u8 = 29;
+const AOT_ALS: u8 = 36;
+const SYNC_EXTENSION_SBR: u64 = 0x2b7;
+const SYNC_EXTENSION_PS: u64 = 0x548;
+
+/// Object types whose specific config is a GASpecificConfig.
+fn is_general_audio(object_type: u8) -> bool {
+    matches!(object_type, 1..=4 | 6 | 7 | 17 | 19..=23)
+}
+
+/// Error resilient object types, which carry an `epConfig`.
+fn is_error_resilient(object_type: u8) -> bool {
+    matches!(object_type, 17 | 19..=27 | 39)
+}
+
+/// How HE-AAC tools are signalled in an AudioSpecificConfig.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum SbrSignalling {
+    /// Not signalled. SBR and PS may still be found in the bitstream.
+    #[default]
+    Implicit,
+    /// Audio object type 5 (SBR) or 29 (PS) ahead of the core object type.
+    Hierarchical,
+    /// A sync extension following the core configuration, ignored by
+    /// decoders without SBR.
+    BackwardCompatible,
+}
+
+/// The decoder specific info of an MPEG-4 stream. For AAC it is parsed as an
+/// AudioSpecificConfig and written from the fields, for other object types
+/// only `data` is set.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct DecoderSpecificDescriptor {
+    /// Audio object type of the core coder, AAC LC for HE-AAC.
     pub profile: u8,
     pub freq_index: u8,
+    /// Sampling frequency of the core coder in Hz, stored explicitly when
+    /// `freq_index` is 15.
+    pub sampling_frequency: u32,
+    /// 0 when the channels are given by `program_config`.
     pub chan_conf: u8,
+
+    pub sbr_signalling: SbrSignalling,
+    pub sbr_present: bool,
+    pub ps_present: bool,
+    /// Output sampling frequency index, the same as `freq_index` without
+    /// SBR.
+    pub extension_freq_index: u8,
+    pub extension_sampling_frequency: u32,
+
+    /// Frames of 960 rather than 1024 samples (480 rather than 512 for low
+    /// delay object types).
+    pub frame_length_flag: bool,
+    pub core_coder_delay: Option<u16>,
+    pub extension_flag: bool,
+    pub program_config: Option<ProgramConfigElement>,
+    /// Channels of an ALS stream, given by its ALSSpecificConfig as
+    /// `chan_conf` is 0.
+    pub als_channels: Option<u16>,
+
+    /// The config as stored, kept only when the fields above do not describe
+    /// all of it, such as for object types other than AAC or a config that
+    /// could not be read to the end. It is written as
+    /// is when set, so changes to the fields are then ignored; otherwise the
+    /// config is encoded from the fields.
+    pub data: Vec<u8>,
 }
 
 impl DecoderSpecificDescriptor {
     pub fn new(config: &AacConfig) -> Self {
+        let profile = config.profile as u8;
+        let he_aac = profile == AOT_SBR || profile == AOT_PS;
+        let extension_freq_index = config.freq_index as u8;
+        // The core coder of HE-AAC runs at half the output sampling rate,
+        // which has an index of its own only down to 8000 Hz.
+        let (freq_index, core_frequency) = if !he_aac {
+            (extension_freq_index, config.freq_index.freq())
+        } else if extension_freq_index + 3 <= 0xb {
+            let freq_index = extension_freq_index + 3;
+            (freq_index, sampling_frequency(freq_index))
+        } else {
+            (15, config.freq_index.freq() / 2)
+        };
         Self {
-            profile: config.profile as u8,
-            freq_index: config.freq_index as u8,
+            profile: if he_aac { 2 } else { profile },
+            freq_index,
+            sampling_frequency: core_frequency,
             chan_conf: config.chan_conf as u8,
+            sbr_signalling: if he_aac {
+                SbrSignalling::Hierarchical
+            } else {
+                SbrSignalling::Implicit
+            },
+            sbr_present: he_aac,
+            ps_present: profile == AOT_PS,
+            extension_freq_index,
+            extension_sampling_frequency: config.freq_index.freq(),
+            ..Self::default()
+        }
+    }
+
+    /// Parse an AudioSpecificConfig.
+    ///
+    /// Only a config shorter than 2 bytes is an error. The fields of a
+    /// truncated or unusual config are filled in as far as they can be read,
+    /// and the config is kept in `data`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut desc = Self::parse(data)?;
+        if desc.encode() == data {
+            desc.data.clear();
+        }
+        Ok(desc)
+    }
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 2 {
+            return Err(Error::InvalidData("AudioSpecificConfig too short"));
+        }
+        let mut desc = DecoderSpecificDescriptor {
+            data: data.to_vec(),
+            ..DecoderSpecificDescriptor::default()
+        };
+        // A truncated or unusual config keeps the fields read up to the
+        // problem. It does not encode to the same bytes, so `data` is kept
+        // and written back as is.
+        let _ = desc.read_fields(&mut BitReader::new(data));
+        Ok(desc)
+    }
+
+    fn read_fields(&mut self, bits: &mut BitReader) -> Result<()> {
+        self.profile = read_object_type(bits)?;
+        let (freq_index, sampling_frequency) = read_sampling_frequency(bits)?;
+        self.freq_index = freq_index;
+        self.sampling_frequency = sampling_frequency;
+        self.extension_freq_index = freq_index;
+        self.extension_sampling_frequency = sampling_frequency;
+        self.chan_conf = bits.read_bits(4)? as u8;
+
+        if self.profile == AOT_SBR || self.profile == AOT_PS {
+            self.sbr_signalling = SbrSignalling::Hierarchical;
+            self.sbr_present = true;
+            self.ps_present = self.profile == AOT_PS;
+            let (index, frequency) = read_sampling_frequency(bits)?;
+            self.extension_freq_index = index;
+            self.extension_sampling_frequency = frequency;
+            self.profile = read_object_type(bits)?;
+            if self.profile == 22 {
+                bits.skip_bits(4)?; // extensionChannelConfiguration
+            }
+        }
+        let profile = self.profile;
+
+        if profile == AOT_ALS {
+            self.als_channels = read_als_channels(bits)?;
+        }
+        // The configuration of other object types is kept in `data` only.
+        if !is_general_audio(profile) {
+            return Ok(());
+        }
+
+        // GASpecificConfig
+        self.frame_length_flag = bits.read_bit()?;
+        if bits.read_bit()? {
+            self.core_coder_delay = Some(bits.read_bits(14)? as u16);
+        }
+        self.extension_flag = bits.read_bit()?;
+        if self.chan_conf == 0 {
+            self.program_config = Some(ProgramConfigElement::read(bits)?);
+        }
+        if profile == 6 || profile == 20 {
+            bits.skip_bits(3)?; // layerNr
+        }
+        if self.extension_flag {
+            if profile == 22 {
+                bits.skip_bits(16)?; // numOfSubFrame, layer_length
+            }
+            if matches!(profile, 17 | 19 | 20 | 23) {
+                bits.skip_bits(3)?; // resilience flags
+            }
+            bits.skip_bits(1)?; // extensionFlag3
+        }
+        if is_error_resilient(profile) {
+            let ep_config = bits.read_bits(2)?;
+            if ep_config == 2 || ep_config == 3 {
+                // ErrorProtectionSpecificConfig is not parsed.
+                return Ok(());
+            }
+        }
+
+        if self.sbr_signalling != SbrSignalling::Hierarchical && bits.remaining() >= 16 {
+            // A malformed extension leaves the core configuration usable.
+            if let Ok(Some((sbr_present, index, frequency, ps_present))) = read_sync_extension(bits)
+            {
+                self.sbr_signalling = SbrSignalling::BackwardCompatible;
+                self.sbr_present = sbr_present;
+                self.ps_present = ps_present;
+                if sbr_present {
+                    self.extension_freq_index = index;
+                    self.extension_sampling_frequency = frequency;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The AudioSpecificConfig to write, `data` if set.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if self.data.is_empty() {
+            self.encode()
+        } else {
+            self.data.clone()
+        }
+    }
+
+    /// Output channel count, including the channels added by PS.
+    pub fn channel_count(&self) -> u16 {
+        let count = match self.chan_conf {
+            0 => self
+                .program_config
+                .as_ref()
+                .map(|pce| pce.channel_count())
+                .or(self.als_channels)
+                .unwrap_or(0),
+            1..=6 => self.chan_conf as u16,
+            7 | 12 | 14 => 8,
+            11 => 7,
+            13 => 24,
+            _ => 0,
+        };
+        if self.ps_present && count == 1 {
+            2
+        } else {
+            count
+        }
+    }
+
+    /// Samples per channel in a frame of the core coder.
+    pub fn frame_length(&self) -> u16 {
+        let low_delay = self.profile == 23 || self.profile == 39;
+        match (low_delay, self.frame_length_flag) {
+            (false, false) => 1024,
+            (false, true) => 960,
+            (true, false) => 512,
+            (true, true) => 480,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bits = BitWriter::new();
+        if self.sbr_signalling == SbrSignalling::Hierarchical {
+            let object_type = if self.ps_present { AOT_PS } else { AOT_SBR };
+            write_object_type(&mut bits, object_type);
+            write_sampling_frequency(&mut bits, self.freq_index, self.sampling_frequency);
+            bits.write_bits(self.chan_conf as u64, 4);
+            write_sampling_frequency(
+                &mut bits,
+                self.extension_freq_index,
+                self.extension_sampling_frequency,
+            );
+            write_object_type(&mut bits, self.profile);
+            if self.profile == 22 {
+                bits.write_bits(self.chan_conf as u64, 4);
+            }
+        } else {
+            write_object_type(&mut bits, self.profile);
+            write_sampling_frequency(&mut bits, self.freq_index, self.sampling_frequency);
+            bits.write_bits(self.chan_conf as u64, 4);
+        }
+
+        if is_general_audio(self.profile) {
+            // GASpecificConfig, with the fields not kept here written as 0.
+            bits.write_bit(self.frame_length_flag);
+            bits.write_bit(self.core_coder_delay.is_some());
+            if let Some(delay) = self.core_coder_delay {
+                bits.write_bits(delay as u64, 14);
+            }
+            bits.write_bit(self.extension_flag);
+            if self.chan_conf == 0 {
+                let pce = self.program_config.clone().unwrap_or_default();
+                pce.write(&mut bits);
+            }
+            if self.profile == 6 || self.profile == 20 {
+                bits.write_bits(0, 3);
+            }
+            if self.extension_flag {
+                if self.profile == 22 {
+                    bits.write_bits(0, 16);
+                }
+                if matches!(self.profile, 17 | 19 | 20 | 23) {
+                    bits.write_bits(0, 3);
+                }
+                bits.write_bit(false);
+            }
+            if is_error_resilient(self.profile) {
+                bits.write_bits(0, 2);
+            }
+        }
+
+        if self.sbr_signalling == SbrSignalling::BackwardCompatible {
+            bits.write_bits(SYNC_EXTENSION_SBR, 11);
+            write_object_type(&mut bits, AOT_SBR);
+            bits.write_bit(self.sbr_present);
+            if self.sbr_present {
+                write_sampling_frequency(
+                    &mut bits,
+                    self.extension_freq_index,
+                    self.extension_sampling_frequency,
+                );
+                if self.ps_present {
+                    bits.write_bits(SYNC_EXTENSION_PS, 11);
+                    bits.write_bit(true);
+                }
+            }
         }
+
+        bits.into_bytes()
     }
 }
 
@@ -504,76 +917,277 @@ impl Descriptor for DecoderSpecificDescriptor {
         0x05
     }
 
-    fn desc_size() -> u32 {
-        2
+    fn desc_size(&self) -> u32 {
+        if self.data.is_empty() {
+            self.encode().len() as u32
+        } else {
+            self.data.len() as u32
+        }
     }
 }
 
-fn get_audio_object_type(byte_a: u8, byte_b: u8) -> u8 {
-    let mut profile = byte_a >> 3;
-    if profile == 31 {
-        profile = 32 + ((byte_a & 7) | (byte_b >> 5));
+fn sampling_frequency(freq_index: u8) -> u32 {
+    SampleFreqIndex::try_from(freq_index).map_or(0, |index| index.freq())
+}
+
+fn read_object_type(bits: &mut BitReader) -> Result<u8> {
+    let object_type = bits.read_bits(5)? as u8;
+    if object_type == 31 {
+        Ok(32 + bits.read_bits(6)? as u8)
+    } else {
+        Ok(object_type)
     }
+}
 
-    profile
+fn write_object_type(bits: &mut BitWriter, object_type: u8) {
+    if object_type >= 31 {
+        bits.write_bits(31, 5);
+        bits.write_bits(object_type as u64 - 32, 6);
+    } else {
+        bits.write_bits(object_type as u64, 5);
+    }
 }
 
-fn get_chan_conf<R: Read + Seek>(
-    reader: &mut R,
-    byte_b: u8,
-    freq_index: u8,
-    extended_profile: bool,
-) -> Result<u8> {
-    let chan_conf;
+fn read_sampling_frequency(bits: &mut BitReader) -> Result<(u8, u32)> {
+    let freq_index = bits.read_bits(4)? as u8;
     if freq_index == 15 {
-        // Skip the 24 bit sample rate
-        let sample_rate = reader.read_u24::<BigEndian>()?;
-        chan_conf = ((sample_rate >> 4) & 0x0F) as u8;
-    } else if extended_profile {
-        let byte_c = reader.read_u8()?;
-        chan_conf = (byte_b & 1) | (byte_c & 0xE0);
+        Ok((freq_index, bits.read_bits(24)? as u32))
     } else {
-        chan_conf = (byte_b >> 3) & 0x0F;
+        Ok((freq_index, sampling_frequency(freq_index)))
     }
+}
 
-    Ok(chan_conf)
+fn write_sampling_frequency(bits: &mut BitWriter, freq_index: u8, frequency: u32) {
+    bits.write_bits(freq_index as u64, 4);
+    if freq_index == 15 {
+        bits.write_bits(frequency as u64, 24);
+    }
 }
 
-impl<R: Read + Seek> ReadDesc<&mut R> for DecoderSpecificDescriptor {
-    fn read_desc(reader: &mut R, _size: u32) -> Result<Self> {
-        let byte_a = reader.read_u8()?;
-        let byte_b = reader.read_u8()?;
-        let profile = get_audio_object_type(byte_a, byte_b);
-        let freq_index;
-        let chan_conf;
-        if profile > 31 {
-            freq_index = (byte_b >> 1) & 0x0F;
-            chan_conf = get_chan_conf(reader, byte_b, freq_index, true)?;
-        } else {
-            freq_index = ((byte_a & 0x07) << 1) + (byte_b >> 7);
-            chan_conf = get_chan_conf(reader, byte_b, freq_index, false)?;
+/// Read the backward compatible SBR and PS signalling, giving whether SBR
+/// is present, the output sampling frequency and whether PS is present.
+fn read_sync_extension(bits: &mut BitReader) -> Result<Option<(bool, u8, u32, bool)>> {
+    if bits.read_bits(11)? != SYNC_EXTENSION_SBR || read_object_type(bits)? != AOT_SBR {
+        return Ok(None);
+    }
+    let sbr_present = bits.read_bit()?;
+    let mut extension = (sbr_present, 0, 0, false);
+    if sbr_present {
+        let (index, frequency) = read_sampling_frequency(bits)?;
+        extension.1 = index;
+        extension.2 = frequency;
+        if bits.remaining() >= 12 && bits.read_bits(11)? == SYNC_EXTENSION_PS {
+            extension.3 = bits.read_bit()?;
         }
+    }
+    Ok(Some(extension))
+}
 
-        Ok(DecoderSpecificDescriptor {
-            profile,
-            freq_index,
-            chan_conf,
-        })
+/// Read the channel count of an ALSSpecificConfig, which starts at the next
+/// byte boundary.
+fn read_als_channels(bits: &mut BitReader) -> Result<Option<u16>> {
+    bits.byte_align();
+    if bits.read_bits(32)? != u32::from_be_bytes(*b"ALS\0") as u64 {
+        return Ok(None);
+    }
+    bits.skip_bits(64)?; // samp_freq, samples
+    Ok(Some(bits.read_bits(16)? as u16 + 1))
+}
+
+impl<R: Read + Seek> ReadDesc<&mut R> for DecoderSpecificDescriptor {
+    fn read_desc(reader: &mut R, size: u32) -> Result<Self> {
+        let mut data = vec![0; size as usize];
+        reader.read_exact(&mut data)?;
+        DecoderSpecificDescriptor::from_bytes(&data)
     }
 }
 
 impl<W: Write> WriteDesc<&mut W> for DecoderSpecificDescriptor {
     fn write_desc(&self, writer: &mut W) -> Result<u32> {
-        let size = Self::desc_size();
+        let size = self.desc_size();
         write_desc(writer, Self::desc_tag(), size)?;
 
-        writer.write_u8((self.profile << 3) + (self.freq_index >> 1))?;
-        writer.write_u8((self.freq_index << 7) + (self.chan_conf << 3))?;
+        writer.write_all(&self.to_bytes())?;
 
         Ok(size)
     }
 }
 
+/// A program config element, describing the channels when the channel
+/// configuration is 0.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct ProgramConfigElement {
+    pub element_instance_tag: u8,
+    pub object_type: u8,
+    pub freq_index: u8,
+    pub front_elements: Vec<PceChannelElement>,
+    pub side_elements: Vec<PceChannelElement>,
+    pub back_elements: Vec<PceChannelElement>,
+    /// Tags of the LFE channel elements.
+    pub lfe_elements: Vec<u8>,
+    /// Tags of the data stream elements.
+    pub assoc_data_elements: Vec<u8>,
+    pub cc_elements: Vec<PceCouplingElement>,
+    pub mono_mixdown_element: Option<u8>,
+    pub stereo_mixdown_element: Option<u8>,
+    pub matrix_mixdown_idx: Option<u8>,
+    pub pseudo_surround_enable: bool,
+    pub comment: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct PceChannelElement {
+    /// A channel pair rather than a single channel.
+    pub is_cpe: bool,
+    pub tag_select: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct PceCouplingElement {
+    pub is_ind_sw: bool,
+    pub tag_select: u8,
+}
+
+impl ProgramConfigElement {
+    pub fn channel_count(&self) -> u16 {
+        let channels = self
+            .front_elements
+            .iter()
+            .chain(self.side_elements.iter())
+            .chain(self.back_elements.iter())
+            .map(|element| if element.is_cpe { 2 } else { 1 })
+            .sum::<u16>();
+        channels + self.lfe_elements.len() as u16
+    }
+
+    fn read(bits: &mut BitReader) -> Result<Self> {
+        let element_instance_tag = bits.read_bits(4)? as u8;
+        let object_type = bits.read_bits(2)? as u8;
+        let freq_index = bits.read_bits(4)? as u8;
+        let num_front = bits.read_bits(4)?;
+        let num_side = bits.read_bits(4)?;
+        let num_back = bits.read_bits(4)?;
+        let num_lfe = bits.read_bits(2)?;
+        let num_assoc_data = bits.read_bits(3)?;
+        let num_cc = bits.read_bits(4)?;
+
+        let optional_element = |bits: &mut BitReader, size| -> Result<Option<u8>> {
+            if bits.read_bit()? {
+                Ok(Some(bits.read_bits(size)? as u8))
+            } else {
+                Ok(None)
+            }
+        };
+        let mono_mixdown_element = optional_element(bits, 4)?;
+        let stereo_mixdown_element = optional_element(bits, 4)?;
+        let matrix_mixdown_idx = optional_element(bits, 2)?;
+        let pseudo_surround_enable = matrix_mixdown_idx.is_some() && bits.read_bit()?;
+
+        let channel_elements = |bits: &mut BitReader, count| -> Result<Vec<PceChannelElement>> {
+            (0..count)
+                .map(|_| {
+                    Ok(PceChannelElement {
+                        is_cpe: bits.read_bit()?,
+                        tag_select: bits.read_bits(4)? as u8,
+                    })
+                })
+                .collect()
+        };
+        let front_elements = channel_elements(bits, num_front)?;
+        let side_elements = channel_elements(bits, num_side)?;
+        let back_elements = channel_elements(bits, num_back)?;
+        let lfe_elements = (0..num_lfe)
+            .map(|_| Ok(bits.read_bits(4)? as u8))
+            .collect::<Result<Vec<u8>>>()?;
+        let assoc_data_elements = (0..num_assoc_data)
+            .map(|_| Ok(bits.read_bits(4)? as u8))
+            .collect::<Result<Vec<u8>>>()?;
+        let cc_elements = (0..num_cc)
+            .map(|_| {
+                Ok(PceCouplingElement {
+                    is_ind_sw: bits.read_bit()?,
+                    tag_select: bits.read_bits(4)? as u8,
+                })
+            })
+            .collect::<Result<Vec<PceCouplingElement>>>()?;
+
+        bits.byte_align();
+        let comment_len = bits.read_bits(8)?;
+        let comment = (0..comment_len)
+            .map(|_| Ok(bits.read_bits(8)? as u8))
+            .collect::<Result<Vec<u8>>>()?;
+
+        Ok(ProgramConfigElement {
+            element_instance_tag,
+            object_type,
+            freq_index,
+            front_elements,
+            side_elements,
+            back_elements,
+            lfe_elements,
+            assoc_data_elements,
+            cc_elements,
+            mono_mixdown_element,
+            stereo_mixdown_element,
+            matrix_mixdown_idx,
+            pseudo_surround_enable,
+            comment,
+        })
+    }
+
+    fn write(&self, bits: &mut BitWriter) {
+        bits.write_bits(self.element_instance_tag as u64, 4);
+        bits.write_bits(self.object_type as u64, 2);
+        bits.write_bits(self.freq_index as u64, 4);
+        bits.write_bits(self.front_elements.len() as u64, 4);
+        bits.write_bits(self.side_elements.len() as u64, 4);
+        bits.write_bits(self.back_elements.len() as u64, 4);
+        bits.write_bits(self.lfe_elements.len() as u64, 2);
+        bits.write_bits(self.assoc_data_elements.len() as u64, 3);
+        bits.write_bits(self.cc_elements.len() as u64, 4);
+
+        let optional_element = |bits: &mut BitWriter, element: Option<u8>, size| {
+            bits.write_bit(element.is_some());
+            if let Some(element) = element {
+                bits.write_bits(element as u64, size);
+            }
+        };
+        optional_element(bits, self.mono_mixdown_element, 4);
+        optional_element(bits, self.stereo_mixdown_element, 4);
+        optional_element(bits, self.matrix_mixdown_idx, 2);
+        if self.matrix_mixdown_idx.is_some() {
+            bits.write_bit(self.pseudo_surround_enable);
+        }
+
+        for element in self
+            .front_elements
+            .iter()
+            .chain(self.side_elements.iter())
+            .chain(self.back_elements.iter())
+        {
+            bits.write_bit(element.is_cpe);
+            bits.write_bits(element.tag_select as u64, 4);
+        }
+        for tag in self
+            .lfe_elements
+            .iter()
+            .chain(self.assoc_data_elements.iter())
+        {
+            bits.write_bits(*tag as u64, 4);
+        }
+        for element in self.cc_elements.iter() {
+            bits.write_bit(element.is_ind_sw);
+            bits.write_bits(element.tag_select as u64, 4);
+        }
+
+        bits.byte_align();
+        bits.write_bits(self.comment.len() as u64, 8);
+        for byte in self.comment.iter() {
+            bits.write_bits(*byte as u64, 8);
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct SLConfigDescriptor {}
 
@@ -588,7 +1202,7 @@ impl Descriptor for SLConfigDescriptor {
         0x06
     }
 
-    fn desc_size() -> u32 {
+    fn desc_size(&self) -> u32 {
         1
     }
 }
@@ -603,7 +1217,7 @@ impl<R: Read + Seek> ReadDesc<&mut R> for SLConfigDescriptor {
 
 impl<W: Write> WriteDesc<&mut W> for SLConfigDescriptor {
     fn write_desc(&self, writer: &mut W) -> Result<u32> {
-        let size = Self::desc_size();
+        let size = self.desc_size();
         write_desc(writer, Self::desc_tag(), size)?;
 
         writer.write_u8(2)?; // pre-defined
@@ -636,15 +1250,12 @@ mod tests {
                         buffer_size_db: 0,
                         max_bitrate: 67695,
                         avg_bitrate: 67695,
-                        dec_specific: DecoderSpecificDescriptor {
-                            profile: 2,
-                            freq_index: 3,
-                            chan_conf: 1,
-                        },
+                        dec_specific: DecoderSpecificDescriptor::from_bytes(&[0x11, 0x88]).unwrap(),
                     },
                     sl_config: SLConfigDescriptor::default(),
                 },
             }),
+            unknown_boxes: Vec::new(),
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -667,6 +1278,7 @@ mod tests {
             samplesize: 16,
             samplerate: FixedPointU16::new(48000),
             esds: None,
+            ..Mp4aBox::default()
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -680,4 +1292,164 @@ mod tests {
         let dst_box = Mp4aBox::read_box(&mut reader, header.size).unwrap();
         assert_eq!(src_box, dst_box);
     }
+
+    #[test]
+    fn test_mp4a_mp3() {
+        let src_box = Mp4aBox::new_mp3(&Mp3Config {
+            sample_rate: 22050,
+            ..Mp3Config::default()
+        });
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::Mp4aBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = Mp4aBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+
+        // No decoder specific info is written for MP3.
+        let dec_config = &dst_box.esds.unwrap().es_desc.dec_config;
+        assert_eq!(
+            dec_config.object_type().unwrap(),
+            ObjectTypeIndication::Mpeg2Audio
+        );
+        assert_eq!(dec_config.desc_size(), 13);
+    }
+
+    #[test]
+    fn test_asc_hierarchical_sbr_ps() {
+        let src_desc = DecoderSpecificDescriptor::new(&AacConfig {
+            profile: AudioObjectType::ParametricStereo,
+            freq_index: SampleFreqIndex::Freq48000,
+            chan_conf: ChannelConfig::Mono,
+            ..AacConfig::default()
+        });
+        assert_eq!(src_desc.to_bytes(), [0xeb, 0x09, 0x88, 0x00]);
+        assert_eq!(src_desc.profile, 2);
+        assert_eq!(src_desc.sampling_frequency, 24000);
+        assert_eq!(src_desc.channel_count(), 2);
+
+        let dst_desc = DecoderSpecificDescriptor::from_bytes(&src_desc.to_bytes()).unwrap();
+        assert_eq!(src_desc, dst_desc);
+    }
+
+    #[test]
+    fn test_asc_backward_compatible_sbr_ps() {
+        let data = [0x13, 0x10, 0x56, 0xe5, 0x9d, 0x48, 0x80];
+        let desc = DecoderSpecificDescriptor::from_bytes(&data).unwrap();
+        assert_eq!(desc.sbr_signalling, SbrSignalling::BackwardCompatible);
+        assert_eq!(desc.profile, 2);
+        assert_eq!(desc.sampling_frequency, 24000);
+        assert!(desc.sbr_present);
+        assert!(desc.ps_present);
+        assert_eq!(desc.extension_freq_index, 3);
+        assert_eq!(desc.extension_sampling_frequency, 48000);
+
+        assert!(desc.data.is_empty());
+        assert_eq!(desc.to_bytes(), data);
+    }
+
+    #[test]
+    fn test_asc_program_config() {
+        let channel = |is_cpe, tag_select| PceChannelElement { is_cpe, tag_select };
+        let src_desc = DecoderSpecificDescriptor {
+            profile: 2,
+            freq_index: 4,
+            sampling_frequency: 44100,
+            extension_freq_index: 4,
+            extension_sampling_frequency: 44100,
+            program_config: Some(ProgramConfigElement {
+                object_type: 1,
+                freq_index: 4,
+                front_elements: vec![channel(false, 0), channel(true, 0)],
+                back_elements: vec![channel(true, 1)],
+                lfe_elements: vec![0],
+                matrix_mixdown_idx: Some(1),
+                pseudo_surround_enable: true,
+                comment: b"5.1".to_vec(),
+                ..ProgramConfigElement::default()
+            }),
+            ..DecoderSpecificDescriptor::default()
+        };
+        let data = src_desc.to_bytes();
+        let dst_desc = DecoderSpecificDescriptor::from_bytes(&data).unwrap();
+        assert_eq!(dst_desc.channel_count(), 6);
+        assert_eq!(src_desc, dst_desc);
+    }
+
+    #[test]
+    fn test_asc_explicit_frequency() {
+        let src_desc = DecoderSpecificDescriptor {
+            profile: 2,
+            freq_index: 15,
+            sampling_frequency: 44056,
+            chan_conf: 2,
+            extension_freq_index: 15,
+            extension_sampling_frequency: 44056,
+            frame_length_flag: true,
+            ..DecoderSpecificDescriptor::default()
+        };
+        let data = src_desc.to_bytes();
+        let dst_desc = DecoderSpecificDescriptor::from_bytes(&data).unwrap();
+        assert_eq!(dst_desc.frame_length(), 960);
+        assert_eq!(src_desc, dst_desc);
+    }
+
+    #[test]
+    fn test_asc_he_aac_low_frequency() {
+        // The 5512 Hz core of 11025 Hz HE-AAC has no frequency index.
+        let src_desc = DecoderSpecificDescriptor::new(&AacConfig {
+            profile: AudioObjectType::SpectralBandReplication,
+            freq_index: SampleFreqIndex::Freq11025,
+            chan_conf: ChannelConfig::Stereo,
+            ..AacConfig::default()
+        });
+        assert_eq!(src_desc.freq_index, 15);
+        assert_eq!(src_desc.sampling_frequency, 5512);
+        assert_eq!(src_desc.extension_freq_index, 0xa);
+
+        let dst_desc = DecoderSpecificDescriptor::from_bytes(&src_desc.to_bytes()).unwrap();
+        assert_eq!(src_desc, dst_desc);
+    }
+
+    #[test]
+    fn test_asc_fields_are_written() {
+        let data = [0x12, 0x10];
+        let mut desc = DecoderSpecificDescriptor::from_bytes(&data).unwrap();
+        assert_eq!(desc.to_bytes(), data);
+        desc.chan_conf = 1;
+        assert_eq!(desc.to_bytes(), [0x12, 0x08]);
+
+        // Object types that are not parsed keep their config as stored.
+        let als = [0xf8, 0x86, 0x20, 0x41, 0x4c, 0x53, 0x00];
+        let desc = DecoderSpecificDescriptor::from_bytes(&als).unwrap();
+        assert_eq!(desc.data, als);
+    }
+
+    #[test]
+    fn test_asc_truncated() {
+        // AAC LC at 44100 Hz with chan_conf 0 but no program config element.
+        let data = [0x12, 0x00];
+        let desc = DecoderSpecificDescriptor::from_bytes(&data).unwrap();
+        assert_eq!(desc.profile, 2);
+        assert_eq!(desc.freq_index, 4);
+        assert_eq!(desc.chan_conf, 0);
+        assert_eq!(desc.program_config, None);
+        assert_eq!(desc.to_bytes(), data);
+
+        // An explicit sampling frequency cut short.
+        let data = [0x17, 0x80, 0x00];
+        let desc = DecoderSpecificDescriptor::from_bytes(&data).unwrap();
+        assert_eq!(desc.profile, 2);
+        assert_eq!(desc.to_bytes(), data);
+
+        assert!(matches!(
+            DecoderSpecificDescriptor::from_bytes(&[0x12]),
+            Err(Error::InvalidData(_))
+        ));
+    }
 }
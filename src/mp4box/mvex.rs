@@ -10,6 +10,9 @@ pub struct MvexBox {
 
     #[serde(rename = "trex")]
     pub trexs: Vec<TrexBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl MvexBox {
@@ -25,7 +28,9 @@ impl MvexBox {
         for trex in self.trexs.iter() {
             size += trex.box_size();
         }
-
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -55,6 +60,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for MvexBox {
 
         let mut mehd = None;
         let mut trexs = Vec::new();
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -76,10 +82,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for MvexBox {
                     trexs.push(TrexBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -90,7 +96,13 @@ impl<R: Read + Seek> ReadBox<&mut R> for MvexBox {
 
         skip_bytes_to(reader, start + size)?;
 
-        Ok(MvexBox { mehd, trexs })
+        let unknown_boxes = unknown.finish();
+
+        Ok(MvexBox {
+            mehd,
+            trexs,
+            unknown_boxes,
+        })
     }
 }
 
@@ -99,13 +111,17 @@ impl<W: Write> WriteBox<&mut W> for MvexBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
         if let Some(mehd) = &self.mehd {
+            unknown.before(writer)?;
             mehd.write_box(writer)?;
         }
 
         for trex in self.trexs.iter() {
+            unknown.before(writer)?;
             trex.write_box(writer)?;
         }
+        unknown.finish(writer)?;
 
         Ok(size)
     }
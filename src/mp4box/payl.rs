@@ -3,19 +3,11 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct PaylBox {
     pub cue_text: String,
 }
 
-impl Default for PaylBox {
-    fn default() -> Self {
-        PaylBox {
-            cue_text: String::new(),
-        }
-    }
-}
-
 impl PaylBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::PaylBox
@@ -0,0 +1,693 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::mp4box::*;
+
+const PCMC_LITTLE_ENDIAN: u8 = 0x01;
+
+// Format flags of the QuickTime `lpcm` sound description.
+const LPCM_FLAG_FLOAT: u32 = 0x01;
+const LPCM_FLAG_BIG_ENDIAN: u32 = 0x02;
+const LPCM_FLAG_SIGNED_INTEGER: u32 = 0x04;
+const LPCM_FLAG_PACKED: u32 = 0x08;
+
+/// Size of the version 2 sound description fields following the data
+/// reference index.
+const LPCM_FIELDS_SIZE: u64 = 56;
+
+/// ISO/IEC 23003-5 uncompressed audio sample entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IpcmBox {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    /// Written as `fpcm` for floating point samples, or as `ipcm` for
+    /// integer samples.
+    pub float: bool,
+    pub pcmc: PcmCBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
+}
+
+impl Default for IpcmBox {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            float: false,
+            pcmc: PcmCBox::default(),
+            unknown_boxes: Vec::new(),
+        }
+    }
+}
+
+impl IpcmBox {
+    pub fn new(config: &PcmConfig) -> Result<Self> {
+        let float = config.sample_format == PcmSampleFormat::Float;
+        if float && config.bit_depth != 32 && config.bit_depth != 64 {
+            return Err(Error::InvalidData("fpcm needs 32 or 64 bit samples"));
+        }
+        if config.sample_rate > u16::MAX as u32 {
+            return Err(Error::InvalidData("unsupported ipcm sample rate"));
+        }
+        Ok(Self {
+            data_reference_index: 1,
+            channelcount: config.channel_count,
+            samplesize: config.bit_depth as u16,
+            samplerate: FixedPointU16::new(config.sample_rate as u16),
+            float,
+            pcmc: PcmCBox {
+                version: 0,
+                flags: 0,
+                format_flags: match config.endianness {
+                    PcmEndianness::Little => PCMC_LITTLE_ENDIAN,
+                    PcmEndianness::Big => 0,
+                },
+                pcm_sample_size: config.bit_depth,
+            },
+            unknown_boxes: Vec::new(),
+        })
+    }
+
+    pub fn pcm_config(&self) -> PcmConfig {
+        PcmConfig {
+            sample_format: if self.float {
+                PcmSampleFormat::Float
+            } else {
+                PcmSampleFormat::Integer
+            },
+            endianness: if self.pcmc.format_flags & PCMC_LITTLE_ENDIAN != 0 {
+                PcmEndianness::Little
+            } else {
+                PcmEndianness::Big
+            },
+            bit_depth: self.pcmc.pcm_sample_size,
+            channel_count: self.channelcount,
+            sample_rate: self.samplerate.value() as u32,
+            sample_entry: PcmSampleEntryKind::Iso,
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        if self.float {
+            BoxType::FpcmBox
+        } else {
+            BoxType::IpcmBox
+        }
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE + 8 + 20 + self.pcmc.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
+    }
+}
+
+impl Mp4Box for IpcmBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount,
+            self.pcmc.pcm_sample_size,
+            self.samplerate.value()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for IpcmBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+        reader.read_u64::<BigEndian>()?; // reserved
+        let channelcount = reader.read_u16::<BigEndian>()?;
+        let samplesize = reader.read_u16::<BigEndian>()?;
+        reader.read_u32::<BigEndian>()?; // pre-defined, reserved
+        let samplerate = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+
+        let mut pcmc = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "ipcm box contains a box with a larger size than it",
+                ));
+            }
+            if name == BoxType::PcmCBox {
+                pcmc = Some(PcmCBox::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
+        }
+        let pcmc = pcmc.ok_or(Error::BoxNotFound(BoxType::PcmCBox))?;
+
+        skip_bytes_to(reader, end)?;
+
+        let unknown_boxes = unknown.finish();
+
+        Ok(IpcmBox {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            float: false,
+            pcmc,
+            unknown_boxes,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for IpcmBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
+        self.pcmc.write_box(writer)?;
+        unknown.finish(writer)?;
+
+        Ok(size)
+    }
+}
+
+/// PCM configuration box of `ipcm` and `fpcm`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PcmCBox {
+    pub version: u8,
+    pub flags: u32,
+    /// Bit 0 is set for little endian samples.
+    pub format_flags: u8,
+    pub pcm_sample_size: u8,
+}
+
+impl Default for PcmCBox {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            flags: 0,
+            format_flags: PCMC_LITTLE_ENDIAN,
+            pcm_sample_size: 16,
+        }
+    }
+}
+
+impl Mp4Box for PcmCBox {
+    fn box_type(&self) -> BoxType {
+        BoxType::PcmCBox
+    }
+
+    fn box_size(&self) -> u64 {
+        HEADER_SIZE + HEADER_EXT_SIZE + 2
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "format_flags={:#x} pcm_sample_size={}",
+            self.format_flags, self.pcm_sample_size
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for PcmCBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let (version, flags) = read_box_header_ext(reader)?;
+        let format_flags = reader.read_u8()?;
+        let pcm_sample_size = reader.read_u8()?;
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(PcmCBox {
+            version,
+            flags,
+            format_flags,
+            pcm_sample_size,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for PcmCBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+        writer.write_u8(self.format_flags)?;
+        writer.write_u8(self.pcm_sample_size)?;
+
+        Ok(size)
+    }
+}
+
+/// QuickTime `lpcm` sample entry, a version 2 sound description.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LpcmBox {
+    pub data_reference_index: u16,
+    /// Stored as a 64-bit float, rounded to whole Hz when read.
+    pub sample_rate: u32,
+    pub channel_count: u32,
+    pub bits_per_channel: u32,
+    pub format_flags: u32,
+    pub bytes_per_packet: u32,
+    pub frames_per_packet: u32,
+}
+
+impl Default for LpcmBox {
+    fn default() -> Self {
+        Self::new(&PcmConfig::default())
+    }
+}
+
+impl LpcmBox {
+    pub fn new(config: &PcmConfig) -> Self {
+        let mut format_flags = LPCM_FLAG_PACKED;
+        format_flags |= match config.sample_format {
+            PcmSampleFormat::Integer => LPCM_FLAG_SIGNED_INTEGER,
+            PcmSampleFormat::Float => LPCM_FLAG_FLOAT,
+        };
+        if config.endianness == PcmEndianness::Big {
+            format_flags |= LPCM_FLAG_BIG_ENDIAN;
+        }
+        Self {
+            data_reference_index: 1,
+            sample_rate: config.sample_rate,
+            channel_count: config.channel_count as u32,
+            bits_per_channel: config.bit_depth as u32,
+            format_flags,
+            bytes_per_packet: config.channel_count as u32 * ((config.bit_depth as u32 + 7) / 8),
+            frames_per_packet: 1,
+        }
+    }
+
+    pub fn pcm_config(&self) -> PcmConfig {
+        PcmConfig {
+            sample_format: if self.format_flags & LPCM_FLAG_FLOAT != 0 {
+                PcmSampleFormat::Float
+            } else {
+                PcmSampleFormat::Integer
+            },
+            endianness: if self.format_flags & LPCM_FLAG_BIG_ENDIAN != 0 {
+                PcmEndianness::Big
+            } else {
+                PcmEndianness::Little
+            },
+            bit_depth: self.bits_per_channel as u8,
+            channel_count: self.channel_count as u16,
+            sample_rate: self.sample_rate,
+            sample_entry: PcmSampleEntryKind::Lpcm,
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        BoxType::LpcmBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + 8 + LPCM_FIELDS_SIZE
+    }
+}
+
+impl Mp4Box for LpcmBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} bits_per_channel={} sample_rate={} format_flags={:#x}",
+            self.channel_count, self.bits_per_channel, self.sample_rate, self.format_flags
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for LpcmBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+
+        let version = reader.read_u16::<BigEndian>()?;
+        if version != 2 {
+            return Err(Error::InvalidData("unsupported lpcm version"));
+        }
+        reader.read_u16::<BigEndian>()?; // revision level
+        reader.read_u32::<BigEndian>()?; // vendor
+        reader.read_u16::<BigEndian>()?; // always 3
+        reader.read_u16::<BigEndian>()?; // always 16
+        reader.read_i16::<BigEndian>()?; // always -2
+        reader.read_u16::<BigEndian>()?; // always 0
+        reader.read_u32::<BigEndian>()?; // always 65536
+        reader.read_u32::<BigEndian>()?; // size of struct only
+        let sample_rate = reader.read_f64::<BigEndian>()?.round() as u32;
+        let channel_count = reader.read_u32::<BigEndian>()?;
+        reader.read_u32::<BigEndian>()?; // always 0x7F000000
+        let bits_per_channel = reader.read_u32::<BigEndian>()?;
+        let format_flags = reader.read_u32::<BigEndian>()?;
+        let bytes_per_packet = reader.read_u32::<BigEndian>()?;
+        let frames_per_packet = reader.read_u32::<BigEndian>()?;
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(LpcmBox {
+            data_reference_index,
+            sample_rate,
+            channel_count,
+            bits_per_channel,
+            format_flags,
+            bytes_per_packet,
+            frames_per_packet,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for LpcmBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u16::<BigEndian>(2)?; // version
+        writer.write_u16::<BigEndian>(0)?; // revision level
+        writer.write_u32::<BigEndian>(0)?; // vendor
+        writer.write_u16::<BigEndian>(3)?;
+        writer.write_u16::<BigEndian>(16)?;
+        writer.write_i16::<BigEndian>(-2)?;
+        writer.write_u16::<BigEndian>(0)?;
+        writer.write_u32::<BigEndian>(65536)?;
+        writer.write_u32::<BigEndian>(size as u32)?; // size of struct only
+        writer.write_f64::<BigEndian>(self.sample_rate as f64)?;
+        writer.write_u32::<BigEndian>(self.channel_count)?;
+        writer.write_u32::<BigEndian>(0x7F000000)?;
+        writer.write_u32::<BigEndian>(self.bits_per_channel)?;
+        writer.write_u32::<BigEndian>(self.format_flags)?;
+        writer.write_u32::<BigEndian>(self.bytes_per_packet)?;
+        writer.write_u32::<BigEndian>(self.frames_per_packet)?;
+
+        Ok(size)
+    }
+}
+
+/// QuickTime `twos` (big endian) and `sowt` (little endian) sample entry of
+/// signed integer samples.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TwosBox {
+    pub data_reference_index: u16,
+    pub channelcount: u16,
+    pub samplesize: u16,
+
+    #[serde(with = "value_u32")]
+    pub samplerate: FixedPointU16,
+    /// Written as `sowt` if set, or as `twos` otherwise.
+    pub little_endian: bool,
+}
+
+impl Default for TwosBox {
+    fn default() -> Self {
+        Self {
+            data_reference_index: 0,
+            channelcount: 2,
+            samplesize: 16,
+            samplerate: FixedPointU16::new(48000),
+            little_endian: false,
+        }
+    }
+}
+
+impl TwosBox {
+    pub fn new(config: &PcmConfig) -> Result<Self> {
+        if config.sample_format != PcmSampleFormat::Integer
+            || (config.bit_depth != 8 && config.bit_depth != 16)
+        {
+            return Err(Error::InvalidData(
+                "twos and sowt need 8 or 16 bit integer samples",
+            ));
+        }
+        if config.sample_rate > u16::MAX as u32 {
+            return Err(Error::InvalidData("unsupported twos sample rate"));
+        }
+        Ok(Self {
+            data_reference_index: 1,
+            channelcount: config.channel_count,
+            samplesize: config.bit_depth as u16,
+            samplerate: FixedPointU16::new(config.sample_rate as u16),
+            little_endian: config.endianness == PcmEndianness::Little,
+        })
+    }
+
+    pub fn pcm_config(&self) -> PcmConfig {
+        PcmConfig {
+            sample_format: PcmSampleFormat::Integer,
+            endianness: if self.little_endian {
+                PcmEndianness::Little
+            } else {
+                PcmEndianness::Big
+            },
+            bit_depth: self.samplesize as u8,
+            channel_count: self.channelcount,
+            sample_rate: self.samplerate.value() as u32,
+            sample_entry: PcmSampleEntryKind::QuickTime,
+        }
+    }
+
+    pub fn get_type(&self) -> BoxType {
+        if self.little_endian {
+            BoxType::SowtBox
+        } else {
+            BoxType::TwosBox
+        }
+    }
+
+    pub fn get_size(&self) -> u64 {
+        HEADER_SIZE + 8 + 20
+    }
+}
+
+impl Mp4Box for TwosBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!(
+            "channel_count={} sample_size={} sample_rate={}",
+            self.channelcount,
+            self.samplesize,
+            self.samplerate.value()
+        );
+        Ok(s)
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for TwosBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        reader.read_u32::<BigEndian>()?; // reserved
+        reader.read_u16::<BigEndian>()?; // reserved
+        let data_reference_index = reader.read_u16::<BigEndian>()?;
+        reader.read_u64::<BigEndian>()?; // version, revision level, vendor
+        let channelcount = reader.read_u16::<BigEndian>()?;
+        let samplesize = reader.read_u16::<BigEndian>()?;
+        reader.read_u32::<BigEndian>()?; // compression id, packet size
+        let samplerate = FixedPointU16::new_raw(reader.read_u32::<BigEndian>()?);
+
+        // Version 1 sound descriptions and child boxes, such as `chan`, are
+        // skipped.
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(TwosBox {
+            data_reference_index,
+            channelcount,
+            samplesize,
+            samplerate,
+            little_endian: false,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TwosBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        writer.write_u32::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(0)?; // reserved
+        writer.write_u16::<BigEndian>(self.data_reference_index)?;
+
+        writer.write_u64::<BigEndian>(0)?; // version 0
+        writer.write_u16::<BigEndian>(self.channelcount)?;
+        writer.write_u16::<BigEndian>(self.samplesize)?;
+        writer.write_u32::<BigEndian>(0)?; // compression id, packet size
+        writer.write_u32::<BigEndian>(self.samplerate.raw_value())?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_fpcm() {
+        let config = PcmConfig {
+            sample_format: PcmSampleFormat::Float,
+            endianness: PcmEndianness::Big,
+            bit_depth: 32,
+            ..PcmConfig::default()
+        };
+        let src_box = IpcmBox::new(&config).unwrap();
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::FpcmBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = IpcmBox {
+            float: true,
+            ..IpcmBox::read_box(&mut reader, header.size).unwrap()
+        };
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.pcm_config(), config);
+
+        assert!(IpcmBox::new(&PcmConfig {
+            bit_depth: 24,
+            ..config
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_lpcm() {
+        let config = PcmConfig {
+            bit_depth: 24,
+            channel_count: 6,
+            sample_rate: 96000,
+            sample_entry: PcmSampleEntryKind::Lpcm,
+            ..PcmConfig::default()
+        };
+        let src_box = LpcmBox::new(&config);
+        assert_eq!(src_box.bytes_per_packet, 18);
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+        assert_eq!(buf.len(), 72);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::LpcmBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = LpcmBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.pcm_config(), config);
+    }
+
+    #[test]
+    fn test_sowt() {
+        let config = PcmConfig {
+            sample_entry: PcmSampleEntryKind::QuickTime,
+            ..PcmConfig::default()
+        };
+        let src_box = TwosBox::new(&config).unwrap();
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::SowtBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = TwosBox {
+            little_endian: true,
+            ..TwosBox::read_box(&mut reader, header.size).unwrap()
+        };
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.pcm_config(), config);
+
+        assert!(TwosBox::new(&PcmConfig {
+            sample_format: PcmSampleFormat::Float,
+            bit_depth: 32,
+            ..config
+        })
+        .is_err());
+    }
+}
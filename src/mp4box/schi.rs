@@ -6,6 +6,9 @@ use crate::mp4box::*;
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct SchiBox {
     pub tenc: Option<TencBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl SchiBox {
@@ -18,6 +21,9 @@ impl SchiBox {
         if let Some(ref tenc) = self.tenc {
             size += tenc.get_size();
         }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -46,6 +52,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for SchiBox {
         let start = box_start(reader)?;
 
         let mut tenc = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -64,17 +71,22 @@ impl<R: Read + Seek> ReadBox<&mut R> for SchiBox {
                     tenc = Some(TencBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
 
         skip_bytes_to(reader, start + size)?;
 
-        Ok(SchiBox { tenc })
+        let unknown_boxes = unknown.finish();
+
+        Ok(SchiBox {
+            tenc,
+            unknown_boxes,
+        })
     }
 }
 
@@ -83,9 +95,12 @@ impl<W: Write> WriteBox<&mut W> for SchiBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
         if let Some(ref tenc) = self.tenc {
+            unknown.before(writer)?;
             tenc.write_box(writer)?;
         }
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -8,6 +8,9 @@ pub struct SinfBox {
     pub frma: FrmaBox,
     pub schm: Option<SchmBox>,
     pub schi: Option<SchiBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl SinfBox {
@@ -23,6 +26,9 @@ impl SinfBox {
         if let Some(ref schi) = self.schi {
             size += schi.get_size();
         }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -53,6 +59,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for SinfBox {
         let mut frma = None;
         let mut schm = None;
         let mut schi = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -77,10 +84,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for SinfBox {
                     schi = Some(SchiBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -91,10 +98,13 @@ impl<R: Read + Seek> ReadBox<&mut R> for SinfBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(SinfBox {
             frma: frma.unwrap(),
             schm,
             schi,
+            unknown_boxes,
         })
     }
 }
@@ -105,15 +115,20 @@ impl<W: Write> WriteBox<&mut W> for SinfBox {
 
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
         self.frma.write_box(writer)?;
 
         if let Some(schm) = &self.schm {
+            unknown.before(writer)?;
             schm.write_box(writer)?;
         }
 
         if let Some(schi) = &self.schi {
+            unknown.before(writer)?;
             schi.write_box(writer)?;
         }
+        unknown.finish(writer)?;
 
         Ok(size)
     }
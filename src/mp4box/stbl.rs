@@ -25,6 +25,9 @@ pub struct StblBox {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub co64: Option<Co64Box>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl StblBox {
@@ -50,6 +53,9 @@ impl StblBox {
         if let Some(ref co64) = self.co64 {
             size += co64.box_size();
         }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -85,6 +91,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
         let mut stsz = None;
         let mut stco = None;
         let mut co64 = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -124,10 +131,11 @@ impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
                     co64 = Some(Co64Box::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
+
             current = reader.stream_position()?;
         }
 
@@ -149,6 +157,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(StblBox {
             stsd: stsd.unwrap(),
             stts: stts.unwrap(),
@@ -158,6 +168,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for StblBox {
             stsz: stsz.unwrap(),
             stco,
             co64,
+            unknown_boxes,
         })
     }
 }
@@ -167,22 +178,33 @@ impl<W: Write> WriteBox<&mut W> for StblBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+
+        unknown.before(writer)?;
         self.stsd.write_box(writer)?;
+        unknown.before(writer)?;
         self.stts.write_box(writer)?;
         if let Some(ref ctts) = self.ctts {
+            unknown.before(writer)?;
             ctts.write_box(writer)?;
         }
         if let Some(ref stss) = self.stss {
+            unknown.before(writer)?;
             stss.write_box(writer)?;
         }
+        unknown.before(writer)?;
         self.stsc.write_box(writer)?;
+        unknown.before(writer)?;
         self.stsz.write_box(writer)?;
         if let Some(ref stco) = self.stco {
+            unknown.before(writer)?;
             stco.write_box(writer)?;
         }
         if let Some(ref co64) = self.co64 {
+            unknown.before(writer)?;
             co64.write_box(writer)?;
         }
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -4,36 +4,201 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::vp09::Vp09Box;
 use crate::mp4box::*;
-use crate::mp4box::{avc1::Avc1Box, hev1::Hev1Box, mp4a::Mp4aBox, tx3g::Tx3gBox};
+use crate::mp4box::{
+    ac3::Ac3Box,
+    ac4::Ac4Box,
+    alac::AlacBox,
+    av01::Av01Box,
+    avc1::Avc1Box,
+    ec3::Ec3Box,
+    flac::FlacBox,
+    hev1::Hev1Box,
+    mp4a::Mp4aBox,
+    mp4v::Mp4vBox,
+    opus::OpusBox,
+    pcm::{IpcmBox, LpcmBox, TwosBox},
+    tx3g::Tx3gBox,
+};
 
+/// Sample description box, holding every sample entry of a track.
+///
+/// The entries replace the former `avc1`, `hev1`, `vp09`, `mp4a`, `tx3g`,
+/// `wvtt`, `enca` and `encv` fields; [StsdBox::avc1] and the other accessors
+/// of the same name return the first entry of that kind.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
 pub struct StsdBox {
     pub version: u8,
     pub flags: u32,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub avc1: Option<Avc1Box>,
-
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub hev1: Option<Hev1Box>,
-
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub vp09: Option<Vp09Box>,
+    /// Sample entries, in order. The sample description index of a sample
+    /// refers to `entries[index - 1]`.
+    pub entries: Vec<StsdEntry>,
+}
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub mp4a: Option<Mp4aBox>,
+/// One sample entry (sample description) of an `stsd` box.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum StsdEntry {
+    #[serde(rename = "avc1")]
+    Avc1(Avc1Box),
+    #[serde(rename = "hev1")]
+    Hev1(Hev1Box),
+    #[serde(rename = "vp09")]
+    Vp09(Vp09Box),
+    #[serde(rename = "av01")]
+    Av01(Av01Box),
+    #[serde(rename = "mp4v")]
+    Mp4v(Mp4vBox),
+    #[serde(rename = "mp4a")]
+    Mp4a(Mp4aBox),
+    #[serde(rename = "Opus")]
+    Opus(OpusBox),
+    #[serde(rename = "ac-3")]
+    Ac3(Ac3Box),
+    #[serde(rename = "ec-3")]
+    Ec3(Ec3Box),
+    #[serde(rename = "ac-4")]
+    Ac4(Ac4Box),
+    #[serde(rename = "fLaC")]
+    Flac(FlacBox),
+    #[serde(rename = "alac")]
+    Alac(AlacBox),
+    #[serde(rename = "ipcm")]
+    Ipcm(IpcmBox),
+    #[serde(rename = "lpcm")]
+    Lpcm(LpcmBox),
+    #[serde(rename = "twos")]
+    Twos(TwosBox),
+    #[serde(rename = "tx3g")]
+    Tx3g(Tx3gBox),
+    #[serde(rename = "wvtt")]
+    Wvtt(WvttBox),
+    #[serde(rename = "enca")]
+    Enca(EncaBox),
+    #[serde(rename = "encv")]
+    Encv(EncvBox),
+    /// A sample entry this crate does not parse, kept as its raw payload so
+    /// that it can be written back.
+    #[serde(rename = "unknown")]
+    Unknown { fourcc: FourCC, data: Vec<u8> },
+}
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tx3g: Option<Tx3gBox>,
+impl StsdEntry {
+    pub fn box_type(&self) -> BoxType {
+        match self {
+            StsdEntry::Avc1(avc1) => avc1.box_type(),
+            StsdEntry::Hev1(hev1) => hev1.box_type(),
+            StsdEntry::Vp09(vp09) => vp09.box_type(),
+            StsdEntry::Av01(av01) => av01.box_type(),
+            StsdEntry::Mp4v(mp4v) => mp4v.box_type(),
+            StsdEntry::Mp4a(mp4a) => mp4a.box_type(),
+            StsdEntry::Opus(opus) => opus.box_type(),
+            StsdEntry::Ac3(ac3) => ac3.box_type(),
+            StsdEntry::Ec3(ec3) => ec3.box_type(),
+            StsdEntry::Ac4(ac4) => ac4.box_type(),
+            StsdEntry::Flac(flac) => flac.box_type(),
+            StsdEntry::Alac(alac) => alac.box_type(),
+            StsdEntry::Ipcm(ipcm) => ipcm.box_type(),
+            StsdEntry::Lpcm(lpcm) => lpcm.box_type(),
+            StsdEntry::Twos(twos) => twos.box_type(),
+            StsdEntry::Tx3g(tx3g) => tx3g.box_type(),
+            StsdEntry::Wvtt(wvtt) => wvtt.box_type(),
+            StsdEntry::Enca(enca) => enca.box_type(),
+            StsdEntry::Encv(encv) => encv.box_type(),
+            StsdEntry::Unknown { fourcc, .. } => BoxType::from(*fourcc),
+        }
+    }
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub wvtt: Option<WvttBox>,
+    pub fn box_size(&self) -> u64 {
+        match self {
+            StsdEntry::Avc1(avc1) => avc1.box_size(),
+            StsdEntry::Hev1(hev1) => hev1.box_size(),
+            StsdEntry::Vp09(vp09) => vp09.box_size(),
+            StsdEntry::Av01(av01) => av01.box_size(),
+            StsdEntry::Mp4v(mp4v) => mp4v.box_size(),
+            StsdEntry::Mp4a(mp4a) => mp4a.box_size(),
+            StsdEntry::Opus(opus) => opus.box_size(),
+            StsdEntry::Ac3(ac3) => ac3.box_size(),
+            StsdEntry::Ec3(ec3) => ec3.box_size(),
+            StsdEntry::Ac4(ac4) => ac4.box_size(),
+            StsdEntry::Flac(flac) => flac.box_size(),
+            StsdEntry::Alac(alac) => alac.box_size(),
+            StsdEntry::Ipcm(ipcm) => ipcm.box_size(),
+            StsdEntry::Lpcm(lpcm) => lpcm.box_size(),
+            StsdEntry::Twos(twos) => twos.box_size(),
+            StsdEntry::Tx3g(tx3g) => tx3g.box_size(),
+            StsdEntry::Wvtt(wvtt) => wvtt.box_size(),
+            StsdEntry::Enca(enca) => enca.box_size(),
+            StsdEntry::Encv(encv) => encv.box_size(),
+            StsdEntry::Unknown { data, .. } => unknown_box_size(data),
+        }
+    }
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub enca: Option<EncaBox>,
+    fn read<R: Read + Seek>(reader: &mut R, name: BoxType, size: u64) -> Result<Self> {
+        let entry = match name {
+            BoxType::Avc1Box => StsdEntry::Avc1(Avc1Box::read_box(reader, size)?),
+            BoxType::Hev1Box => StsdEntry::Hev1(Hev1Box::read_box(reader, size)?),
+            BoxType::Hvc1Box => StsdEntry::Hev1(Hev1Box {
+                inband_parameter_sets: false,
+                ..Hev1Box::read_box(reader, size)?
+            }),
+            BoxType::Vp09Box => StsdEntry::Vp09(Vp09Box::read_box(reader, size)?),
+            BoxType::Av01Box => StsdEntry::Av01(Av01Box::read_box(reader, size)?),
+            BoxType::Mp4vBox => StsdEntry::Mp4v(Mp4vBox::read_box(reader, size)?),
+            BoxType::Mp4aBox => StsdEntry::Mp4a(Mp4aBox::read_box(reader, size)?),
+            BoxType::OpusBox => StsdEntry::Opus(OpusBox::read_box(reader, size)?),
+            BoxType::Ac3Box => StsdEntry::Ac3(Ac3Box::read_box(reader, size)?),
+            BoxType::Ec3Box => StsdEntry::Ec3(Ec3Box::read_box(reader, size)?),
+            BoxType::Ac4Box => StsdEntry::Ac4(Ac4Box::read_box(reader, size)?),
+            BoxType::FlacBox => StsdEntry::Flac(FlacBox::read_box(reader, size)?),
+            BoxType::AlacBox => StsdEntry::Alac(AlacBox::read_box(reader, size)?),
+            BoxType::IpcmBox => StsdEntry::Ipcm(IpcmBox::read_box(reader, size)?),
+            BoxType::FpcmBox => StsdEntry::Ipcm(IpcmBox {
+                float: true,
+                ..IpcmBox::read_box(reader, size)?
+            }),
+            BoxType::LpcmBox => StsdEntry::Lpcm(LpcmBox::read_box(reader, size)?),
+            BoxType::TwosBox => StsdEntry::Twos(TwosBox::read_box(reader, size)?),
+            BoxType::SowtBox => StsdEntry::Twos(TwosBox {
+                little_endian: true,
+                ..TwosBox::read_box(reader, size)?
+            }),
+            BoxType::Tx3gBox => StsdEntry::Tx3g(Tx3gBox::read_box(reader, size)?),
+            BoxType::WvttBox => StsdEntry::Wvtt(WvttBox::read_box(reader, size)?),
+            BoxType::EncaBox => StsdEntry::Enca(EncaBox::read_box(reader, size)?),
+            BoxType::EncvBox => StsdEntry::Encv(EncvBox::read_box(reader, size)?),
+            _ => StsdEntry::Unknown {
+                fourcc: FourCC::from(name),
+                data: read_unknown_box(reader, size)?,
+            },
+        };
+        Ok(entry)
+    }
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub encv: Option<EncvBox>,
+    fn write<W: Write>(&self, writer: &mut W) -> Result<u64> {
+        match self {
+            StsdEntry::Avc1(avc1) => avc1.write_box(writer),
+            StsdEntry::Hev1(hev1) => hev1.write_box(writer),
+            StsdEntry::Vp09(vp09) => vp09.write_box(writer),
+            StsdEntry::Av01(av01) => av01.write_box(writer),
+            StsdEntry::Mp4v(mp4v) => mp4v.write_box(writer),
+            StsdEntry::Mp4a(mp4a) => mp4a.write_box(writer),
+            StsdEntry::Opus(opus) => opus.write_box(writer),
+            StsdEntry::Ac3(ac3) => ac3.write_box(writer),
+            StsdEntry::Ec3(ec3) => ec3.write_box(writer),
+            StsdEntry::Ac4(ac4) => ac4.write_box(writer),
+            StsdEntry::Flac(flac) => flac.write_box(writer),
+            StsdEntry::Alac(alac) => alac.write_box(writer),
+            StsdEntry::Ipcm(ipcm) => ipcm.write_box(writer),
+            StsdEntry::Lpcm(lpcm) => lpcm.write_box(writer),
+            StsdEntry::Twos(twos) => twos.write_box(writer),
+            StsdEntry::Tx3g(tx3g) => tx3g.write_box(writer),
+            StsdEntry::Wvtt(wvtt) => wvtt.write_box(writer),
+            StsdEntry::Enca(enca) => enca.write_box(writer),
+            StsdEntry::Encv(encv) => encv.write_box(writer),
+            StsdEntry::Unknown { data, .. } => write_unknown_box(writer, self.box_type(), data),
+        }
+    }
 }
 
 impl StsdBox {
@@ -43,25 +208,169 @@ impl StsdBox {
 
     pub fn get_size(&self) -> u64 {
         let mut size = HEADER_SIZE + HEADER_EXT_SIZE + 4;
-        if let Some(ref avc1) = self.avc1 {
-            size += avc1.box_size();
-        } else if let Some(ref hev1) = self.hev1 {
-            size += hev1.box_size();
-        } else if let Some(ref vp09) = self.vp09 {
-            size += vp09.box_size();
-        } else if let Some(ref mp4a) = self.mp4a {
-            size += mp4a.box_size();
-        } else if let Some(ref tx3g) = self.tx3g {
-            size += tx3g.box_size();
-        } else if let Some(ref wvtt) = self.wvtt {
-            size += wvtt.box_size();
-        } else if let Some(ref enca) = self.enca {
-            size += enca.box_size();
-        } else if let Some(ref encv) = self.encv {
-            size += encv.box_size();
+        for entry in self.entries.iter() {
+            size += entry.box_size();
         }
         size
     }
+
+    /// The sample entry a sample description index refers to.
+    pub fn entry(&self, sample_description_index: u32) -> Option<&StsdEntry> {
+        self.entries
+            .get((sample_description_index as usize).checked_sub(1)?)
+    }
+
+    /// The first `avc1` sample entry.
+    pub fn avc1(&self) -> Option<&Avc1Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Avc1(avc1) => Some(avc1),
+            _ => None,
+        })
+    }
+
+    /// The first `hev1` or `hvc1` sample entry.
+    pub fn hev1(&self) -> Option<&Hev1Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Hev1(hev1) => Some(hev1),
+            _ => None,
+        })
+    }
+
+    /// The first `vp09` sample entry.
+    pub fn vp09(&self) -> Option<&Vp09Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Vp09(vp09) => Some(vp09),
+            _ => None,
+        })
+    }
+
+    /// The first `av01` sample entry.
+    pub fn av01(&self) -> Option<&Av01Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Av01(av01) => Some(av01),
+            _ => None,
+        })
+    }
+
+    /// The first `mp4v` sample entry.
+    pub fn mp4v(&self) -> Option<&Mp4vBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Mp4v(mp4v) => Some(mp4v),
+            _ => None,
+        })
+    }
+
+    /// The first `mp4a` sample entry.
+    pub fn mp4a(&self) -> Option<&Mp4aBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Mp4a(mp4a) => Some(mp4a),
+            _ => None,
+        })
+    }
+
+    /// The first `Opus` sample entry.
+    pub fn opus(&self) -> Option<&OpusBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Opus(opus) => Some(opus),
+            _ => None,
+        })
+    }
+
+    /// The first `ac-3` sample entry.
+    pub fn ac3(&self) -> Option<&Ac3Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Ac3(ac3) => Some(ac3),
+            _ => None,
+        })
+    }
+
+    /// The first `ec-3` sample entry.
+    pub fn ec3(&self) -> Option<&Ec3Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Ec3(ec3) => Some(ec3),
+            _ => None,
+        })
+    }
+
+    /// The first `ac-4` sample entry.
+    pub fn ac4(&self) -> Option<&Ac4Box> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Ac4(ac4) => Some(ac4),
+            _ => None,
+        })
+    }
+
+    /// The first `fLaC` sample entry.
+    pub fn flac(&self) -> Option<&FlacBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Flac(flac) => Some(flac),
+            _ => None,
+        })
+    }
+
+    /// The first `alac` sample entry.
+    pub fn alac(&self) -> Option<&AlacBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Alac(alac) => Some(alac),
+            _ => None,
+        })
+    }
+
+    /// The first `ipcm` or `fpcm` sample entry.
+    pub fn ipcm(&self) -> Option<&IpcmBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Ipcm(ipcm) => Some(ipcm),
+            _ => None,
+        })
+    }
+
+    /// The first `lpcm` sample entry.
+    pub fn lpcm(&self) -> Option<&LpcmBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Lpcm(lpcm) => Some(lpcm),
+            _ => None,
+        })
+    }
+
+    /// The first `twos` or `sowt` sample entry.
+    pub fn twos(&self) -> Option<&TwosBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Twos(twos) => Some(twos),
+            _ => None,
+        })
+    }
+
+    /// The first `tx3g` sample entry.
+    pub fn tx3g(&self) -> Option<&Tx3gBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Tx3g(tx3g) => Some(tx3g),
+            _ => None,
+        })
+    }
+
+    /// The first `wvtt` sample entry.
+    pub fn wvtt(&self) -> Option<&WvttBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Wvtt(wvtt) => Some(wvtt),
+            _ => None,
+        })
+    }
+
+    /// The first `enca` sample entry.
+    pub fn enca(&self) -> Option<&EncaBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Enca(enca) => Some(enca),
+            _ => None,
+        })
+    }
+
+    /// The first `encv` sample entry.
+    pub fn encv(&self) -> Option<&EncvBox> {
+        self.entries.iter().find_map(|entry| match entry {
+            StsdEntry::Encv(encv) => Some(encv),
+            _ => None,
+        })
+    }
 }
 
 impl Mp4Box for StsdBox {
@@ -78,7 +387,7 @@ impl Mp4Box for StsdBox {
     }
 
     fn summary(&self) -> Result<String> {
-        let s = String::new();
+        let s = format!("entries={}", self.entries.len());
         Ok(s)
     }
 }
@@ -89,52 +398,31 @@ impl<R: Read + Seek> ReadBox<&mut R> for StsdBox {
 
         let (version, flags) = read_box_header_ext(reader)?;
 
-        reader.read_u32::<BigEndian>()?; // XXX entry_count
-
-        let mut avc1 = None;
-        let mut hev1 = None;
-        let mut vp09 = None;
-        let mut mp4a = None;
-        let mut tx3g = None;
-        let mut wvtt = None;
-        let mut enca = None;
-        let mut encv = None;
-
-        // Get box header.
-        let header = BoxHeader::read(reader)?;
-        let BoxHeader { name, size: s } = header;
-        if s > size {
+        let entry_count = reader.read_u32::<BigEndian>()?;
+        if u64::from(entry_count)
+            > size.saturating_sub(HEADER_SIZE + HEADER_EXT_SIZE + 4) / HEADER_SIZE
+        {
             return Err(Error::InvalidData(
-                "stsd box contains a box with a larger size than it",
+                "stsd entry_count indicates more entries than could fit in the box",
             ));
         }
 
-        match name {
-            BoxType::Avc1Box => {
-                avc1 = Some(Avc1Box::read_box(reader, s)?);
-            }
-            BoxType::Hev1Box => {
-                hev1 = Some(Hev1Box::read_box(reader, s)?);
-            }
-            BoxType::Vp09Box => {
-                vp09 = Some(Vp09Box::read_box(reader, s)?);
-            }
-            BoxType::Mp4aBox => {
-                mp4a = Some(Mp4aBox::read_box(reader, s)?);
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        let end = start + size;
+        for _ in 0..entry_count {
+            if reader.stream_position()? + HEADER_SIZE > end {
+                break;
             }
-            BoxType::Tx3gBox => {
-                tx3g = Some(Tx3gBox::read_box(reader, s)?);
-            }
-            BoxType::WvttBox => {
-                wvtt = Some(WvttBox::read_box(reader, s)?);
-            }
-            BoxType::EncaBox => {
-                enca = Some(EncaBox::read_box(reader, s)?);
-            }
-            BoxType::EncvBox => {
-                encv = Some(EncvBox::read_box(reader, s)?);
+
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "stsd box contains a box with a larger size than it",
+                ));
             }
-            _ => {}
+            entries.push(StsdEntry::read(reader, name, s)?);
         }
 
         skip_bytes_to(reader, start + size)?;
@@ -142,14 +430,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for StsdBox {
         Ok(StsdBox {
             version,
             flags,
-            avc1,
-            hev1,
-            vp09,
-            mp4a,
-            tx3g,
-            wvtt,
-            enca,
-            encv,
+            entries,
         })
     }
 }
@@ -161,26 +442,66 @@ impl<W: Write> WriteBox<&mut W> for StsdBox {
 
         write_box_header_ext(writer, self.version, self.flags)?;
 
-        writer.write_u32::<BigEndian>(1)?; // entry_count
-
-        if let Some(ref avc1) = self.avc1 {
-            avc1.write_box(writer)?;
-        } else if let Some(ref hev1) = self.hev1 {
-            hev1.write_box(writer)?;
-        } else if let Some(ref vp09) = self.vp09 {
-            vp09.write_box(writer)?;
-        } else if let Some(ref mp4a) = self.mp4a {
-            mp4a.write_box(writer)?;
-        } else if let Some(ref tx3g) = self.tx3g {
-            tx3g.write_box(writer)?;
-        } else if let Some(ref wvtt) = self.wvtt {
-            wvtt.write_box(writer)?;
-        } else if let Some(ref enca) = self.enca {
-            enca.write_box(writer)?;
-        } else if let Some(ref encv) = self.encv {
-            encv.write_box(writer)?;
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+        for entry in self.entries.iter() {
+            entry.write(writer)?;
         }
 
         Ok(size)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_stsd_multiple_entries() {
+        let src_box = StsdBox {
+            version: 0,
+            flags: 0,
+            entries: vec![
+                StsdEntry::Mp4a(Mp4aBox::new(&AacConfig {
+                    bitrate: 128000,
+                    freq_index: SampleFreqIndex::Freq44100,
+                    chan_conf: ChannelConfig::Stereo,
+                    ..AacConfig::default()
+                })),
+                StsdEntry::Mp4a(Mp4aBox::new(&AacConfig {
+                    bitrate: 64000,
+                    freq_index: SampleFreqIndex::Freq48000,
+                    chan_conf: ChannelConfig::Mono,
+                    ..AacConfig::default()
+                })),
+                StsdEntry::Unknown {
+                    fourcc: str::parse("xyz1").unwrap(),
+                    data: vec![1, 2, 3, 4],
+                },
+            ],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::StsdBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = StsdBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+        assert_eq!(dst_box.mp4a().unwrap().samplerate.value(), 44100);
+        assert!(matches!(
+            dst_box.entry(2),
+            Some(StsdEntry::Mp4a(mp4a)) if mp4a.samplerate.value() == 48000
+        ));
+        assert!(matches!(
+            dst_box.entry(3),
+            Some(StsdEntry::Unknown { data, .. }) if data == &[1, 2, 3, 4]
+        ));
+        assert!(dst_box.entry(0).is_none());
+        assert!(dst_box.entry(4).is_none());
+    }
+}
@@ -3,19 +3,11 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct SttgBox {
     pub settings: String,
 }
 
-impl Default for SttgBox {
-    fn default() -> Self {
-        SttgBox {
-            settings: String::new(),
-        }
-    }
-}
-
 impl SttgBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::SttgBox
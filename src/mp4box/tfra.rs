@@ -0,0 +1,289 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::mp4box::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct TfraBox {
+    pub version: u8,
+    pub flags: u32,
+    pub track_id: u32,
+
+    /// Sizes of the traf, trun and sample number fields, in bytes minus one.
+    pub length_size_of_traf_num: u8,
+    pub length_size_of_trun_num: u8,
+    pub length_size_of_sample_num: u8,
+
+    pub entries: Vec<TfraEntry>,
+}
+
+/// A sync sample of a track fragment. The traf, trun and sample numbers are
+/// 1-based and locate the sample within the `moof` at `moof_offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct TfraEntry {
+    /// Presentation time of the sample, in media timescale units.
+    pub time: u64,
+    pub moof_offset: u64,
+    pub traf_number: u32,
+    pub trun_number: u32,
+    pub sample_number: u32,
+}
+
+impl TfraBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::TfraBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let entry_size = (if self.version == 1 { 16 } else { 8 })
+            + self.length_size_of_traf_num as u64
+            + 1
+            + self.length_size_of_trun_num as u64
+            + 1
+            + self.length_size_of_sample_num as u64
+            + 1;
+        HEADER_SIZE + HEADER_EXT_SIZE + 12 + self.entries.len() as u64 * entry_size
+    }
+}
+
+impl Mp4Box for TfraBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let s = format!("track_id={} entries={}", self.track_id, self.entries.len());
+        Ok(s)
+    }
+}
+
+fn read_sized_u32<R: Read>(reader: &mut R, length_size: u8) -> Result<u32> {
+    Ok(reader.read_uint::<BigEndian>(length_size as usize + 1)? as u32)
+}
+
+fn write_sized_u32<W: Write>(writer: &mut W, length_size: u8, value: u32) -> Result<()> {
+    let bytes = length_size as usize + 1;
+    if bytes < 4 && value >> (bytes * 8) != 0 {
+        return Err(Error::InvalidData("tfra number does not fit in its field"));
+    }
+    writer.write_uint::<BigEndian>(value as u64, bytes)?;
+    Ok(())
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for TfraBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let (version, flags) = read_box_header_ext(reader)?;
+        if version > 1 {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        }
+        let track_id = reader.read_u32::<BigEndian>()?;
+        let length_sizes = reader.read_u32::<BigEndian>()?;
+        let length_size_of_traf_num = ((length_sizes >> 4) & 0x3) as u8;
+        let length_size_of_trun_num = ((length_sizes >> 2) & 0x3) as u8;
+        let length_size_of_sample_num = (length_sizes & 0x3) as u8;
+        let entry_count = reader.read_u32::<BigEndian>()?;
+
+        let entry_size = (if version == 1 { 16 } else { 8 })
+            + length_size_of_traf_num as u64
+            + length_size_of_trun_num as u64
+            + length_size_of_sample_num as u64
+            + 3;
+        if u64::from(entry_count)
+            > size.saturating_sub(HEADER_SIZE + HEADER_EXT_SIZE + 12) / entry_size
+        {
+            return Err(Error::InvalidData(
+                "tfra entry_count indicates more entries than could fit in the box",
+            ));
+        }
+
+        let mut entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            let (time, moof_offset) = if version == 1 {
+                (
+                    reader.read_u64::<BigEndian>()?,
+                    reader.read_u64::<BigEndian>()?,
+                )
+            } else {
+                (
+                    reader.read_u32::<BigEndian>()? as u64,
+                    reader.read_u32::<BigEndian>()? as u64,
+                )
+            };
+            entries.push(TfraEntry {
+                time,
+                moof_offset,
+                traf_number: read_sized_u32(reader, length_size_of_traf_num)?,
+                trun_number: read_sized_u32(reader, length_size_of_trun_num)?,
+                sample_number: read_sized_u32(reader, length_size_of_sample_num)?,
+            });
+        }
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(TfraBox {
+            version,
+            flags,
+            track_id,
+            length_size_of_traf_num,
+            length_size_of_trun_num,
+            length_size_of_sample_num,
+            entries,
+        })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TfraBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        if self.version > 1 {
+            return Err(Error::InvalidData("version must be 0 or 1"));
+        }
+
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        write_box_header_ext(writer, self.version, self.flags)?;
+        writer.write_u32::<BigEndian>(self.track_id)?;
+        writer.write_u32::<BigEndian>(
+            ((self.length_size_of_traf_num as u32 & 0x3) << 4)
+                | ((self.length_size_of_trun_num as u32 & 0x3) << 2)
+                | (self.length_size_of_sample_num as u32 & 0x3),
+        )?;
+        writer.write_u32::<BigEndian>(self.entries.len() as u32)?;
+
+        for entry in self.entries.iter() {
+            if self.version == 1 {
+                writer.write_u64::<BigEndian>(entry.time)?;
+                writer.write_u64::<BigEndian>(entry.moof_offset)?;
+            } else {
+                writer.write_u32::<BigEndian>(entry.time as u32)?;
+                writer.write_u32::<BigEndian>(entry.moof_offset as u32)?;
+            }
+            write_sized_u32(writer, self.length_size_of_traf_num, entry.traf_number)?;
+            write_sized_u32(writer, self.length_size_of_trun_num, entry.trun_number)?;
+            write_sized_u32(writer, self.length_size_of_sample_num, entry.sample_number)?;
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_tfra32() {
+        let src_box = TfraBox {
+            version: 0,
+            flags: 0,
+            track_id: 1,
+            length_size_of_traf_num: 0,
+            length_size_of_trun_num: 0,
+            length_size_of_sample_num: 1,
+            entries: vec![
+                TfraEntry {
+                    time: 0,
+                    moof_offset: 1024,
+                    traf_number: 1,
+                    trun_number: 1,
+                    sample_number: 1,
+                },
+                TfraEntry {
+                    time: 90000,
+                    moof_offset: 56789,
+                    traf_number: 1,
+                    trun_number: 1,
+                    sample_number: 300,
+                },
+            ],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::TfraBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = TfraBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_tfra64() {
+        let src_box = TfraBox {
+            version: 1,
+            flags: 0,
+            track_id: 2,
+            length_size_of_traf_num: 3,
+            length_size_of_trun_num: 2,
+            length_size_of_sample_num: 0,
+            entries: vec![TfraEntry {
+                time: u32::MAX as u64 + 1,
+                moof_offset: u32::MAX as u64 + 2,
+                traf_number: 70000,
+                trun_number: 65536,
+                sample_number: 255,
+            }],
+        };
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::TfraBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = TfraBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+
+    #[test]
+    fn test_tfra_number_too_large() {
+        let src_box = TfraBox {
+            entries: vec![TfraEntry {
+                sample_number: 256,
+                ..TfraEntry::default()
+            }],
+            ..TfraBox::default()
+        };
+        let mut buf = Vec::new();
+        assert!(matches!(
+            src_box.write_box(&mut buf),
+            Err(Error::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_tfra_invalid_version() {
+        let src_box = TfraBox {
+            version: 2,
+            track_id: 1,
+            ..TfraBox::default()
+        };
+        let mut buf = Vec::new();
+        assert!(src_box.write_box(&mut buf).is_err());
+
+        let mut buf = Vec::new();
+        TfraBox::default().write_box(&mut buf).unwrap();
+        buf[8] = 2; // version
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert!(TfraBox::read_box(&mut reader, header.size).is_err());
+    }
+}
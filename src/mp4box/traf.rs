@@ -25,6 +25,9 @@ pub struct TrafBox {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(rename = "uuid")]
     pub uuids: Vec<UuidBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl TrafBox {
@@ -50,6 +53,9 @@ impl TrafBox {
         if let Some(ref senc) = self.senc {
             size += senc.box_size();
         }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -84,6 +90,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrafBox {
         let mut saio = None;
         let mut senc = None;
         let mut uuids = Vec::new();
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -121,10 +128,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrafBox {
                     uuids.push(uuid);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -135,6 +142,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrafBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(TrafBox {
             tfhd: tfhd.unwrap(),
             tfdt,
@@ -143,6 +152,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrafBox {
             saio,
             senc,
             uuids,
+            unknown_boxes,
         })
     }
 }
@@ -152,22 +162,31 @@ impl<W: Write> WriteBox<&mut W> for TrafBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+
+        unknown.before(writer)?;
         self.tfhd.write_box(writer)?;
         if let Some(ref tfdt) = self.tfdt {
+            unknown.before(writer)?;
             tfdt.write_box(writer)?;
         }
         if let Some(ref trun) = self.trun {
+            unknown.before(writer)?;
             trun.write_box(writer)?;
         }
         if let Some(ref saiz) = self.saiz {
+            unknown.before(writer)?;
             saiz.write_box(writer)?;
         }
         if let Some(ref saio) = self.saio {
+            unknown.before(writer)?;
             saio.write_box(writer)?;
         }
         if let Some(ref senc) = self.senc {
+            unknown.before(writer)?;
             senc.write_box(writer)?;
         }
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -9,6 +9,9 @@ use crate::mp4box::{edts::EdtsBox, mdia::MdiaBox, tkhd::TkhdBox};
 pub struct TrakBox {
     pub tkhd: TkhdBox,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tref: Option<TrefBox>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub edts: Option<EdtsBox>,
 
@@ -16,6 +19,9 @@ pub struct TrakBox {
     pub meta: Option<MetaBox>,
 
     pub mdia: MdiaBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl TrakBox {
@@ -26,10 +32,19 @@ impl TrakBox {
     pub fn get_size(&self) -> u64 {
         let mut size = HEADER_SIZE;
         size += self.tkhd.box_size();
+        if let Some(ref tref) = self.tref {
+            size += tref.box_size();
+        }
         if let Some(ref edts) = self.edts {
             size += edts.box_size();
         }
         size += self.mdia.box_size();
+        if let Some(ref meta) = self.meta {
+            size += meta.box_size();
+        }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -58,9 +73,11 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrakBox {
         let start = box_start(reader)?;
 
         let mut tkhd = None;
+        let mut tref = None;
         let mut edts = None;
         let mut meta = None;
         let mut mdia = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -78,6 +95,9 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrakBox {
                 BoxType::TkhdBox => {
                     tkhd = Some(TkhdBox::read_box(reader, s)?);
                 }
+                BoxType::TrefBox => {
+                    tref = Some(TrefBox::read_box(reader, s)?);
+                }
                 BoxType::EdtsBox => {
                     edts = Some(EdtsBox::read_box(reader, s)?);
                 }
@@ -88,10 +108,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrakBox {
                     mdia = Some(MdiaBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -105,11 +125,15 @@ impl<R: Read + Seek> ReadBox<&mut R> for TrakBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(TrakBox {
             tkhd: tkhd.unwrap(),
+            tref,
             edts,
             meta,
             mdia: mdia.unwrap(),
+            unknown_boxes,
         })
     }
 }
@@ -119,11 +143,25 @@ impl<W: Write> WriteBox<&mut W> for TrakBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+
+        unknown.before(writer)?;
         self.tkhd.write_box(writer)?;
+        if let Some(ref tref) = self.tref {
+            unknown.before(writer)?;
+            tref.write_box(writer)?;
+        }
         if let Some(ref edts) = self.edts {
+            unknown.before(writer)?;
             edts.write_box(writer)?;
         }
+        unknown.before(writer)?;
         self.mdia.write_box(writer)?;
+        if let Some(ref meta) = self.meta {
+            unknown.before(writer)?;
+            meta.write_box(writer)?;
+        }
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -0,0 +1,177 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+use crate::mp4box::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct TrefBox {
+    pub references: Vec<TrackReference>,
+}
+
+/// One typed entry of a `tref` box, referencing tracks by id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrackReference {
+    pub reference_type: TrackReferenceType,
+    pub track_ids: Vec<u32>,
+}
+
+impl TrackReference {
+    pub fn new(reference_type: TrackReferenceType, track_ids: Vec<u32>) -> Self {
+        Self {
+            reference_type,
+            track_ids,
+        }
+    }
+
+    fn get_size(&self) -> u64 {
+        HEADER_SIZE + 4 * self.track_ids.len() as u64
+    }
+}
+
+impl TrefBox {
+    pub fn get_type(&self) -> BoxType {
+        BoxType::TrefBox
+    }
+
+    pub fn get_size(&self) -> u64 {
+        let mut size = HEADER_SIZE;
+        for reference in self.references.iter() {
+            size += reference.get_size();
+        }
+        size
+    }
+
+    /// Ids of the tracks referenced with `reference_type`.
+    pub fn track_ids(&self, reference_type: TrackReferenceType) -> &[u32] {
+        self.references
+            .iter()
+            .find(|reference| reference.reference_type == reference_type)
+            .map(|reference| &reference.track_ids[..])
+            .unwrap_or(&[])
+    }
+
+    /// Add a reference to `track_id`, joining the entry of the same type if
+    /// there is one.
+    pub fn add(&mut self, reference_type: TrackReferenceType, track_id: u32) {
+        let entry = self
+            .references
+            .iter_mut()
+            .find(|reference| reference.reference_type == reference_type);
+        match entry {
+            Some(reference) => {
+                if !reference.track_ids.contains(&track_id) {
+                    reference.track_ids.push(track_id);
+                }
+            }
+            None => self
+                .references
+                .push(TrackReference::new(reference_type, vec![track_id])),
+        }
+    }
+}
+
+impl Mp4Box for TrefBox {
+    fn box_type(&self) -> BoxType {
+        self.get_type()
+    }
+
+    fn box_size(&self) -> u64 {
+        self.get_size()
+    }
+
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self).unwrap())
+    }
+
+    fn summary(&self) -> Result<String> {
+        let references: Vec<String> = self
+            .references
+            .iter()
+            .map(|reference| format!("{}={:?}", reference.reference_type, reference.track_ids))
+            .collect();
+        Ok(references.join(" "))
+    }
+}
+
+impl<R: Read + Seek> ReadBox<&mut R> for TrefBox {
+    fn read_box(reader: &mut R, size: u64) -> Result<Self> {
+        let start = box_start(reader)?;
+
+        let mut references = Vec::new();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
+            // Get box header.
+            let header = BoxHeader::read(reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size || s < HEADER_SIZE {
+                return Err(Error::InvalidData(
+                    "tref box contains a box with an invalid size",
+                ));
+            }
+
+            let count = (s - HEADER_SIZE) / 4;
+            let mut track_ids = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                track_ids.push(reader.read_u32::<BigEndian>()?);
+            }
+            references.push(TrackReference::new(FourCC::from(name).into(), track_ids));
+
+            skip_bytes_to(reader, current + s)?;
+            current = reader.stream_position()?;
+        }
+
+        skip_bytes_to(reader, start + size)?;
+
+        Ok(TrefBox { references })
+    }
+}
+
+impl<W: Write> WriteBox<&mut W> for TrefBox {
+    fn write_box(&self, writer: &mut W) -> Result<u64> {
+        let size = self.box_size();
+        BoxHeader::new(self.box_type(), size).write(writer)?;
+
+        for reference in self.references.iter() {
+            let name = BoxType::from(FourCC::from(reference.reference_type));
+            BoxHeader::new(name, reference.get_size()).write(writer)?;
+            for track_id in reference.track_ids.iter() {
+                writer.write_u32::<BigEndian>(*track_id)?;
+            }
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4box::BoxHeader;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_tref() {
+        let mut src_box = TrefBox::default();
+        src_box.add(TrackReferenceType::Chap, 3);
+        src_box.add(TrackReferenceType::Subt, 4);
+        src_box.add(TrackReferenceType::Chap, 5);
+        src_box.add(TrackReferenceType::Other((*b"fall").into()), 2);
+        assert_eq!(src_box.track_ids(TrackReferenceType::Chap), [3, 5]);
+        assert!(src_box.track_ids(TrackReferenceType::Hint).is_empty());
+
+        let mut buf = Vec::new();
+        src_box.write_box(&mut buf).unwrap();
+        assert_eq!(buf.len(), src_box.box_size() as usize);
+
+        let mut reader = Cursor::new(&buf);
+        let header = BoxHeader::read(&mut reader).unwrap();
+        assert_eq!(header.name, BoxType::TrefBox);
+        assert_eq!(src_box.box_size(), header.size);
+
+        let dst_box = TrefBox::read_box(&mut reader, header.size).unwrap();
+        assert_eq!(src_box, dst_box);
+    }
+}
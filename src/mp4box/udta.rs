@@ -9,6 +9,12 @@ use crate::mp4box::*;
 pub struct UdtaBox {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta: Option<MetaBox>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chpl: Option<ChplBox>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl UdtaBox {
@@ -21,6 +27,12 @@ impl UdtaBox {
         if let Some(meta) = &self.meta {
             size += meta.box_size();
         }
+        if let Some(chpl) = &self.chpl {
+            size += chpl.box_size();
+        }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -48,6 +60,8 @@ impl<R: Read + Seek> ReadBox<&mut R> for UdtaBox {
         let start = box_start(reader)?;
 
         let mut meta = None;
+        let mut chpl = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -65,18 +79,27 @@ impl<R: Read + Seek> ReadBox<&mut R> for UdtaBox {
                 BoxType::MetaBox => {
                     meta = Some(MetaBox::read_box(reader, s)?);
                 }
+                BoxType::ChplBox => {
+                    chpl = Some(ChplBox::read_box(reader, s)?);
+                }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
 
         skip_bytes_to(reader, start + size)?;
 
-        Ok(UdtaBox { meta })
+        let unknown_boxes = unknown.finish();
+
+        Ok(UdtaBox {
+            meta,
+            chpl,
+            unknown_boxes,
+        })
     }
 }
 
@@ -85,9 +108,18 @@ impl<W: Write> WriteBox<&mut W> for UdtaBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+
         if let Some(meta) = &self.meta {
+            unknown.before(writer)?;
             meta.write_box(writer)?;
         }
+        if let Some(chpl) = &self.chpl {
+            unknown.before(writer)?;
+            chpl.write_box(writer)?;
+        }
+        unknown.finish(writer)?;
+
         Ok(size)
     }
 }
@@ -100,7 +132,7 @@ mod tests {
 
     #[test]
     fn test_udta_empty() {
-        let src_box = UdtaBox { meta: None };
+        let src_box = UdtaBox::default();
 
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -119,6 +151,18 @@ mod tests {
     fn test_udta() {
         let src_box = UdtaBox {
             meta: Some(MetaBox::default()),
+            chpl: Some(ChplBox {
+                version: 1,
+                flags: 0,
+                entries: vec![ChplEntry {
+                    start_time: 0,
+                    title: String::from("Intro"),
+                }],
+            }),
+            unknown_boxes: vec![UnknownBox::new(
+                BoxType::from(0xa978797a),
+                b"+12.3-045.6/".to_vec(),
+            )],
         };
 
         let mut buf = Vec::new();
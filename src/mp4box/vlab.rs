@@ -3,19 +3,11 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct VlabBox {
     pub source_label: String,
 }
 
-impl Default for VlabBox {
-    fn default() -> Self {
-        VlabBox {
-            source_label: String::new(),
-        }
-    }
-}
-
 impl VlabBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::VlabBox
@@ -20,6 +20,9 @@ pub struct Vp09Box {
     pub depth: u16,
     pub end_code: u16,
     pub vpcc: VpccBox,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl Vp09Box {
@@ -61,6 +64,7 @@ impl Vp09Box {
                 matrix_coefficients: 0,
                 codec_initialization_data_size: 0,
             },
+            unknown_boxes: Vec::new(),
         }
     }
 }
@@ -71,7 +75,11 @@ impl Mp4Box for Vp09Box {
     }
 
     fn box_size(&self) -> u64 {
-        0x6A
+        let mut size = HEADER_SIZE + 78 + self.vpcc.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
+        size
     }
 
     fn to_json(&self) -> Result<String> {
@@ -119,17 +127,33 @@ impl<R: Read + Seek> ReadBox<&mut R> for Vp09Box {
         let depth: u16 = reader.read_u16::<BigEndian>()?;
         let end_code: u16 = reader.read_u16::<BigEndian>()?;
 
-        let vpcc = {
+        let mut vpcc = None;
+        let mut unknown = UnknownBoxReader::default();
+
+        let mut current = reader.stream_position()?;
+        let end = start + size;
+        while current < end {
             let header = BoxHeader::read(reader)?;
-            if header.size > size {
+            let BoxHeader { name, size: s } = header;
+            if s > size {
                 return Err(Error::InvalidData(
                     "vp09 box contains a box with a larger size than it",
                 ));
             }
-            VpccBox::read_box(reader, header.size)?
-        };
+            if name == BoxType::VpccBox {
+                vpcc = Some(VpccBox::read_box(reader, s)?);
+            } else {
+                unknown.read(reader, name, s)?;
+            }
+            unknown.child_read();
+
+            current = reader.stream_position()?;
+        }
+        let vpcc = vpcc.ok_or(Error::BoxNotFound(BoxType::VpccBox))?;
+
+        skip_bytes_to(reader, end)?;
 
-        skip_bytes_to(reader, start + size)?;
+        let unknown_boxes = unknown.finish();
 
         Ok(Self {
             version,
@@ -147,6 +171,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for Vp09Box {
             depth,
             end_code,
             vpcc,
+            unknown_boxes,
         })
     }
 }
@@ -172,7 +197,10 @@ impl<W: Write> WriteBox<&mut W> for Vp09Box {
         writer.write_all(&self.compressorname)?;
         writer.write_u16::<BigEndian>(self.depth)?;
         writer.write_u16::<BigEndian>(self.end_code)?;
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
         VpccBox::write_box(&self.vpcc, writer)?;
+        unknown.finish(writer)?;
 
         Ok(size)
     }
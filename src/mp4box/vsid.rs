@@ -3,17 +3,11 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct VsidBox {
     pub source_id: u32,
 }
 
-impl Default for VsidBox {
-    fn default() -> Self {
-        VsidBox { source_id: 0 }
-    }
-}
-
 impl VsidBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::VsidBox
@@ -74,9 +68,7 @@ mod tests {
 
     #[test]
     fn test_vsid() {
-        let src_box = VsidBox {
-            source_id: 1234,
-        };
+        let src_box = VsidBox { source_id: 1234 };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
         assert_eq!(buf.len(), src_box.box_size() as usize);
@@ -3,19 +3,11 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct VttCBox {
     pub config: String,
 }
 
-impl Default for VttCBox {
-    fn default() -> Self {
-        VttCBox {
-            config: String::new(),
-        }
-    }
-}
-
 impl VttCBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::VttCBox
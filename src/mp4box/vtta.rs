@@ -3,19 +3,11 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct VttaBox {
     pub additional_text: String,
 }
 
-impl Default for VttaBox {
-    fn default() -> Self {
-        VttaBox {
-            additional_text: String::new(),
-        }
-    }
-}
-
 impl VttaBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::VttaBox
@@ -3,25 +3,16 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct VttcBox {
     pub source_id: Option<VsidBox>,
     pub cue_id: Option<IdenBox>,
     pub cue_time: Option<CtimBox>,
     pub cue_settings: Option<SttgBox>,
     pub payload: PaylBox,
-}
 
-impl Default for VttcBox {
-    fn default() -> Self {
-        VttcBox {
-            source_id: None,
-            cue_id: None,
-            cue_time: None,
-            cue_settings: None,
-            payload: PaylBox::default(),
-        }
-    }
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl VttcBox {
@@ -44,6 +35,9 @@ impl VttcBox {
             size += cue_settings.box_size();
         }
         size += self.payload.box_size();
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -76,6 +70,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for VttcBox {
         let mut ctim = None;
         let mut sttg = None;
         let mut payl = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -106,10 +101,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for VttcBox {
                     sttg = Some(SttgBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -120,12 +115,15 @@ impl<R: Read + Seek> ReadBox<&mut R> for VttcBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(VttcBox {
             source_id: vsid,
             cue_id: iden,
             cue_time: ctim,
             cue_settings: sttg,
             payload: payl.unwrap(),
+            unknown_boxes,
         })
     }
 }
@@ -135,19 +133,26 @@ impl<W: Write> WriteBox<&mut W> for VttcBox {
         let size = self.box_size();
         BoxHeader::new(self.box_type(), size).write(writer)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
         if let Some(ref source_id) = self.source_id {
+            unknown.before(writer)?;
             source_id.write_box(writer)?;
         }
         if let Some(ref cue_id) = self.cue_id {
+            unknown.before(writer)?;
             cue_id.write_box(writer)?;
         }
         if let Some(ref cue_time) = self.cue_time {
+            unknown.before(writer)?;
             cue_time.write_box(writer)?;
         }
         if let Some(ref cue_settings) = self.cue_settings {
+            unknown.before(writer)?;
             cue_settings.write_box(writer)?;
         }
+        unknown.before(writer)?;
         self.payload.write_box(writer)?;
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -173,6 +178,7 @@ mod tests {
             payload: PaylBox {
                 cue_text: "test me".into(),
             },
+            unknown_boxes: Vec::new(),
         };
         let mut buf = Vec::new();
         src_box.write_box(&mut buf).unwrap();
@@ -3,15 +3,9 @@ use std::io::{Read, Seek, Write};
 
 use crate::mp4box::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
 pub struct VtteBox {}
 
-impl Default for VtteBox {
-    fn default() -> Self {
-        VtteBox {}
-    }
-}
-
 impl VtteBox {
     pub fn get_type(&self) -> BoxType {
         BoxType::VtteBox
@@ -10,6 +10,8 @@ pub struct WvttBox {
     pub config: VttCBox,
     pub label: Option<VlabBox>,
     // pub bitrate: Option<BtrtBox>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unknown_boxes: Vec<UnknownBox>,
 }
 
 impl Default for WvttBox {
@@ -18,6 +20,7 @@ impl Default for WvttBox {
             data_reference_index: 1,
             config: VttCBox::default(),
             label: None,
+            unknown_boxes: Vec::new(),
         }
     }
 }
@@ -33,6 +36,9 @@ impl WvttBox {
         if let Some(ref label) = self.label {
             size += label.box_size();
         }
+        for unknown in self.unknown_boxes.iter() {
+            size += unknown.box_size();
+        }
         size
     }
 }
@@ -65,6 +71,7 @@ impl<R: Read + Seek> ReadBox<&mut R> for WvttBox {
 
         let mut vttc = None;
         let mut vlab = None;
+        let mut unknown = UnknownBoxReader::default();
 
         let mut current = reader.stream_position()?;
         let end = start + size;
@@ -86,10 +93,10 @@ impl<R: Read + Seek> ReadBox<&mut R> for WvttBox {
                     vlab = Some(VlabBox::read_box(reader, s)?);
                 }
                 _ => {
-                    // XXX warn!()
-                    skip_box(reader, s)?;
+                    unknown.read(reader, name, s)?;
                 }
             }
+            unknown.child_read();
 
             current = reader.stream_position()?;
         }
@@ -100,10 +107,13 @@ impl<R: Read + Seek> ReadBox<&mut R> for WvttBox {
 
         skip_bytes_to(reader, start + size)?;
 
+        let unknown_boxes = unknown.finish();
+
         Ok(WvttBox {
             data_reference_index,
             config: vttc.unwrap(),
             label: vlab,
+            unknown_boxes,
         })
     }
 }
@@ -117,11 +127,15 @@ impl<W: Write> WriteBox<&mut W> for WvttBox {
         writer.write_u16::<BigEndian>(0)?; // reserved
         writer.write_u16::<BigEndian>(self.data_reference_index)?;
 
+        let mut unknown = UnknownBoxWriter::new(&self.unknown_boxes);
+        unknown.before(writer)?;
         self.config.write_box(writer)?;
 
         if let Some(ref label) = self.label {
+            unknown.before(writer)?;
             label.write_box(writer)?;
         }
+        unknown.finish(writer)?;
 
         Ok(size)
     }
@@ -0,0 +1,244 @@
+use bytes::{Buf, BytesMut};
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::io::Cursor;
+
+use crate::mp4box::*;
+use crate::reader::build_tracks;
+use crate::track::{SampleCursor, SampleEntry};
+use crate::*;
+
+/// Something [Mp4PushParser] found in its input.
+#[derive(Debug)]
+pub enum Mp4Event {
+    Ftyp(FtypBox),
+    Moov(Box<MoovBox>),
+    /// A movie fragment starting at `offset`. Its samples follow as
+    /// [Mp4Event::Sample] once their data has arrived.
+    Moof {
+        offset: u64,
+        moof: MoofBox,
+    },
+    Emsg(EmsgBox),
+    /// A sample whose data is complete.
+    Sample {
+        track_id: u32,
+        sample: Mp4Sample,
+    },
+}
+
+#[derive(Debug)]
+struct PendingSample {
+    track_id: u32,
+    entry: SampleEntry,
+}
+
+/// Incremental parser for MP4 data that arrives in chunks, such as
+/// low-latency CMAF segments over HTTP chunked transfer.
+///
+/// Feed bytes with [Mp4PushParser::push] and drain the resulting events with
+/// [Mp4PushParser::next_event]. Metadata boxes are buffered until they are
+/// complete; media data is only kept until the samples it contains have been
+/// emitted. Samples are known once the `moov` box (for progressive files) or
+/// a `moof` box has been parsed, so media data that precedes them is
+/// dropped.
+#[derive(Debug, Default)]
+pub struct Mp4PushParser {
+    buf: BytesMut,
+    // File offset of the first byte in `buf`.
+    offset: u64,
+    // End offset of the box being streamed through (e.g. `mdat`), if any.
+    streaming_end: Option<u64>,
+
+    moov: Option<MoovBox>,
+    pending_samples: VecDeque<PendingSample>,
+    events: VecDeque<Mp4Event>,
+}
+
+impl Mp4PushParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The `moov` box, once it has been parsed.
+    pub fn moov(&self) -> Option<&MoovBox> {
+        self.moov.as_ref()
+    }
+
+    /// Number of bytes currently held back waiting for more data.
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Parse the next chunk of input.
+    pub fn push(&mut self, data: &[u8]) -> Result<()> {
+        self.buf.extend_from_slice(data);
+        self.parse()
+    }
+
+    pub fn next_event(&mut self) -> Option<Mp4Event> {
+        self.events.pop_front()
+    }
+
+    fn buffered_end(&self) -> u64 {
+        self.offset + self.buf.len() as u64
+    }
+
+    fn consume(&mut self, len: u64) {
+        self.buf.advance(len as usize);
+        self.offset += len;
+    }
+
+    fn parse(&mut self) -> Result<()> {
+        loop {
+            if let Some(end) = self.streaming_end {
+                self.emit_samples();
+
+                // Drop everything up to the next sample that is still missing data.
+                let next_sample = self
+                    .pending_samples
+                    .front()
+                    .map_or(u64::MAX, |sample| sample.entry.offset);
+                let keep_from = cmp::min(cmp::min(end, next_sample), self.buffered_end());
+                if keep_from > self.offset {
+                    self.consume(keep_from - self.offset);
+                }
+                if self.offset < end {
+                    return Ok(());
+                }
+                self.streaming_end = None;
+            }
+
+            if self.buf.len() < HEADER_SIZE as usize {
+                return Ok(());
+            }
+            let header_size = if self.buf[..4] == [0, 0, 0, 1] {
+                HEADER_SIZE + 8
+            } else {
+                HEADER_SIZE
+            };
+            if self.buf.len() < header_size as usize {
+                return Ok(());
+            }
+            let BoxHeader { name, size } = BoxHeader::read(&mut &self.buf[..])?;
+
+            // BoxHeader excludes the 64-bit largesize field from the size,
+            // and a size of zero extends the box to the end of the stream.
+            let box_size = if size == 0 {
+                None
+            } else {
+                Some(size + header_size - HEADER_SIZE)
+            };
+            if box_size.is_some_and(|box_size| box_size < header_size) {
+                return Err(Error::InvalidData("box size smaller than its header"));
+            }
+
+            match name {
+                BoxType::FtypBox
+                | BoxType::MoovBox
+                | BoxType::MoofBox
+                | BoxType::EmsgBox
+                | BoxType::UuidBox => {
+                    let box_size = box_size.ok_or(Error::InvalidData(
+                        "metadata box extending to the end of the stream",
+                    ))?;
+                    if (self.buf.len() as u64) < box_size {
+                        return Ok(());
+                    }
+                    let offset = self.offset;
+                    let data = self.buf.split_to(box_size as usize);
+                    self.offset += box_size;
+
+                    let mut reader = Cursor::new(&data[..]);
+                    reader.set_position(header_size);
+                    self.parse_box(name, offset, &mut reader, size)?;
+                }
+                _ => {
+                    // Media data and other boxes are streamed through.
+                    self.streaming_end = Some(box_size.map_or(u64::MAX, |s| self.offset + s));
+                    self.consume(header_size);
+                }
+            }
+        }
+    }
+
+    fn parse_box(
+        &mut self,
+        name: BoxType,
+        offset: u64,
+        reader: &mut Cursor<&[u8]>,
+        size: u64,
+    ) -> Result<()> {
+        match name {
+            BoxType::FtypBox => {
+                let ftyp = FtypBox::read_box(reader, size)?;
+                self.events.push_back(Mp4Event::Ftyp(ftyp));
+            }
+            BoxType::MoovBox => {
+                let moov = MoovBox::read_box(reader, size)?;
+                // Samples of progressive files are described by the moov box.
+                self.queue_samples(&build_tracks(&moov, &[], &[])?)?;
+                self.moov = Some(moov.clone());
+                self.events.push_back(Mp4Event::Moov(Box::new(moov)));
+            }
+            BoxType::MoofBox => {
+                let moof = MoofBox::read_box(reader, size)?;
+                let moov = self
+                    .moov
+                    .as_ref()
+                    .ok_or(Error::BoxNotFound(BoxType::MoovBox))?;
+                let tracks = build_tracks(moov, std::slice::from_ref(&moof), &[offset])?;
+                self.queue_samples(&tracks)?;
+                self.events.push_back(Mp4Event::Moof { offset, moof });
+            }
+            BoxType::EmsgBox => {
+                let emsg = EmsgBox::read_box(reader, size)?;
+                self.events.push_back(Mp4Event::Emsg(emsg));
+            }
+            _ => {
+                // uuid boxes are only needed for their size.
+            }
+        }
+        Ok(())
+    }
+
+    fn queue_samples(&mut self, tracks: &HashMap<u32, Mp4Track>) -> Result<()> {
+        let mut samples = Vec::new();
+        for (track_id, track) in tracks.iter() {
+            let mut cursor = SampleCursor::new(track, 1)?;
+            while let Some(entry) = cursor.next(track)? {
+                samples.push(PendingSample {
+                    track_id: *track_id,
+                    entry,
+                });
+            }
+        }
+        samples.sort_by_key(|sample| (sample.entry.offset, sample.track_id));
+        self.pending_samples.extend(samples);
+        Ok(())
+    }
+
+    /// Emit the pending samples whose data is in the buffer.
+    fn emit_samples(&mut self) {
+        let buffered_end = self.buffered_end();
+        while let Some(sample) = self.pending_samples.front() {
+            let start = sample.entry.offset;
+            let end = start + sample.entry.size as u64;
+            if start < self.offset {
+                // The data has already gone by.
+                self.pending_samples.pop_front();
+                continue;
+            }
+            if end > buffered_end {
+                break;
+            }
+
+            let sample = self.pending_samples.pop_front().unwrap();
+            let data = &self.buf[(start - self.offset) as usize..(end - self.offset) as usize];
+            self.events.push_back(Mp4Event::Sample {
+                track_id: sample.track_id,
+                sample: sample.entry.to_sample(Bytes::copy_from_slice(data)),
+            });
+        }
+    }
+}
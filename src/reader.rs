@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom};
 use std::time::Duration;
 
 use crate::meta::MetaBox;
@@ -7,17 +7,38 @@ use crate::*;
 
 #[derive(Debug)]
 pub struct Mp4Reader<R> {
-    reader: R,
+    pub(crate) reader: R,
     pub ftyp: FtypBox,
     pub moov: MoovBox,
     pub moofs: Vec<MoofBox>,
     pub emsgs: Vec<EmsgBox>,
     pub uuids: Vec<UuidBox>,
-
+    pub sidxs: Vec<SidxBox>,
+    pub mfra: Option<MfraBox>,
+
+    // File offset of the end of each sidx box, which the byte offsets in
+    // the box are relative to.
+    sidx_ends: Vec<u64>,
+    // Whether fragments are loaded one at a time through the mfra box.
+    fragments_from_mfra: bool,
     tracks: HashMap<u32, Mp4Track>,
     size: u64,
 }
 
+/// The top-level boxes of a file that [Mp4Reader] keeps.
+#[derive(Debug, Default)]
+pub(crate) struct TopLevelBoxes {
+    pub ftyp: Option<FtypBox>,
+    pub moov: Option<MoovBox>,
+    pub moofs: Vec<MoofBox>,
+    pub moof_offsets: Vec<u64>,
+    pub emsgs: Vec<EmsgBox>,
+    pub uuids: Vec<UuidBox>,
+    pub sidxs: Vec<SidxBox>,
+    pub sidx_ends: Vec<u64>,
+    pub mfra: Option<MfraBox>,
+}
+
 impl<R: Read + Seek> Mp4Reader<R> {
     pub fn read_header(mut reader: R, size: u64) -> Result<Self> {
         let start = reader.stream_position()?;
@@ -28,6 +49,9 @@ impl<R: Read + Seek> Mp4Reader<R> {
         let mut moof_offsets = Vec::new();
         let mut emsgs = Vec::new();
         let mut uuids = Vec::new();
+        let mut sidxs = Vec::new();
+        let mut sidx_ends = Vec::new();
+        let mut mfra = None;
 
         let mut current = start;
         while current < size {
@@ -73,6 +97,14 @@ impl<R: Read + Seek> Mp4Reader<R> {
                     let uuid = UuidBox::read_box(&mut reader, s)?;
                     uuids.push(uuid);
                 }
+                BoxType::SidxBox => {
+                    let sidx = SidxBox::read_box(&mut reader, s)?;
+                    sidxs.push(sidx);
+                    sidx_ends.push(reader.stream_position()?);
+                }
+                BoxType::MfraBox => {
+                    mfra = Some(MfraBox::read_box(&mut reader, s)?);
+                }
                 _ => {
                     // XXX warn!()
                     skip_box(&mut reader, s)?;
@@ -81,77 +113,80 @@ impl<R: Read + Seek> Mp4Reader<R> {
             current = reader.stream_position()?;
         }
 
-        if ftyp.is_none() {
-            return Err(Error::BoxNotFound(BoxType::FtypBox));
-        }
-        if moov.is_none() {
-            return Err(Error::BoxNotFound(BoxType::MoovBox));
-        }
-
         let size = current - start;
-        let mut tracks = if let Some(ref moov) = moov {
-            if moov.traks.iter().any(|trak| trak.tkhd.track_id == 0) {
-                return Err(Error::InvalidData("illegal track id 0"));
+        let boxes = TopLevelBoxes {
+            ftyp,
+            moov,
+            moofs,
+            moof_offsets,
+            emsgs,
+            uuids,
+            sidxs,
+            sidx_ends,
+            mfra,
+        };
+        Self::from_boxes(reader, size, boxes)
+    }
+
+    /// Read the header of a fragmented file without parsing its `moof` boxes.
+    ///
+    /// The fragments are found through the `mfra` box at the end of the file
+    /// instead, and loaded one at a time by [Mp4Reader::seek_fragment]. Falls
+    /// back to [Mp4Reader::read_header] if the file does not end with an
+    /// `mfra` box.
+    pub fn read_header_with_mfra(mut reader: R, size: u64) -> Result<Self> {
+        let start = reader.stream_position()?;
+        let mfra = match read_mfra(&mut reader, start, size)? {
+            Some(mfra) => mfra,
+            None => {
+                reader.seek(SeekFrom::Start(start))?;
+                return Self::read_header(reader, size);
             }
-            moov.traks
-                .iter()
-                .map(|trak| (trak.tkhd.track_id, Mp4Track::from(trak)))
-                .collect()
-        } else {
-            HashMap::new()
         };
+        reader.seek(SeekFrom::Start(start))?;
 
-        // Update tracks if any fragmented (moof) boxes are found.
-        if !moofs.is_empty() {
-            // Grab the mvex box if it exists.
-            let mvex = moov.as_ref().and_then(|moov| moov.mvex.as_ref());
-
-            for (moof, moof_offset) in moofs.iter().zip(moof_offsets) {
-                for traf in moof.trafs.iter() {
-                    let track_id = traf.tfhd.track_id;
-
-                    // Get the default sample duration for the indicated track.
-                    // This is buried in the optional mvex box, in a trex box per track.
-                    let default_sample_duration = mvex
-                        .and_then(|mvex| {
-                            mvex.trexs
-                                .iter()
-                                .find(|trex| trex.track_id == track_id)
-                                .map(|trex| trex.default_sample_duration)
-                        })
-                        .unwrap_or(0);
-
-                    let default_sample_size = mvex
-                        .and_then(|mvex| {
-                            mvex.trexs
-                                .iter()
-                                .find(|trex| trex.track_id == track_id)
-                                .map(|trex| trex.default_sample_size)
-                        })
-                        .unwrap_or(0);
-
-                    if let Some(track) = tracks.get_mut(&track_id) {
-                        track.default_sample_duration = default_sample_duration;
-                        track.default_sample_size = default_sample_size;
-                        track.moof_offsets.push(moof_offset);
-                        track.trafs.push(traf.clone())
-                    } else {
-                        return Err(Error::TrakNotFound(track_id));
-                    }
+        let mut boxes = TopLevelBoxes {
+            mfra: Some(mfra),
+            ..TopLevelBoxes::default()
+        };
+        let mut current = start;
+        while current < size {
+            // Get box header.
+            let header = BoxHeader::read(&mut reader)?;
+            let BoxHeader { name, size: s } = header;
+            if s > size {
+                return Err(Error::InvalidData(
+                    "file contains a box with a larger size than it",
+                ));
+            }
+
+            // Break if size zero BoxHeader, which can result in dead-loop.
+            if s == 0 {
+                break;
+            }
+
+            // Stop at the first fragment, everything after it is reached
+            // through the mfra box.
+            match name {
+                BoxType::FtypBox => {
+                    boxes.ftyp = Some(FtypBox::read_box(&mut reader, s)?);
+                }
+                BoxType::MoovBox => {
+                    boxes.moov = Some(MoovBox::read_box(&mut reader, s)?);
+                }
+                BoxType::MoofBox => {
+                    break;
+                }
+                _ => {
+                    skip_box(&mut reader, s)?;
                 }
             }
+            current = reader.stream_position()?;
         }
 
-        Ok(Mp4Reader {
-            reader,
-            ftyp: ftyp.unwrap(),
-            moov: moov.unwrap(),
-            moofs,
-            emsgs,
-            uuids,
-            size,
-            tracks,
-        })
+        let mut mp4 = Self::from_boxes(reader, size - start, boxes)?;
+        mp4.fragments_from_mfra = true;
+        Ok(mp4)
     }
 
     pub fn read_fragment_header<FR: Read + Seek>(
@@ -165,6 +200,8 @@ impl<R: Read + Seek> Mp4Reader<R> {
         let mut moof_offsets = Vec::new();
         let mut emsgs = Vec::new();
         let mut uuids = Vec::new();
+        let mut sidxs = Vec::new();
+        let mut sidx_ends = Vec::new();
 
         let mut current = start;
         while current < size {
@@ -201,6 +238,11 @@ impl<R: Read + Seek> Mp4Reader<R> {
                     let uuid = UuidBox::read_box(&mut reader, s)?;
                     uuids.push(uuid);
                 }
+                BoxType::SidxBox => {
+                    let sidx = SidxBox::read_box(&mut reader, s)?;
+                    sidxs.push(sidx);
+                    sidx_ends.push(reader.stream_position()?);
+                }
                 _ => {
                     // XXX warn!()
                     skip_box(&mut reader, s)?;
@@ -214,43 +256,177 @@ impl<R: Read + Seek> Mp4Reader<R> {
         }
 
         let size = current - start;
-        let mut tracks: HashMap<u32, Mp4Track> = self
-            .moov
-            .traks
-            .iter()
-            .map(|trak| (trak.tkhd.track_id, Mp4Track::from(trak)))
-            .collect();
-
-        for (moof, moof_offset) in moofs.iter().zip(moof_offsets) {
-            for traf in moof.trafs.iter() {
-                let track_id = traf.tfhd.track_id;
-                if let Some(track) = tracks.get_mut(&track_id) {
-                    let default_sample_duration = if let Some(ref mvex) = &self.moov.mvex {
-                        mvex.trexs.iter()
-                                    .find(|trex| trex.track_id == track_id)
-                                    .map(|trex| trex.default_sample_duration).unwrap_or(0)
-                    } else {
-                        0
-                    };
-
-                    track.default_sample_duration = default_sample_duration;
-                    track.moof_offsets.push(moof_offset);
-                    track.trafs.push(traf.clone())
+        let tracks = build_tracks(&self.moov, &moofs, &moof_offsets)?;
+
+        Ok(Mp4Reader {
+            reader,
+            ftyp: self.ftyp.clone(),
+            moov: self.moov.clone(),
+            moofs,
+            emsgs,
+            uuids,
+            sidxs,
+            mfra: self.mfra.clone(),
+            sidx_ends,
+            fragments_from_mfra: false,
+            tracks,
+            size,
+        })
+    }
+
+    /// The chapters of the file, read from the QuickTime chapter track if a
+    /// track references one through `tref/chap`, or else from the Nero `chpl`
+    /// box in `moov/udta`.
+    pub fn chapters(&mut self) -> Result<Vec<Chapter>> {
+        let movie_timescale = self.timescale();
+        let track = match chapter::chapter_track_id(&self.moov) {
+            Some(track_id) => self.tracks.get(&track_id),
+            None => None,
+        };
+        if let Some(track) = track {
+            let edits = track.edit_list();
+            let timescale = track.timescale();
+            let mut chapters = Vec::new();
+            for sample_id in 1..=track.sample_count() {
+                let sample = match track.read_sample(&mut self.reader, sample_id)? {
+                    Some(sample) => sample,
+                    None => break,
+                };
+                let start_time = if edits.is_empty() {
+                    Some(sample.start_time)
                 } else {
-                    return Err(Error::TrakNotFound(track_id));
+                    edit::presentation_time(&edits, sample.start_time, movie_timescale, timescale)
+                };
+                if let Some(start_time) = start_time {
+                    let micros = start_time as u128 * 1_000_000 / timescale as u128;
+                    chapters.push(Chapter::new(
+                        Duration::from_micros(micros as u64),
+                        chapter::text_sample_title(&sample.bytes),
+                    ));
                 }
             }
+            return Ok(chapters);
+        }
+
+        Ok(self
+            .moov
+            .udta
+            .as_ref()
+            .and_then(|udta| udta.chpl.as_ref())
+            .map(chapter::chapters_from_chpl)
+            .unwrap_or_default())
+    }
+
+    pub fn read_sample(&mut self, track_id: u32, sample_id: u32) -> Result<Option<Mp4Sample>> {
+        if let Some(track) = self.tracks.get(&track_id) {
+            track.read_sample(&mut self.reader, sample_id)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+
+    /// Return the id of the nearest sync sample at or before `time`, like
+    /// [Mp4Reader::seek_to_time].
+    ///
+    /// For a reader created by [Mp4Reader::read_header_with_mfra], the
+    /// `moof` box holding that sample is located through the `mfra` box and
+    /// loaded in place of the previously loaded fragment, and the returned
+    /// sample id is relative to that fragment.
+    pub fn seek_fragment(&mut self, track_id: u32, time: Duration) -> Result<u32> {
+        if !self.fragments_from_mfra {
+            return self.seek_to_time(track_id, time);
+        }
+
+        let track = self
+            .tracks
+            .get(&track_id)
+            .ok_or(Error::TrakNotFound(track_id))?;
+        let target = (time.as_nanos() * track.timescale() as u128 / 1_000_000_000) as u64;
+        let tfra = self
+            .mfra
+            .as_ref()
+            .and_then(|mfra| mfra.tfra(track_id))
+            .ok_or(Error::BoxNotFound(BoxType::TfraBox))?;
+        let idx = tfra.entries.partition_point(|entry| entry.time <= target);
+        let entry = *tfra
+            .entries
+            .get(idx.saturating_sub(1))
+            .ok_or(Error::InvalidData("tfra box has no entries"))?;
+
+        self.reader.seek(SeekFrom::Start(entry.moof_offset))?;
+        let header = BoxHeader::read(&mut self.reader)?;
+        if header.name != BoxType::MoofBox {
+            return Err(Error::BoxNotFound(BoxType::MoofBox));
         }
+        let moof = MoofBox::read_box(&mut self.reader, header.size)?;
+
+        // Sample numbers count from the start of the traf; a traf only
+        // carries a single trun here.
+        let first_sample_id = 1 + moof
+            .trafs
+            .iter()
+            .take(entry.traf_number.saturating_sub(1) as usize)
+            .filter(|traf| traf.tfhd.track_id == track_id)
+            .filter_map(|traf| traf.trun.as_ref())
+            .map(|trun| trun.sample_count)
+            .sum::<u32>();
+
+        self.tracks = build_tracks(
+            &self.moov,
+            std::slice::from_ref(&moof),
+            &[entry.moof_offset],
+        )?;
+        self.moofs = vec![moof];
+        Ok(first_sample_id + entry.sample_number.saturating_sub(1))
+    }
+
+    /// Iterate over all samples of a track in decode order.
+    pub fn samples(&mut self, track_id: u32) -> Result<Mp4SampleIter<'_, R>> {
+        self.samples_from(track_id, 1)
+    }
+
+    /// Iterate over the samples of a track, starting at `sample_id`.
+    pub fn samples_from(&mut self, track_id: u32, sample_id: u32) -> Result<Mp4SampleIter<'_, R>> {
+        if let Some(track) = self.tracks.get(&track_id) {
+            Mp4SampleIter::new(&mut self.reader, track, sample_id)
+        } else {
+            Err(Error::TrakNotFound(track_id))
+        }
+    }
+}
+
+impl<R> Mp4Reader<R> {
+    /// Build a reader from the parsed top-level boxes of a file.
+    pub(crate) fn from_boxes(reader: R, size: u64, boxes: TopLevelBoxes) -> Result<Self> {
+        let TopLevelBoxes {
+            ftyp,
+            moov,
+            moofs,
+            moof_offsets,
+            emsgs,
+            uuids,
+            sidxs,
+            sidx_ends,
+            mfra,
+        } = boxes;
+
+        let ftyp = ftyp.ok_or(Error::BoxNotFound(BoxType::FtypBox))?;
+        let moov = moov.ok_or(Error::BoxNotFound(BoxType::MoovBox))?;
+        let tracks = build_tracks(&moov, &moofs, &moof_offsets)?;
 
         Ok(Mp4Reader {
             reader,
-            ftyp: self.ftyp.clone(),
-            moov: self.moov.clone(),
+            ftyp,
+            moov,
             moofs,
             emsgs,
             uuids,
-            tracks,
+            sidxs,
+            mfra,
+            sidx_ends,
+            fragments_from_mfra: false,
             size,
+            tracks,
         })
     }
 
@@ -294,30 +470,135 @@ impl<R: Read + Seek> Mp4Reader<R> {
         }
     }
 
-    pub fn read_sample(&mut self, track_id: u32, sample_id: u32) -> Result<Option<Mp4Sample>> {
+    pub fn sample_offset(&mut self, track_id: u32, sample_id: u32) -> Result<u64> {
         if let Some(track) = self.tracks.get(&track_id) {
-            track.read_sample(&mut self.reader, sample_id)
+            track.sample_offset(sample_id)
         } else {
             Err(Error::TrakNotFound(track_id))
         }
     }
 
-    pub fn sample_offset(&mut self, track_id: u32, sample_id: u32) -> Result<u64> {
+    /// Return the id of the nearest sync sample at or before `time`. See
+    /// [Mp4Track::seek_to_time].
+    pub fn seek_to_time(&self, track_id: u32, time: Duration) -> Result<u32> {
         if let Some(track) = self.tracks.get(&track_id) {
-            track.sample_offset(sample_id)
+            track.seek_to_time(time)
         } else {
             Err(Error::TrakNotFound(track_id))
         }
     }
-}
 
-impl<R> Mp4Reader<R> {
+    /// The media subsegments indexed by the `sidx` boxes of the file, in file
+    /// order. References to other `sidx` boxes are followed through the
+    /// indexes they point at rather than listed.
+    pub fn subsegments(&self) -> Vec<Subsegment> {
+        let mut subsegments = Vec::new();
+        for (sidx, sidx_end) in self.sidxs.iter().zip(self.sidx_ends.iter()) {
+            let mut offset = sidx_end + sidx.first_offset;
+            let mut start_time = sidx.earliest_presentation_time;
+            for reference in sidx.references.iter() {
+                if !reference.reference_type {
+                    subsegments.push(Subsegment {
+                        reference_id: sidx.reference_id,
+                        timescale: sidx.timescale,
+                        start_time,
+                        duration: reference.subsegment_duration,
+                        offset,
+                        size: reference.referenced_size,
+                        starts_with_sap: reference.starts_with_sap,
+                        sap_type: reference.sap_type,
+                        sap_delta_time: reference.sap_delta_time,
+                    });
+                }
+                offset += reference.referenced_size as u64;
+                start_time += reference.subsegment_duration as u64;
+            }
+        }
+        subsegments.sort_by_key(|subsegment| subsegment.offset);
+        subsegments
+    }
+
     pub fn metadata(&self) -> impl Metadata<'_> {
         self.moov.udta.as_ref().and_then(|udta| {
             udta.meta.as_ref().and_then(|meta| match meta {
-                MetaBox::Mdir { ilst } => ilst.as_ref(),
+                MetaBox::Mdir { ilst, .. } => ilst.as_ref(),
                 _ => None,
             })
         })
     }
 }
+
+/// Read the `mfra` box that the `mfro` box at the end of the file points to,
+/// if there is one.
+fn read_mfra<R: Read + Seek>(reader: &mut R, start: u64, size: u64) -> Result<Option<MfraBox>> {
+    let mfro_size = MfroBox::default().box_size();
+    if size < start + mfro_size {
+        return Ok(None);
+    }
+    reader.seek(SeekFrom::Start(size - mfro_size))?;
+    let header = BoxHeader::read(reader)?;
+    if header.name != BoxType::MfroBox || header.size != mfro_size {
+        return Ok(None);
+    }
+    let mfro = MfroBox::read_box(reader, header.size)?;
+    if (mfro.size as u64) < mfro_size || size < start + mfro.size as u64 {
+        return Err(Error::InvalidData("mfro box points outside of the file"));
+    }
+
+    reader.seek(SeekFrom::Start(size - mfro.size as u64))?;
+    let header = BoxHeader::read(reader)?;
+    if header.name != BoxType::MfraBox {
+        return Err(Error::BoxNotFound(BoxType::MfraBox));
+    }
+    Ok(Some(MfraBox::read_box(reader, header.size)?))
+}
+
+/// Build the tracks described by `moov`, adding the track fragments of
+/// `moofs` (found at `moof_offsets`) to them.
+pub(crate) fn build_tracks(
+    moov: &MoovBox,
+    moofs: &[MoofBox],
+    moof_offsets: &[u64],
+) -> Result<HashMap<u32, Mp4Track>> {
+    if moov.traks.iter().any(|trak| trak.tkhd.track_id == 0) {
+        return Err(Error::InvalidData("illegal track id 0"));
+    }
+    let mut tracks: HashMap<u32, Mp4Track> = moov
+        .traks
+        .iter()
+        .map(|trak| {
+            (
+                trak.tkhd.track_id,
+                Mp4Track::from(trak, moov.mvhd.timescale),
+            )
+        })
+        .collect();
+
+    // Grab the mvex box if it exists.
+    let mvex = moov.mvex.as_ref();
+
+    for (moof, moof_offset) in moofs.iter().zip(moof_offsets) {
+        for traf in moof.trafs.iter() {
+            let track_id = traf.tfhd.track_id;
+
+            // Get the sample defaults for the indicated track.
+            // These are buried in the optional mvex box, in a trex box per track.
+            let trex =
+                mvex.and_then(|mvex| mvex.trexs.iter().find(|trex| trex.track_id == track_id));
+
+            if let Some(track) = tracks.get_mut(&track_id) {
+                if let Some(trex) = trex {
+                    track.default_sample_duration = trex.default_sample_duration;
+                    track.default_sample_size = trex.default_sample_size;
+                    track.default_sample_flags = trex.default_sample_flags;
+                    track.default_sample_description_index = trex.default_sample_description_index;
+                }
+                track.moof_offsets.push(*moof_offset);
+                track.trafs.push(traf.clone())
+            } else {
+                return Err(Error::TrakNotFound(track_id));
+            }
+        }
+    }
+    Ok(tracks)
+}
@@ -1,17 +1,28 @@
 use byteorder::{BigEndian, ReadBytesExt};
 use bytes::BytesMut;
+use serde::Serialize;
 use std::cmp;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::OnceLock;
 use std::time::Duration;
 
+use crate::mp4box::ac3::{ac3_sample_rate, Ac3Box, Dac3Box};
+use crate::mp4box::ac4::{Ac4Box, Dac4Box};
+use crate::mp4box::alac::{AlacBox, AlacSpecificBox};
+use crate::mp4box::av01::{Av01Box, Av1CBox};
+use crate::mp4box::ec3::{Dec3Box, Ec3Box};
+use crate::mp4box::flac::{DflaBox, FlacBox};
+use crate::mp4box::hev1::{HEVC_NAL_PPS, HEVC_NAL_SPS, HEVC_NAL_VPS};
+use crate::mp4box::opus::{DopsBox, OpusBox};
+use crate::mp4box::pcm::{IpcmBox, LpcmBox, TwosBox};
 use crate::mp4box::traf::TrafBox;
 use crate::mp4box::trak::TrakBox;
-use crate::mp4box::trun::TrunBox;
 use crate::mp4box::{
     avc1::Avc1Box, co64::Co64Box, ctts::CttsBox, ctts::CttsEntry, hev1::Hev1Box, mp4a::Mp4aBox,
-    smhd::SmhdBox, stco::StcoBox, stsc::StscEntry, stss::StssBox, stts::SttsEntry, tx3g::Tx3gBox,
-    vmhd::VmhdBox, vp09::Vp09Box,
+    mp4v::Mp4vBox, smhd::SmhdBox, stco::StcoBox, stsc::StscEntry, stss::StssBox, stts::SttsEntry,
+    tx3g::Tx3gBox, vmhd::VmhdBox, vp09::Vp09Box,
 };
 use crate::*;
 
@@ -21,6 +32,18 @@ pub struct TrackConfig {
     pub timescale: u32,
     pub language: String,
     pub media_conf: MediaConfig,
+    /// References from this track to other tracks, written to its `tref`
+    /// box.
+    pub references: Vec<TrackReference>,
+}
+
+impl TrackConfig {
+    /// Sets the references from this track to other tracks, for example
+    /// `TrackConfig::from(aac_conf).with_references(references)`.
+    pub fn with_references(mut self, references: Vec<TrackReference>) -> Self {
+        self.references = references;
+        self
+    }
 }
 
 impl From<MediaConfig> for TrackConfig {
@@ -29,8 +52,18 @@ impl From<MediaConfig> for TrackConfig {
             MediaConfig::AvcConfig(avc_conf) => Self::from(avc_conf),
             MediaConfig::HevcConfig(hevc_conf) => Self::from(hevc_conf),
             MediaConfig::AacConfig(aac_conf) => Self::from(aac_conf),
+            MediaConfig::Mp3Config(mp3_conf) => Self::from(mp3_conf),
+            MediaConfig::OpusConfig(opus_conf) => Self::from(opus_conf),
+            MediaConfig::Ac3Config(ac3_conf) => Self::from(ac3_conf),
+            MediaConfig::Ec3Config(ec3_conf) => Self::from(ec3_conf),
+            MediaConfig::Ac4Config(ac4_conf) => Self::from(ac4_conf),
+            MediaConfig::FlacConfig(flac_conf) => Self::from(flac_conf),
+            MediaConfig::AlacConfig(alac_conf) => Self::from(alac_conf),
+            MediaConfig::PcmConfig(pcm_conf) => Self::from(pcm_conf),
             MediaConfig::TtxtConfig(ttxt_conf) => Self::from(ttxt_conf),
             MediaConfig::Vp9Config(vp9_config) => Self::from(vp9_config),
+            MediaConfig::Av1Config(av1_config) => Self::from(av1_config),
+            MediaConfig::Mpeg4VisualConfig(mp4v_config) => Self::from(mp4v_config),
             MediaConfig::WvttConfig(wvtt_conf) => Self::from(wvtt_conf),
         }
     }
@@ -43,6 +76,7 @@ impl From<AvcConfig> for TrackConfig {
             timescale: 1000,               // XXX
             language: String::from("und"), // XXX
             media_conf: MediaConfig::AvcConfig(avc_conf),
+            references: Vec::new(),
         }
     }
 }
@@ -54,6 +88,7 @@ impl From<HevcConfig> for TrackConfig {
             timescale: 1000,               // XXX
             language: String::from("und"), // XXX
             media_conf: MediaConfig::HevcConfig(hevc_conf),
+            references: Vec::new(),
         }
     }
 }
@@ -65,6 +100,104 @@ impl From<AacConfig> for TrackConfig {
             timescale: 1000,               // XXX
             language: String::from("und"), // XXX
             media_conf: MediaConfig::AacConfig(aac_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<Mp3Config> for TrackConfig {
+    fn from(mp3_conf: Mp3Config) -> Self {
+        Self {
+            track_type: TrackType::Audio,
+            timescale: mp3_conf.sample_rate,
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::Mp3Config(mp3_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<OpusConfig> for TrackConfig {
+    fn from(opus_conf: OpusConfig) -> Self {
+        Self {
+            track_type: TrackType::Audio,
+            timescale: 48000,
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::OpusConfig(opus_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<Ac3Config> for TrackConfig {
+    fn from(ac3_conf: Ac3Config) -> Self {
+        Self {
+            track_type: TrackType::Audio,
+            timescale: ac3_conf.sample_rate,
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::Ac3Config(ac3_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<Ec3Config> for TrackConfig {
+    fn from(ec3_conf: Ec3Config) -> Self {
+        let fscod = ec3_conf.substreams.first().map_or(0, |s| s.fscod);
+        Self {
+            track_type: TrackType::Audio,
+            timescale: ac3_sample_rate(fscod),
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::Ec3Config(ec3_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<Ac4Config> for TrackConfig {
+    fn from(ac4_conf: Ac4Config) -> Self {
+        Self {
+            track_type: TrackType::Audio,
+            timescale: 48000,
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::Ac4Config(ac4_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<FlacConfig> for TrackConfig {
+    fn from(flac_conf: FlacConfig) -> Self {
+        Self {
+            track_type: TrackType::Audio,
+            timescale: flac_conf.stream_info.sample_rate,
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::FlacConfig(flac_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<AlacConfig> for TrackConfig {
+    fn from(alac_conf: AlacConfig) -> Self {
+        Self {
+            track_type: TrackType::Audio,
+            timescale: alac_conf.sample_rate,
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::AlacConfig(alac_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<PcmConfig> for TrackConfig {
+    fn from(pcm_conf: PcmConfig) -> Self {
+        Self {
+            track_type: TrackType::Audio,
+            timescale: pcm_conf.sample_rate,
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::PcmConfig(pcm_conf),
+            references: Vec::new(),
         }
     }
 }
@@ -76,6 +209,7 @@ impl From<TtxtConfig> for TrackConfig {
             timescale: 1000,               // XXX
             language: String::from("und"), // XXX
             media_conf: MediaConfig::TtxtConfig(txtt_conf),
+            references: Vec::new(),
         }
     }
 }
@@ -87,6 +221,31 @@ impl From<Vp9Config> for TrackConfig {
             timescale: 1000,               // XXX
             language: String::from("und"), // XXX
             media_conf: MediaConfig::Vp9Config(vp9_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<Av1Config> for TrackConfig {
+    fn from(av1_conf: Av1Config) -> Self {
+        Self {
+            track_type: TrackType::Video,
+            timescale: 1000,               // XXX
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::Av1Config(av1_conf),
+            references: Vec::new(),
+        }
+    }
+}
+
+impl From<Mpeg4VisualConfig> for TrackConfig {
+    fn from(mp4v_conf: Mpeg4VisualConfig) -> Self {
+        Self {
+            track_type: TrackType::Video,
+            timescale: 1000,               // XXX
+            language: String::from("und"), // XXX
+            media_conf: MediaConfig::Mpeg4VisualConfig(mp4v_conf),
+            references: Vec::new(),
         }
     }
 }
@@ -98,6 +257,7 @@ impl From<WvttConfig> for TrackConfig {
             timescale: 1000,               // XXX
             language: String::from("und"), // XXX
             media_conf: MediaConfig::WvttConfig(wvtt_conf),
+            references: Vec::new(),
         }
     }
 }
@@ -111,17 +271,26 @@ pub struct Mp4Track {
     // Fragmented Tracks Defaults.
     pub default_sample_duration: u32,
     pub default_sample_size: u32,
+    pub default_sample_flags: u32,
+    pub default_sample_description_index: u32,
+
+    movie_timescale: u32,
+    sample_table: OnceLock<SampleTable>,
 }
 
 impl Mp4Track {
-    pub(crate) fn from(trak: &TrakBox) -> Self {
+    pub(crate) fn from(trak: &TrakBox, movie_timescale: u32) -> Self {
         let trak = trak.clone();
         Self {
             trak,
+            movie_timescale,
             trafs: Vec::new(),
             moof_offsets: Vec::new(),
             default_sample_duration: 0,
             default_sample_size: 0,
+            default_sample_flags: 0,
+            default_sample_description_index: 1,
+            sample_table: OnceLock::new(),
         }
     }
 
@@ -134,31 +303,51 @@ impl Mp4Track {
     }
 
     pub fn media_type(&self) -> Result<MediaType> {
-        if self.trak.mdia.minf.stbl.stsd.avc1.is_some() {
+        if self.trak.mdia.minf.stbl.stsd.avc1().is_some() {
             Ok(MediaType::H264)
-        } else if self.trak.mdia.minf.stbl.stsd.hev1.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.hev1().is_some() {
             Ok(MediaType::H265)
-        } else if self.trak.mdia.minf.stbl.stsd.vp09.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.vp09().is_some() {
             Ok(MediaType::VP9)
-        } else if self.trak.mdia.minf.stbl.stsd.mp4a.is_some() {
-            Ok(MediaType::AAC)
-        } else if self.trak.mdia.minf.stbl.stsd.tx3g.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.av01().is_some() {
+            Ok(MediaType::AV1)
+        } else if self.trak.mdia.minf.stbl.stsd.mp4v().is_some() {
+            Ok(MediaType::MP4V)
+        } else if self.trak.mdia.minf.stbl.stsd.mp4a().is_some() {
+            Ok(self.mp4a_media_type())
+        } else if self.trak.mdia.minf.stbl.stsd.opus().is_some() {
+            Ok(MediaType::OPUS)
+        } else if self.trak.mdia.minf.stbl.stsd.ac3().is_some() {
+            Ok(MediaType::AC3)
+        } else if self.trak.mdia.minf.stbl.stsd.ec3().is_some() {
+            Ok(MediaType::EAC3)
+        } else if self.trak.mdia.minf.stbl.stsd.ac4().is_some() {
+            Ok(MediaType::AC4)
+        } else if self.trak.mdia.minf.stbl.stsd.flac().is_some() {
+            Ok(MediaType::FLAC)
+        } else if self.trak.mdia.minf.stbl.stsd.alac().is_some() {
+            Ok(MediaType::ALAC)
+        } else if self.pcm_config().is_ok() {
+            Ok(MediaType::PCM)
+        } else if self.trak.mdia.minf.stbl.stsd.tx3g().is_some() {
             Ok(MediaType::TTXT)
-        } else if self.trak.mdia.minf.stbl.stsd.wvtt.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.wvtt().is_some() {
             Ok(MediaType::WVTT)
-        } else if let Some(ref enca) = self.trak.mdia.minf.stbl.stsd.enca {
+        } else if let Some(enca) = self.trak.mdia.minf.stbl.stsd.enca() {
             if enca.mp4a.is_some() {
-                Ok(MediaType::AAC)
+                Ok(self.mp4a_media_type())
             } else {
                 Err(Error::InvalidData("unsupported media type"))
             }
-        } else if let Some(ref encv) = self.trak.mdia.minf.stbl.stsd.encv {
+        } else if let Some(encv) = self.trak.mdia.minf.stbl.stsd.encv() {
             if encv.avc1.is_some() {
                 Ok(MediaType::H264)
             } else if encv.hev1.is_some() {
                 Ok(MediaType::H265)
             } else if encv.vp09.is_some() {
                 Ok(MediaType::VP9)
+            } else if encv.av01.is_some() {
+                Ok(MediaType::AV1)
             } else {
                 Err(Error::InvalidData("unsupported media type"))
             }
@@ -167,32 +356,67 @@ impl Mp4Track {
         }
     }
 
+    /// The sample entry a sample description index refers to. See
+    /// [Mp4Sample::sample_description_index].
+    pub fn sample_description(&self, sample_description_index: u32) -> Option<&StsdEntry> {
+        self.trak
+            .mdia
+            .minf
+            .stbl
+            .stsd
+            .entry(sample_description_index)
+    }
+
     pub fn box_type(&self) -> Result<FourCC> {
-        if self.trak.mdia.minf.stbl.stsd.avc1.is_some() {
+        if self.trak.mdia.minf.stbl.stsd.avc1().is_some() {
             Ok(FourCC::from(BoxType::Avc1Box))
-        } else if self.trak.mdia.minf.stbl.stsd.hev1.is_some() {
-            Ok(FourCC::from(BoxType::Hev1Box))
-        } else if self.trak.mdia.minf.stbl.stsd.vp09.is_some() {
+        } else if let Some(hev1) = self.trak.mdia.minf.stbl.stsd.hev1() {
+            Ok(FourCC::from(hev1.box_type()))
+        } else if self.trak.mdia.minf.stbl.stsd.vp09().is_some() {
             Ok(FourCC::from(BoxType::Vp09Box))
-        } else if self.trak.mdia.minf.stbl.stsd.mp4a.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.av01().is_some() {
+            Ok(FourCC::from(BoxType::Av01Box))
+        } else if self.trak.mdia.minf.stbl.stsd.mp4v().is_some() {
+            Ok(FourCC::from(BoxType::Mp4vBox))
+        } else if self.trak.mdia.minf.stbl.stsd.mp4a().is_some() {
             Ok(FourCC::from(BoxType::Mp4aBox))
-        } else if self.trak.mdia.minf.stbl.stsd.tx3g.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.opus().is_some() {
+            Ok(FourCC::from(BoxType::OpusBox))
+        } else if self.trak.mdia.minf.stbl.stsd.ac3().is_some() {
+            Ok(FourCC::from(BoxType::Ac3Box))
+        } else if self.trak.mdia.minf.stbl.stsd.ec3().is_some() {
+            Ok(FourCC::from(BoxType::Ec3Box))
+        } else if self.trak.mdia.minf.stbl.stsd.ac4().is_some() {
+            Ok(FourCC::from(BoxType::Ac4Box))
+        } else if self.trak.mdia.minf.stbl.stsd.flac().is_some() {
+            Ok(FourCC::from(BoxType::FlacBox))
+        } else if self.trak.mdia.minf.stbl.stsd.alac().is_some() {
+            Ok(FourCC::from(BoxType::AlacBox))
+        } else if let Some(ipcm) = self.trak.mdia.minf.stbl.stsd.ipcm() {
+            Ok(FourCC::from(ipcm.box_type()))
+        } else if self.trak.mdia.minf.stbl.stsd.lpcm().is_some() {
+            Ok(FourCC::from(BoxType::LpcmBox))
+        } else if let Some(twos) = self.trak.mdia.minf.stbl.stsd.twos() {
+            Ok(FourCC::from(twos.box_type()))
+        } else if self.trak.mdia.minf.stbl.stsd.tx3g().is_some() {
             Ok(FourCC::from(BoxType::Tx3gBox))
-        } else if self.trak.mdia.minf.stbl.stsd.wvtt.is_some() {
+        } else if self.trak.mdia.minf.stbl.stsd.wvtt().is_some() {
             Ok(FourCC::from(BoxType::WvttBox))
-        } else if let Some(ref enca) = self.trak.mdia.minf.stbl.stsd.enca {
+        } else if let Some(enca) = self.trak.mdia.minf.stbl.stsd.enca() {
             if enca.mp4a.is_some() {
                 Ok(FourCC::from(BoxType::Mp4aBox))
             } else {
                 Err(Error::InvalidData("unsupported sample entry box"))
             }
-        } else if let Some(ref encv) = self.trak.mdia.minf.stbl.stsd.encv {
+        } else if let Some(encv) = self.trak.mdia.minf.stbl.stsd.encv() {
             if encv.avc1.is_some() {
                 Ok(FourCC::from(BoxType::Avc1Box))
-            } else if encv.hev1.is_some() {
-                Ok(FourCC::from(BoxType::Hev1Box))
+            } else if let Some(ref hev1) = encv.hev1 {
+                Ok(FourCC::from(hev1.box_type()))
             } else if encv.vp09.is_some() {
                 Ok(FourCC::from(BoxType::Vp09Box))
+            } else if encv.av01.is_some() {
+                Ok(FourCC::from(BoxType::Av01Box))
             } else {
                 Err(Error::InvalidData("unsupported sample entry box"))
             }
@@ -202,7 +426,7 @@ impl Mp4Track {
     }
 
     pub fn width(&self) -> u16 {
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
             avc1.width
         } else {
             self.trak.tkhd.width.value()
@@ -210,7 +434,7 @@ impl Mp4Track {
     }
 
     pub fn height(&self) -> u16 {
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
             avc1.height
         } else {
             self.trak.tkhd.height.value()
@@ -226,43 +450,203 @@ impl Mp4Track {
         }
     }
 
-    pub fn sample_freq_index(&self) -> Result<SampleFreqIndex> {
-        let mut mp4a_opt: Option<&Mp4aBox> = None;
-        if let Some(ref mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
-            mp4a_opt = Some(mp4a);
-        } else if let Some(ref enca) = self.trak.mdia.minf.stbl.stsd.enca {
-            if let Some(ref mp4a) = enca.mp4a {
-                mp4a_opt = Some(mp4a);
-            }
+    /// The `dOps` configuration of an Opus track, giving its channel
+    /// mapping and pre-skip.
+    pub fn opus_config(&self) -> Result<&DopsBox> {
+        match self.trak.mdia.minf.stbl.stsd.opus() {
+            Some(opus) => Ok(&opus.dops),
+            None => Err(Error::BoxInStblNotFound(self.track_id(), BoxType::OpusBox)),
         }
-        if let Some(mp4a) = mp4a_opt {
-            if let Some(ref esds) = mp4a.esds {
-                SampleFreqIndex::try_from(esds.es_desc.dec_config.dec_specific.freq_index)
-            } else {
-                Err(Error::BoxInStblNotFound(self.track_id(), BoxType::EsdsBox))
-            }
+    }
+
+    /// The `dac3` configuration of an AC-3 track.
+    pub fn ac3_config(&self) -> Result<&Dac3Box> {
+        match self.trak.mdia.minf.stbl.stsd.ac3() {
+            Some(ac3) => Ok(&ac3.dac3),
+            None => Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Ac3Box)),
+        }
+    }
+
+    /// The `dec3` configuration of an E-AC-3 track, describing its
+    /// substreams.
+    pub fn eac3_config(&self) -> Result<&Dec3Box> {
+        match self.trak.mdia.minf.stbl.stsd.ec3() {
+            Some(ec3) => Ok(&ec3.dec3),
+            None => Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Ec3Box)),
+        }
+    }
+
+    /// The `dac4` configuration of an AC-4 track.
+    pub fn ac4_config(&self) -> Result<&Dac4Box> {
+        match self.trak.mdia.minf.stbl.stsd.ac4() {
+            Some(ac4) => Ok(&ac4.dac4),
+            None => Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Ac4Box)),
+        }
+    }
+
+    /// Channel layout of an AC-3 or E-AC-3 track.
+    pub fn dolby_channel_layout(&self) -> Result<DolbyChannelLayout> {
+        if let Ok(dac3) = self.ac3_config() {
+            dac3.channel_layout()
+        } else if let Ok(dec3) = self.eac3_config() {
+            dec3.channel_layout()
+        } else {
+            Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Ac3Box))
+        }
+    }
+
+    /// The `dfLa` configuration of a FLAC track, holding its `STREAMINFO`.
+    pub fn flac_config(&self) -> Result<&DflaBox> {
+        match self.trak.mdia.minf.stbl.stsd.flac() {
+            Some(flac) => Ok(&flac.dfla),
+            None => Err(Error::BoxInStblNotFound(self.track_id(), BoxType::FlacBox)),
+        }
+    }
+
+    /// The decoder configuration of an ALAC track.
+    pub fn alac_config(&self) -> Result<&AlacSpecificBox> {
+        match self.trak.mdia.minf.stbl.stsd.alac() {
+            Some(alac) => Ok(&alac.config),
+            None => Err(Error::BoxInStblNotFound(self.track_id(), BoxType::AlacBox)),
+        }
+    }
+
+    /// Sample format, bit depth and layout of a PCM track, from its `ipcm`,
+    /// `fpcm`, `lpcm`, `sowt` or `twos` sample entry.
+    pub fn pcm_config(&self) -> Result<PcmConfig> {
+        let stsd = &self.trak.mdia.minf.stbl.stsd;
+        if let Some(ipcm) = stsd.ipcm() {
+            Ok(ipcm.pcm_config())
+        } else if let Some(lpcm) = stsd.lpcm() {
+            Ok(lpcm.pcm_config())
+        } else if let Some(twos) = stsd.twos() {
+            Ok(twos.pcm_config())
+        } else {
+            Err(Error::InvalidData("track has no PCM sample entry"))
+        }
+    }
+
+    /// Sample rate of a lossless (FLAC, ALAC or PCM), AAC or MP3 track, which
+    /// may exceed what the sample entry can hold. For HE-AAC this is the
+    /// output rate.
+    pub fn sample_rate(&self) -> Result<u32> {
+        if let Ok(dfla) = self.flac_config() {
+            Ok(dfla.stream_info.sample_rate)
+        } else if let Ok(alac) = self.alac_config() {
+            Ok(alac.sample_rate)
+        } else if let Ok(pcm) = self.pcm_config() {
+            Ok(pcm.sample_rate)
+        } else if let Ok(asc) = self.audio_specific_config() {
+            Ok(asc.extension_sampling_frequency)
+        } else if let Some(mp4a) = self.mp4a() {
+            Ok(mp4a.samplerate.value() as u32)
+        } else {
+            Err(Error::InvalidData("track has no sample rate"))
+        }
+    }
+
+    /// Bits per sample of a lossless (FLAC, ALAC or PCM) track.
+    pub fn bit_depth(&self) -> Result<u8> {
+        if let Ok(dfla) = self.flac_config() {
+            Ok(dfla.stream_info.bits_per_sample)
+        } else if let Ok(alac) = self.alac_config() {
+            Ok(alac.bit_depth)
+        } else if let Ok(pcm) = self.pcm_config() {
+            Ok(pcm.bit_depth)
+        } else {
+            Err(Error::InvalidData("track has no bit depth"))
+        }
+    }
+
+    /// Channel count of a lossless (FLAC, ALAC or PCM), AAC or MP3 track,
+    /// including the channels of an AAC program config element or added by
+    /// PS.
+    pub fn channel_count(&self) -> Result<u16> {
+        if let Ok(dfla) = self.flac_config() {
+            Ok(dfla.stream_info.channel_count as u16)
+        } else if let Ok(alac) = self.alac_config() {
+            Ok(alac.num_channels as u16)
+        } else if let Ok(pcm) = self.pcm_config() {
+            Ok(pcm.channel_count)
+        } else if let Ok(asc) = self.audio_specific_config() {
+            Ok(asc.channel_count())
+        } else if let Some(mp4a) = self.mp4a() {
+            Ok(mp4a.channelcount)
         } else {
-            Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Mp4aBox))
+            Err(Error::InvalidData("track has no channel count"))
+        }
+    }
+
+    /// Sampling frequency index of an AAC track. For HE-AAC this is the
+    /// output sampling rate, twice the rate of the core coder.
+    ///
+    /// A frequency stored explicitly gives the index of the same frequency.
+    /// Other frequencies have no index; use [Mp4Track::sample_rate] for them.
+    pub fn sample_freq_index(&self) -> Result<SampleFreqIndex> {
+        let asc = self.audio_specific_config()?;
+        if asc.extension_freq_index != 15 {
+            return SampleFreqIndex::try_from(asc.extension_freq_index);
         }
+        (0..=0xc)
+            .filter_map(|index| SampleFreqIndex::try_from(index).ok())
+            .find(|index| index.freq() == asc.extension_sampling_frequency)
+            .ok_or(Error::InvalidData("sampling frequency has no index"))
     }
 
+    /// Channel configuration of an AAC track. When the channels are given by
+    /// a program config element or an ALS config, this is the standard
+    /// configuration with as many channels, if there is one.
     pub fn channel_config(&self) -> Result<ChannelConfig> {
-        let mut mp4a_opt: Option<&Mp4aBox> = None;
-        if let Some(ref mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
-            mp4a_opt = Some(mp4a);
-        } else if let Some(ref enca) = self.trak.mdia.minf.stbl.stsd.enca {
-            if let Some(ref mp4a) = enca.mp4a {
-                mp4a_opt = Some(mp4a);
-            }
+        let asc = self.audio_specific_config()?;
+        match (asc.chan_conf, asc.channel_count()) {
+            (0, count @ 1..=6) => ChannelConfig::try_from(count as u8),
+            (0, 8) => Ok(ChannelConfig::SevenOne),
+            (chan_conf, _) => ChannelConfig::try_from(chan_conf),
         }
-        if let Some(mp4a) = mp4a_opt {
-            if let Some(ref esds) = mp4a.esds {
-                ChannelConfig::try_from(esds.es_desc.dec_config.dec_specific.chan_conf)
-            } else {
-                Err(Error::BoxInStblNotFound(self.track_id(), BoxType::EsdsBox))
-            }
+    }
+
+    /// AudioSpecificConfig of an AAC track.
+    pub fn audio_specific_config(&self) -> Result<&DecoderSpecificDescriptor> {
+        let dec_config = self.decoder_config()?;
+        if dec_config.object_type()?.is_aac() {
+            Ok(&dec_config.dec_specific)
+        } else {
+            Err(Error::InvalidData("not an aac track"))
+        }
+    }
+
+    /// Decoder config descriptor of an `mp4a` or `mp4v` track.
+    pub fn decoder_config(&self) -> Result<&DecoderConfigDescriptor> {
+        let stsd = &self.trak.mdia.minf.stbl.stsd;
+        let esds = if let Some(mp4a) = self.mp4a() {
+            mp4a.esds.as_ref()
+        } else if let Some(mp4v) = stsd.mp4v() {
+            mp4v.esds.as_ref()
         } else {
-            Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Mp4aBox))
+            return Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Mp4aBox));
+        };
+        esds.map(|esds| &esds.es_desc.dec_config)
+            .ok_or(Error::BoxInStblNotFound(self.track_id(), BoxType::EsdsBox))
+    }
+
+    /// Codec of an `mp4a` or `mp4v` track, such as AAC, MP3 or MPEG-4
+    /// Visual.
+    pub fn object_type_indication(&self) -> Result<ObjectTypeIndication> {
+        self.decoder_config()?.object_type()
+    }
+
+    /// The `mp4a` sample entry, possibly encrypted.
+    fn mp4a(&self) -> Option<&Mp4aBox> {
+        let stsd = &self.trak.mdia.minf.stbl.stsd;
+        stsd.mp4a()
+            .or_else(|| stsd.enca().and_then(|enca| enca.mp4a.as_ref()))
+    }
+
+    /// Media type of an `mp4a` track, AAC unless signalled as MP3.
+    fn mp4a_media_type(&self) -> MediaType {
+        match self.object_type_indication() {
+            Ok(object_type) if object_type.is_mpeg_audio() => MediaType::MP3,
+            _ => MediaType::AAC,
         }
     }
 
@@ -280,11 +664,67 @@ impl Mp4Track {
         )
     }
 
+    /// Ids of the tracks this track references with `reference_type`.
+    pub fn references(&self, reference_type: TrackReferenceType) -> &[u32] {
+        match self.trak.tref {
+            Some(ref tref) => tref.track_ids(reference_type),
+            None => &[],
+        }
+    }
+
+    /// The tracks of `tracks` this track references with `reference_type`, in
+    /// reference order. References to missing tracks are skipped.
+    pub fn referenced_tracks<'a>(
+        &self,
+        reference_type: TrackReferenceType,
+        tracks: &'a HashMap<u32, Mp4Track>,
+    ) -> Vec<&'a Mp4Track> {
+        self.references(reference_type)
+            .iter()
+            .filter_map(|track_id| tracks.get(track_id))
+            .collect()
+    }
+
+    /// Edits of the track's edit list, empty if the track has none.
+    pub fn edit_list(&self) -> Vec<Edit> {
+        self.trak
+            .edts
+            .as_ref()
+            .and_then(|edts| edts.elst.as_ref())
+            .map(|elst| elst.entries.iter().map(Edit::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// Map a media time (such as a sample's `start_time + rendering_offset`)
+    /// onto the presentation timeline, applying the edit list. Both times are
+    /// in media timescale units.
+    ///
+    /// Returns `None` if the edit list does not present `media_time`, e.g.
+    /// encoder delay that is cut off at the start of the track. Without an
+    /// edit list the media time is returned unchanged.
+    pub fn presentation_time(&self, media_time: u64) -> Option<u64> {
+        let edits = self.edit_list();
+        if edits.is_empty() {
+            return Some(media_time);
+        }
+        edit::presentation_time(&edits, media_time, self.movie_timescale, self.timescale())
+    }
+
+    /// Duration of the track after applying the edit list.
+    pub fn presentation_duration(&self) -> Duration {
+        let edits = self.edit_list();
+        if edits.is_empty() || self.movie_timescale == 0 {
+            return self.duration();
+        }
+        let duration: u64 = edits.iter().map(|edit| edit.duration).sum();
+        Duration::from_micros(duration * 1_000_000 / self.movie_timescale as u64)
+    }
+
     pub fn bitrate(&self) -> u32 {
         let mut mp4a_opt: Option<&Mp4aBox> = None;
-        if let Some(ref mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
+        if let Some(mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a() {
             mp4a_opt = Some(mp4a);
-        } else if let Some(ref enca) = self.trak.mdia.minf.stbl.stsd.enca {
+        } else if let Some(enca) = self.trak.mdia.minf.stbl.stsd.enca() {
             if let Some(ref mp4a) = enca.mp4a {
                 mp4a_opt = Some(mp4a);
             }
@@ -296,6 +736,20 @@ impl Mp4Track {
                 0
             }
             // mp4a.esds.es_desc.dec_config.avg_bitrate
+        } else if let Some(bitrate) = self
+            .trak
+            .mdia
+            .minf
+            .stbl
+            .stsd
+            .mp4v()
+            .and_then(|mp4v| mp4v.esds.as_ref())
+            .map(|esds| esds.es_desc.dec_config.avg_bitrate)
+            .filter(|&bitrate| bitrate > 0)
+        {
+            bitrate
+        } else if let Some(bitrate) = self.dolby_bitrate() {
+            bitrate
         } else {
             let dur = self.duration();
             if dur.is_zero() {
@@ -307,6 +761,20 @@ impl Mp4Track {
         }
     }
 
+    /// Bit rate signalled by a Dolby sample entry, if any.
+    fn dolby_bitrate(&self) -> Option<u32> {
+        if let Ok(dac3) = self.ac3_config() {
+            dac3.bitrate().ok()
+        } else if let Ok(dec3) = self.eac3_config() {
+            Some(dec3.data_rate as u32 * 1000)
+        } else {
+            self.ac4_config()
+                .ok()
+                .map(|dac4| dac4.bit_rate)
+                .filter(|&bit_rate| bit_rate > 0)
+        }
+    }
+
     pub fn sample_count(&self) -> u32 {
         if !self.trafs.is_empty() {
             let mut sample_count = 0u32;
@@ -325,9 +793,9 @@ impl Mp4Track {
 
     pub fn video_profile(&self) -> Result<AvcProfile> {
         let mut avc1_opt: Option<&Avc1Box> = None;
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
             avc1_opt = Some(avc1);
-        } else if let Some(ref encv) = self.trak.mdia.minf.stbl.stsd.encv {
+        } else if let Some(encv) = self.trak.mdia.minf.stbl.stsd.encv() {
             if let Some(ref avc1) = encv.avc1 {
                 avc1_opt = Some(avc1);
             }
@@ -342,11 +810,33 @@ impl Mp4Track {
         }
     }
 
+    /// The `av1C` configuration of an AV1 track, giving its level, tier,
+    /// bit depth and chroma subsampling.
+    pub fn av1_config(&self) -> Result<&Av1CBox> {
+        let mut av01_opt: Option<&Av01Box> = None;
+        if let Some(av01) = self.trak.mdia.minf.stbl.stsd.av01() {
+            av01_opt = Some(av01);
+        } else if let Some(encv) = self.trak.mdia.minf.stbl.stsd.encv() {
+            if let Some(ref av01) = encv.av01 {
+                av01_opt = Some(av01);
+            }
+        }
+        if let Some(av01) = av01_opt {
+            Ok(&av01.av1c)
+        } else {
+            Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Av01Box))
+        }
+    }
+
+    pub fn av1_profile(&self) -> Result<Av1Profile> {
+        Av1Profile::try_from(self.av1_config()?.seq_profile)
+    }
+
     pub fn sequence_parameter_set(&self) -> Result<&[u8]> {
         let mut avc1_opt: Option<&Avc1Box> = None;
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
             avc1_opt = Some(avc1);
-        } else if let Some(ref encv) = self.trak.mdia.minf.stbl.stsd.encv {
+        } else if let Some(encv) = self.trak.mdia.minf.stbl.stsd.encv() {
             if let Some(ref avc1) = encv.avc1 {
                 avc1_opt = Some(avc1);
             }
@@ -360,6 +850,8 @@ impl Mp4Track {
                     0,
                 )),
             }
+        } else if self.hev1().is_some() {
+            self.hevc_parameter_set(HEVC_NAL_SPS)
         } else {
             Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Avc1Box))
         }
@@ -367,9 +859,9 @@ impl Mp4Track {
 
     pub fn picture_parameter_set(&self) -> Result<&[u8]> {
         let mut avc1_opt: Option<&Avc1Box> = None;
-        if let Some(ref avc1) = self.trak.mdia.minf.stbl.stsd.avc1 {
+        if let Some(avc1) = self.trak.mdia.minf.stbl.stsd.avc1() {
             avc1_opt = Some(avc1);
-        } else if let Some(ref encv) = self.trak.mdia.minf.stbl.stsd.encv {
+        } else if let Some(encv) = self.trak.mdia.minf.stbl.stsd.encv() {
             if let Some(ref avc1) = encv.avc1 {
                 avc1_opt = Some(avc1);
             }
@@ -383,35 +875,72 @@ impl Mp4Track {
                     0,
                 )),
             }
+        } else if self.hev1().is_some() {
+            self.hevc_parameter_set(HEVC_NAL_PPS)
         } else {
             Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Avc1Box))
         }
     }
 
+    /// The video parameter set of an H.265 track.
+    pub fn video_parameter_set(&self) -> Result<&[u8]> {
+        self.hevc_parameter_set(HEVC_NAL_VPS)
+    }
+
+    fn hev1(&self) -> Option<&Hev1Box> {
+        if let Some(hev1) = self.trak.mdia.minf.stbl.stsd.hev1() {
+            Some(hev1)
+        } else if let Some(encv) = self.trak.mdia.minf.stbl.stsd.encv() {
+            encv.hev1.as_ref()
+        } else {
+            None
+        }
+    }
+
+    fn hevc_parameter_set(&self, nal_unit_type: u8) -> Result<&[u8]> {
+        match self.hev1() {
+            Some(hev1) => hev1
+                .hvcc
+                .nal_unit(nal_unit_type)
+                .ok_or(Error::EntryInStblNotFound(
+                    self.track_id(),
+                    BoxType::HvcCBox,
+                    0,
+                )),
+            None => Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Hev1Box)),
+        }
+    }
+
+    /// Audio object type of an `mp4a` track, reporting HE-AAC (SBR) and
+    /// HE-AACv2 (PS) rather than their AAC LC core, and layer 3 for MP3.
     pub fn audio_profile(&self) -> Result<AudioObjectType> {
-        let mut mp4a_opt: Option<&Mp4aBox> = None;
-        if let Some(ref mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
-            mp4a_opt = Some(mp4a);
-        } else if let Some(ref enca) = self.trak.mdia.minf.stbl.stsd.enca {
-            if let Some(ref mp4a) = enca.mp4a {
-                mp4a_opt = Some(mp4a);
+        match self.object_type_indication()? {
+            ObjectTypeIndication::Mpeg1Audio | ObjectTypeIndication::Mpeg2Audio => {
+                return Ok(AudioObjectType::MpegLayer3)
             }
-        }
-        if let Some(mp4a) = mp4a_opt {
-            if let Some(ref esds) = mp4a.esds {
-                AudioObjectType::try_from(esds.es_desc.dec_config.dec_specific.profile)
-            } else {
-                Err(Error::BoxInStblNotFound(self.track_id(), BoxType::EsdsBox))
+            ObjectTypeIndication::Mpeg2AacMain => return Ok(AudioObjectType::AacMain),
+            ObjectTypeIndication::Mpeg2AacLowComplexity => {
+                return Ok(AudioObjectType::AacLowComplexity)
             }
+            ObjectTypeIndication::Mpeg2AacScalableSampleRate => {
+                return Ok(AudioObjectType::AacScalableSampleRate)
+            }
+            _ => {}
+        }
+        let asc = self.audio_specific_config()?;
+        if asc.ps_present {
+            Ok(AudioObjectType::ParametricStereo)
+        } else if asc.sbr_present {
+            Ok(AudioObjectType::SpectralBandReplication)
         } else {
-            Err(Error::BoxInStblNotFound(self.track_id(), BoxType::Mp4aBox))
+            AudioObjectType::try_from(asc.profile)
         }
     }
 
     pub fn protection_scheme_info(&self) -> Option<&SinfBox> {
-        if let Some(ref enca) = self.trak.mdia.minf.stbl.stsd.enca {
+        if let Some(enca) = self.trak.mdia.minf.stbl.stsd.enca() {
             Some(&enca.sinf)
-        } else if let Some(ref encv) = self.trak.mdia.minf.stbl.stsd.encv {
+        } else if let Some(encv) = self.trak.mdia.minf.stbl.stsd.encv() {
             Some(&encv.sinf)
         } else {
             None
@@ -443,6 +972,22 @@ impl Mp4Track {
         Ok(sample_infos)
     }
 
+    /// Per-sample table of the track, built from the sample tables (or track
+    /// fragments) on first use.
+    ///
+    /// The table is only built when this is called; single sample reads
+    /// resolve the sample from the boxes without it. Once built, sample
+    /// lookups, [Mp4Track::seek_to_time] and sample iterators use the table
+    /// instead of walking the boxes. The reader builds new tracks, with empty
+    /// tables, whenever it loads other fragments.
+    pub fn sample_table(&self) -> Result<&SampleTable> {
+        if let Some(table) = self.sample_table.get() {
+            return Ok(table);
+        }
+        let table = SampleTable::build(self)?;
+        Ok(self.sample_table.get_or_init(|| table))
+    }
+
     fn stsc_index(&self, sample_id: u32) -> Result<usize> {
         if self.trak.mdia.minf.stbl.stsc.entries.is_empty() {
             return Err(Error::InvalidData("no stsc entries"));
@@ -528,22 +1073,100 @@ impl Mp4Track {
         None
     }
 
+    fn traf_sample_size(&self, traf_idx: usize, sample_idx: usize) -> u32 {
+        let traf = &self.trafs[traf_idx];
+        if let Some(size) = traf
+            .trun
+            .as_ref()
+            .and_then(|trun| trun.sample_sizes.get(sample_idx))
+        {
+            *size
+        } else {
+            traf.tfhd
+                .default_sample_size
+                .unwrap_or(self.default_sample_size)
+        }
+    }
+
+    fn traf_sample_duration(&self, traf_idx: usize, sample_idx: usize) -> u32 {
+        let traf = &self.trafs[traf_idx];
+        if let Some(duration) = traf
+            .trun
+            .as_ref()
+            .and_then(|trun| trun.sample_durations.get(sample_idx))
+        {
+            *duration
+        } else {
+            traf.tfhd
+                .default_sample_duration
+                .unwrap_or(self.default_sample_duration)
+        }
+    }
+
+    fn traf_sample_rendering_offset(&self, traf_idx: usize, sample_idx: usize) -> i32 {
+        self.trafs[traf_idx]
+            .trun
+            .as_ref()
+            .and_then(|trun| trun.sample_cts.get(sample_idx))
+            .map_or(0, |cts| *cts as i32)
+    }
+
+    fn traf_sample_is_sync(&self, traf_idx: usize, sample_idx: usize) -> bool {
+        let traf = &self.trafs[traf_idx];
+        let trun = traf.trun.as_ref();
+        let flags = if let Some(flags) = trun.and_then(|trun| trun.sample_flags.get(sample_idx)) {
+            *flags
+        } else if let Some(flags) = trun
+            .and_then(|trun| trun.first_sample_flags)
+            .filter(|_| sample_idx == 0)
+        {
+            flags
+        } else {
+            traf.tfhd
+                .default_sample_flags
+                .unwrap_or(self.default_sample_flags)
+        };
+        flags & SAMPLE_IS_NON_SYNC_SAMPLE == 0
+    }
+
+    fn traf_sample_description_index(&self, traf_idx: usize) -> u32 {
+        self.trafs[traf_idx]
+            .tfhd
+            .sample_description_index
+            .unwrap_or(self.default_sample_description_index)
+    }
+
+    /// Offset of the first sample of a traf.
+    fn traf_data_offset(&self, traf_idx: usize) -> Result<u64> {
+        let traf = &self.trafs[traf_idx];
+        let mut offset = traf
+            .tfhd
+            .base_data_offset
+            .unwrap_or(self.moof_offsets[traf_idx]);
+        if let Some(data_offset) = traf.trun.as_ref().and_then(|trun| trun.data_offset) {
+            offset = offset
+                .checked_add_signed(data_offset as i64)
+                .ok_or(Error::InvalidData(
+                    "attempt to calculate trun sample offset with overflow",
+                ))?;
+        }
+        Ok(offset)
+    }
+
+    /// Decode time of the first sample of a traf.
+    fn traf_base_time(&self, traf_idx: usize) -> u64 {
+        let traf = &self.trafs[traf_idx];
+        if let Some(tfdt) = &traf.tfdt {
+            tfdt.base_media_decode_time
+        } else {
+            find_smooth_decode_time(&traf.uuids).unwrap_or(0)
+        }
+    }
+
     fn sample_size(&self, sample_id: u32) -> Result<u32> {
         if !self.trafs.is_empty() {
             if let Some((traf_idx, sample_idx)) = self.find_traf_idx_and_sample_idx(sample_id) {
-                let mut default_sample_size = self.default_sample_size;
-                let traf = &self.trafs[traf_idx];
-                if let Some(size) = traf.tfhd.default_sample_size {
-                    default_sample_size = size;
-                }
-                if let Some(size) = traf
-                    .trun
-                    .as_ref()
-                    .and_then(|trun| trun.sample_sizes.get(sample_idx))
-                {
-                    default_sample_size = *size;
-                }
-                Ok(default_sample_size)
+                Ok(self.traf_sample_size(traf_idx, sample_idx))
             } else {
                 Err(Error::BoxInTrafNotFound(self.track_id(), BoxType::TrafBox))
             }
@@ -578,27 +1201,19 @@ impl Mp4Track {
     }
 
     pub fn sample_offset(&self, sample_id: u32) -> Result<u64> {
+        if let Some(table) = self.sample_table.get() {
+            return table.offset(sample_id).ok_or(Error::EntryInStblNotFound(
+                self.track_id(),
+                BoxType::StszBox,
+                sample_id,
+            ));
+        }
         if !self.trafs.is_empty() {
             if let Some((traf_idx, sample_idx)) = self.find_traf_idx_and_sample_idx(sample_id) {
-                let mut sample_offset = self.trafs[traf_idx]
-                    .tfhd
-                    .base_data_offset
-                    .unwrap_or(self.moof_offsets[traf_idx]);
-
-                if let Some(data_offset) = self.trafs[traf_idx]
-                    .trun
-                    .as_ref()
-                    .and_then(|trun| trun.data_offset)
-                {
-                    sample_offset = sample_offset.checked_add_signed(data_offset as i64).ok_or(
-                        Error::InvalidData("attempt to calculate trun sample offset with overflow"),
-                    )?;
-                }
-
-                let first_sample_in_trun = sample_id - sample_idx as u32;
-                for i in first_sample_in_trun..sample_id {
+                let mut sample_offset = self.traf_data_offset(traf_idx)?;
+                for i in 0..sample_idx {
                     sample_offset = sample_offset
-                        .checked_add(self.sample_size(i)? as u64)
+                        .checked_add(self.traf_sample_size(traf_idx, i) as u64)
                         .ok_or(Error::InvalidData(
                             "attempt to calculate trun entry sample offset with overflow",
                         ))?;
@@ -641,36 +1256,23 @@ impl Mp4Track {
 
     fn sample_time(&self, sample_id: u32) -> Result<(u64, u32)> {
         if !self.trafs.is_empty() {
-            let mut base_start_time = 0;
-            let mut default_sample_duration = self.default_sample_duration;
             if let Some((traf_idx, sample_idx)) = self.find_traf_idx_and_sample_idx(sample_id) {
-                let traf = &self.trafs[traf_idx];
-                if let Some(tfdt) = &traf.tfdt {
-                    base_start_time = tfdt.base_media_decode_time;
-                } else if let Some(decode_time) = find_smooth_decode_time(&traf.uuids) {
-                    base_start_time = decode_time;
-                }
-                if let Some(duration) = traf.tfhd.default_sample_duration {
-                    default_sample_duration = duration;
-                }
-                if let Some(trun) = &traf.trun {
-                    if TrunBox::FLAG_SAMPLE_DURATION & trun.flags != 0 {
-                        let mut start_offset = 0u64;
-                        for duration in &trun.sample_durations[..sample_idx] {
-                            start_offset = start_offset.checked_add(*duration as u64).ok_or(
-                                Error::InvalidData("attempt to sum sample durations with overflow"),
-                            )?;
-                        }
-                        let duration = trun.sample_durations[sample_idx];
-                        return Ok((base_start_time.wrapping_add(start_offset), duration));
-                    }
+                let mut start_offset = 0u64;
+                for i in 0..sample_idx {
+                    start_offset = start_offset
+                        .checked_add(self.traf_sample_duration(traf_idx, i) as u64)
+                        .ok_or(Error::InvalidData(
+                            "attempt to sum sample durations with overflow",
+                        ))?;
                 }
+                let duration = self.traf_sample_duration(traf_idx, sample_idx);
+                Ok((
+                    self.traf_base_time(traf_idx).wrapping_add(start_offset),
+                    duration,
+                ))
+            } else {
+                Err(Error::BoxInTrafNotFound(self.track_id(), BoxType::TrafBox))
             }
-            let start_offset = ((sample_id - 1) * default_sample_duration) as u64;
-            Ok((
-                base_start_time.wrapping_add(start_offset),
-                default_sample_duration,
-            ))
         } else {
             let stts = &self.trak.mdia.minf.stbl.stts;
 
@@ -702,37 +1304,138 @@ impl Mp4Track {
         }
     }
 
-    fn sample_rendering_offset(&self, sample_id: u32) -> i32 {
-        if !self.trafs.is_empty() {
-            if let Some((traf_idx, sample_idx)) = self.find_traf_idx_and_sample_idx(sample_id) {
-                if let Some(cts) = self.trafs[traf_idx]
+    /// Find the sample to start decoding from in order to present `time`:
+    /// the nearest sync sample whose decode time is at or before `time`.
+    ///
+    /// `time` is measured on the media timeline of the track. If no sync
+    /// sample precedes it, the first sync sample of the track is returned.
+    pub fn seek_to_time(&self, time: Duration) -> Result<u32> {
+        let target = (time.as_nanos() * self.timescale() as u128 / 1_000_000_000) as u64;
+        if let Some(table) = self.sample_table.get() {
+            return table
+                .sync_sample_at_time(target)
+                .ok_or(Error::InvalidData("track has no samples"));
+        }
+        let sample_id = self
+            .sample_id_at_time(target)?
+            .ok_or(Error::InvalidData("track has no samples"))?;
+
+        if self.trafs.is_empty() {
+            return Ok(match self.trak.mdia.minf.stbl.stss {
+                Some(ref stss) => {
+                    let idx = stss.entries.partition_point(|s| *s <= sample_id);
+                    if idx > 0 {
+                        stss.entries[idx - 1]
+                    } else {
+                        stss.entries.first().copied().unwrap_or(sample_id)
+                    }
+                }
+                None => sample_id,
+            });
+        }
+
+        let first_sample_id = |traf_idx: usize| -> u32 {
+            1 + self.trafs[..traf_idx]
+                .iter()
+                .filter_map(|traf| traf.trun.as_ref())
+                .map(|trun| trun.sample_count)
+                .sum::<u32>()
+        };
+        let (traf_idx, sample_idx) = self
+            .find_traf_idx_and_sample_idx(sample_id)
+            .ok_or(Error::BoxInTrafNotFound(self.track_id(), BoxType::TrafBox))?;
+        for t in (0..=traf_idx).rev() {
+            let last = if t == traf_idx {
+                sample_idx + 1
+            } else {
+                self.trafs[t]
                     .trun
                     .as_ref()
-                    .and_then(|trun| trun.sample_cts.get(sample_idx))
-                {
-                    return *cts as i32;
-                }
+                    .map_or(0, |trun| trun.sample_count) as usize
+            };
+            if let Some(i) = (0..last).rev().find(|i| self.traf_sample_is_sync(t, *i)) {
+                return Ok(first_sample_id(t) + i as u32);
             }
-        } else if let Some(ref ctts) = self.trak.mdia.minf.stbl.ctts {
-            if let Ok((ctts_index, _)) = self.ctts_index(sample_id) {
-                let ctts_entry = ctts.entries.get(ctts_index).unwrap();
-                return ctts_entry.sample_offset;
+        }
+        for t in traf_idx..self.trafs.len() {
+            let count = self.trafs[t]
+                .trun
+                .as_ref()
+                .map_or(0, |trun| trun.sample_count);
+            if let Some(i) = (0..count as usize).find(|i| self.traf_sample_is_sync(t, *i)) {
+                return Ok(first_sample_id(t) + i as u32);
             }
         }
-        0
+        Ok(sample_id)
     }
 
-    fn is_sync_sample(&self, sample_id: u32) -> bool {
+    /// Id of the last sample whose decode time is at or before `time`, or the
+    /// first sample if they all start later.
+    fn sample_id_at_time(&self, time: u64) -> Result<Option<u32>> {
+        if self.sample_count() == 0 {
+            return Ok(None);
+        }
+
         if !self.trafs.is_empty() {
-            let sample_sizes_count = self.sample_count() / self.trafs.len() as u32;
-            return sample_id == 1 || sample_id % sample_sizes_count == 0;
+            let mut found = None;
+            let mut first_sample_id = 1u32;
+            for (traf_idx, traf) in self.trafs.iter().enumerate() {
+                let sample_count = traf.trun.as_ref().map_or(0, |trun| trun.sample_count);
+                if sample_count == 0 {
+                    continue;
+                }
+                if found.is_some() && self.traf_base_time(traf_idx) > time {
+                    break;
+                }
+                found = Some((traf_idx, first_sample_id));
+                first_sample_id += sample_count;
+            }
+            let (traf_idx, first_sample_id) = found.unwrap();
+            let sample_count = self.trafs[traf_idx].trun.as_ref().unwrap().sample_count;
+            let mut elapsed = self.traf_base_time(traf_idx);
+            for i in 0..sample_count {
+                elapsed =
+                    elapsed.wrapping_add(self.traf_sample_duration(traf_idx, i as usize) as u64);
+                if elapsed > time {
+                    return Ok(Some(first_sample_id + i));
+                }
+            }
+            return Ok(Some(first_sample_id + sample_count - 1));
         }
 
-        if let Some(ref stss) = self.trak.mdia.minf.stbl.stss {
-            stss.entries.binary_search(&sample_id).is_ok()
-        } else {
-            true
+        let mut first_sample = 1u32;
+        let mut elapsed = 0u64;
+        for entry in self.trak.mdia.minf.stbl.stts.entries.iter() {
+            if entry.sample_count == 0 {
+                continue;
+            }
+            let end_time = elapsed + entry.sample_count as u64 * entry.sample_delta as u64;
+            if time < end_time {
+                let idx = (time - elapsed) / entry.sample_delta as u64;
+                return Ok(Some(first_sample + idx as u32));
+            }
+            first_sample =
+                first_sample
+                    .checked_add(entry.sample_count)
+                    .ok_or(Error::InvalidData(
+                        "attempt to sum stts entries sample_count with overflow",
+                    ))?;
+            elapsed = end_time;
+        }
+        Ok(Some(cmp::max(first_sample - 1, 1)))
+    }
+
+    /// Resolve the location and timing of a sample, `None` if the track has
+    /// no such sample.
+    pub(crate) fn sample_entry(&self, sample_id: u32) -> Result<Option<SampleEntry>> {
+        if sample_id == 0 {
+            return Ok(None);
+        }
+        let entry = SampleCursor::new(self, sample_id)?.next(self)?;
+        if entry.is_none() && !self.trafs.is_empty() {
+            return Err(Error::BoxInTrafNotFound(self.track_id(), BoxType::TrafBox));
         }
+        Ok(entry)
     }
 
     pub(crate) fn read_sample<R: Read + Seek>(
@@ -740,35 +1443,533 @@ impl Mp4Track {
         reader: &mut R,
         sample_id: u32,
     ) -> Result<Option<Mp4Sample>> {
-        let sample_offset = match self.sample_offset(sample_id) {
-            Ok(offset) => offset,
-            Err(Error::EntryInStblNotFound(_, _, _)) => return Ok(None),
-            Err(err) => return Err(err),
+        match self.sample_entry(sample_id)? {
+            Some(entry) => read_sample_entry(reader, &entry).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Location and timing of one sample, resolved from the sample tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SampleEntry {
+    pub offset: u64,
+    pub size: u32,
+    pub start_time: u64,
+    pub duration: u32,
+    pub rendering_offset: i32,
+    pub is_sync: bool,
+    pub sample_description_index: u32,
+}
+
+impl SampleEntry {
+    pub fn to_sample(self, bytes: Bytes) -> Mp4Sample {
+        Mp4Sample {
+            start_time: self.start_time,
+            duration: self.duration,
+            rendering_offset: self.rendering_offset,
+            is_sync: self.is_sync,
+            sample_description_index: self.sample_description_index,
+            bytes,
+        }
+    }
+}
+
+/// Per-sample data of a track, expanded from `stts`, `ctts`, `stsc`, `stsz`,
+/// `stss` and `stco`/`co64` (or from the `trun` boxes of a fragmented track).
+///
+/// Every array is indexed by `sample_id - 1`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SampleTable {
+    /// File offset of each sample.
+    pub offsets: Vec<u64>,
+    pub sizes: Vec<u32>,
+    /// Decode time of each sample, in media timescale units.
+    pub decode_times: Vec<u64>,
+    pub durations: Vec<u32>,
+    /// Composition time offset of each sample (`cts - dts`).
+    pub rendering_offsets: Vec<i32>,
+    pub is_sync: Vec<bool>,
+    pub sample_description_indexes: Vec<u32>,
+}
+
+impl SampleTable {
+    pub(crate) fn build(track: &Mp4Track) -> Result<Self> {
+        let sample_count = track.sample_count() as usize;
+        let mut table = Self {
+            offsets: Vec::with_capacity(sample_count),
+            sizes: Vec::with_capacity(sample_count),
+            decode_times: Vec::with_capacity(sample_count),
+            durations: Vec::with_capacity(sample_count),
+            rendering_offsets: Vec::with_capacity(sample_count),
+            is_sync: Vec::with_capacity(sample_count),
+            sample_description_indexes: Vec::with_capacity(sample_count),
+        };
+        let mut cursor = SampleCursor::new(track, 1)?;
+        while let Some(entry) = cursor.next(track)? {
+            table.offsets.push(entry.offset);
+            table.sizes.push(entry.size);
+            table.decode_times.push(entry.start_time);
+            table.durations.push(entry.duration);
+            table.rendering_offsets.push(entry.rendering_offset);
+            table.is_sync.push(entry.is_sync);
+            table
+                .sample_description_indexes
+                .push(entry.sample_description_index);
+        }
+        Ok(table)
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sizes.len() as u32
+    }
+
+    pub fn offset(&self, sample_id: u32) -> Option<u64> {
+        self.offsets
+            .get((sample_id as usize).checked_sub(1)?)
+            .copied()
+    }
+
+    /// Composition (presentation) time of a sample, in media timescale units.
+    pub fn composition_time(&self, sample_id: u32) -> Option<i64> {
+        let idx = (sample_id as usize).checked_sub(1)?;
+        let dts = *self.decode_times.get(idx)?;
+        Some(dts as i64 + self.rendering_offsets[idx] as i64)
+    }
+
+    pub(crate) fn entry(&self, sample_id: u32) -> Option<SampleEntry> {
+        let idx = (sample_id as usize).checked_sub(1)?;
+        Some(SampleEntry {
+            offset: *self.offsets.get(idx)?,
+            size: self.sizes[idx],
+            start_time: self.decode_times[idx],
+            duration: self.durations[idx],
+            rendering_offset: self.rendering_offsets[idx],
+            is_sync: self.is_sync[idx],
+            sample_description_index: self.sample_description_indexes[idx],
+        })
+    }
+
+    /// Id of the nearest sync sample whose decode time is at or before
+    /// `time`, falling back to the first sync sample.
+    pub fn sync_sample_at_time(&self, time: u64) -> Option<u32> {
+        if self.is_sync.is_empty() {
+            return None;
+        }
+        let idx = self
+            .decode_times
+            .partition_point(|t| *t <= time)
+            .saturating_sub(1);
+        let sync_idx = self.is_sync[..=idx]
+            .iter()
+            .rposition(|is_sync| *is_sync)
+            .or_else(|| self.is_sync.iter().position(|is_sync| *is_sync))
+            .unwrap_or(idx);
+        Some(sync_idx as u32 + 1)
+    }
+}
+
+/// Walks the samples of a track in order, keeping its position in the
+/// `stts`, `ctts`, `stsc` and `stss` tables (or in the track fragments) so
+/// that every step is constant time.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SampleCursor {
+    sample_id: u32,
+    offset: u64,
+    time: u64,
+
+    stts_idx: usize,
+    stts_remaining: u32,
+    ctts_idx: usize,
+    ctts_remaining: u32,
+    stsc_idx: usize,
+    chunk_id: u32,
+    chunk_remaining: u32,
+    stss_idx: usize,
+
+    traf_idx: usize,
+    trun_idx: usize,
+}
+
+impl SampleCursor {
+    /// Position a cursor on `sample_id`. Positioning past the last sample
+    /// yields a cursor that is already exhausted.
+    pub fn new(track: &Mp4Track, sample_id: u32) -> Result<Self> {
+        let sample_id = cmp::max(sample_id, 1);
+        let mut cursor = Self {
+            sample_id,
+            ..Self::default()
+        };
+        if sample_id > track.sample_count() || track.sample_table.get().is_some() {
+            return Ok(cursor);
+        }
+
+        if !track.trafs.is_empty() {
+            let (traf_idx, sample_idx) = track
+                .find_traf_idx_and_sample_idx(sample_id)
+                .ok_or(Error::BoxInTrafNotFound(track.track_id(), BoxType::TrafBox))?;
+            cursor.traf_idx = traf_idx;
+            cursor.trun_idx = sample_idx;
+            // At the start of a traf the position is taken from the traf itself.
+            if sample_idx > 0 {
+                cursor.offset = track.sample_offset(sample_id)?;
+                cursor.time = track.sample_time(sample_id)?.0;
+            }
+            return Ok(cursor);
+        }
+
+        let stbl = &track.trak.mdia.minf.stbl;
+
+        let mut first_sample = 1u32;
+        let mut elapsed = 0u64;
+        cursor.stts_idx = stbl.stts.entries.len();
+        for (i, entry) in stbl.stts.entries.iter().enumerate() {
+            let next_first_sample =
+                first_sample
+                    .checked_add(entry.sample_count)
+                    .ok_or(Error::InvalidData(
+                        "attempt to sum stts entries sample_count with overflow",
+                    ))?;
+            if sample_id < next_first_sample {
+                cursor.stts_idx = i;
+                cursor.stts_remaining = next_first_sample - sample_id;
+                cursor.time =
+                    elapsed + (sample_id - first_sample) as u64 * entry.sample_delta as u64;
+                break;
+            }
+            first_sample = next_first_sample;
+            elapsed += entry.sample_count as u64 * entry.sample_delta as u64;
+        }
+
+        if let Some(ref ctts) = stbl.ctts {
+            match track.ctts_index(sample_id) {
+                Ok((ctts_idx, first_sample)) => {
+                    cursor.ctts_idx = ctts_idx;
+                    cursor.ctts_remaining =
+                        ctts.entries[ctts_idx].sample_count - (sample_id - first_sample);
+                }
+                Err(_) => cursor.ctts_idx = ctts.entries.len(),
+            }
+        }
+
+        let stsc_idx = track.stsc_index(sample_id)?;
+        let stsc_entry = &stbl.stsc.entries[stsc_idx];
+        if stsc_entry.samples_per_chunk == 0 {
+            return Err(Error::InvalidData("stsc entry with zero samples per chunk"));
+        }
+        let sample_in_run = sample_id - stsc_entry.first_sample;
+        cursor.stsc_idx = stsc_idx;
+        cursor.chunk_id = (sample_in_run / stsc_entry.samples_per_chunk)
+            .checked_add(stsc_entry.first_chunk)
+            .ok_or(Error::InvalidData(
+                "attempt to calculate stsc chunk_id with overflow",
+            ))?;
+        let sample_in_chunk = sample_in_run % stsc_entry.samples_per_chunk;
+        cursor.chunk_remaining = stsc_entry.samples_per_chunk - sample_in_chunk;
+        cursor.offset = track.chunk_offset(cursor.chunk_id)?;
+        for i in sample_id - sample_in_chunk..sample_id {
+            cursor.offset += track.sample_size(i)? as u64;
+        }
+
+        if let Some(ref stss) = stbl.stss {
+            cursor.stss_idx = stss.entries.partition_point(|s| *s < sample_id);
+        }
+
+        Ok(cursor)
+    }
+
+    /// Id of the sample returned by the next call to [SampleCursor::next].
+    pub fn sample_id(&self) -> u32 {
+        self.sample_id
+    }
+
+    /// Make every further call to [SampleCursor::next] return `None`.
+    pub fn finish(&mut self) {
+        self.sample_id = u32::MAX;
+    }
+
+    pub fn next(&mut self, track: &Mp4Track) -> Result<Option<SampleEntry>> {
+        if let Some(table) = track.sample_table.get() {
+            let entry = table.entry(self.sample_id);
+            if entry.is_some() {
+                self.sample_id += 1;
+            }
+            return Ok(entry);
+        }
+        if self.sample_id > track.sample_count() {
+            return Ok(None);
+        }
+        let entry = if track.trafs.is_empty() {
+            self.next_in_stbl(track)?
+        } else {
+            self.next_in_traf(track)?
         };
-        let sample_size = match self.sample_size(sample_id) {
-            Ok(size) => size,
-            Err(Error::EntryInStblNotFound(_, _, _)) => return Ok(None),
-            Err(err) => return Err(err),
+        self.sample_id += 1;
+        Ok(Some(entry))
+    }
+
+    fn next_in_traf(&mut self, track: &Mp4Track) -> Result<SampleEntry> {
+        loop {
+            let traf = track
+                .trafs
+                .get(self.traf_idx)
+                .ok_or(Error::BoxInTrafNotFound(track.track_id(), BoxType::TrafBox))?;
+            let sample_count = traf.trun.as_ref().map_or(0, |trun| trun.sample_count);
+            if self.trun_idx < sample_count as usize {
+                break;
+            }
+            self.traf_idx += 1;
+            self.trun_idx = 0;
+        }
+        if self.trun_idx == 0 {
+            self.offset = track.traf_data_offset(self.traf_idx)?;
+            self.time = track.traf_base_time(self.traf_idx);
+        }
+
+        let entry = SampleEntry {
+            offset: self.offset,
+            size: track.traf_sample_size(self.traf_idx, self.trun_idx),
+            start_time: self.time,
+            duration: track.traf_sample_duration(self.traf_idx, self.trun_idx),
+            rendering_offset: track.traf_sample_rendering_offset(self.traf_idx, self.trun_idx),
+            is_sync: track.traf_sample_is_sync(self.traf_idx, self.trun_idx),
+            sample_description_index: track.traf_sample_description_index(self.traf_idx),
         };
 
-        let mut buffer = vec![0x0u8; sample_size as usize];
-        reader.seek(SeekFrom::Start(sample_offset))?;
-        reader.read_exact(&mut buffer)?;
+        self.trun_idx += 1;
+        self.offset += entry.size as u64;
+        self.time = self.time.wrapping_add(entry.duration as u64);
+        Ok(entry)
+    }
+
+    fn next_in_stbl(&mut self, track: &Mp4Track) -> Result<SampleEntry> {
+        let stbl = &track.trak.mdia.minf.stbl;
+        let sample_id = self.sample_id;
+
+        while self.stts_remaining == 0 {
+            self.stts_idx += 1;
+            let stts_entry =
+                stbl.stts
+                    .entries
+                    .get(self.stts_idx)
+                    .ok_or(Error::EntryInStblNotFound(
+                        track.track_id(),
+                        BoxType::SttsBox,
+                        sample_id,
+                    ))?;
+            self.stts_remaining = stts_entry.sample_count;
+        }
+        let duration = stbl.stts.entries[self.stts_idx].sample_delta;
+
+        let mut rendering_offset = 0;
+        if let Some(ref ctts) = stbl.ctts {
+            while self.ctts_remaining == 0 && self.ctts_idx < ctts.entries.len() {
+                self.ctts_idx += 1;
+                if let Some(ctts_entry) = ctts.entries.get(self.ctts_idx) {
+                    self.ctts_remaining = ctts_entry.sample_count;
+                }
+            }
+            if let Some(ctts_entry) = ctts.entries.get(self.ctts_idx) {
+                rendering_offset = ctts_entry.sample_offset;
+                self.ctts_remaining -= 1;
+            }
+        }
+
+        if self.chunk_remaining == 0 {
+            self.chunk_id += 1;
+            while stbl
+                .stsc
+                .entries
+                .get(self.stsc_idx + 1)
+                .is_some_and(|entry| entry.first_chunk <= self.chunk_id)
+            {
+                self.stsc_idx += 1;
+            }
+            self.chunk_remaining = stbl.stsc.entries[self.stsc_idx].samples_per_chunk;
+            if self.chunk_remaining == 0 {
+                return Err(Error::InvalidData("stsc entry with zero samples per chunk"));
+            }
+            self.offset = track.chunk_offset(self.chunk_id)?;
+        }
 
-        let (start_time, duration) = self.sample_time(sample_id).unwrap(); // XXX
-        let rendering_offset = self.sample_rendering_offset(sample_id);
-        let is_sync = self.is_sync_sample(sample_id);
+        let is_sync = if let Some(ref stss) = stbl.stss {
+            while stss
+                .entries
+                .get(self.stss_idx)
+                .is_some_and(|s| *s < sample_id)
+            {
+                self.stss_idx += 1;
+            }
+            stss.entries.get(self.stss_idx) == Some(&sample_id)
+        } else {
+            true
+        };
 
-        Ok(Some(Mp4Sample {
-            start_time,
+        let entry = SampleEntry {
+            offset: self.offset,
+            size: track.sample_size(sample_id)?,
+            start_time: self.time,
             duration,
             rendering_offset,
             is_sync,
-            bytes: Bytes::from(buffer),
-        }))
+            sample_description_index: stbl.stsc.entries[self.stsc_idx].sample_description_index,
+        };
+
+        self.stts_remaining -= 1;
+        self.chunk_remaining -= 1;
+        self.offset += entry.size as u64;
+        self.time += duration as u64;
+        Ok(entry)
+    }
+}
+
+/// Iterator over the samples of a track, created by [Mp4Reader::samples].
+#[derive(Debug)]
+pub struct Mp4SampleIter<'a, R> {
+    reader: &'a mut R,
+    track: &'a Mp4Track,
+    cursor: SampleCursor,
+}
+
+impl<'a, R: Read + Seek> Mp4SampleIter<'a, R> {
+    pub(crate) fn new(reader: &'a mut R, track: &'a Mp4Track, sample_id: u32) -> Result<Self> {
+        let cursor = SampleCursor::new(track, sample_id)?;
+        Ok(Self {
+            reader,
+            track,
+            cursor,
+        })
+    }
+
+    /// Id of the sample the next call to `next` will return.
+    pub fn sample_id(&self) -> u32 {
+        self.cursor.sample_id()
+    }
+
+    fn read_next(&mut self) -> Result<Option<Mp4Sample>> {
+        match self.cursor.next(self.track)? {
+            Some(entry) => read_sample_entry(self.reader, &entry).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+fn read_sample_entry<R: Read + Seek>(reader: &mut R, entry: &SampleEntry) -> Result<Mp4Sample> {
+    let mut buffer = vec![0x0u8; entry.size as usize];
+    reader.seek(SeekFrom::Start(entry.offset))?;
+    reader.read_exact(&mut buffer)?;
+    Ok(entry.to_sample(Bytes::from(buffer)))
+}
+
+impl<R: Read + Seek> Iterator for Mp4SampleIter<'_, R> {
+    type Item = Result<Mp4Sample>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_next() {
+            Ok(sample) => sample.map(Ok),
+            Err(err) => {
+                self.cursor.finish();
+                Some(Err(err))
+            }
+        }
     }
 }
 
+/// Build an empty trak box (no samples) describing the given track.
+pub(crate) fn new_trak(track_id: u32, config: &TrackConfig) -> Result<TrakBox> {
+    let mut trak = TrakBox::default();
+    trak.tkhd.track_id = track_id;
+    trak.mdia.mdhd.timescale = config.timescale;
+    trak.mdia.mdhd.language = config.language.to_owned();
+    trak.mdia.hdlr.handler_type = config.track_type.into();
+    if !config.references.is_empty() {
+        trak.tref = Some(TrefBox {
+            references: config.references.clone(),
+        });
+    }
+    match config.media_conf {
+        MediaConfig::AvcConfig(ref avc_config) => {
+            trak.tkhd.set_width(avc_config.width);
+            trak.tkhd.set_height(avc_config.height);
+
+            let vmhd = VmhdBox::default();
+            trak.mdia.minf.vmhd = Some(vmhd);
+        }
+        MediaConfig::HevcConfig(ref hevc_config) => {
+            trak.tkhd.set_width(hevc_config.width);
+            trak.tkhd.set_height(hevc_config.height);
+
+            let vmhd = VmhdBox::default();
+            trak.mdia.minf.vmhd = Some(vmhd);
+        }
+        MediaConfig::Vp9Config(ref config) => {
+            trak.tkhd.set_width(config.width);
+            trak.tkhd.set_height(config.height);
+        }
+        MediaConfig::Av1Config(ref config) => {
+            trak.tkhd.set_width(config.width);
+            trak.tkhd.set_height(config.height);
+
+            let vmhd = VmhdBox::default();
+            trak.mdia.minf.vmhd = Some(vmhd);
+        }
+        MediaConfig::Mpeg4VisualConfig(ref config) => {
+            trak.tkhd.set_width(config.width);
+            trak.tkhd.set_height(config.height);
+
+            let vmhd = VmhdBox::default();
+            trak.mdia.minf.vmhd = Some(vmhd);
+        }
+        MediaConfig::AacConfig(_)
+        | MediaConfig::Mp3Config(_)
+        | MediaConfig::OpusConfig(_)
+        | MediaConfig::Ac3Config(_)
+        | MediaConfig::Ec3Config(_)
+        | MediaConfig::Ac4Config(_)
+        | MediaConfig::FlacConfig(_)
+        | MediaConfig::AlacConfig(_)
+        | MediaConfig::PcmConfig(_) => {
+            let smhd = SmhdBox::default();
+            trak.mdia.minf.smhd = Some(smhd);
+        }
+        MediaConfig::TtxtConfig(_) | MediaConfig::WvttConfig(_) => {}
+    }
+    trak.mdia
+        .minf
+        .stbl
+        .stsd
+        .entries
+        .push(new_sample_entry(&config.media_conf)?);
+    Ok(trak)
+}
+
+/// Build the sample entry describing media of the given configuration.
+pub(crate) fn new_sample_entry(media_conf: &MediaConfig) -> Result<StsdEntry> {
+    let entry = match media_conf {
+        MediaConfig::AvcConfig(ref avc_config) => StsdEntry::Avc1(Avc1Box::new(avc_config)),
+        MediaConfig::HevcConfig(ref hevc_config) => StsdEntry::Hev1(Hev1Box::new(hevc_config)?),
+        MediaConfig::Vp9Config(ref config) => StsdEntry::Vp09(Vp09Box::new(config)),
+        MediaConfig::Av1Config(ref config) => StsdEntry::Av01(Av01Box::new(config)?),
+        MediaConfig::Mpeg4VisualConfig(ref config) => StsdEntry::Mp4v(Mp4vBox::new(config)),
+        MediaConfig::AacConfig(ref aac_config) => StsdEntry::Mp4a(Mp4aBox::new(aac_config)),
+        MediaConfig::Mp3Config(ref config) => StsdEntry::Mp4a(Mp4aBox::new_mp3(config)),
+        MediaConfig::OpusConfig(ref config) => StsdEntry::Opus(OpusBox::new(config)),
+        MediaConfig::Ac3Config(ref config) => StsdEntry::Ac3(Ac3Box::new(config)?),
+        MediaConfig::Ec3Config(ref config) => StsdEntry::Ec3(Ec3Box::new(config)?),
+        MediaConfig::Ac4Config(ref config) => StsdEntry::Ac4(Ac4Box::new(config)?),
+        MediaConfig::FlacConfig(ref config) => StsdEntry::Flac(FlacBox::new(config)),
+        MediaConfig::AlacConfig(ref config) => StsdEntry::Alac(AlacBox::new(config)),
+        MediaConfig::PcmConfig(ref config) => match config.sample_entry {
+            PcmSampleEntryKind::Iso => StsdEntry::Ipcm(IpcmBox::new(config)?),
+            PcmSampleEntryKind::Lpcm => StsdEntry::Lpcm(LpcmBox::new(config)),
+            PcmSampleEntryKind::QuickTime => StsdEntry::Twos(TwosBox::new(config)?),
+        },
+        MediaConfig::TtxtConfig(_) => StsdEntry::Tx3g(Tx3gBox::default()),
+        MediaConfig::WvttConfig(_) => StsdEntry::Wvtt(WvttBox::default()),
+    };
+    Ok(entry)
+}
+
 // TODO creation_time, modification_time
 #[derive(Debug, Default)]
 pub(crate) struct Mp4TrackWriter {
@@ -780,67 +1981,35 @@ pub(crate) struct Mp4TrackWriter {
     chunk_samples: u32,
     chunk_duration: u32,
     chunk_buffer: BytesMut,
+    chunk_sample_description_index: u32,
 
     samples_per_chunk: u32,
     duration_per_chunk: u32,
+
+    edits: Vec<Edit>,
 }
 
 impl Mp4TrackWriter {
     pub(crate) fn new(track_id: u32, config: &TrackConfig) -> Result<Self> {
-        let mut trak = TrakBox::default();
-        trak.tkhd.track_id = track_id;
-        trak.mdia.mdhd.timescale = config.timescale;
-        trak.mdia.mdhd.language = config.language.to_owned();
-        trak.mdia.hdlr.handler_type = config.track_type.into();
+        let mut trak = new_trak(track_id, config)?;
         trak.mdia.minf.stbl.co64 = Some(Co64Box::default());
-        match config.media_conf {
-            MediaConfig::AvcConfig(ref avc_config) => {
-                trak.tkhd.set_width(avc_config.width);
-                trak.tkhd.set_height(avc_config.height);
-
-                let vmhd = VmhdBox::default();
-                trak.mdia.minf.vmhd = Some(vmhd);
-
-                let avc1 = Avc1Box::new(avc_config);
-                trak.mdia.minf.stbl.stsd.avc1 = Some(avc1);
-            }
-            MediaConfig::HevcConfig(ref hevc_config) => {
-                trak.tkhd.set_width(hevc_config.width);
-                trak.tkhd.set_height(hevc_config.height);
-
-                let vmhd = VmhdBox::default();
-                trak.mdia.minf.vmhd = Some(vmhd);
 
-                let hev1 = Hev1Box::new(hevc_config);
-                trak.mdia.minf.stbl.stsd.hev1 = Some(hev1);
-            }
-            MediaConfig::Vp9Config(ref config) => {
-                trak.tkhd.set_width(config.width);
-                trak.tkhd.set_height(config.height);
-
-                trak.mdia.minf.stbl.stsd.vp09 = Some(Vp09Box::new(config));
-            }
-            MediaConfig::AacConfig(ref aac_config) => {
-                let smhd = SmhdBox::default();
-                trak.mdia.minf.smhd = Some(smhd);
-
-                let mp4a = Mp4aBox::new(aac_config);
-                trak.mdia.minf.stbl.stsd.mp4a = Some(mp4a);
-            }
-            MediaConfig::TtxtConfig(ref _ttxt_config) => {
-                let tx3g = Tx3gBox::default();
-                trak.mdia.minf.stbl.stsd.tx3g = Some(tx3g);
-            }
-            MediaConfig::WvttConfig(ref _wvtt_config) => {
-                let wvtt = WvttBox::default();
-                trak.mdia.minf.stbl.stsd.wvtt = Some(wvtt);
+        // Hide the decoder delay of Opus, counted at 48 kHz.
+        let mut edits = Vec::new();
+        if let MediaConfig::OpusConfig(ref opus_config) = config.media_conf {
+            if opus_config.pre_skip > 0 {
+                let pre_skip = opus_config.pre_skip as u64 * config.timescale as u64 / 48000;
+                edits.push(Edit::media(pre_skip, 0));
             }
         }
+
         Ok(Mp4TrackWriter {
             trak,
             chunk_buffer: BytesMut::new(),
             sample_id: 1,
+            chunk_sample_description_index: 1,
             duration_per_chunk: config.timescale, // 1 second
+            edits,
             ..Self::default()
         })
     }
@@ -971,7 +2140,24 @@ impl Mp4TrackWriter {
         writer: &mut W,
         sample: &Mp4Sample,
         movie_timescale: u32,
-    ) -> Result<u64> {
+    ) -> Result<()> {
+        if self
+            .trak
+            .mdia
+            .minf
+            .stbl
+            .stsd
+            .entry(sample.sample_description_index)
+            .is_none()
+        {
+            return Err(Error::InvalidData("invalid sample description index"));
+        }
+        // A chunk only holds samples of one sample description.
+        if sample.sample_description_index != self.chunk_sample_description_index {
+            self.write_chunk(writer)?;
+            self.chunk_sample_description_index = sample.sample_description_index;
+        }
+
         self.chunk_buffer.extend_from_slice(&sample.bytes);
         self.chunk_samples += 1;
         self.chunk_duration += sample.duration;
@@ -986,7 +2172,7 @@ impl Mp4TrackWriter {
 
         self.sample_id += 1;
 
-        Ok(self.trak.tkhd.duration)
+        Ok(())
     }
 
     fn chunk_count(&self) -> u32 {
@@ -996,7 +2182,9 @@ impl Mp4TrackWriter {
 
     fn update_sample_to_chunk(&mut self, chunk_id: u32) {
         if let Some(entry) = self.trak.mdia.minf.stbl.stsc.entries.last() {
-            if entry.samples_per_chunk == self.chunk_samples {
+            if entry.samples_per_chunk == self.chunk_samples
+                && entry.sample_description_index == self.chunk_sample_description_index
+            {
                 return;
             }
         }
@@ -1004,7 +2192,7 @@ impl Mp4TrackWriter {
         let entry = StscEntry {
             first_chunk: chunk_id,
             samples_per_chunk: self.chunk_samples,
-            sample_description_index: 1,
+            sample_description_index: self.chunk_sample_description_index,
             first_sample: self.sample_id - self.chunk_samples + 1,
         };
         self.trak.mdia.minf.stbl.stsc.entries.push(entry);
@@ -1045,17 +2233,74 @@ impl Mp4TrackWriter {
         }
     }
 
-    pub(crate) fn write_end<W: Write + Seek>(&mut self, writer: &mut W) -> Result<TrakBox> {
+    /// Add a sample entry for a new configuration of the track's media,
+    /// returning its sample description index.
+    pub(crate) fn add_sample_description(&mut self, media_conf: &MediaConfig) -> Result<u32> {
+        let track_type = TrackConfig::from(media_conf.clone()).track_type;
+        if FourCC::from(track_type) != self.trak.mdia.hdlr.handler_type {
+            return Err(Error::InvalidData(
+                "sample description does not match the track type",
+            ));
+        }
+        let stsd = &mut self.trak.mdia.minf.stbl.stsd;
+        stsd.entries.push(new_sample_entry(media_conf)?);
+        Ok(stsd.entries.len() as u32)
+    }
+
+    pub(crate) fn set_edit_list(&mut self, edits: Vec<Edit>) {
+        self.edits = edits;
+    }
+
+    /// Write the edit list, if any, and make the track duration match it. A
+    /// trailing edit with a zero duration is extended to the end of the media.
+    fn update_edit_list(&mut self, movie_timescale: u32) {
+        if self.edits.is_empty() {
+            return;
+        }
+
+        let media_duration = self.trak.mdia.mdhd.duration;
+        let media_timescale = self.trak.mdia.mdhd.timescale as u64;
+        if let Some(last) = self.edits.last_mut() {
+            if let (0, Some(media_time)) = (last.duration, last.media_time) {
+                if !last.is_dwell() {
+                    last.duration = media_duration.saturating_sub(media_time)
+                        * movie_timescale as u64
+                        / media_timescale;
+                }
+            }
+        }
+
+        self.trak.tkhd.duration = self.edits.iter().map(|edit| edit.duration).sum();
+        if self.trak.tkhd.duration > (u32::MAX as u64) {
+            self.trak.tkhd.version = 1
+        }
+        self.trak.edts = Some(EdtsBox {
+            elst: Some(edit::elst_from_edits(&self.edits)),
+        });
+    }
+
+    pub(crate) fn write_end<W: Write + Seek>(
+        &mut self,
+        writer: &mut W,
+        movie_timescale: u32,
+    ) -> Result<TrakBox> {
         self.write_chunk(writer)?;
+        self.update_edit_list(movie_timescale);
 
         let max_sample_size = self.max_sample_size();
-        if let Some(ref mut mp4a) = self.trak.mdia.minf.stbl.stsd.mp4a {
-            if let Some(ref mut esds) = mp4a.esds {
-                esds.es_desc.dec_config.buffer_size_db = max_sample_size;
+        for entry in self.trak.mdia.minf.stbl.stsd.entries.iter_mut() {
+            if let StsdEntry::Mp4a(ref mut mp4a) = entry {
+                if let Some(ref mut esds) = mp4a.esds {
+                    esds.es_desc.dec_config.buffer_size_db = max_sample_size;
+                }
+                // TODO
+                // mp4a.esds.es_desc.dec_config.max_bitrate
+                // mp4a.esds.es_desc.dec_config.avg_bitrate
+            } else if let StsdEntry::Mp4v(ref mut mp4v) = entry {
+                if let Some(ref mut esds) = mp4v.esds {
+                    esds.es_desc.dec_config.buffer_size_db = max_sample_size;
+                }
             }
-            // TODO
-            // mp4a.esds.es_desc.dec_config.max_bitrate
-            // mp4a.esds.es_desc.dec_config.avg_bitrate
         }
         if let Ok(stco) = StcoBox::try_from(self.trak.mdia.minf.stbl.co64.as_ref().unwrap()) {
             self.trak.mdia.minf.stbl.stco = Some(stco);
@@ -1066,6 +2311,9 @@ impl Mp4TrackWriter {
     }
 }
 
+// sample_is_non_sync_sample bit of the fragment sample flags.
+const SAMPLE_IS_NON_SYNC_SAMPLE: u32 = 0x10000;
+
 const SMOOTH_TRACK_FRAGMENT_EXTEDED_HEADER: [u8; 16] = [
     0x6d, 0x1d, 0x9b, 0x05, 0x42, 0xd5, 0x44, 0xe6, 0x80, 0xe2, 0x14, 0x1d, 0xaf, 0xf7, 0x57, 0xb2,
 ];
@@ -3,6 +3,7 @@ use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::fmt;
 
+use crate::mp4box::ilst::{item_to_pair, item_to_str, item_to_u32};
 use crate::mp4box::*;
 use crate::*;
 
@@ -86,6 +87,15 @@ impl fmt::Display for BoxType {
     }
 }
 
+impl Serialize for BoxType {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 #[derive(Default, PartialEq, Eq, Clone, Copy, Serialize)]
 pub struct FourCC {
     pub value: [u8; 4],
@@ -233,10 +243,86 @@ impl From<TrackType> for FourCC {
     }
 }
 
+/// Type of a track reference, the kind of relationship a `tref` box entry
+/// declares with the referenced tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackReferenceType {
+    /// `chap`: QuickTime chapter track.
+    Chap,
+    /// `hint`: media tracks a hint track describes.
+    Hint,
+    /// `cdsc`: tracks this timed metadata track describes.
+    Cdsc,
+    /// `sync`: tracks this one is synchronised with.
+    Sync,
+    /// `vdep`: auxiliary depth video track.
+    Vdep,
+    /// `subt`: subtitle or caption tracks for this track.
+    Subt,
+    /// `font`: tracks carrying fonts used by this track.
+    Font,
+    Other(FourCC),
+}
+
+impl From<FourCC> for TrackReferenceType {
+    fn from(fourcc: FourCC) -> Self {
+        match &fourcc.value {
+            b"chap" => TrackReferenceType::Chap,
+            b"hint" => TrackReferenceType::Hint,
+            b"cdsc" => TrackReferenceType::Cdsc,
+            b"sync" => TrackReferenceType::Sync,
+            b"vdep" => TrackReferenceType::Vdep,
+            b"subt" => TrackReferenceType::Subt,
+            b"font" => TrackReferenceType::Font,
+            _ => TrackReferenceType::Other(fourcc),
+        }
+    }
+}
+
+impl From<TrackReferenceType> for FourCC {
+    fn from(t: TrackReferenceType) -> FourCC {
+        match t {
+            TrackReferenceType::Chap => (*b"chap").into(),
+            TrackReferenceType::Hint => (*b"hint").into(),
+            TrackReferenceType::Cdsc => (*b"cdsc").into(),
+            TrackReferenceType::Sync => (*b"sync").into(),
+            TrackReferenceType::Vdep => (*b"vdep").into(),
+            TrackReferenceType::Subt => (*b"subt").into(),
+            TrackReferenceType::Font => (*b"font").into(),
+            TrackReferenceType::Other(fourcc) => fourcc,
+        }
+    }
+}
+
+impl fmt::Display for TrackReferenceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", FourCC::from(*self))
+    }
+}
+
+impl Serialize for TrackReferenceType {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 const MEDIA_TYPE_H264: &str = "h264";
 const MEDIA_TYPE_H265: &str = "h265";
 const MEDIA_TYPE_VP9: &str = "vp9";
+const MEDIA_TYPE_AV1: &str = "av1";
+const MEDIA_TYPE_MP4V: &str = "mp4v";
 const MEDIA_TYPE_AAC: &str = "aac";
+const MEDIA_TYPE_MP3: &str = "mp3";
+const MEDIA_TYPE_OPUS: &str = "opus";
+const MEDIA_TYPE_AC3: &str = "ac3";
+const MEDIA_TYPE_EAC3: &str = "eac3";
+const MEDIA_TYPE_AC4: &str = "ac4";
+const MEDIA_TYPE_FLAC: &str = "flac";
+const MEDIA_TYPE_ALAC: &str = "alac";
+const MEDIA_TYPE_PCM: &str = "pcm";
 const MEDIA_TYPE_TTXT: &str = "ttxt";
 const MEDIA_TYPE_WVTT: &str = "wvtt";
 
@@ -245,7 +331,17 @@ pub enum MediaType {
     H264,
     H265,
     VP9,
+    AV1,
+    MP4V,
     AAC,
+    MP3,
+    OPUS,
+    AC3,
+    EAC3,
+    AC4,
+    FLAC,
+    ALAC,
+    PCM,
     TTXT,
     WVTT,
 }
@@ -264,7 +360,17 @@ impl TryFrom<&str> for MediaType {
             MEDIA_TYPE_H264 => Ok(MediaType::H264),
             MEDIA_TYPE_H265 => Ok(MediaType::H265),
             MEDIA_TYPE_VP9 => Ok(MediaType::VP9),
+            MEDIA_TYPE_AV1 => Ok(MediaType::AV1),
+            MEDIA_TYPE_MP4V => Ok(MediaType::MP4V),
             MEDIA_TYPE_AAC => Ok(MediaType::AAC),
+            MEDIA_TYPE_MP3 => Ok(MediaType::MP3),
+            MEDIA_TYPE_OPUS => Ok(MediaType::OPUS),
+            MEDIA_TYPE_AC3 => Ok(MediaType::AC3),
+            MEDIA_TYPE_EAC3 => Ok(MediaType::EAC3),
+            MEDIA_TYPE_AC4 => Ok(MediaType::AC4),
+            MEDIA_TYPE_FLAC => Ok(MediaType::FLAC),
+            MEDIA_TYPE_ALAC => Ok(MediaType::ALAC),
+            MEDIA_TYPE_PCM => Ok(MediaType::PCM),
             MEDIA_TYPE_TTXT => Ok(MediaType::TTXT),
             MEDIA_TYPE_WVTT => Ok(MediaType::WVTT),
             _ => Err(Error::InvalidData("unsupported media type")),
@@ -278,7 +384,17 @@ impl From<MediaType> for &str {
             MediaType::H264 => MEDIA_TYPE_H264,
             MediaType::H265 => MEDIA_TYPE_H265,
             MediaType::VP9 => MEDIA_TYPE_VP9,
+            MediaType::AV1 => MEDIA_TYPE_AV1,
+            MediaType::MP4V => MEDIA_TYPE_MP4V,
             MediaType::AAC => MEDIA_TYPE_AAC,
+            MediaType::MP3 => MEDIA_TYPE_MP3,
+            MediaType::OPUS => MEDIA_TYPE_OPUS,
+            MediaType::AC3 => MEDIA_TYPE_AC3,
+            MediaType::EAC3 => MEDIA_TYPE_EAC3,
+            MediaType::AC4 => MEDIA_TYPE_AC4,
+            MediaType::FLAC => MEDIA_TYPE_FLAC,
+            MediaType::ALAC => MEDIA_TYPE_ALAC,
+            MediaType::PCM => MEDIA_TYPE_PCM,
             MediaType::TTXT => MEDIA_TYPE_TTXT,
             MediaType::WVTT => MEDIA_TYPE_WVTT,
         }
@@ -291,7 +407,17 @@ impl From<&MediaType> for &str {
             MediaType::H264 => MEDIA_TYPE_H264,
             MediaType::H265 => MEDIA_TYPE_H265,
             MediaType::VP9 => MEDIA_TYPE_VP9,
+            MediaType::AV1 => MEDIA_TYPE_AV1,
+            MediaType::MP4V => MEDIA_TYPE_MP4V,
             MediaType::AAC => MEDIA_TYPE_AAC,
+            MediaType::MP3 => MEDIA_TYPE_MP3,
+            MediaType::OPUS => MEDIA_TYPE_OPUS,
+            MediaType::AC3 => MEDIA_TYPE_AC3,
+            MediaType::EAC3 => MEDIA_TYPE_EAC3,
+            MediaType::AC4 => MEDIA_TYPE_AC4,
+            MediaType::FLAC => MEDIA_TYPE_FLAC,
+            MediaType::ALAC => MEDIA_TYPE_ALAC,
+            MediaType::PCM => MEDIA_TYPE_PCM,
             MediaType::TTXT => MEDIA_TYPE_TTXT,
             MediaType::WVTT => MEDIA_TYPE_WVTT,
         }
@@ -337,6 +463,103 @@ impl fmt::Display for AvcProfile {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Av1Profile {
+    Av1Main,         // 0
+    Av1High,         // 1
+    Av1Professional, // 2
+}
+
+impl TryFrom<u8> for Av1Profile {
+    type Error = Error;
+    fn try_from(seq_profile: u8) -> Result<Av1Profile> {
+        match seq_profile {
+            0 => Ok(Av1Profile::Av1Main),
+            1 => Ok(Av1Profile::Av1High),
+            2 => Ok(Av1Profile::Av1Professional),
+            _ => Err(Error::InvalidData("unsupported av1 profile")),
+        }
+    }
+}
+
+impl fmt::Display for Av1Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let profile = match self {
+            Av1Profile::Av1Main => "Main",
+            Av1Profile::Av1High => "High",
+            Av1Profile::Av1Professional => "Professional",
+        };
+        write!(f, "{profile}")
+    }
+}
+
+/// Codec of an MPEG-4 elementary stream, the `objectTypeIndication` of its
+/// decoder config descriptor.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ObjectTypeIndication {
+    Mpeg4Visual = 0x20,
+    Mpeg4Audio = 0x40,
+    Mpeg2AacMain = 0x66,
+    Mpeg2AacLowComplexity = 0x67,
+    Mpeg2AacScalableSampleRate = 0x68,
+    /// MPEG-2 (half sample rate) layer 1 to 3 audio.
+    Mpeg2Audio = 0x69,
+    Mpeg1Audio = 0x6B,
+}
+
+impl ObjectTypeIndication {
+    /// Whether the decoder specific info is an AudioSpecificConfig.
+    pub fn is_aac(&self) -> bool {
+        matches!(
+            self,
+            ObjectTypeIndication::Mpeg4Audio
+                | ObjectTypeIndication::Mpeg2AacMain
+                | ObjectTypeIndication::Mpeg2AacLowComplexity
+                | ObjectTypeIndication::Mpeg2AacScalableSampleRate
+        )
+    }
+
+    /// Whether the stream is MPEG-1 or MPEG-2 layer 1 to 3 audio, usually
+    /// MP3.
+    pub fn is_mpeg_audio(&self) -> bool {
+        matches!(
+            self,
+            ObjectTypeIndication::Mpeg1Audio | ObjectTypeIndication::Mpeg2Audio
+        )
+    }
+}
+
+impl TryFrom<u8> for ObjectTypeIndication {
+    type Error = Error;
+    fn try_from(value: u8) -> Result<ObjectTypeIndication> {
+        match value {
+            0x20 => Ok(ObjectTypeIndication::Mpeg4Visual),
+            0x40 => Ok(ObjectTypeIndication::Mpeg4Audio),
+            0x66 => Ok(ObjectTypeIndication::Mpeg2AacMain),
+            0x67 => Ok(ObjectTypeIndication::Mpeg2AacLowComplexity),
+            0x68 => Ok(ObjectTypeIndication::Mpeg2AacScalableSampleRate),
+            0x69 => Ok(ObjectTypeIndication::Mpeg2Audio),
+            0x6B => Ok(ObjectTypeIndication::Mpeg1Audio),
+            _ => Err(Error::InvalidData("unsupported object type indication")),
+        }
+    }
+}
+
+impl fmt::Display for ObjectTypeIndication {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            ObjectTypeIndication::Mpeg4Visual => "MPEG-4 Visual",
+            ObjectTypeIndication::Mpeg4Audio => "MPEG-4 Audio",
+            ObjectTypeIndication::Mpeg2AacMain => "MPEG-2 AAC Main",
+            ObjectTypeIndication::Mpeg2AacLowComplexity => "MPEG-2 AAC LC",
+            ObjectTypeIndication::Mpeg2AacScalableSampleRate => "MPEG-2 AAC SSR",
+            ObjectTypeIndication::Mpeg2Audio => "MPEG-2 Audio",
+            ObjectTypeIndication::Mpeg1Audio => "MPEG-1 Audio",
+        };
+        write!(f, "{s}")
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum AudioObjectType {
     AacMain = 1,                                       // AAC Main Profile
@@ -585,6 +808,94 @@ impl fmt::Display for ChannelConfig {
     }
 }
 
+/// Audio coding mode, `acmod`, of AC-3 and E-AC-3.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Ac3ChannelMode {
+    DualMono = 0,  // 1+1
+    Mono = 1,      // 1/0
+    Stereo = 2,    // 2/0
+    ThreeZero = 3, // 3/0
+    TwoOne = 4,    // 2/1
+    ThreeOne = 5,  // 3/1
+    TwoTwo = 6,    // 2/2
+    ThreeTwo = 7,  // 3/2
+}
+
+impl Ac3ChannelMode {
+    /// Number of full bandwidth channels.
+    pub fn channel_count(&self) -> u16 {
+        match self {
+            Ac3ChannelMode::DualMono => 2,
+            Ac3ChannelMode::Mono => 1,
+            Ac3ChannelMode::Stereo => 2,
+            Ac3ChannelMode::ThreeZero => 3,
+            Ac3ChannelMode::TwoOne => 3,
+            Ac3ChannelMode::ThreeOne => 4,
+            Ac3ChannelMode::TwoTwo => 4,
+            Ac3ChannelMode::ThreeTwo => 5,
+        }
+    }
+}
+
+impl TryFrom<u8> for Ac3ChannelMode {
+    type Error = Error;
+    fn try_from(acmod: u8) -> Result<Ac3ChannelMode> {
+        match acmod {
+            0 => Ok(Ac3ChannelMode::DualMono),
+            1 => Ok(Ac3ChannelMode::Mono),
+            2 => Ok(Ac3ChannelMode::Stereo),
+            3 => Ok(Ac3ChannelMode::ThreeZero),
+            4 => Ok(Ac3ChannelMode::TwoOne),
+            5 => Ok(Ac3ChannelMode::ThreeOne),
+            6 => Ok(Ac3ChannelMode::TwoTwo),
+            7 => Ok(Ac3ChannelMode::ThreeTwo),
+            _ => Err(Error::InvalidData("invalid ac-3 audio coding mode")),
+        }
+    }
+}
+
+impl fmt::Display for Ac3ChannelMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Ac3ChannelMode::DualMono => "1+1",
+            Ac3ChannelMode::Mono => "1/0",
+            Ac3ChannelMode::Stereo => "2/0",
+            Ac3ChannelMode::ThreeZero => "3/0",
+            Ac3ChannelMode::TwoOne => "2/1",
+            Ac3ChannelMode::ThreeOne => "3/1",
+            Ac3ChannelMode::TwoTwo => "2/2",
+            Ac3ChannelMode::ThreeTwo => "3/2",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Channel layout of an AC-3 or E-AC-3 track.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DolbyChannelLayout {
+    pub channel_mode: Ac3ChannelMode,
+    pub lfe: bool,
+    /// Channel locations added by dependent substreams, always 0 for AC-3.
+    pub chan_loc: u16,
+}
+
+impl DolbyChannelLayout {
+    pub fn channel_count(&self) -> u16 {
+        let mut count = self.channel_mode.channel_count() + self.lfe as u16;
+        for bit in 0..9 {
+            if self.chan_loc & (1 << bit) != 0 {
+                // Bits 2, 3, 7 and 8 locate a single channel, the others a
+                // pair.
+                count += match bit {
+                    2 | 3 | 7 | 8 => 1,
+                    _ => 2,
+                };
+            }
+        }
+        count
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 pub struct AvcConfig {
     pub width: u16,
@@ -597,6 +908,9 @@ pub struct AvcConfig {
 pub struct HevcConfig {
     pub width: u16,
     pub height: u16,
+    pub video_param_set: Vec<u8>,
+    pub seq_param_set: Vec<u8>,
+    pub pic_param_set: Vec<u8>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
@@ -605,10 +919,32 @@ pub struct Vp9Config {
     pub height: u16,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Av1Config {
+    pub width: u16,
+    pub height: u16,
+    /// The sequence header OBU, from which the profile, level and color
+    /// format are taken.
+    pub sequence_header: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Mpeg4VisualConfig {
+    pub width: u16,
+    pub height: u16,
+    pub bitrate: u32,
+    /// Visual object sequence and object layer headers, stored as the
+    /// decoder specific info.
+    pub decoder_config: Vec<u8>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct AacConfig {
     pub bitrate: u32,
+    /// HE-AAC and HE-AACv2 are signalled hierarchically over an AAC LC
+    /// core.
     pub profile: AudioObjectType,
+    /// Output sampling rate, twice the core rate for HE-AAC profiles.
     pub freq_index: SampleFreqIndex,
     pub chan_conf: ChannelConfig,
 }
@@ -624,6 +960,189 @@ impl Default for AacConfig {
     }
 }
 
+/// MPEG-1 or MPEG-2 layer 3 audio, written as an `mp4a` sample entry.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mp3Config {
+    pub bitrate: u32,
+    /// Rates below 32 kHz are signalled as MPEG-2 audio.
+    pub sample_rate: u32,
+    pub channel_count: u16,
+}
+
+impl Default for Mp3Config {
+    fn default() -> Self {
+        Self {
+            bitrate: 128000,
+            sample_rate: 44100,
+            channel_count: 2,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OpusConfig {
+    pub channel_count: u8,
+    /// Samples at 48 kHz of encoder delay. The writer hides them with an
+    /// edit list unless one is set for the track.
+    pub pre_skip: u16,
+    pub input_sample_rate: u32,
+    pub output_gain: i16,
+    pub channel_mapping_family: u8,
+    /// Required for any channel mapping family but 0.
+    pub channel_mapping_table: Option<OpusChannelMappingTable>,
+}
+
+impl Default for OpusConfig {
+    fn default() -> Self {
+        Self {
+            channel_count: 2,
+            pre_skip: 312,
+            input_sample_rate: 48000,
+            output_gain: 0,
+            channel_mapping_family: 0,
+            channel_mapping_table: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Ac3Config {
+    /// Nominal bit rate in bit/s, one of the AC-3 bit rates.
+    pub bitrate: u32,
+    pub sample_rate: u32,
+    pub channel_mode: Ac3ChannelMode,
+    pub lfe: bool,
+    pub bsid: u8,
+    pub bsmod: u8,
+}
+
+impl Default for Ac3Config {
+    fn default() -> Self {
+        Self {
+            bitrate: 192000,
+            sample_rate: 48000,
+            channel_mode: Ac3ChannelMode::Stereo,
+            lfe: false,
+            bsid: 8,
+            bsmod: 0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Ec3Config {
+    pub bitrate: u32,
+    /// Independent substreams, from 1 to 8.
+    pub substreams: Vec<Ec3IndependentSubstream>,
+    /// Object complexity of Dolby Atmos content.
+    pub complexity_index: Option<u8>,
+}
+
+impl Default for Ec3Config {
+    fn default() -> Self {
+        Self {
+            bitrate: 640000,
+            substreams: vec![Ec3IndependentSubstream {
+                fscod: 0,
+                bsid: 16,
+                acmod: Ac3ChannelMode::ThreeTwo as u8,
+                lfeon: true,
+                ..Ec3IndependentSubstream::default()
+            }],
+            complexity_index: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Ac4Config {
+    pub channel_count: u16,
+    /// The decoder specific information (`ac4_dsi_v1`) from the encoder.
+    pub dsi: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FlacConfig {
+    pub stream_info: FlacStreamInfo,
+    /// Metadata blocks to store after `STREAMINFO`.
+    pub metadata_blocks: Vec<FlacMetadataBlock>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AlacConfig {
+    /// Samples per channel in a frame.
+    pub frame_length: u32,
+    pub bit_depth: u8,
+    pub channel_count: u8,
+    pub sample_rate: u32,
+    /// Largest frame in bytes, 0 if unknown.
+    pub max_frame_bytes: u32,
+    /// Average bit rate in bit/s, 0 if unknown.
+    pub bitrate: u32,
+}
+
+impl Default for AlacConfig {
+    fn default() -> Self {
+        Self {
+            frame_length: 4096,
+            bit_depth: 16,
+            channel_count: 2,
+            sample_rate: 44100,
+            max_frame_bytes: 0,
+            bitrate: 0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PcmSampleFormat {
+    /// Signed integer samples.
+    Integer,
+    /// IEEE 754 floating point samples.
+    Float,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PcmEndianness {
+    Big,
+    Little,
+}
+
+/// The sample entry describing a PCM track.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PcmSampleEntryKind {
+    /// `ipcm` or `fpcm` with `pcmC`, from ISO/IEC 23003-5.
+    Iso,
+    /// QuickTime `lpcm`, a version 2 sound description.
+    Lpcm,
+    /// QuickTime `sowt` (little endian) or `twos` (big endian), limited to
+    /// 8 and 16 bit integer samples.
+    QuickTime,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PcmConfig {
+    pub sample_format: PcmSampleFormat,
+    pub endianness: PcmEndianness,
+    pub bit_depth: u8,
+    pub channel_count: u16,
+    pub sample_rate: u32,
+    pub sample_entry: PcmSampleEntryKind,
+}
+
+impl Default for PcmConfig {
+    fn default() -> Self {
+        Self {
+            sample_format: PcmSampleFormat::Integer,
+            endianness: PcmEndianness::Little,
+            bit_depth: 16,
+            channel_count: 2,
+            sample_rate: 48000,
+            sample_entry: PcmSampleEntryKind::Iso,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 pub struct TtxtConfig {}
 
@@ -635,7 +1154,17 @@ pub enum MediaConfig {
     AvcConfig(AvcConfig),
     HevcConfig(HevcConfig),
     Vp9Config(Vp9Config),
+    Av1Config(Av1Config),
+    Mpeg4VisualConfig(Mpeg4VisualConfig),
     AacConfig(AacConfig),
+    Mp3Config(Mp3Config),
+    OpusConfig(OpusConfig),
+    Ac3Config(Ac3Config),
+    Ec3Config(Ec3Config),
+    Ac4Config(Ac4Config),
+    FlacConfig(FlacConfig),
+    AlacConfig(AlacConfig),
+    PcmConfig(PcmConfig),
     TtxtConfig(TtxtConfig),
     WvttConfig(WvttConfig),
 }
@@ -646,15 +1175,32 @@ pub struct Mp4Sample {
     pub duration: u32,
     pub rendering_offset: i32,
     pub is_sync: bool,
+    /// Index of the sample entry (in the track's `stsd` box) describing the
+    /// sample, starting at 1. Defaults to 1.
+    pub sample_description_index: u32,
     pub bytes: Bytes,
 }
 
+impl Default for Mp4Sample {
+    fn default() -> Self {
+        Self {
+            start_time: 0,
+            duration: 0,
+            rendering_offset: 0,
+            is_sync: false,
+            sample_description_index: 1,
+            bytes: Bytes::new(),
+        }
+    }
+}
+
 impl PartialEq for Mp4Sample {
     fn eq(&self, other: &Self) -> bool {
         self.start_time == other.start_time
             && self.duration == other.duration
             && self.rendering_offset == other.rendering_offset
             && self.is_sync == other.is_sync
+            && self.sample_description_index == other.sample_description_index
             && self.bytes.len() == other.bytes.len() // XXX for easy check
     }
 }
@@ -673,6 +1219,24 @@ impl fmt::Display for Mp4Sample {
     }
 }
 
+/// A media subsegment indexed by a `sidx` box, resolved to a byte range of
+/// the file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Subsegment {
+    /// Id of the track the index refers to.
+    pub reference_id: u32,
+    pub timescale: u32,
+    /// Earliest presentation time of the subsegment, in `timescale` units.
+    pub start_time: u64,
+    pub duration: u32,
+    /// File offset of the first byte of the subsegment (its `moof` box).
+    pub offset: u64,
+    pub size: u32,
+    pub starts_with_sap: bool,
+    pub sap_type: u8,
+    pub sap_delta_time: u32,
+}
+
 pub fn creation_time(creation_time: u64) -> u64 {
     // convert from MP4 epoch (1904-01-01) to Unix epoch (1970-01-01)
     if creation_time >= 2082844800 {
@@ -682,12 +1246,17 @@ pub fn creation_time(creation_time: u64) -> u64 {
     }
 }
 
+/// Type code of an iTunes metadata `data` box. Convert it with `u32::from`
+/// to get the numeric code.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum DataType {
-    Binary = 0x000000,
-    Text = 0x000001,
-    Image = 0x00000D,
-    TempoCpil = 0x000015,
+    Binary,
+    Text,
+    Image,
+    Png,
+    TempoCpil,
+    /// A type code without a variant of its own.
+    Other(u32),
 }
 
 #[allow(clippy::derivable_impls)]
@@ -704,33 +1273,200 @@ impl TryFrom<u32> for DataType {
             0x000000 => Ok(DataType::Binary),
             0x000001 => Ok(DataType::Text),
             0x00000D => Ok(DataType::Image),
+            0x00000E => Ok(DataType::Png),
             0x000015 => Ok(DataType::TempoCpil),
-            _ => Err(Error::InvalidData("invalid data type")),
+            _ => Ok(DataType::Other(value)),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+impl From<DataType> for u32 {
+    fn from(data_type: DataType) -> u32 {
+        (&data_type).into()
+    }
+}
+
+impl From<&DataType> for u32 {
+    fn from(data_type: &DataType) -> u32 {
+        match data_type {
+            DataType::Binary => 0x000000,
+            DataType::Text => 0x000001,
+            DataType::Image => 0x00000D,
+            DataType::Png => 0x00000E,
+            DataType::TempoCpil => 0x000015,
+            DataType::Other(value) => *value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum MetadataKey {
     Title,
     Year,
     Poster,
     Summary,
+    Artist,
+    Album,
+    AlbumArtist,
+    Genre,
+    TrackNumber,
+    DiscNumber,
+    Composer,
+    Comment,
+    Encoder,
+    Copyright,
+    Rating,
+    TvShow,
+    TvSeason,
+    TvEpisode,
+    SortTitle,
+    SortArtist,
+    SortAlbum,
+    SortAlbumArtist,
+    SortComposer,
+    SortTvShow,
+    /// A free-form `----` item, identified by a reverse DNS `mean` such as
+    /// `com.apple.iTunes` and a `name`.
+    Freeform {
+        mean: String,
+        name: String,
+    },
+}
+
+impl Serialize for MetadataKey {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            MetadataKey::Freeform { mean, name } => {
+                serializer.collect_str(&format_args!("----:{mean}:{name}"))
+            }
+            _ => serializer.collect_str(&format_args!("{self:?}")),
+        }
+    }
 }
 
 pub trait Metadata<'a> {
     /// The video's title
-    fn title(&self) -> Option<Cow<str>>;
+    fn title(&self) -> Option<Cow<'_, str>>;
     /// The video's release year
     fn year(&self) -> Option<u32>;
     /// The video's poster (cover art)
     fn poster(&self) -> Option<&[u8]>;
     /// The video's summary
-    fn summary(&self) -> Option<Cow<str>>;
+    fn summary(&self) -> Option<Cow<'_, str>>;
+
+    /// The raw item stored under `key`
+    fn item(&self, _key: &MetadataKey) -> Option<&IlstItemBox> {
+        None
+    }
+
+    fn artist(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::Artist).map(item_to_str)
+    }
+
+    fn album(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::Album).map(item_to_str)
+    }
+
+    fn album_artist(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::AlbumArtist).map(item_to_str)
+    }
+
+    fn genre(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::Genre).map(item_to_str)
+    }
+
+    /// The track number and the total number of tracks, 0 if unknown
+    fn track_number(&self) -> Option<(u16, u16)> {
+        self.item(&MetadataKey::TrackNumber).and_then(item_to_pair)
+    }
+
+    /// The disc number and the total number of discs, 0 if unknown
+    fn disc_number(&self) -> Option<(u16, u16)> {
+        self.item(&MetadataKey::DiscNumber).and_then(item_to_pair)
+    }
+
+    fn composer(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::Composer).map(item_to_str)
+    }
+
+    fn comment(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::Comment).map(item_to_str)
+    }
+
+    /// The tool that created the file
+    fn encoder(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::Encoder).map(item_to_str)
+    }
+
+    fn copyright(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::Copyright).map(item_to_str)
+    }
+
+    /// The content rating: 0 for none, 1 for explicit and 2 for clean
+    fn rating(&self) -> Option<u8> {
+        self.item(&MetadataKey::Rating)
+            .and_then(item_to_u32)
+            .and_then(|rating| u8::try_from(rating).ok())
+    }
+
+    fn tv_show(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::TvShow).map(item_to_str)
+    }
+
+    fn tv_season(&self) -> Option<u32> {
+        self.item(&MetadataKey::TvSeason).and_then(item_to_u32)
+    }
+
+    fn tv_episode(&self) -> Option<u32> {
+        self.item(&MetadataKey::TvEpisode).and_then(item_to_u32)
+    }
+
+    fn sort_title(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::SortTitle).map(item_to_str)
+    }
+
+    fn sort_artist(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::SortArtist).map(item_to_str)
+    }
+
+    fn sort_album(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::SortAlbum).map(item_to_str)
+    }
+
+    fn sort_album_artist(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::SortAlbumArtist).map(item_to_str)
+    }
+
+    fn sort_composer(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::SortComposer).map(item_to_str)
+    }
+
+    fn sort_tv_show(&self) -> Option<Cow<'_, str>> {
+        self.item(&MetadataKey::SortTvShow).map(item_to_str)
+    }
+
+    /// All cover images, with their JPEG or PNG data type
+    fn posters(&self) -> Vec<&DataBox> {
+        self.item(&MetadataKey::Poster)
+            .map(|item| item.data_boxes().collect())
+            .unwrap_or_default()
+    }
+
+    /// The value of a free-form `----` item
+    fn freeform(&self, mean: &str, name: &str) -> Option<&[u8]> {
+        let key = MetadataKey::Freeform {
+            mean: mean.to_string(),
+            name: name.to_string(),
+        };
+        self.item(&key).map(|item| item.data.data.as_slice())
+    }
 }
 
 impl<'a, T: Metadata<'a>> Metadata<'a> for &'a T {
-    fn title(&self) -> Option<Cow<str>> {
+    fn title(&self) -> Option<Cow<'_, str>> {
         (**self).title()
     }
 
@@ -742,13 +1478,17 @@ impl<'a, T: Metadata<'a>> Metadata<'a> for &'a T {
         (**self).poster()
     }
 
-    fn summary(&self) -> Option<Cow<str>> {
+    fn summary(&self) -> Option<Cow<'_, str>> {
         (**self).summary()
     }
+
+    fn item(&self, key: &MetadataKey) -> Option<&IlstItemBox> {
+        (**self).item(key)
+    }
 }
 
 impl<'a, T: Metadata<'a>> Metadata<'a> for Option<T> {
-    fn title(&self) -> Option<Cow<str>> {
+    fn title(&self) -> Option<Cow<'_, str>> {
         self.as_ref().and_then(|t| t.title())
     }
 
@@ -760,9 +1500,13 @@ impl<'a, T: Metadata<'a>> Metadata<'a> for Option<T> {
         self.as_ref().and_then(|t| t.poster())
     }
 
-    fn summary(&self) -> Option<Cow<str>> {
+    fn summary(&self) -> Option<Cow<'_, str>> {
         self.as_ref().and_then(|t| t.summary())
     }
+
+    fn item(&self, key: &MetadataKey) -> Option<&IlstItemBox> {
+        self.as_ref().and_then(|t| t.item(key))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
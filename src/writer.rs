@@ -1,6 +1,7 @@
 use byteorder::{BigEndian, WriteBytesExt};
-use std::io::{Seek, SeekFrom, Write};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 
+use crate::faststart;
 use crate::mp4box::*;
 use crate::track::Mp4TrackWriter;
 use crate::*;
@@ -19,7 +20,8 @@ pub struct Mp4Writer<W> {
     tracks: Vec<Mp4TrackWriter>,
     mdat_pos: u64,
     timescale: u32,
-    duration: u64,
+    metadata: Option<IlstBox>,
+    chapters: Vec<Chapter>,
 }
 
 impl<W> Mp4Writer<W> {
@@ -75,13 +77,13 @@ impl<W: Write + Seek> Mp4Writer<W> {
 
         let tracks = Vec::new();
         let timescale = config.timescale;
-        let duration = 0;
         Ok(Self {
             writer,
             tracks,
             mdat_pos,
             timescale,
-            duration,
+            metadata: None,
+            chapters: Vec::new(),
         })
     }
 
@@ -92,32 +94,54 @@ impl<W: Write + Seek> Mp4Writer<W> {
         Ok(())
     }
 
-    fn update_durations(&mut self, track_dur: u64) {
-        if track_dur > self.duration {
-            self.duration = track_dur;
-        }
+    /// Set the edit list of a track, written when the file is finished.
+    ///
+    /// A trailing [Edit] with a zero duration is extended to the end of the
+    /// media. For example, `vec![Edit::media(priming, 0)]` hides `priming`
+    /// samples of encoder delay at the start of an audio track. Opus tracks
+    /// get such an edit for their pre-skip, which this replaces.
+    pub fn set_edit_list(&mut self, track_id: u32, edits: Vec<Edit>) -> Result<()> {
+        track_mut(&mut self.tracks, track_id)?.set_edit_list(edits);
+        Ok(())
     }
 
-    pub fn write_sample(&mut self, track_id: u32, sample: &Mp4Sample) -> Result<()> {
-        if track_id == 0 {
-            return Err(Error::TrakNotFound(track_id));
-        }
+    /// Add a sample description to a track for a mid-stream change of codec
+    /// configuration, such as new parameter sets after a splice. Returns the
+    /// index to put in [Mp4Sample::sample_description_index] of the samples
+    /// that use it.
+    pub fn add_sample_description(
+        &mut self,
+        track_id: u32,
+        media_conf: &MediaConfig,
+    ) -> Result<u32> {
+        track_mut(&mut self.tracks, track_id)?.add_sample_description(media_conf)
+    }
 
-        let track_dur = if let Some(ref mut track) = self.tracks.get_mut(track_id as usize - 1) {
-            track.write_sample(&mut self.writer, sample, self.timescale)?
-        } else {
-            return Err(Error::TrakNotFound(track_id));
-        };
+    /// Set the iTunes style metadata, written to `moov/udta/meta/ilst` when
+    /// the file is finished.
+    pub fn set_metadata(&mut self, metadata: IlstBox) {
+        self.metadata = Some(metadata);
+    }
 
-        self.update_durations(track_dur);
+    /// Set the chapters, written when the file is finished both as a Nero
+    /// `chpl` box and as a QuickTime chapter text track referenced by the audio
+    /// and video tracks. The last chapter ends with the movie.
+    pub fn set_chapters(&mut self, chapters: Vec<Chapter>) {
+        self.chapters = chapters;
+    }
 
-        Ok(())
+    pub fn write_sample(&mut self, track_id: u32, sample: &Mp4Sample) -> Result<()> {
+        track_mut(&mut self.tracks, track_id)?.write_sample(
+            &mut self.writer,
+            sample,
+            self.timescale,
+        )
     }
 
     fn update_mdat_size(&mut self) -> Result<()> {
         let mdat_end = self.writer.stream_position()?;
         let mdat_size = mdat_end - self.mdat_pos;
-        if mdat_size > std::u32::MAX as u64 {
+        if mdat_size > u32::MAX as u64 {
             self.writer.seek(SeekFrom::Start(self.mdat_pos))?;
             self.writer.write_u32::<BigEndian>(1)?;
             self.writer.seek(SeekFrom::Start(self.mdat_pos + 8))?;
@@ -130,20 +154,181 @@ impl<W: Write + Seek> Mp4Writer<W> {
         Ok(())
     }
 
+    fn finish_moov(&mut self) -> Result<MoovBox> {
+        let moov = build_moov(
+            &mut self.tracks,
+            &mut self.writer,
+            self.timescale,
+            self.metadata.as_ref(),
+            &self.chapters,
+        )?;
+        self.update_mdat_size()?;
+        Ok(moov)
+    }
+
     pub fn write_end(&mut self) -> Result<()> {
-        let mut moov = MoovBox::default();
+        let moov = self.finish_moov()?;
+        moov.write_box(&mut self.writer)?;
+        Ok(())
+    }
+}
 
-        for track in self.tracks.iter_mut() {
-            moov.traks.push(track.write_end(&mut self.writer)?);
-        }
-        self.update_mdat_size()?;
+fn track_mut(tracks: &mut [Mp4TrackWriter], track_id: u32) -> Result<&mut Mp4TrackWriter> {
+    (track_id as usize)
+        .checked_sub(1)
+        .and_then(move |idx| tracks.get_mut(idx))
+        .ok_or(Error::TrakNotFound(track_id))
+}
 
-        moov.mvhd.timescale = self.timescale;
-        moov.mvhd.duration = self.duration;
-        if moov.mvhd.duration > (u32::MAX as u64) {
-            moov.mvhd.version = 1
-        }
+/// Write the last chunk of every track, and the chapter track if there are
+/// chapters, and build the `moov` box describing them.
+fn build_moov<W: Write + Seek>(
+    tracks: &mut [Mp4TrackWriter],
+    writer: &mut W,
+    timescale: u32,
+    metadata: Option<&IlstBox>,
+    chapters: &[Chapter],
+) -> Result<MoovBox> {
+    let mut moov = MoovBox::default();
+
+    for track in tracks.iter_mut() {
+        moov.traks.push(track.write_end(writer, timescale)?);
+    }
+
+    moov.mvhd.timescale = timescale;
+    chapter::add_chapters(&mut moov, writer, chapters)?;
+
+    // Edit lists may have changed the track durations, and the last chapter
+    // may start after the end of the other tracks.
+    moov.mvhd.duration = moov
+        .traks
+        .iter()
+        .map(|trak| trak.tkhd.duration)
+        .max()
+        .unwrap_or(0);
+    if moov.mvhd.duration > (u32::MAX as u64) {
+        moov.mvhd.version = 1
+    }
+
+    if let Some(ilst) = metadata {
+        moov.udta.get_or_insert_with(UdtaBox::default).meta = Some(MetaBox::Mdir {
+            ilst: Some(ilst.clone()),
+            unknown_boxes: Vec::new(),
+        });
+    }
+    Ok(moov)
+}
+
+impl<W: Read + Write + Seek> Mp4Writer<W> {
+    /// Finish the file like [Mp4Writer::write_end], but place the `moov` box
+    /// in front of the `mdat` box ("fast start") so that the file can be
+    /// played while it is being downloaded.
+    ///
+    /// The media data is moved forward in place to make room for `moov`, and
+    /// chunk offsets are adjusted accordingly, switching to `co64` if they no
+    /// longer fit in 32 bits.
+    pub fn write_end_fast_start(&mut self) -> Result<()> {
+        let moov = self.finish_moov()?;
+        let mdat_pos = self.mdat_pos;
+        let mdat_end = self.writer.stream_position()?;
+
+        let moov = faststart::relocate_moov(&moov, |moov_size, offset| Ok(offset + moov_size))?;
+        let moov_size = moov.box_size();
+
+        faststart::shift_bytes(&mut self.writer, mdat_pos, mdat_end, moov_size)?;
+        self.writer.seek(SeekFrom::Start(mdat_pos))?;
         moov.write_box(&mut self.writer)?;
+        self.writer.seek(SeekFrom::Start(mdat_end + moov_size))?;
+        Ok(())
+    }
+}
+
+impl Mp4Writer<Cursor<Vec<u8>>> {
+    /// Finish a file buffered in memory with `moov` in front of `mdat`, and
+    /// take its bytes out of the buffer.
+    pub(crate) fn finish_buffer(&mut self) -> Result<Vec<u8>> {
+        self.write_end_fast_start()?;
+        Ok(std::mem::take(self.writer.get_mut()))
+    }
+}
+
+/// Writes a progressive MP4 file to a sink that cannot seek, such as stdout, a
+/// pipe or a socket.
+///
+/// Samples are buffered in memory until [Mp4StreamWriter::write_end], which
+/// emits `ftyp`, `moov` and an `mdat` of known size in a single pass. Memory
+/// use therefore grows to the full size of the output file, and nothing
+/// reaches the sink before the end. For live output, or output too large to
+/// hold in memory, use [Mp4FragmentWriter](crate::Mp4FragmentWriter) instead.
+#[derive(Debug)]
+pub struct Mp4StreamWriter<W> {
+    writer: W,
+    inner: Mp4Writer<Cursor<Vec<u8>>>,
+}
+
+impl<W> Mp4StreamWriter<W> {
+    /// Consume self, returning the inner writer.
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Mp4StreamWriter<W> {
+    /// Start a file that is written to `writer` by [Mp4StreamWriter::write_end].
+    /// Nothing is written to `writer` until then.
+    pub fn write_start(writer: W, config: &Mp4Config) -> Result<Self> {
+        let inner = Mp4Writer::write_start(Cursor::new(Vec::new()), config)?;
+        Ok(Self { writer, inner })
+    }
+
+    /// Add a track, like [Mp4Writer::add_track].
+    pub fn add_track(&mut self, config: &TrackConfig) -> Result<()> {
+        self.inner.add_track(config)
+    }
+
+    /// Set the edit list of a track, like [Mp4Writer::set_edit_list].
+    pub fn set_edit_list(&mut self, track_id: u32, edits: Vec<Edit>) -> Result<()> {
+        self.inner.set_edit_list(track_id, edits)
+    }
+
+    /// Add a sample description to a track, like
+    /// [Mp4Writer::add_sample_description].
+    pub fn add_sample_description(
+        &mut self,
+        track_id: u32,
+        media_conf: &MediaConfig,
+    ) -> Result<u32> {
+        self.inner.add_sample_description(track_id, media_conf)
+    }
+
+    /// Set the iTunes style metadata, like [Mp4Writer::set_metadata].
+    pub fn set_metadata(&mut self, metadata: IlstBox) {
+        self.inner.set_metadata(metadata)
+    }
+
+    /// Set the chapters, like [Mp4Writer::set_chapters].
+    pub fn set_chapters(&mut self, chapters: Vec<Chapter>) {
+        self.inner.set_chapters(chapters)
+    }
+
+    /// Buffer a sample in memory. Every sample stays in memory until
+    /// [Mp4StreamWriter::write_end], so the buffer grows to the size of the
+    /// whole file; use [Mp4FragmentWriter](crate::Mp4FragmentWriter) to write
+    /// samples out as they arrive.
+    pub fn write_sample(&mut self, track_id: u32, sample: &Mp4Sample) -> Result<()> {
+        self.inner.write_sample(track_id, sample)
+    }
+
+    /// Finish the file and write it to the sink in one pass.
+    ///
+    /// The `moov` box is placed in front of the buffered media data, which
+    /// grows the in-memory buffer by the size of `moov`; while the buffer is
+    /// reallocated to make room, peak memory use can briefly reach twice the
+    /// size of the output file.
+    pub fn write_end(&mut self) -> Result<()> {
+        let buf = self.inner.finish_buffer()?;
+        self.writer.write_all(&buf)?;
+        self.writer.flush()?;
         Ok(())
     }
 }
@@ -1,9 +1,15 @@
 use mp4::{
-    AudioObjectType, AvcProfile, ChannelConfig, MediaType, Metadata, Mp4Reader, SampleFreqIndex,
-    TrackType,
+    AacConfig, Ac3ChannelMode, Ac3Config, AlacConfig, AudioObjectType, Av1Config, Av1Profile,
+    AvcConfig, AvcProfile, BoxHeader, BoxType, Bytes, ChannelConfig, Chapter, DataBox, DataType,
+    Ec3Config, Ec3IndependentSubstream, Edit, Error, FlacConfig, FlacStreamInfo, HevcConfig,
+    IlstBox, MediaConfig, MediaType, Metadata, MetadataKey, Mp3Config, Mp4Config, Mp4Editor,
+    Mp4Event, Mp4FragmentConfig, Mp4FragmentWriter, Mp4PushParser, Mp4Reader, Mp4Sample,
+    Mp4StreamWriter, Mp4Writer, Mpeg4VisualConfig, ObjectTypeIndication, OpusConfig, PcmConfig,
+    PcmEndianness, PcmSampleEntryKind, PcmSampleFormat, SampleFreqIndex, SbrSignalling, StsdEntry,
+    TrackConfig, TrackReference, TrackReferenceType, TrackType, TtxtConfig, UnknownBox,
 };
 use std::fs::{self, File};
-use std::io::BufReader;
+use std::io::{BufReader, Cursor};
 use std::time::Duration;
 
 #[test]
@@ -43,6 +49,7 @@ fn test_read_mp4() {
             duration: 512,
             rendering_offset: 0,
             is_sync: true,
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 751]),
         }
     );
@@ -60,6 +67,7 @@ fn test_read_mp4() {
             duration: 1024,
             rendering_offset: 0,
             is_sync: true,
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 179]),
         }
     );
@@ -72,6 +80,7 @@ fn test_read_mp4() {
             duration: 1024,
             rendering_offset: 0,
             is_sync: true,
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 180]),
         }
     );
@@ -84,6 +93,7 @@ fn test_read_mp4() {
             duration: 896,
             rendering_offset: 0,
             is_sync: true,
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 160]),
         }
     );
@@ -137,8 +147,7 @@ fn test_read_extended_audio_object_type() {
             .minf
             .stbl
             .stsd
-            .mp4a
-            .as_ref()
+            .mp4a()
             .unwrap()
             .esds
             .as_ref()
@@ -149,10 +158,120 @@ fn test_read_extended_audio_object_type() {
             .freq_index,
         15
     );
-    assert_eq!(track.channel_config().unwrap(), ChannelConfig::Stereo);
+    // The explicit frequency has no index.
+    assert_eq!(track.sample_rate().unwrap(), 48016);
+    assert!(track.sample_freq_index().is_err());
+    // The channels are given by the ALSSpecificConfig.
+    assert_eq!(track.channel_config().unwrap(), ChannelConfig::Mono);
+    assert_eq!(track.channel_count().unwrap(), 1);
     assert_eq!(track.bitrate(), 839250);
 }
 
+#[test]
+fn test_write_he_aac() {
+    let configs = [
+        AacConfig {
+            profile: AudioObjectType::SpectralBandReplication,
+            freq_index: SampleFreqIndex::Freq44100,
+            ..AacConfig::default()
+        },
+        AacConfig {
+            profile: AudioObjectType::ParametricStereo,
+            freq_index: SampleFreqIndex::Freq48000,
+            chan_conf: ChannelConfig::Mono,
+            ..AacConfig::default()
+        },
+    ];
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    for aac_config in configs.iter() {
+        writer
+            .add_track(&TrackConfig::from(aac_config.clone()))
+            .unwrap();
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+    for (i, aac_config) in configs.iter().enumerate() {
+        let track = mp4.tracks().get(&(i as u32 + 1)).unwrap();
+        assert_eq!(track.media_type().unwrap(), MediaType::AAC);
+        assert_eq!(track.audio_profile().unwrap(), aac_config.profile);
+        assert_eq!(track.sample_freq_index().unwrap(), aac_config.freq_index);
+        assert_eq!(track.sample_rate().unwrap(), aac_config.freq_index.freq());
+        assert_eq!(track.channel_config().unwrap(), aac_config.chan_conf);
+        assert_eq!(track.channel_count().unwrap(), 2);
+
+        let asc = track.audio_specific_config().unwrap();
+        assert_eq!(asc.profile, 2);
+        assert_eq!(asc.sbr_signalling, SbrSignalling::Hierarchical);
+        assert_eq!(asc.sampling_frequency * 2, aac_config.freq_index.freq());
+    }
+}
+
+#[test]
+fn test_write_mp3_mpeg4_visual() {
+    let mp3_config = Mp3Config::default();
+    let mp4v_config = Mpeg4VisualConfig {
+        width: 320,
+        height: 240,
+        bitrate: 400000,
+        decoder_config: vec![0x00, 0x00, 0x01, 0xb0, 0x01, 0x00, 0x00, 0x01, 0xb5, 0x89],
+    };
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(mp4v_config.clone()))
+        .unwrap();
+    writer
+        .add_track(&TrackConfig::from(mp3_config.clone()))
+        .unwrap();
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+
+    let video = mp4.tracks().get(&1).unwrap();
+    assert_eq!(video.track_type().unwrap(), TrackType::Video);
+    assert_eq!(video.media_type().unwrap(), MediaType::MP4V);
+    assert_eq!(video.box_type().unwrap(), BoxType::Mp4vBox.into());
+    assert_eq!(
+        video.object_type_indication().unwrap(),
+        ObjectTypeIndication::Mpeg4Visual
+    );
+    assert_eq!(
+        video.decoder_config().unwrap().dec_specific.data,
+        mp4v_config.decoder_config
+    );
+    assert_eq!(video.width(), 320);
+    assert_eq!(video.bitrate(), 400000);
+    assert!(video.audio_profile().is_err());
+
+    let audio = mp4.tracks().get(&2).unwrap();
+    assert_eq!(audio.track_type().unwrap(), TrackType::Audio);
+    assert_eq!(audio.media_type().unwrap(), MediaType::MP3);
+    assert_eq!(audio.box_type().unwrap(), BoxType::Mp4aBox.into());
+    assert_eq!(
+        audio.object_type_indication().unwrap(),
+        ObjectTypeIndication::Mpeg1Audio
+    );
+    assert_eq!(audio.audio_profile().unwrap(), AudioObjectType::MpegLayer3);
+    assert_eq!(audio.sample_rate().unwrap(), 44100);
+    assert_eq!(audio.channel_count().unwrap(), 2);
+    assert_eq!(audio.bitrate(), 128000);
+    assert!(audio.audio_specific_config().is_err());
+}
+
 fn get_reader(path: &str) -> Mp4Reader<BufReader<File>> {
     let f = File::open(path).unwrap();
     let f_size = f.metadata().unwrap().len();
@@ -177,6 +296,104 @@ fn test_read_metadata() {
     assert_eq!(poster, want_poster.as_slice());
 }
 
+#[test]
+fn test_preserve_unknown_boxes() {
+    use mp4::{Mp4Box, ReadBox, WriteBox};
+
+    let sgpd = BoxType::from(u32::from_be_bytes(*b"sgpd"));
+    let sbgp = BoxType::from(u32::from_be_bytes(*b"sbgp"));
+    let mp4 = get_reader("tests/samples/minimal.mp4");
+    let mut moov = mp4.moov.clone();
+    let stbl = &mut moov.traks[0].mdia.minf.stbl;
+    stbl.unknown_boxes
+        .push(UnknownBox::new(sgpd, vec![0, 0, 0, 0, 1, 2, 3, 4]));
+    stbl.unknown_boxes
+        .push(UnknownBox::new(sbgp, vec![5, 6, 7, 8]));
+
+    let mut buf = Vec::new();
+    moov.write_box(&mut buf).unwrap();
+    assert_eq!(buf.len() as u64, moov.box_size());
+
+    let mut reader = Cursor::new(&buf);
+    let header = BoxHeader::read(&mut reader).unwrap();
+    let dst = mp4::MoovBox::read_box(&mut reader, header.size).unwrap();
+    assert_eq!(dst, moov);
+    assert_eq!(
+        dst.traks[0].mdia.minf.stbl.unknown_boxes,
+        vec![
+            UnknownBox::new(sgpd, vec![0, 0, 0, 0, 1, 2, 3, 4]),
+            UnknownBox::new(sbgp, vec![5, 6, 7, 8])
+        ]
+    );
+}
+
+#[test]
+fn test_preserve_unknown_box_between_traks() {
+    use mp4::{BoxTree, Mp4Box, ReadBox, WriteBox};
+
+    let mp4 = get_reader("tests/samples/minimal.mp4");
+    let mut moov = mp4.moov.clone();
+    assert!(moov.traks.len() >= 2);
+    // mvhd and the first trak come before it.
+    moov.unknown_boxes.push(UnknownBox {
+        position: Some(2),
+        ..UnknownBox::new(BoxType::FreeBox, vec![1, 2, 3, 4])
+    });
+
+    let mut buf = Vec::new();
+    moov.write_box(&mut buf).unwrap();
+    assert_eq!(buf.len() as u64, moov.box_size());
+
+    let tree = BoxTree::read(&mut Cursor::new(&buf), buf.len() as u64).unwrap();
+    let children: Vec<BoxType> = tree
+        .find("moov")
+        .unwrap()
+        .children
+        .iter()
+        .map(|node| node.box_type)
+        .collect();
+    assert_eq!(
+        &children[..4],
+        &[
+            BoxType::MvhdBox,
+            BoxType::TrakBox,
+            BoxType::FreeBox,
+            BoxType::TrakBox
+        ]
+    );
+
+    let mut reader = Cursor::new(&buf);
+    let header = BoxHeader::read(&mut reader).unwrap();
+    let dst = mp4::MoovBox::read_box(&mut reader, header.size).unwrap();
+    assert_eq!(dst, moov);
+}
+
+#[test]
+fn test_box_tree() {
+    use mp4::BoxTree;
+
+    let data = fs::read("tests/samples/minimal.mp4").unwrap();
+    let tree = BoxTree::read(&mut Cursor::new(&data), data.len() as u64).unwrap();
+    assert_eq!(
+        tree.boxes.iter().map(|b| b.size()).sum::<u64>(),
+        data.len() as u64
+    );
+
+    let mp4 = get_reader("tests/samples/minimal.mp4");
+    let stsd = tree.find("moov/trak[1]/mdia/minf/stbl/stsd").unwrap();
+    assert_eq!(
+        stsd.children.len(),
+        mp4.moov.traks[1].mdia.minf.stbl.stsd.entries.len()
+    );
+    assert_eq!(stsd.find("mp4a/esds").unwrap().box_type, BoxType::EsdsBox);
+
+    let data = fs::read("tests/samples/big_buck_bunny_metadata.m4v").unwrap();
+    let tree = BoxTree::read(&mut Cursor::new(&data), data.len() as u64).unwrap();
+    let title = tree.find("moov/udta/meta/ilst/©nam/data").unwrap();
+    let end = (title.offset + title.size()) as usize;
+    assert!(data[..end].ends_with(b"Big Buck Bunny"));
+}
+
 #[test]
 fn test_read_fragments() {
     let mp4 = get_reader("tests/samples/minimal_init.mp4");
@@ -203,9 +420,1392 @@ fn test_read_fragments() {
             duration: 512,
             rendering_offset: 0,
             is_sync: true,
+            sample_description_index: 1,
             bytes: mp4::Bytes::from(vec![0x0u8; 751]),
         }
     );
     let eos = mp4_fragment.read_sample(1, 2);
     assert!(eos.is_err());
 }
+
+fn write_fragmented(write_sidx: bool, write_mfra: bool) -> Vec<u8> {
+    let config = Mp4Config {
+        major_brand: str::parse("iso6").unwrap(),
+        minor_version: 0,
+        compatible_brands: vec![str::parse("iso6").unwrap(), str::parse("cmfc").unwrap()],
+        timescale: 1000,
+    };
+    let fragment_config = Mp4FragmentConfig {
+        fragment_duration: 1000,
+        split_on_sync: true,
+        write_sidx,
+        write_mfra,
+    };
+    let mut writer =
+        Mp4FragmentWriter::write_start(Cursor::new(Vec::new()), &config, &fragment_config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(AvcConfig {
+            width: 320,
+            height: 240,
+            seq_param_set: vec![0x67, 0x64, 0x00, 0x0d],
+            pic_param_set: vec![0x68, 0xee, 0x3c, 0x80],
+        }))
+        .unwrap();
+
+    // 3 seconds of 10 fps video with a sync sample every second.
+    for i in 0..30u32 {
+        let sample = Mp4Sample {
+            start_time: i as u64 * 100,
+            duration: 100,
+            rendering_offset: if i % 10 == 0 { 0 } else { 200 },
+            is_sync: i % 10 == 0,
+            sample_description_index: 1,
+            bytes: Bytes::from(vec![i as u8; 10 + i as usize]),
+        };
+        writer.write_sample(1, &sample).unwrap();
+    }
+    writer.write_end().unwrap();
+
+    writer.into_writer().into_inner()
+}
+
+#[test]
+fn test_write_fragments() {
+    let data = write_fragmented(false, false);
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(Cursor::new(data), size).unwrap();
+
+    assert!(mp4.is_fragmented());
+    assert_eq!(mp4.moofs.len(), 3);
+    assert_eq!(mp4.sample_count(1).unwrap(), 30);
+    assert_eq!(mp4.moov.mvex.as_ref().unwrap().trexs.len(), 1);
+
+    for i in 0..30u32 {
+        let sample = mp4.read_sample(1, i + 1).unwrap().unwrap();
+        assert_eq!(sample.start_time, i as u64 * 100);
+        assert_eq!(sample.duration, 100);
+        assert_eq!(sample.rendering_offset, if i % 10 == 0 { 0 } else { 200 });
+        assert_eq!(sample.is_sync, i % 10 == 0);
+        assert_eq!(sample.bytes, Bytes::from(vec![i as u8; 10 + i as usize]));
+    }
+
+    let samples: Vec<Mp4Sample> = mp4.samples(1).unwrap().map(|s| s.unwrap()).collect();
+    assert_eq!(samples.len(), 30);
+    for (i, sample) in samples.into_iter().enumerate() {
+        assert_eq!(sample, mp4.read_sample(1, i as u32 + 1).unwrap().unwrap());
+    }
+
+    assert_eq!(mp4.seek_to_time(1, Duration::from_millis(0)).unwrap(), 1);
+    assert_eq!(
+        mp4.seek_to_time(1, Duration::from_millis(1999)).unwrap(),
+        11
+    );
+    assert_eq!(
+        mp4.seek_to_time(1, Duration::from_millis(2000)).unwrap(),
+        21
+    );
+    assert_eq!(mp4.seek_to_time(1, Duration::from_secs(60)).unwrap(), 21);
+
+    let table = mp4.tracks()[&1].sample_table().unwrap();
+    assert_eq!(table.sample_count(), 30);
+    assert_eq!(table.decode_times[29], 2900);
+    assert_eq!(table.sizes[29], 39);
+    assert_eq!(
+        mp4.seek_to_time(1, Duration::from_millis(1999)).unwrap(),
+        11
+    );
+}
+
+#[test]
+fn test_write_fragments_single_sample_description() {
+    let config = Mp4Config {
+        major_brand: str::parse("iso6").unwrap(),
+        minor_version: 0,
+        compatible_brands: vec![str::parse("iso6").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4FragmentWriter::write_start(
+        Cursor::new(Vec::new()),
+        &config,
+        &Mp4FragmentConfig::default(),
+    )
+    .unwrap();
+    writer
+        .add_track(&TrackConfig::from(AvcConfig {
+            width: 320,
+            height: 240,
+            seq_param_set: vec![0x67, 0x64, 0x00, 0x0d],
+            pic_param_set: vec![0x68, 0xee, 0x3c, 0x80],
+        }))
+        .unwrap();
+
+    let sample = Mp4Sample {
+        duration: 100,
+        is_sync: true,
+        bytes: Bytes::from(vec![0u8; 10]),
+        ..Default::default()
+    };
+    assert_eq!(sample.sample_description_index, 1);
+    writer.write_sample(1, &sample).unwrap();
+
+    let sample = Mp4Sample {
+        sample_description_index: 2,
+        ..sample
+    };
+    assert!(writer.write_sample(1, &sample).is_err());
+}
+
+#[test]
+fn test_write_fragments_with_sidx() {
+    let data = write_fragmented(true, false);
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(Cursor::new(data), size).unwrap();
+
+    assert_eq!(mp4.sidxs.len(), 1);
+    assert_eq!(mp4.sidxs[0].reference_id, 1);
+    assert_eq!(mp4.moofs.len(), 3);
+
+    let subsegments = mp4.subsegments();
+    assert_eq!(subsegments.len(), 3);
+    let moof_offsets = &mp4.tracks()[&1].moof_offsets;
+    for (i, subsegment) in subsegments.iter().enumerate() {
+        assert_eq!(subsegment.offset, moof_offsets[i]);
+        assert_eq!(subsegment.start_time, i as u64 * 1000);
+        assert_eq!(subsegment.duration, 1000);
+        assert!(subsegment.starts_with_sap);
+        assert_eq!(subsegment.sap_type, 1);
+    }
+    let last = subsegments.last().unwrap();
+    assert_eq!(last.offset + last.size as u64, size);
+
+    assert_eq!(mp4.sample_count(1).unwrap(), 30);
+    let sample = mp4.read_sample(1, 30).unwrap().unwrap();
+    assert_eq!(sample.bytes, Bytes::from(vec![29u8; 39]));
+}
+
+#[test]
+fn test_read_sidx_with_largesize() {
+    let data = write_fragmented(true, false);
+    let moof_offsets = {
+        let size = data.len() as u64;
+        let mp4 = Mp4Reader::read_header(Cursor::new(data.clone()), size).unwrap();
+        mp4.tracks()[&1].moof_offsets.clone()
+    };
+
+    // Rewrite the sidx box with a 64-bit largesize header and 4 bytes of
+    // padding at the end, which moves every later box 12 bytes further into
+    // the file. Byte ranges are relative to the end of the box as read.
+    let pos = data.windows(4).position(|w| w == b"sidx").unwrap() - 4;
+    let sidx_size =
+        u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+    let mut large = data[..pos].to_vec();
+    large.extend_from_slice(&1u32.to_be_bytes());
+    large.extend_from_slice(b"sidx");
+    large.extend_from_slice(&(sidx_size as u64 + 12).to_be_bytes());
+    large.extend_from_slice(&data[pos + 8..pos + sidx_size]);
+    large.extend_from_slice(&[0; 4]);
+    large.extend_from_slice(&data[pos + sidx_size..]);
+
+    let size = large.len() as u64;
+    let mp4 = Mp4Reader::read_header(Cursor::new(large), size).unwrap();
+    let subsegments = mp4.subsegments();
+    assert_eq!(subsegments.len(), 3);
+    for (subsegment, moof_offset) in subsegments.iter().zip(moof_offsets) {
+        assert_eq!(subsegment.offset, moof_offset + 12);
+    }
+    let last = subsegments.last().unwrap();
+    assert_eq!(last.offset + last.size as u64, size);
+}
+
+#[test]
+fn test_write_fragments_with_mfra() {
+    for write_sidx in [false, true] {
+        let data = write_fragmented(write_sidx, true);
+        let size = data.len() as u64;
+
+        let mp4 = Mp4Reader::read_header(Cursor::new(data.clone()), size).unwrap();
+        let tfra = mp4.mfra.as_ref().unwrap().tfra(1).unwrap();
+        let moof_offsets = &mp4.tracks()[&1].moof_offsets;
+        assert_eq!(tfra.entries.len(), 3);
+        for (i, entry) in tfra.entries.iter().enumerate() {
+            assert_eq!(entry.time, i as u64 * 1000);
+            assert_eq!(entry.moof_offset, moof_offsets[i]);
+            assert_eq!(entry.sample_number, 1);
+        }
+
+        let mut mp4 = Mp4Reader::read_header_with_mfra(Cursor::new(data), size).unwrap();
+        assert!(mp4.moofs.is_empty());
+        let sample_id = mp4.seek_fragment(1, Duration::from_millis(1500)).unwrap();
+        assert_eq!(sample_id, 1);
+        assert_eq!(mp4.moofs.len(), 1);
+        assert_eq!(mp4.sample_count(1).unwrap(), 10);
+        let sample = mp4.read_sample(1, sample_id).unwrap().unwrap();
+        assert_eq!(sample.start_time, 1000);
+        assert!(sample.is_sync);
+        assert_eq!(sample.bytes, Bytes::from(vec![10u8; 20]));
+
+        let sample_id = mp4.seek_fragment(1, Duration::from_secs(60)).unwrap();
+        let sample = mp4.read_sample(1, sample_id).unwrap().unwrap();
+        assert_eq!(sample.start_time, 2000);
+    }
+
+    // Without an mfra box, the whole file is read.
+    let data = write_fragmented(false, false);
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header_with_mfra(Cursor::new(data), size).unwrap();
+    assert!(mp4.mfra.is_none());
+    assert_eq!(mp4.moofs.len(), 3);
+    assert_eq!(
+        mp4.seek_fragment(1, Duration::from_millis(1500)).unwrap(),
+        11
+    );
+}
+
+#[test]
+fn test_sample_iter() {
+    let mut mp4 = get_reader("tests/samples/minimal.mp4");
+    let mut track_ids: Vec<u32> = mp4.tracks().keys().copied().collect();
+    track_ids.sort();
+    for track_id in track_ids {
+        let sample_count = mp4.sample_count(track_id).unwrap();
+        let samples: Vec<Mp4Sample> = mp4.samples(track_id).unwrap().map(|s| s.unwrap()).collect();
+        assert_eq!(samples.len() as u32, sample_count);
+        for (i, sample) in samples.into_iter().enumerate() {
+            let expected = mp4.read_sample(track_id, i as u32 + 1).unwrap().unwrap();
+            assert_eq!(sample, expected);
+        }
+
+        let mut iter = mp4.samples_from(track_id, sample_count).unwrap();
+        assert_eq!(iter.sample_id(), sample_count);
+        assert!(iter.next().unwrap().is_ok());
+        assert!(iter.next().is_none());
+    }
+}
+
+#[test]
+fn test_sample_description_change() {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("avc1").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(AvcConfig {
+            width: 320,
+            height: 240,
+            seq_param_set: vec![0x67, 0x64, 0x00, 0x0d],
+            pic_param_set: vec![0x68, 0xee, 0x3c, 0x80],
+        }))
+        .unwrap();
+    // New parameter sets after a splice.
+    let index = writer
+        .add_sample_description(
+            1,
+            &MediaConfig::AvcConfig(AvcConfig {
+                width: 640,
+                height: 480,
+                seq_param_set: vec![0x67, 0x64, 0x00, 0x1e],
+                pic_param_set: vec![0x68, 0xeb, 0xe3, 0xcb],
+            }),
+        )
+        .unwrap();
+    assert_eq!(index, 2);
+    assert!(writer
+        .add_sample_description(1, &MediaConfig::AacConfig(AacConfig::default()))
+        .is_err());
+
+    // Five seconds at 10 fps, switching description after 2.5 seconds.
+    let description = |i: u32| if i < 25 { 1 } else { 2 };
+    for i in 0..50u32 {
+        let sample = Mp4Sample {
+            start_time: i as u64 * 100,
+            duration: 100,
+            rendering_offset: 0,
+            is_sync: i % 25 == 0,
+            sample_description_index: description(i),
+            bytes: Bytes::from(vec![i as u8; 10]),
+        };
+        writer.write_sample(1, &sample).unwrap();
+    }
+    let bad_sample = Mp4Sample {
+        start_time: 5000,
+        duration: 100,
+        rendering_offset: 0,
+        is_sync: true,
+        sample_description_index: 3,
+        bytes: Bytes::from(vec![0u8; 10]),
+    };
+    assert!(matches!(
+        writer.write_sample(1, &bad_sample),
+        Err(Error::InvalidData(_))
+    ));
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(Cursor::new(data), size).unwrap();
+
+    let track = &mp4.tracks()[&1];
+    assert_eq!(track.trak.mdia.minf.stbl.stsd.entries.len(), 2);
+    assert!(matches!(
+        track.sample_description(2),
+        Some(StsdEntry::Avc1(avc1)) if avc1.width == 640
+    ));
+    assert_eq!(
+        track.sequence_parameter_set().unwrap(),
+        &[0x67, 0x64, 0x00, 0x0d]
+    );
+
+    for i in 0..50u32 {
+        let sample = mp4.read_sample(1, i + 1).unwrap().unwrap();
+        assert_eq!(sample.sample_description_index, description(i));
+        assert_eq!(sample.bytes, Bytes::from(vec![i as u8; 10]));
+    }
+    let samples: Vec<Mp4Sample> = mp4.samples(1).unwrap().map(|s| s.unwrap()).collect();
+    for (i, sample) in samples.iter().enumerate() {
+        assert_eq!(sample.sample_description_index, description(i as u32));
+    }
+    let table = mp4.tracks()[&1].sample_table().unwrap();
+    assert_eq!(table.sample_description_indexes[24], 1);
+    assert_eq!(table.sample_description_indexes[25], 2);
+}
+
+#[test]
+fn test_seek_to_time() {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("avc1").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(AvcConfig {
+            width: 320,
+            height: 240,
+            seq_param_set: vec![0x67, 0x64, 0x00, 0x0d],
+            pic_param_set: vec![0x68, 0xee, 0x3c, 0x80],
+        }))
+        .unwrap();
+
+    // 10 seconds at 25 fps (timescale 1000) with a sync sample every 2 seconds.
+    for i in 0..250u32 {
+        let sample = Mp4Sample {
+            start_time: i as u64 * 40,
+            duration: 40,
+            rendering_offset: if i % 3 == 1 { 80 } else { 0 },
+            is_sync: i % 50 == 0,
+            sample_description_index: 1,
+            bytes: Bytes::from(vec![i as u8; 20 + i as usize % 7]),
+        };
+        writer.write_sample(1, &sample).unwrap();
+    }
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(Cursor::new(data), size).unwrap();
+
+    let expected: Vec<Mp4Sample> = mp4.samples(1).unwrap().map(|s| s.unwrap()).collect();
+    assert_eq!(expected.len(), 250);
+    // Lookups without and with the precomputed sample table.
+    for build_table in [false, true] {
+        if build_table {
+            let table = mp4.tracks()[&1].sample_table().unwrap();
+            assert_eq!(table.sample_count(), 250);
+            assert_eq!(table.decode_times[10], 400);
+            assert_eq!(table.composition_time(2), Some(120));
+            assert_eq!(table.is_sync.iter().filter(|s| **s).count(), 5);
+        }
+        assert_eq!(mp4.seek_to_time(1, Duration::from_millis(0)).unwrap(), 1);
+        assert_eq!(mp4.seek_to_time(1, Duration::from_millis(1999)).unwrap(), 1);
+        assert_eq!(
+            mp4.seek_to_time(1, Duration::from_millis(2000)).unwrap(),
+            51
+        );
+        assert_eq!(
+            mp4.seek_to_time(1, Duration::from_millis(7500)).unwrap(),
+            151
+        );
+        assert_eq!(mp4.seek_to_time(1, Duration::from_secs(20)).unwrap(), 201);
+        assert!(mp4.seek_to_time(2, Duration::from_secs(1)).is_err());
+
+        let samples: Vec<Mp4Sample> = mp4.samples(1).unwrap().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, expected);
+        for (i, sample) in expected.iter().enumerate() {
+            assert_eq!(&mp4.read_sample(1, i as u32 + 1).unwrap().unwrap(), sample);
+        }
+        assert!(mp4.read_sample(1, 251).unwrap().is_none());
+    }
+
+    let sample_id = mp4.seek_to_time(1, Duration::from_millis(4100)).unwrap();
+    assert_eq!(sample_id, 101);
+    let samples: Vec<Mp4Sample> = mp4
+        .samples_from(1, sample_id)
+        .unwrap()
+        .map(|s| s.unwrap())
+        .collect();
+    assert_eq!(samples.len(), 150);
+    assert!(samples[0].is_sync);
+    assert_eq!(samples[0].start_time, 4000);
+    for (i, sample) in samples.into_iter().enumerate() {
+        let expected = mp4.read_sample(1, sample_id + i as u32).unwrap().unwrap();
+        assert_eq!(sample, expected);
+    }
+}
+
+fn top_level_boxes(data: &[u8]) -> Vec<BoxType> {
+    let mut reader = Cursor::new(data);
+    let mut boxes = Vec::new();
+    while (reader.position() as usize) < data.len() {
+        let start = reader.position();
+        let header = BoxHeader::read(&mut reader).unwrap();
+        boxes.push(header.name);
+        reader.set_position(start + header.size);
+    }
+    boxes
+}
+
+fn write_progressive(fast_start: bool) -> Vec<u8> {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("mp41").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(AacConfig::default()))
+        .unwrap();
+    for i in 0..100u32 {
+        let sample = Mp4Sample {
+            start_time: i as u64 * 1024,
+            duration: 1024,
+            rendering_offset: 0,
+            is_sync: true,
+            sample_description_index: 1,
+            bytes: Bytes::from(vec![i as u8; 100 + i as usize]),
+        };
+        writer.write_sample(1, &sample).unwrap();
+    }
+    if fast_start {
+        writer.write_end_fast_start().unwrap();
+    } else {
+        writer.write_end().unwrap();
+    }
+    writer.into_writer().into_inner()
+}
+
+fn assert_progressive_samples(data: Vec<u8>) {
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(Cursor::new(data), size).unwrap();
+    assert_eq!(mp4.sample_count(1).unwrap(), 100);
+    for i in 0..100u32 {
+        let sample = mp4.read_sample(1, i + 1).unwrap().unwrap();
+        assert_eq!(sample.start_time, i as u64 * 1024);
+        assert_eq!(sample.bytes, Bytes::from(vec![i as u8; 100 + i as usize]));
+    }
+}
+
+#[test]
+fn test_write_fast_start() {
+    let data = write_progressive(false);
+    assert_eq!(
+        top_level_boxes(&data),
+        vec![BoxType::FtypBox, BoxType::MdatBox, BoxType::MoovBox]
+    );
+
+    let data = write_progressive(true);
+    assert_eq!(
+        top_level_boxes(&data),
+        vec![BoxType::FtypBox, BoxType::MoovBox, BoxType::MdatBox]
+    );
+    assert_progressive_samples(data);
+}
+
+#[test]
+fn test_optimize_for_streaming() {
+    let data = write_progressive(false);
+    let size = data.len() as u64;
+    let mut mp4 = Mp4Reader::read_header(Cursor::new(data), size).unwrap();
+
+    let mut optimized = Vec::new();
+    mp4::optimize_for_streaming(&mut mp4, &mut optimized).unwrap();
+    assert_eq!(
+        top_level_boxes(&optimized),
+        vec![BoxType::FtypBox, BoxType::MoovBox, BoxType::MdatBox]
+    );
+    assert_progressive_samples(optimized);
+}
+
+#[test]
+fn test_write_stream() {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("mp41").unwrap()],
+        timescale: 1000,
+    };
+
+    // Vec<u8> only implements Write, standing in for a pipe or socket.
+    let mut writer = Mp4StreamWriter::write_start(Vec::new(), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(AacConfig::default()))
+        .unwrap();
+    for i in 0..100u32 {
+        let sample = Mp4Sample {
+            start_time: i as u64 * 1024,
+            duration: 1024,
+            rendering_offset: 0,
+            is_sync: true,
+            sample_description_index: 1,
+            bytes: Bytes::from(vec![i as u8; 100 + i as usize]),
+        };
+        writer.write_sample(1, &sample).unwrap();
+    }
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer();
+    assert_eq!(
+        top_level_boxes(&data),
+        vec![BoxType::FtypBox, BoxType::MoovBox, BoxType::MdatBox]
+    );
+    assert_progressive_samples(data);
+}
+
+#[test]
+fn test_edit_list() {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("mp41").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(AacConfig::default()))
+        .unwrap();
+    writer
+        .add_track(&TrackConfig::from(AacConfig::default()))
+        .unwrap();
+    // Hide one frame of encoder delay on track 1, delay track 2 by 500ms.
+    writer.set_edit_list(1, vec![Edit::media(1024, 0)]).unwrap();
+    writer
+        .set_edit_list(2, vec![Edit::empty(500), Edit::media(0, 0)])
+        .unwrap();
+    assert!(writer.set_edit_list(3, vec![]).is_err());
+    for i in 0..100u32 {
+        let sample = Mp4Sample {
+            start_time: i as u64 * 1024,
+            duration: 1024,
+            rendering_offset: 0,
+            is_sync: true,
+            sample_description_index: 1,
+            bytes: Bytes::from(vec![i as u8; 100]),
+        };
+        writer.write_sample(1, &sample).unwrap();
+        writer.write_sample(2, &sample).unwrap();
+    }
+    writer.write_end().unwrap();
+
+    let data = writer.into_writer().into_inner();
+    let size = data.len() as u64;
+    let mp4 = Mp4Reader::read_header(Cursor::new(data), size).unwrap();
+
+    // Track and movie timescales are both 1000.
+    let track = &mp4.tracks()[&1];
+    assert_eq!(track.edit_list(), vec![Edit::media(1024, 101_376)]);
+    assert_eq!(track.duration(), Duration::from_millis(102_400));
+    assert_eq!(
+        track.presentation_duration(),
+        Duration::from_millis(101_376)
+    );
+    assert_eq!(track.presentation_time(0), None);
+    assert_eq!(track.presentation_time(1024), Some(0));
+    assert_eq!(track.presentation_time(4096), Some(3072));
+
+    let track = &mp4.tracks()[&2];
+    assert_eq!(
+        track.edit_list(),
+        vec![Edit::empty(500), Edit::media(0, 102_400)]
+    );
+    assert_eq!(
+        track.presentation_duration(),
+        Duration::from_millis(102_900)
+    );
+    assert_eq!(track.presentation_time(0), Some(500));
+    assert_eq!(mp4.duration(), Duration::from_millis(102_900));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_read_write() {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("mp41").unwrap()],
+        timescale: 1000,
+    };
+    // A Vec<u8> sink cannot seek.
+    let mut writer = mp4::AsyncMp4Writer::write_start(Vec::new(), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(AacConfig::default()))
+        .unwrap();
+    for i in 0..100u32 {
+        let sample = Mp4Sample {
+            start_time: i as u64 * 1024,
+            duration: 1024,
+            rendering_offset: 0,
+            is_sync: true,
+            sample_description_index: 1,
+            bytes: Bytes::from(vec![i as u8; 100 + i as usize]),
+        };
+        writer.write_sample(1, &sample).unwrap();
+    }
+    writer.write_end().await.unwrap();
+
+    // The async writer lays out the file like a fast start blocking one.
+    let data = writer.into_writer();
+    assert_eq!(data, write_progressive(true));
+
+    let size = data.len() as u64;
+    let mut mp4 = mp4::AsyncMp4Reader::read_header(Cursor::new(data), size)
+        .await
+        .unwrap();
+    assert_eq!(mp4.sample_count(1).unwrap(), 100);
+    for i in 0..100u32 {
+        let sample = mp4.read_sample(1, i + 1).await.unwrap().unwrap();
+        assert_eq!(sample.start_time, i as u64 * 1024);
+        assert_eq!(sample.bytes, Bytes::from(vec![i as u8; 100 + i as usize]));
+    }
+    assert!(mp4.read_sample(1, 101).await.unwrap().is_none());
+
+    let data = fs::read("tests/samples/minimal.mp4").unwrap();
+    let size = data.len() as u64;
+    let mut async_mp4 = mp4::AsyncMp4Reader::read_header(Cursor::new(data), size)
+        .await
+        .unwrap();
+    let mut mp4 = get_reader("tests/samples/minimal.mp4");
+    assert_eq!(async_mp4.moov, mp4.moov);
+    let track_ids: Vec<u32> = mp4.tracks().keys().copied().collect();
+    for track_id in track_ids {
+        for sample_id in 1..=mp4.sample_count(track_id).unwrap() {
+            assert_eq!(
+                async_mp4.read_sample(track_id, sample_id).await.unwrap(),
+                mp4.read_sample(track_id, sample_id).unwrap()
+            );
+        }
+    }
+}
+
+fn push_in_chunks(data: &[u8], chunk_size: usize) -> Vec<Mp4Event> {
+    let mut parser = Mp4PushParser::new();
+    let mut events = Vec::new();
+    for chunk in data.chunks(chunk_size) {
+        parser.push(chunk).unwrap();
+        while let Some(event) = parser.next_event() {
+            events.push(event);
+        }
+    }
+    assert_eq!(parser.buffered_len(), 0);
+    events
+}
+
+#[test]
+fn test_push_parser_fragments() {
+    let data = write_fragmented(false, false);
+    for chunk_size in [1, 7, 1000, data.len()] {
+        let events = push_in_chunks(&data, chunk_size);
+        assert!(matches!(events[0], Mp4Event::Ftyp(_)));
+        assert!(matches!(events[1], Mp4Event::Moov(_)));
+        assert!(matches!(events[2], Mp4Event::Moof { .. }));
+
+        let mut moofs = 0;
+        let mut samples = Vec::new();
+        for event in events {
+            match event {
+                Mp4Event::Moof { .. } => moofs += 1,
+                Mp4Event::Sample { track_id, sample } => {
+                    assert_eq!(track_id, 1);
+                    samples.push(sample);
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(moofs, 3);
+        assert_eq!(samples.len(), 30);
+        for (i, sample) in samples.iter().enumerate() {
+            assert_eq!(sample.start_time, i as u64 * 100);
+            assert_eq!(sample.is_sync, i % 10 == 0);
+            assert_eq!(sample.bytes, Bytes::from(vec![i as u8; 10 + i]));
+        }
+    }
+}
+
+#[test]
+fn test_push_parser_progressive() {
+    let events = push_in_chunks(&write_progressive(true), 100);
+    let samples: Vec<Mp4Sample> = events
+        .into_iter()
+        .filter_map(|event| match event {
+            Mp4Event::Sample { sample, .. } => Some(sample),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(samples.len(), 100);
+    for (i, sample) in samples.iter().enumerate() {
+        assert_eq!(sample.start_time, i as u64 * 1024);
+        assert_eq!(sample.bytes, Bytes::from(vec![i as u8; 100 + i]));
+    }
+
+    // Without fast start the media data has gone by before the moov box.
+    let events = push_in_chunks(&write_progressive(false), 100);
+    assert_eq!(events.len(), 2);
+    assert!(matches!(events[1], Mp4Event::Moov(_)));
+}
+
+fn read_all_samples(data: &[u8]) -> Vec<Mp4Sample> {
+    let mut mp4 = Mp4Reader::read_header(Cursor::new(data), data.len() as u64).unwrap();
+    let mut track_ids: Vec<u32> = mp4.tracks().keys().copied().collect();
+    track_ids.sort_unstable();
+    let mut samples = Vec::new();
+    for track_id in track_ids {
+        for sample_id in 1..=mp4.sample_count(track_id).unwrap() {
+            samples.push(mp4.read_sample(track_id, sample_id).unwrap().unwrap());
+        }
+    }
+    samples
+}
+
+#[test]
+fn test_edit_metadata_moves_moov() {
+    let original = fs::read("tests/samples/minimal.mp4").unwrap();
+    let mut editor = Mp4Editor::new(Cursor::new(original.clone())).unwrap();
+    assert!(editor.metadata().title().is_none());
+    editor
+        .ilst_mut()
+        .unwrap()
+        .set_title("A title that does not fit into the free box");
+    editor.save().unwrap();
+    let edited = editor.into_inner().into_inner();
+
+    // The old moov and free box become one free box; mdat stays in place.
+    assert_eq!(
+        top_level_boxes(&edited),
+        [
+            BoxType::FtypBox,
+            BoxType::FreeBox,
+            BoxType::MdatBox,
+            BoxType::MoovBox
+        ]
+    );
+    let mdat = mdat_offset(&original);
+    assert_eq!(edited[mdat..original.len()], original[mdat..]);
+    assert_eq!(read_all_samples(&edited), read_all_samples(&original));
+    let mp4 = Mp4Reader::read_header(Cursor::new(&edited), edited.len() as u64).unwrap();
+    assert_eq!(
+        mp4.metadata().title(),
+        Some("A title that does not fit into the free box".into())
+    );
+
+    // A smaller moov at the end of the file is padded with a free box.
+    let mut editor = Mp4Editor::new(Cursor::new(edited.clone())).unwrap();
+    editor.ilst_mut().unwrap().remove(&MetadataKey::Title);
+    editor.save().unwrap();
+    let shrunk = editor.into_inner().into_inner();
+    assert_eq!(shrunk.len(), edited.len());
+    assert_eq!(
+        top_level_boxes(&shrunk),
+        [
+            BoxType::FtypBox,
+            BoxType::FreeBox,
+            BoxType::MdatBox,
+            BoxType::MoovBox,
+            BoxType::FreeBox
+        ]
+    );
+    assert_eq!(read_all_samples(&shrunk), read_all_samples(&original));
+    let mp4 = Mp4Reader::read_header(Cursor::new(&shrunk), shrunk.len() as u64).unwrap();
+    assert!(mp4.metadata().title().is_none());
+}
+
+#[test]
+fn test_edit_metadata_keeps_unknown_boxes() {
+    use mp4::{BoxTree, WriteBox};
+
+    // Move the moov of minimal.mp4 to the end and add boxes this crate has
+    // no typed parser for: colr and pasp in avc1 and a vendor atom in trak.
+    let minimal = fs::read("tests/samples/minimal.mp4").unwrap();
+    let mp4 = Mp4Reader::read_header(Cursor::new(&minimal), minimal.len() as u64).unwrap();
+    let ftyp_size = mp4.ftyp.get_size() as usize;
+    let mdat = mdat_offset(&minimal);
+    let mut moov = mp4.moov.clone();
+    for trak in moov.traks.iter_mut() {
+        if let Some(ref mut stco) = trak.mdia.minf.stbl.stco {
+            for offset in stco.entries.iter_mut() {
+                *offset -= (mdat - ftyp_size) as u32;
+            }
+        }
+    }
+    let trak = &mut moov.traks[0];
+    trak.unknown_boxes.push(UnknownBox::new(
+        BoxType::from(u32::from_be_bytes(*b"XVND")),
+        b"vendor".to_vec(),
+    ));
+    match trak.mdia.minf.stbl.stsd.entries.first_mut() {
+        Some(StsdEntry::Avc1(avc1)) => {
+            avc1.unknown_boxes.push(UnknownBox {
+                position: Some(0),
+                ..UnknownBox::new(
+                    BoxType::from(u32::from_be_bytes(*b"colr")),
+                    b"nclx\x00\x01\x00\x01\x00\x01\x00".to_vec(),
+                )
+            });
+            avc1.unknown_boxes.push(UnknownBox::new(
+                BoxType::from(u32::from_be_bytes(*b"pasp")),
+                vec![0, 0, 0, 1, 0, 0, 0, 1],
+            ));
+        }
+        _ => panic!("minimal.mp4 has no avc1 track"),
+    }
+    let mut original = minimal[..ftyp_size].to_vec();
+    original.extend_from_slice(&minimal[mdat..]);
+    moov.write_box(&mut original).unwrap();
+
+    let mut editor = Mp4Editor::new(Cursor::new(original.clone())).unwrap();
+    editor.ilst_mut().unwrap().set_title("Title");
+    editor.save().unwrap();
+    let edited = editor.into_inner().into_inner();
+
+    let children = |data: &[u8]| {
+        let tree = BoxTree::read(&mut Cursor::new(data), data.len() as u64).unwrap();
+        let moov = tree.find("moov").unwrap();
+        let mut children: Vec<Vec<u8>> = moov
+            .children
+            .iter()
+            .filter(|node| node.box_type != BoxType::UdtaBox)
+            .map(|node| data[node.offset as usize..(node.offset + node.size()) as usize].to_vec())
+            .collect();
+        children.insert(0, data[..moov.offset as usize].to_vec());
+        children
+    };
+    assert_eq!(children(&edited), children(&original));
+    assert_eq!(read_all_samples(&edited), read_all_samples(&original));
+    let mp4 = Mp4Reader::read_header(Cursor::new(&edited), edited.len() as u64).unwrap();
+    assert_eq!(mp4.metadata().title(), Some("Title".into()));
+}
+
+#[test]
+fn test_edit_metadata_in_place() {
+    let original = fs::read("tests/samples/big_buck_bunny_metadata.m4v").unwrap();
+    let mut editor = Mp4Editor::new(Cursor::new(original.clone())).unwrap();
+    let ilst = editor.ilst_mut().unwrap();
+    ilst.set_title("Big Buck");
+    ilst.set_year(2009);
+    editor.save().unwrap();
+    let edited = editor.into_inner().into_inner();
+
+    assert_eq!(edited.len(), original.len());
+    assert_eq!(top_level_boxes(&edited), top_level_boxes(&original));
+    let mdat = mdat_offset(&original);
+    assert_eq!(mdat_offset(&edited), mdat);
+    assert_eq!(edited[mdat..], original[mdat..]);
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&edited), edited.len() as u64).unwrap();
+    assert_eq!(mp4.metadata().title(), Some("Big Buck".into()));
+    assert_eq!(mp4.metadata().year(), Some(2009));
+    assert_eq!(
+        mp4.metadata().poster(),
+        Some(
+            fs::read("tests/samples/big_buck_bunny.jpg")
+                .unwrap()
+                .as_slice()
+        )
+    );
+}
+
+fn mdat_offset(data: &[u8]) -> usize {
+    let mut reader = Cursor::new(data);
+    loop {
+        let start = reader.position();
+        let header = BoxHeader::read(&mut reader).unwrap();
+        if header.name == BoxType::MdatBox {
+            return start as usize;
+        }
+        reader.set_position(start + header.size);
+    }
+}
+
+#[test]
+fn test_write_metadata() {
+    let config = Mp4Config {
+        major_brand: str::parse("M4A ").unwrap(),
+        minor_version: 0,
+        compatible_brands: vec![str::parse("M4A ").unwrap(), str::parse("mp42").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(AacConfig::default()))
+        .unwrap();
+    let mut ilst = IlstBox::default();
+    ilst.set_title("Song");
+    ilst.set_artist("Band");
+    ilst.set_track_number(2, 10);
+    ilst.set_posters(vec![DataBox {
+        data_type: DataType::Png,
+        data: b"\x89PNG\r\n".to_vec(),
+    }]);
+    ilst.set_freeform("com.apple.iTunes", "MusicBrainz Track Id", "abc");
+    writer.set_metadata(ilst);
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+    let metadata = mp4.metadata();
+    assert_eq!(metadata.title(), Some("Song".into()));
+    assert_eq!(metadata.artist(), Some("Band".into()));
+    assert_eq!(metadata.track_number(), Some((2, 10)));
+    assert_eq!(metadata.posters()[0].data_type, DataType::Png);
+    assert_eq!(
+        metadata.freeform("com.apple.iTunes", "MusicBrainz Track Id"),
+        Some(&b"abc"[..])
+    );
+}
+
+#[test]
+fn test_write_chapters() {
+    let config = Mp4Config {
+        major_brand: str::parse("M4B ").unwrap(),
+        minor_version: 0,
+        compatible_brands: vec![str::parse("M4B ").unwrap(), str::parse("mp42").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(AacConfig::default()))
+        .unwrap();
+    let chapters = vec![
+        Chapter::new(Duration::from_secs(5), "One"),
+        Chapter::new(Duration::from_millis(60_500), "Trois — fin"),
+        Chapter::new(Duration::from_secs(30), "Two"),
+    ];
+    writer.set_chapters(chapters.clone());
+    for i in 0..100u32 {
+        let sample = Mp4Sample {
+            start_time: i as u64 * 1024,
+            duration: 1024,
+            rendering_offset: 0,
+            is_sync: true,
+            sample_description_index: 1,
+            bytes: Bytes::from(vec![i as u8; 100]),
+        };
+        writer.write_sample(1, &sample).unwrap();
+    }
+    writer.write_end_fast_start().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mut mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+    let want = vec![
+        chapters[0].clone(),
+        chapters[2].clone(),
+        chapters[1].clone(),
+    ];
+    assert_eq!(mp4.tracks().len(), 2);
+    let chapter_track = mp4.tracks().get(&2).unwrap();
+    assert_eq!(chapter_track.track_type().unwrap(), TrackType::Text);
+    assert_eq!(chapter_track.trak.tkhd.duration, mp4.moov.mvhd.duration);
+    assert_eq!(mp4.chapters().unwrap(), want);
+
+    // Without the chapter track reference, the Nero chapter list is used.
+    mp4.moov.traks[0].tref = None;
+    assert_eq!(mp4.chapters().unwrap(), want);
+    mp4.moov.udta = None;
+    assert!(mp4.chapters().unwrap().is_empty());
+}
+
+#[test]
+fn test_track_references() {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    let audio = TrackConfig::from(AacConfig::default())
+        .with_references(vec![TrackReference::new(TrackReferenceType::Subt, vec![2])]);
+    writer.add_track(&audio).unwrap();
+    writer
+        .add_track(&TrackConfig::from(TtxtConfig::default()))
+        .unwrap();
+    writer.set_chapters(vec![Chapter::new(Duration::ZERO, "Start")]);
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+    // The chapter track outlasts the empty tracks and sets the movie duration.
+    let chapters = mp4.tracks().get(&3).unwrap();
+    assert_eq!(chapters.trak.tkhd.duration, 1);
+    assert_eq!(mp4.moov.mvhd.duration, 1);
+    let audio = mp4.tracks().get(&1).unwrap();
+    assert_eq!(audio.references(TrackReferenceType::Subt), [2]);
+    assert_eq!(audio.references(TrackReferenceType::Chap), [3]);
+    assert!(audio.references(TrackReferenceType::Hint).is_empty());
+    let subtitles = audio.referenced_tracks(TrackReferenceType::Subt, mp4.tracks());
+    assert_eq!(subtitles.len(), 1);
+    assert_eq!(subtitles[0].track_type().unwrap(), TrackType::Subtitle);
+
+    let subtitle = mp4.tracks().get(&2).unwrap();
+    assert!(subtitle.trak.tref.is_none());
+    assert!(subtitle
+        .referenced_tracks(TrackReferenceType::Chap, mp4.tracks())
+        .is_empty());
+}
+
+#[test]
+fn test_write_hevc() {
+    let vps = vec![
+        0x40, 0x01, 0x0C, 0x01, 0xFF, 0xFF, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00,
+        0x03, 0x00, 0x00, 0x03, 0x00, 0x5D, 0x95, 0x98, 0x09,
+    ];
+    let sps = vec![
+        0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x03, 0x00, 0x5D, 0xA0, 0x02, 0x80, 0x80, 0x2D, 0x16, 0x59, 0x59, 0xA4, 0x93, 0x2B, 0xC0,
+        0x5A, 0x70, 0x80, 0x00, 0x01, 0xF4, 0x80, 0x00, 0x3A, 0x98, 0x04,
+    ];
+    let pps = vec![0x44, 0x01, 0xC1, 0x72, 0xB4, 0x62, 0x40];
+
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(HevcConfig {
+            width: 1280,
+            height: 720,
+            video_param_set: vps.clone(),
+            seq_param_set: sps.clone(),
+            pic_param_set: pps.clone(),
+        }))
+        .unwrap();
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+    let track = mp4.tracks().get(&1).unwrap();
+    assert_eq!(track.media_type().unwrap(), MediaType::H265);
+    assert_eq!(track.box_type().unwrap(), BoxType::Hvc1Box.into());
+    assert_eq!(track.video_parameter_set().unwrap(), vps);
+    assert_eq!(track.sequence_parameter_set().unwrap(), sps);
+    assert_eq!(track.picture_parameter_set().unwrap(), pps);
+
+    let hvc1 = track.trak.mdia.minf.stbl.stsd.hev1().unwrap();
+    assert!(!hvc1.inband_parameter_sets);
+    assert_eq!(hvc1.hvcc.general_profile_idc, 1);
+    assert_eq!(hvc1.hvcc.general_level_idc, 93);
+    assert_eq!(hvc1.hvcc.chroma_format_idc, 1);
+    assert_eq!(hvc1.hvcc.length_size_minus_one, 3);
+}
+
+#[test]
+fn test_write_av1() {
+    // 1920x1080 Main profile, level 4.0, 8-bit 4:2:0.
+    let sequence_header = vec![
+        0x0A, 0x0B, 0x00, 0x00, 0x00, 0x42, 0xAB, 0xBF, 0xC3, 0x73, 0xFF, 0xE6, 0x01,
+    ];
+
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("av01").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(Av1Config {
+            width: 1920,
+            height: 1080,
+            sequence_header: sequence_header.clone(),
+        }))
+        .unwrap();
+    writer
+        .write_sample(
+            1,
+            &Mp4Sample {
+                start_time: 0,
+                duration: 40,
+                rendering_offset: 0,
+                is_sync: true,
+                sample_description_index: 1,
+                bytes: Bytes::from_static(&[0x12, 0x00]),
+            },
+        )
+        .unwrap();
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+    let track = mp4.tracks().get(&1).unwrap();
+    assert_eq!(track.media_type().unwrap(), MediaType::AV1);
+    assert_eq!(track.box_type().unwrap(), BoxType::Av01Box.into());
+    assert_eq!(track.width(), 1920);
+    assert_eq!(track.height(), 1080);
+    assert_eq!(track.av1_profile().unwrap(), Av1Profile::Av1Main);
+    assert!(track.video_profile().is_err());
+
+    let av1c = track.av1_config().unwrap();
+    assert_eq!(av1c.seq_level_idx_0, 8);
+    assert!(!av1c.seq_tier_0);
+    assert_eq!(av1c.bit_depth(), 8);
+    assert!(av1c.chroma_subsampling_x && av1c.chroma_subsampling_y);
+    assert_eq!(av1c.config_obus, sequence_header);
+}
+
+#[test]
+fn test_write_opus() {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("Opus").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(OpusConfig::default()))
+        .unwrap();
+    for i in 0..50u32 {
+        let sample = Mp4Sample {
+            start_time: i as u64 * 960,
+            duration: 960,
+            rendering_offset: 0,
+            is_sync: true,
+            sample_description_index: 1,
+            bytes: Bytes::from(vec![0xFC; 40]),
+        };
+        writer.write_sample(1, &sample).unwrap();
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+    let track = mp4.tracks().get(&1).unwrap();
+    assert_eq!(track.media_type().unwrap(), MediaType::OPUS);
+    assert_eq!(track.box_type().unwrap(), BoxType::OpusBox.into());
+    assert_eq!(track.timescale(), 48000);
+    let dops = track.opus_config().unwrap();
+    assert_eq!(dops.output_channel_count, 2);
+    assert_eq!(dops.pre_skip, 312);
+    assert_eq!(dops.input_sample_rate, 48000);
+    assert!(dops.channel_mapping_table.is_none());
+
+    // The pre-skip is hidden by the edit list.
+    let edits = track.edit_list();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].media_time, Some(312));
+    assert_eq!(track.presentation_time(0), None);
+    assert_eq!(track.presentation_time(312), Some(0));
+}
+
+#[test]
+fn test_write_dolby() {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(Ac3Config {
+            bitrate: 448000,
+            channel_mode: Ac3ChannelMode::ThreeTwo,
+            lfe: true,
+            ..Ac3Config::default()
+        }))
+        .unwrap();
+    // 5.1 with the rear surround pair of a dependent substream.
+    writer
+        .add_track(&TrackConfig::from(Ec3Config {
+            bitrate: 768000,
+            substreams: vec![Ec3IndependentSubstream {
+                bsid: 16,
+                acmod: 7,
+                lfeon: true,
+                num_dep_sub: 1,
+                chan_loc: 0x02,
+                ..Ec3IndependentSubstream::default()
+            }],
+            complexity_index: Some(16),
+        }))
+        .unwrap();
+    for track_id in 1..=2 {
+        for i in 0..10u32 {
+            let sample = Mp4Sample {
+                start_time: i as u64 * 1536,
+                duration: 1536,
+                rendering_offset: 0,
+                is_sync: true,
+                sample_description_index: 1,
+                bytes: Bytes::from(vec![0x0B; 64]),
+            };
+            writer.write_sample(track_id, &sample).unwrap();
+        }
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+
+    let ac3 = mp4.tracks().get(&1).unwrap();
+    assert_eq!(ac3.media_type().unwrap(), MediaType::AC3);
+    assert_eq!(ac3.box_type().unwrap(), BoxType::Ac3Box.into());
+    assert_eq!(ac3.timescale(), 48000);
+    assert_eq!(ac3.bitrate(), 448000);
+    let layout = ac3.dolby_channel_layout().unwrap();
+    assert_eq!(layout.channel_mode, Ac3ChannelMode::ThreeTwo);
+    assert!(layout.lfe);
+    assert_eq!(layout.channel_count(), 6);
+    assert!(matches!(ac3.bit_depth(), Err(Error::InvalidData(_))));
+    assert!(matches!(ac3.pcm_config(), Err(Error::InvalidData(_))));
+
+    let ec3 = mp4.tracks().get(&2).unwrap();
+    assert_eq!(ec3.media_type().unwrap(), MediaType::EAC3);
+    assert_eq!(ec3.box_type().unwrap(), BoxType::Ec3Box.into());
+    assert_eq!(ec3.bitrate(), 768000);
+    assert_eq!(ec3.eac3_config().unwrap().complexity_index, Some(16));
+    assert_eq!(ec3.dolby_channel_layout().unwrap().channel_count(), 8);
+    assert!(ec3.ac3_config().is_err());
+}
+
+#[test]
+fn test_write_lossless() {
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&TrackConfig::from(FlacConfig {
+            stream_info: FlacStreamInfo {
+                sample_rate: 96000,
+                bits_per_sample: 24,
+                ..FlacStreamInfo::default()
+            },
+            metadata_blocks: Vec::new(),
+        }))
+        .unwrap();
+    writer
+        .add_track(&TrackConfig::from(AlacConfig {
+            channel_count: 6,
+            ..AlacConfig::default()
+        }))
+        .unwrap();
+    for track_id in 1..=2 {
+        for i in 0..10u32 {
+            let sample = Mp4Sample {
+                start_time: i as u64 * 4096,
+                duration: 4096,
+                rendering_offset: 0,
+                is_sync: true,
+                sample_description_index: 1,
+                bytes: Bytes::from(vec![0xFF; 128]),
+            };
+            writer.write_sample(track_id, &sample).unwrap();
+        }
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+
+    let flac = mp4.tracks().get(&1).unwrap();
+    assert_eq!(flac.media_type().unwrap(), MediaType::FLAC);
+    assert_eq!(flac.box_type().unwrap(), BoxType::FlacBox.into());
+    assert_eq!(flac.timescale(), 96000);
+    assert_eq!(flac.sample_rate().unwrap(), 96000);
+    assert_eq!(flac.bit_depth().unwrap(), 24);
+    assert_eq!(flac.channel_count().unwrap(), 2);
+
+    let alac = mp4.tracks().get(&2).unwrap();
+    assert_eq!(alac.media_type().unwrap(), MediaType::ALAC);
+    assert_eq!(alac.box_type().unwrap(), BoxType::AlacBox.into());
+    assert_eq!(alac.sample_rate().unwrap(), 44100);
+    assert_eq!(alac.bit_depth().unwrap(), 16);
+    assert_eq!(alac.channel_count().unwrap(), 6);
+    assert!(alac.flac_config().is_err());
+}
+
+#[test]
+fn test_write_pcm() {
+    let configs = [
+        PcmConfig::default(),
+        PcmConfig {
+            sample_format: PcmSampleFormat::Float,
+            endianness: PcmEndianness::Big,
+            bit_depth: 32,
+            channel_count: 1,
+            ..PcmConfig::default()
+        },
+        PcmConfig {
+            bit_depth: 24,
+            channel_count: 6,
+            sample_rate: 96000,
+            sample_entry: PcmSampleEntryKind::Lpcm,
+            ..PcmConfig::default()
+        },
+        PcmConfig {
+            endianness: PcmEndianness::Big,
+            sample_entry: PcmSampleEntryKind::QuickTime,
+            ..PcmConfig::default()
+        },
+    ];
+    let config = Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    for (i, pcm_config) in configs.iter().enumerate() {
+        writer
+            .add_track(&TrackConfig::from(pcm_config.clone()))
+            .unwrap();
+        let frame_size = pcm_config.channel_count as usize * pcm_config.bit_depth as usize / 8;
+        let sample = Mp4Sample {
+            start_time: 0,
+            duration: 1024,
+            rendering_offset: 0,
+            is_sync: true,
+            sample_description_index: 1,
+            bytes: Bytes::from(vec![0; 1024 * frame_size]),
+        };
+        writer.write_sample(i as u32 + 1, &sample).unwrap();
+    }
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    let mp4 = Mp4Reader::read_header(Cursor::new(&data), data.len() as u64).unwrap();
+    let box_types = [
+        BoxType::IpcmBox,
+        BoxType::FpcmBox,
+        BoxType::LpcmBox,
+        BoxType::TwosBox,
+    ];
+    for (i, pcm_config) in configs.iter().enumerate() {
+        let track = mp4.tracks().get(&(i as u32 + 1)).unwrap();
+        assert_eq!(track.media_type().unwrap(), MediaType::PCM);
+        assert_eq!(track.box_type().unwrap(), box_types[i].into());
+        assert_eq!(&track.pcm_config().unwrap(), pcm_config);
+        assert_eq!(track.timescale(), pcm_config.sample_rate);
+        assert_eq!(track.bit_depth().unwrap(), pcm_config.bit_depth);
+        assert_eq!(track.channel_count().unwrap(), pcm_config.channel_count);
+    }
+}